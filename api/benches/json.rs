@@ -0,0 +1,42 @@
+// Compares the serialiser list responses go through against plain serde_json. Run with and
+// without the features to see what they buy:
+//
+//     cargo bench -p api --bench json
+//     cargo bench -p api --bench json --features fast-json
+//     cargo bench -p api --bench json --features arena
+use api::models::todo::{Todo, TodoId};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn todos(n: u64) -> Vec<Todo> {
+    (1..=n)
+        .map(|id| Todo {
+            id: TodoId(id.into()),
+            task: format!("Task number {}, with \"quotes\" and ünicode", id),
+            done: id % 3 == 0,
+            version: id,
+            list_id: None,
+            position: id,
+        })
+        .collect()
+}
+
+fn bench_list(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list");
+    for n in [10, 1_000, 10_000] {
+        let todos = todos(n);
+        group.bench_with_input(BenchmarkId::new("serde_json", n), &todos, |b, todos| {
+            b.iter(|| serde_json::to_vec(black_box(todos)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("api::json", n), &todos, |b, todos| {
+            b.iter(|| api::json::to_vec(black_box(todos)).unwrap())
+        });
+        // What responses go through, so also covers the arena
+        group.bench_with_input(BenchmarkId::new("to_bytes", n), &todos, |b, todos| {
+            b.iter(|| api::json::to_bytes(black_box(todos)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_list);
+criterion_main!(benches);
@@ -1,5 +1,184 @@
-use actix_web_static_files::resource_dir;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
 
-fn main() {
-    resource_dir("./static").build().unwrap();
+include!("src/bundle_hash.rs");
+
+static STATIC_DIR: &str = "./static";
+// Hex digits of the contents' SHA-256 that go in hashed file names
+static NAME_HASH_LEN: usize = 16;
+
+struct Asset {
+    path: String,
+    contents: Vec<u8>,
+    mime_type: String,
+}
+
+fn main() -> io::Result<()> {
+    println!("cargo:rerun-if-changed={}", STATIC_DIR);
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/bundle_hash.rs");
+    let mut files = Vec::new();
+    read_files(Path::new(STATIC_DIR), Path::new(STATIC_DIR), &mut files)?;
+    files.sort();
+    let mut assets: Vec<Asset> = files
+        .into_iter()
+        .map(|(path, contents)| Asset {
+            mime_type: mime_guess::from_path(&path)
+                .first_or_octet_stream()
+                .to_string(),
+            path,
+            contents,
+        })
+        .collect();
+    // Pages refer to the hashed names, so that they can be cached for good. Pages themselves
+    // aren't, so that they can point to new names.
+    let hashed_names: Vec<(String, String)> = assets
+        .iter()
+        .filter(|a| !is_page(a))
+        .map(|a| (a.path.clone(), hashed_path(&a.path, &a.contents)))
+        .collect();
+    for asset in assets.iter_mut().filter(|a| is_page(a)) {
+        asset.contents = link_hashed(&asset.path, &asset.contents, &hashed_names);
+    }
+
+    let hash = bundle_hash(
+        assets
+            .iter()
+            .map(|a| (a.path.as_str(), a.contents.as_slice())),
+    );
+    // Checked against the embedded assets at startup
+    println!("cargo:rustc-env=ASSET_BUNDLE_SHA256={}", hash);
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").map_err(io::Error::other)?);
+    let assets_dir = out_dir.join("assets");
+    let mut generated = String::from("pub static ASSETS: &[Asset] = &[\n");
+    for asset in &assets {
+        let identity = write_variant(&assets_dir, &asset.path, "", &asset.contents)?;
+        let (gzip, brotli) = if is_compressible(&asset.mime_type) {
+            (
+                smaller(&asset.contents, gzip(&asset.contents)?)
+                    .map(|c| write_variant(&assets_dir, &asset.path, ".gz", &c))
+                    .transpose()?,
+                smaller(&asset.contents, brotli(&asset.contents)?)
+                    .map(|c| write_variant(&assets_dir, &asset.path, ".br", &c))
+                    .transpose()?,
+            )
+        } else {
+            (None, None)
+        };
+        let included = |p: Option<PathBuf>| match p {
+            Some(p) => format!("Some(include_bytes!({:?}))", p),
+            None => "None".to_string(),
+        };
+        generated.push_str(&format!(
+            "    Asset {{\n        path: {:?},\n        hashed_path: {:?},\n        content_hash: {:?},\n        mime_type: {:?},\n        data: include_bytes!({:?}),\n        gzip: {},\n        brotli: {},\n    }},\n",
+            asset.path,
+            hashed_path(&asset.path, &asset.contents),
+            content_hash(&asset.contents),
+            asset.mime_type,
+            identity,
+            included(gzip),
+            included(brotli),
+        ));
+    }
+    generated.push_str("];\n");
+    fs::write(out_dir.join("assets.rs"), generated)
+}
+
+// Keys are paths relative to `root` with `/` separators
+fn read_files(root: &Path, dir: &Path, files: &mut Vec<(String, Vec<u8>)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_files(root, &path, files)?;
+        } else {
+            let key = path
+                .strip_prefix(root)
+                .map_err(|e| io::Error::other(e.to_string()))?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((key, fs::read(&path)?));
+        }
+    }
+    Ok(())
+}
+
+fn content_hash(contents: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(contents)[..NAME_HASH_LEN / 2]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// "js/app.js" becomes "js/app.0123456789abcdef.js"
+fn hashed_path(path: &str, contents: &[u8]) -> String {
+    let hash = content_hash(contents);
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = name_start + dot;
+            format!("{}.{}{}", &path[..dot], hash, &path[dot..])
+        }
+        _ => format!("{}.{}", path, hash),
+    }
+}
+
+fn is_page(asset: &Asset) -> bool {
+    asset.mime_type == "text/html"
+}
+
+// Swaps quoted references to assets in the page's directory for their hashed names
+fn link_hashed(page: &str, contents: &[u8], hashed_names: &[(String, String)]) -> Vec<u8> {
+    let dir = page.rfind('/').map_or("", |i| &page[..=i]);
+    let mut linked = String::from_utf8_lossy(contents).into_owned();
+    for (path, hashed) in hashed_names {
+        if let (Some(relative), Some(hashed)) = (path.strip_prefix(dir), hashed.strip_prefix(dir)) {
+            linked = linked.replace(&format!("=\"{}\"", relative), &format!("=\"{}\"", hashed));
+        }
+    }
+    linked.into_bytes()
+}
+
+// Images, fonts and the like are compressed already
+fn is_compressible(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || mime_type == "application/javascript"
+        || mime_type == "application/json"
+        || mime_type == "image/svg+xml"
+}
+
+fn gzip(contents: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(contents)?;
+    encoder.finish()
+}
+
+fn brotli(contents: &[u8]) -> io::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        encoder.write_all(contents)?;
+    }
+    Ok(compressed)
+}
+
+fn smaller(identity: &[u8], compressed: Vec<u8>) -> Option<Vec<u8>> {
+    if compressed.len() < identity.len() {
+        Some(compressed)
+    } else {
+        None
+    }
+}
+
+fn write_variant(dir: &Path, path: &str, suffix: &str, contents: &[u8]) -> io::Result<PathBuf> {
+    let file = dir.join(format!("{}{}", path, suffix));
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&file, contents)?;
+    Ok(file)
 }
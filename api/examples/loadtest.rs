@@ -0,0 +1,182 @@
+// Drives concurrent CRUD traffic at the server and reports throughput and latency percentiles per
+// operation, for capacity planning and for spotting regressions between builds:
+//
+//     cargo run --release -p api --example loadtest -- --concurrency 64 --duration 30
+//
+// Without --url, the server is started in-process on a random port, configured from the
+// environment as `todddo-openapi-rs` would be (e.g. ID_STRATEGY=uuid). With it, traffic goes to
+// a server that's already running, e.g. one built with other features or on another machine.
+use actix_web::rt;
+use clap::{value_t, App, Arg};
+use client::models::todo::{Todo, TodoData};
+use client::{ClientError, TodoApiClient};
+use futures::future::join_all;
+use std::cell::RefCell;
+use std::future::Future;
+use std::io;
+use std::time::{Duration, Instant};
+
+// What each client does over and over, in this order, on a todo of its own
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Create,
+    Get,
+    Update,
+    List,
+    Delete,
+}
+
+static OPS: [Op; 5] = [Op::Create, Op::Get, Op::Update, Op::List, Op::Delete];
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Create => "create",
+            Op::Get => "get",
+            Op::Update => "update",
+            Op::List => "list",
+            Op::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    errors: u64,
+}
+
+// By op; the clients all run on this thread
+type Recorded = RefCell<Vec<Samples>>;
+
+#[actix_web::main]
+async fn main() -> io::Result<()> {
+    let matches = App::new("loadtest")
+        .about("Drives concurrent CRUD traffic at todddo and reports latencies")
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .takes_value(true)
+                .help("A running server to drive, rather than one started in-process"),
+        )
+        .arg(
+            Arg::with_name("concurrency")
+                .long("concurrency")
+                .takes_value(true)
+                .default_value("16")
+                .help("How many clients send requests at the same time"),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .takes_value(true)
+                .default_value("10")
+                .help("For how many seconds"),
+        )
+        .get_matches();
+    let concurrency = value_t!(matches, "concurrency", usize).unwrap_or_else(|e| e.exit());
+    let duration = value_t!(matches, "duration", u64).unwrap_or_else(|e| e.exit());
+
+    let (base_url, handle) = match matches.value_of("url") {
+        Some(url) => (url.trim_end_matches('/').to_string(), None),
+        None => {
+            let args = vec!["todddo", "--bind", "127.0.0.1:0"];
+            let config = api::config::load_from(args, |key| std::env::var(key).ok())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+            let built = api::build_server(config).await?;
+            let handle = built.server.handle();
+            rt::spawn(built.server);
+            (format!("http://{}", built.addrs[0]), Some(handle))
+        }
+    };
+    println!(
+        "Driving {} with {} clients for {}s",
+        base_url, concurrency, duration
+    );
+
+    let recorded: Recorded = RefCell::new(OPS.iter().map(|_| Samples::default()).collect());
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(duration);
+    let clients = (0..concurrency).map(|n| drive(client::new(&base_url), n, deadline, &recorded));
+    join_all(clients).await;
+    report(&recorded.into_inner(), started.elapsed());
+
+    if let Some(handle) = handle {
+        handle.stop(true).await;
+    }
+    Ok(())
+}
+
+async fn drive(client: TodoApiClient, n: usize, deadline: Instant, recorded: &Recorded) {
+    let todo_data = TodoData {
+        task: format!("Load test task from client {}", n),
+        done: false,
+        list_id: None,
+    };
+    while Instant::now() < deadline {
+        let created = match timed(recorded, Op::Create, client.create_todo(&todo_data)).await {
+            Some(created) => Todo::from(created),
+            None => continue,
+        };
+        timed(recorded, Op::Get, client.get_todo(created.id)).await;
+        let done = Todo {
+            done: true,
+            ..created.clone()
+        };
+        timed(recorded, Op::Update, client.update_todo(&done)).await;
+        timed(recorded, Op::List, client.list_todos_page(0, 20)).await;
+        timed(recorded, Op::Delete, client.delete_todo(created.id)).await;
+    }
+}
+
+async fn timed<T>(
+    recorded: &Recorded,
+    op: Op,
+    call: impl Future<Output = Result<T, ClientError>>,
+) -> Option<T> {
+    let start = Instant::now();
+    let result = call.await;
+    let mut recorded = recorded.borrow_mut();
+    let samples = &mut recorded[op as usize];
+    samples.latencies.push(start.elapsed());
+    match result {
+        Ok(answer) => Some(answer),
+        Err(_) => {
+            samples.errors += 1;
+            None
+        }
+    }
+}
+
+fn report(recorded: &[Samples], elapsed: Duration) {
+    println!(
+        "{:<8} {:>10} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        "op", "requests", "errors", "p50", "p90", "p99", "max"
+    );
+    let mut total = 0;
+    for (op, samples) in OPS.iter().zip(recorded) {
+        let mut latencies = samples.latencies.clone();
+        latencies.sort();
+        total += latencies.len();
+        let at = |p: f64| match latencies.len() {
+            0 => "-".to_string(),
+            len => format!("{:.2?}", latencies[((len - 1) as f64 * p).round() as usize]),
+        };
+        println!(
+            "{:<8} {:>10} {:>8} {:>10} {:>10} {:>10} {:>10}",
+            op.name(),
+            latencies.len(),
+            samples.errors,
+            at(0.5),
+            at(0.9),
+            at(0.99),
+            at(1.0)
+        );
+    }
+    println!(
+        "{} requests in {:.1?}, {:.0} requests/s",
+        total,
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+}
@@ -0,0 +1,172 @@
+use crate::models::common::{ErrorCode, Message};
+use crate::request_id;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{error, Error, HttpRequest, HttpResponse, ResponseError};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use thiserror::Error;
+
+/// Shared secret that every admin endpoint requires as a `Bearer` token.
+#[derive(Clone)]
+pub struct AdminToken(Option<String>);
+
+pub fn new(token: Option<String>) -> AdminToken {
+    AdminToken(token)
+}
+
+#[derive(Error, Debug)]
+pub enum AdminTokenError {
+    #[error("No admin token configured")]
+    NotConfigured,
+    #[error("Missing or wrong admin token")]
+    Unauthorized,
+}
+
+impl AdminToken {
+    pub fn authorize(&self, req: &HttpRequest) -> Result<(), AdminTokenError> {
+        let expected = self.0.as_ref().ok_or(AdminTokenError::NotConfigured)?;
+        let given = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        match given {
+            Some(given) if constant_time_eq(given.as_bytes(), expected.as_bytes()) => Ok(()),
+            _ => Err(AdminTokenError::Unauthorized),
+        }
+    }
+}
+
+// Avoids leaking how much of the token matched through response timings
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Middleware that turns down requests without the admin token, for wrapping the whole `/admin`
+/// scope so that no admin route can be added without it
+pub struct RequireAdminToken {
+    token: AdminToken,
+}
+
+impl RequireAdminToken {
+    pub fn new(token: AdminToken) -> RequireAdminToken {
+        RequireAdminToken { token }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireAdminToken
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireAdminTokenMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequireAdminTokenMiddleware {
+            service,
+            token: self.token.clone(),
+        })
+    }
+}
+
+pub struct RequireAdminTokenMiddleware<S> {
+    service: S,
+    token: AdminToken,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireAdminTokenMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Err(e) = self.token.authorize(req.request()) {
+            let (req, _) = req.into_parts();
+            return Box::pin(async move {
+                Ok(ServiceResponse::new(req, e.error_response()).map_into_right_body())
+            });
+        }
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
+
+impl error::ResponseError for AdminTokenError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            AdminTokenError::NotConfigured => HttpResponse::Forbidden().json(&Message {
+                message: "Set an admin token to enable this endpoint".to_string(),
+                code: Some(ErrorCode::AdminTokenNotConfigured),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+            AdminTokenError::Unauthorized => HttpResponse::Unauthorized().json(&Message {
+                message: "Missing or wrong admin token".to_string(),
+                code: Some(ErrorCode::Unauthorized),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App};
+
+    #[test]
+    fn test_authorize() {
+        let req = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Bearer s3cret"))
+            .to_http_request();
+        assert!(new(Some("s3cret".to_string())).authorize(&req).is_ok());
+        match new(Some("other".to_string())).authorize(&req) {
+            Err(AdminTokenError::Unauthorized) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+        match new(None).authorize(&req) {
+            Err(AdminTokenError::NotConfigured) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_require_admin_token() {
+        let app = |token: Option<&str>| {
+            init_service(
+                App::new().service(
+                    web::scope("/admin")
+                        .wrap(RequireAdminToken::new(new(token.map(|t| t.to_string()))))
+                        .route("/read-only", web::get().to(HttpResponse::Ok)),
+                ),
+            )
+        };
+        let req = |token: &str| {
+            TestRequest::get()
+                .uri("/admin/read-only")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+                .to_request()
+        };
+        let guarded = app(Some("s3cret")).await;
+        assert_eq!(200, call_service(&guarded, req("s3cret")).await.status());
+        assert_eq!(401, call_service(&guarded, req("other")).await.status());
+        let unconfigured = app(None).await;
+        assert_eq!(
+            403,
+            call_service(&unconfigured, req("s3cret")).await.status()
+        );
+    }
+}
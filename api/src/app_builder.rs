@@ -0,0 +1,670 @@
+use crate::config::{Config, IdStrategy, StorageBackend};
+use crate::controllers::history_controller;
+use crate::controllers::history_controller::HistoryControllerImpl;
+use crate::controllers::list_controller;
+use crate::controllers::list_controller::ListControllerImpl;
+use crate::controllers::recurrence_controller;
+use crate::controllers::recurrence_controller::RecurrenceControllerImpl;
+use crate::controllers::reminder_controller;
+use crate::controllers::reminder_controller::ReminderControllerImpl;
+use crate::controllers::rule_controller;
+use crate::controllers::rule_controller::RuleControllerImpl;
+use crate::controllers::share_controller;
+use crate::controllers::share_controller::ShareControllerImpl;
+use crate::controllers::todo_controller;
+use crate::controllers::todo_controller::{TodoController, TodoControllerImpl};
+use crate::handlers::admin_routes_handler::{EventQueues, SharedAuditLog};
+use crate::handlers::{
+    admin_routes_handler, asset_routes_handler, health_routes_handler, history_routes_handler,
+    list_routes_handler, recurrence_routes_handler, reminder_routes_handler, rule_routes_handler,
+    share_routes_handler, todo_routes_handler, v2_todo_routes_handler,
+};
+use crate::metrics::SharedMetrics;
+use crate::read_only::ReadOnly;
+use crate::server_timing::{timed, Timed};
+use crate::{
+    admin_token, cors, deprecation, errors, event_queues, fallback, health_history, json_bodies,
+    lifecycle, messaging, metrics, notifications, plugins, read_only, request_id, scheduler, seed,
+    server_timing, spec, tls, BuiltServer,
+};
+use actix_service::boxed::{self, BoxService};
+use actix_service::ServiceExt;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::middleware::Logger;
+use actix_web::*;
+use domain::events::Subscriber;
+use domain::flood::{self, FloodLimit};
+use domain::history::{AuditSink, ChangeOrigin};
+use domain::services::history_service;
+use domain::services::history_service::HistoryServiceImpl;
+use domain::services::list_service;
+use domain::services::list_service::ListServiceImpl;
+use domain::services::recurrence_service;
+use domain::services::recurrence_service::RecurrenceServiceImpl;
+use domain::services::reminder_service;
+use domain::services::reminder_service::{DispatchPolicy, ReminderServiceImpl};
+use domain::services::rule_service;
+use domain::services::rule_service::RuleServiceImpl;
+use domain::services::share_service;
+use domain::services::share_service::ShareServiceImpl;
+use domain::services::todo_service;
+use domain::services::todo_service::{TodoService, TodoServiceImpl};
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use infra::audit::file_audit_log;
+use infra::decorators::resilient_todo_repo::{self, ResilientTodoRepo};
+use infra::events::logging_subscriber;
+use infra::ids::uuid_ids;
+use infra::in_mem::history_repo::InMemHistoryRepo;
+use infra::in_mem::list_repo::InMemListRepo;
+use infra::in_mem::recurrence_repo::InMemRecurrenceRepo;
+use infra::in_mem::reminder_repo::InMemReminderRepo;
+use infra::in_mem::rule_repo::InMemRuleRepo;
+use infra::in_mem::share_repo::InMemShareRepo;
+use infra::in_mem::todo_repo::InMemTodoRepo;
+use infra::in_mem::{
+    audit_log, history_repo, list_repo, recurrence_repo, reminder_repo, rule_repo, share_repo,
+    todo_repo,
+};
+use infra::metrics::prometheus_metrics::PrometheusMetrics;
+use log::*;
+use paperclip::actix::{
+    // use this instead of actix_web::web
+    web,
+    // extension trait for actix_web::App and proc-macro attributes
+    OpenApiExt,
+};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The todos' storage as the services use it, behind timeouts, retries and a circuit breaker
+pub type GuardedTodoRepo = ResilientTodoRepo<InMemTodoRepo>;
+pub type DefaultTodoService = Timed<TodoServiceImpl<Timed<GuardedTodoRepo>>>;
+pub type DefaultTodoController = TodoControllerImpl<DefaultTodoService>;
+
+type BoxedService = BoxService<ServiceRequest, ServiceResponse<BoxBody>, Error>;
+// Wraps a worker's service in a middleware
+type Layer =
+    Arc<dyn Fn(BoxedService) -> LocalBoxFuture<'static, Result<BoxedService, ()>> + Send + Sync>;
+
+type Configurer = Arc<dyn Fn(&mut actix_web::web::ServiceConfig) + Send + Sync>;
+
+// Each route along with the method it's served under, which 405s' Allow headers are built from
+macro_rules! routes {
+    ($($method:ident $path:literal => $handler:expr,)*) => {
+        vec![$(($path, Method::$method, web::method(Method::$method).to($handler)),)*]
+    };
+}
+
+/// Sets up the app like `build_server` does, plus whatever an embedder adds. Everything added is
+/// created once per worker, like the rest of the app.
+pub struct AppBuilder<C = DefaultTodoController> {
+    config: Config,
+    todo_controller_factory: Arc<dyn Fn(&DefaultTodoService) -> C + Send + Sync>,
+    configurers: Vec<Configurer>,
+    middleware: Vec<Layer>,
+}
+
+impl AppBuilder {
+    pub fn new(config: Config) -> AppBuilder {
+        AppBuilder {
+            config,
+            todo_controller_factory: Arc::new(|todo_service| {
+                todo_controller::new(todo_service.clone())
+            }),
+            configurers: Vec::new(),
+            middleware: Vec::new(),
+        }
+    }
+}
+
+impl<C: TodoController + Send + Sync + 'static> AppBuilder<C> {
+    /// Serves the todo routes with another controller, e.g. one that decorates the default
+    /// controller or service it is given
+    pub fn todo_controller<D, F>(self, factory: F) -> AppBuilder<D>
+    where
+        F: Fn(&DefaultTodoService) -> D + Send + Sync + 'static,
+    {
+        AppBuilder {
+            config: self.config,
+            todo_controller_factory: Arc::new(factory),
+            configurers: self.configurers,
+            middleware: self.middleware,
+        }
+    }
+
+    /// Registers extra routes, services or app data. They are matched before the built-in routes
+    /// and don't show up in the OpenAPI spec.
+    pub fn configure<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut actix_web::web::ServiceConfig) + Send + Sync + 'static,
+    {
+        self.configurers.push(Arc::new(f));
+        self
+    }
+
+    /// Wraps every route in a middleware made by `factory`. As with `App::wrap`, middleware added
+    /// later runs earlier, and all of it runs before the built-in middleware.
+    pub fn wrap<F, M, B>(mut self, factory: F) -> Self
+    where
+        F: Fn() -> M + Send + Sync + 'static,
+        M: Transform<
+                BoxedService,
+                ServiceRequest,
+                Response = ServiceResponse<B>,
+                Error = Error,
+                InitError = (),
+            > + 'static,
+        B: MessageBody + 'static,
+    {
+        self.middleware.push(Arc::new(move |service| {
+            let transform = factory().new_transform(service);
+            Box::pin(async move {
+                let service = transform.await?;
+                Ok(boxed::service(
+                    service.map(ServiceResponse::map_into_boxed_body),
+                ))
+            })
+        }));
+        self
+    }
+
+    /// Binds the app as configured, without handling signals: stop it through `Server::handle`.
+    /// Must be called from within an actix runtime.
+    pub async fn build(self) -> Result<BuiltServer, std::io::Error> {
+        let AppBuilder {
+            config,
+            todo_controller_factory,
+            configurers,
+            middleware,
+        } = self;
+        if config.read_only {
+            warn!("Starting in read-only mode; changes are turned down until it's turned off");
+        }
+        // Before assembling, so that the background jobs hold off too
+        let read_only = read_only::new(config.read_only);
+        let Assembled {
+            todo_repo,
+            todo_service,
+            list_service,
+            rule_service,
+            share_service,
+            history_service,
+            recurrence_service,
+            reminder_service,
+            shared_metrics,
+            prometheus,
+            audit_log,
+            queues,
+            background_jobs,
+            push_metrics,
+        } = assemble(&config, &read_only).await?;
+        let scheduler: LocalBoxFuture<'static, ()> =
+            Box::pin(future::join(background_jobs, push_metrics).map(|_| ()));
+        let deprecation_registry = deprecation::new(deprecation::deprecated_routes());
+        let admin_token = admin_token::new(config.admin_token.clone());
+        let readiness = lifecycle::readiness();
+        let app_readiness = readiness.clone();
+        let health_history = health_history::new(config.health_history_size);
+        let cors_settings = config.cors.clone();
+        let server_timing = config.server_timing;
+        let max_json_bytes = config.max_json_bytes;
+        type ListsController = ListControllerImpl<ListServiceImpl<InMemListRepo, GuardedTodoRepo>>;
+        type RulesController = RuleControllerImpl<RuleServiceImpl<InMemRuleRepo>>;
+        type SharesController =
+            ShareControllerImpl<ShareServiceImpl<GuardedTodoRepo, InMemShareRepo>>;
+        type HistoriesController =
+            HistoryControllerImpl<HistoryServiceImpl<GuardedTodoRepo, InMemHistoryRepo>>;
+        type RemindersController =
+            ReminderControllerImpl<ReminderServiceImpl<GuardedTodoRepo, InMemReminderRepo>>;
+        type RecurrencesController = RecurrenceControllerImpl<
+            RecurrenceServiceImpl<DefaultTodoService, InMemRecurrenceRepo>,
+        >;
+        let server = HttpServer::new(move || {
+            let todo_controller = timed(todo_controller_factory(&todo_service));
+            let list_controller = list_controller::new(list_service.clone());
+            let rule_controller = rule_controller::new(rule_service.clone());
+            let share_controller = share_controller::new(share_service.clone());
+            let history_controller = history_controller::new(history_service.clone());
+            let recurrence_controller = recurrence_controller::new(recurrence_service.clone());
+            let reminder_controller = reminder_controller::new(reminder_service.clone());
+            let mut allowed = fallback::AllowedMethods::default();
+            let mut app = App::new()
+                // Innermost, so that it times the routes rather than the other middleware
+                .wrap(middleware::Condition::new(
+                    server_timing,
+                    server_timing::ServerTiming,
+                ))
+                .wrap(request_id::access_logger())
+                .wrap(middleware::Compress::default())
+                .wrap(deprecation::DeprecationHeaders::new(
+                    deprecation_registry.clone(),
+                ))
+                // So that errors parsing JSON bodies can name the field
+                .wrap(json_bodies::KeepJsonBodies::new(max_json_bytes))
+                .wrap(read_only::ReadOnlyGuard::new(read_only.clone()))
+                // Outside the routes, so that preflights for every route are answered
+                .wrap(cors::middleware(&cors_settings))
+                .wrap(request_id::RequestIds)
+                .wrap(metrics::RecordMetrics::new(shared_metrics.clone()))
+                .wrap(Layers(middleware.clone()))
+                .app_data(web::Data::new(todo_controller))
+                .app_data(web::Data::new(list_controller))
+                .app_data(web::Data::new(rule_controller))
+                .app_data(web::Data::new(share_controller))
+                .app_data(web::Data::new(history_controller))
+                .app_data(web::Data::new(recurrence_controller))
+                .app_data(web::Data::new(reminder_controller))
+                .app_data(web::Data::new(deprecation_registry.clone()))
+                .app_data(web::Data::new(todo_repo.clone()))
+                .app_data(web::Data::new(app_readiness.clone()))
+                .app_data(web::Data::new(read_only.clone()))
+                .app_data(web::Data::new(health_history.clone()))
+                .app_data(web::Data::new(audit_log.clone()))
+                .app_data(web::Data::new(queues.clone()))
+                // So that unparseable and oversized requests also get a Message with a code
+                .app_data(
+                    actix_web::web::JsonConfig::default()
+                        .limit(max_json_bytes)
+                        .error_handler(errors::malformed_json),
+                )
+                .app_data(
+                    actix_web::web::PathConfig::default()
+                        .error_handler(|e, _| errors::malformed(e)),
+                )
+                .app_data(
+                    actix_web::web::QueryConfig::default()
+                        .error_handler(|e, _| errors::malformed(e)),
+                )
+                .service(
+                    actix_web::web::resource("/swagger/{path:.*}").route(
+                        actix_web::web::route()
+                            .guard(guard::Any(guard::Get()).or(guard::Head()))
+                            .to(asset_routes_handler::asset),
+                    ),
+                )
+                .default_service(actix_web::web::route().to(fallback::unmatched));
+            allowed.record("/swagger/{path:.*}", Method::GET);
+            allowed.record("/swagger/{path:.*}", Method::HEAD);
+            if let Some(prometheus) = &prometheus {
+                app = app.app_data(web::Data::new(prometheus.clone())).route(
+                    "/metrics",
+                    actix_web::web::get().to(admin_routes_handler::metrics),
+                );
+                allowed.record("/metrics", Method::GET);
+            }
+            for configure in &configurers {
+                let configure = configure.clone();
+                app = app.configure(move |cfg| configure(cfg));
+            }
+            let mut app = app
+                .wrap_api_with_spec(spec::api_spec())
+                .with_json_spec_at("/api/spec");
+            allowed.record("/api/spec", Method::GET);
+            let routes = routes![
+                GET "/tasks" => todo_routes_handler::list::<Timed<C>>,
+                POST "/tasks" => todo_routes_handler::create::<Timed<C>>,
+                // These must come before /tasks/{id} so that their names aren't taken for ids
+                GET "/tasks/ws" => todo_routes_handler::subscribe::<Timed<C>>,
+                GET "/tasks/export" => todo_routes_handler::export::<Timed<C>>,
+                POST "/tasks/import" => todo_routes_handler::import::<Timed<C>>,
+                POST "/tasks/complete" => todo_routes_handler::complete::<Timed<C>>,
+                PATCH "/tasks/reorder" => todo_routes_handler::reorder::<Timed<C>>,
+                GET "/tasks/{id}" => todo_routes_handler::get::<Timed<C>>,
+                DELETE "/tasks/{id}" => todo_routes_handler::delete::<Timed<C>>,
+                PUT "/tasks/{id}" => todo_routes_handler::update::<Timed<C>>,
+                GET "/tasks/{id}/history" => history_routes_handler::history::<HistoriesController>,
+                GET "/tasks/{id}/recurrence" =>
+                    recurrence_routes_handler::get::<RecurrencesController>,
+                PUT "/tasks/{id}/recurrence" =>
+                    recurrence_routes_handler::set::<RecurrencesController>,
+                DELETE "/tasks/{id}/recurrence" =>
+                    recurrence_routes_handler::delete::<RecurrencesController>,
+                GET "/tasks/{id}/reminder" => reminder_routes_handler::get::<RemindersController>,
+                PUT "/tasks/{id}/reminder" => reminder_routes_handler::set::<RemindersController>,
+                DELETE "/tasks/{id}/reminder" =>
+                    reminder_routes_handler::delete::<RemindersController>,
+                GET "/reminders" => reminder_routes_handler::pending::<RemindersController>,
+                POST "/tasks/{id}/share" => share_routes_handler::create::<SharesController>,
+                GET "/tasks/{id}/share" => share_routes_handler::list::<SharesController>,
+                DELETE "/tasks/{id}/share/{token}" =>
+                    share_routes_handler::revoke::<SharesController>,
+                GET "/shared/{token}" => share_routes_handler::shared::<SharesController>,
+                GET "/shared/{token}/embed" => share_routes_handler::embed::<SharesController>,
+                GET "/lists" => list_routes_handler::list::<ListsController>,
+                POST "/lists" => list_routes_handler::create::<ListsController>,
+                GET "/lists/{id}" => list_routes_handler::get::<ListsController>,
+                DELETE "/lists/{id}" => list_routes_handler::delete::<ListsController>,
+                GET "/lists/{id}/tasks" => list_routes_handler::todos::<ListsController>,
+                // The v2 representation, being soft launched; served by the same controllers
+                GET "/v2/tasks" => v2_todo_routes_handler::list::<Timed<C>, HistoriesController>,
+                POST "/v2/tasks" => v2_todo_routes_handler::create::<Timed<C>, HistoriesController>,
+                GET "/v2/tasks/{id}" =>
+                    v2_todo_routes_handler::get::<Timed<C>, HistoriesController>,
+                PUT "/v2/tasks/{id}" => v2_todo_routes_handler::update::<Timed<C>>,
+                DELETE "/v2/tasks/{id}" => v2_todo_routes_handler::delete::<Timed<C>>,
+                GET "/rules" => rule_routes_handler::list::<RulesController>,
+                POST "/rules" => rule_routes_handler::create::<RulesController>,
+                DELETE "/rules/{id}" => rule_routes_handler::delete::<RulesController>,
+                GET "/healthz" => health_routes_handler::healthz,
+                GET "/readyz" => health_routes_handler::readyz,
+                GET "/version" => admin_routes_handler::version,
+            ];
+            for (path, method, route) in routes {
+                allowed.record(path, method);
+                app = app.route(path, route);
+            }
+            let admin_routes = routes![
+                GET "/deprecations" => admin_routes_handler::deprecations,
+                GET "/diagnostics" => admin_routes_handler::diagnostics,
+                GET "/health/history" => admin_routes_handler::health_history,
+                GET "/audit" => admin_routes_handler::audit,
+                DELETE "/tasks" => admin_routes_handler::delete_all_todos,
+                GET "/tasks/dump" => admin_routes_handler::dump_todos,
+                POST "/tasks/purge-done" => admin_routes_handler::purge_done_todos,
+                GET "/tasks/stats" => admin_routes_handler::todo_stats,
+                GET "/read-only" => admin_routes_handler::read_only,
+                PUT "/read-only" => admin_routes_handler::set_read_only,
+                GET "/export/stream" => admin_routes_handler::export_stream,
+            ];
+            #[cfg(feature = "profiling")]
+            let admin_routes = admin_routes
+                .into_iter()
+                .chain(routes![GET "/profile" => admin_routes_handler::profile,])
+                .collect::<Vec<_>>();
+            // Every route in here is behind the admin token
+            let mut admin =
+                web::scope("/admin").wrap(admin_token::RequireAdminToken::new(admin_token.clone()));
+            for (path, method, route) in admin_routes {
+                allowed.record(&format!("/admin{}", path), method);
+                admin = admin.route(path, route);
+            }
+            let app = app.service(admin).app_data(web::Data::new(allowed));
+            app.build()
+        });
+
+        // Signals are handled by lifecycle so that readiness can be flipped before draining
+        let server = server
+            .disable_signals()
+            .shutdown_timeout(config.shutdown_timeout_secs);
+        let server = match config.workers {
+            Some(workers) => server.workers(workers),
+            None => server,
+        };
+
+        let bind_to = config.bind_addr;
+        info!("Binding to [{}]", bind_to);
+        let (main_server, addrs, redirect_server) = match config.tls {
+            None => {
+                let server = server.bind(bind_to)?;
+                let addrs = server.addrs();
+                (server.run(), addrs, None)
+            }
+            Some(tls_settings) => {
+                let rustls_config = tls::load_rustls_config(&tls_settings)?;
+                info!("Serving HTTPS using cert [{}]", tls_settings.cert_path);
+                let https_server = server.bind_rustls(&bind_to, rustls_config)?;
+                let addrs = https_server.addrs();
+                let redirect_server = match tls_settings.redirect_from {
+                    None => None,
+                    Some(redirect_from) => {
+                        let https_port = tls::port_of(&bind_to).unwrap_or(443);
+                        info!(
+                            "Redirecting plaintext HTTP on [{}] to HTTPS port [{}]",
+                            redirect_from, https_port
+                        );
+                        let redirect_server = HttpServer::new(move || {
+                            App::new().wrap(Logger::default()).default_service(
+                                actix_web::web::route().to(move |req: HttpRequest| async move {
+                                    tls::redirect_to_https(&req, https_port)
+                                }),
+                            )
+                        })
+                        .disable_signals()
+                        .bind(redirect_from)?
+                        .run();
+                        Some(redirect_server)
+                    }
+                };
+                (https_server.run(), addrs, redirect_server)
+            }
+        };
+        Ok(BuiltServer {
+            server: main_server,
+            redirect_server,
+            addrs,
+            readiness,
+            scheduler,
+        })
+    }
+}
+
+// What the server runs on, apart from its routes
+struct Assembled {
+    // Unguarded, for the admin routes to reach storage even while the breaker turns calls down
+    todo_repo: InMemTodoRepo,
+    todo_service: DefaultTodoService,
+    list_service: ListServiceImpl<InMemListRepo, GuardedTodoRepo>,
+    rule_service: RuleServiceImpl<InMemRuleRepo>,
+    share_service: ShareServiceImpl<GuardedTodoRepo, InMemShareRepo>,
+    history_service: HistoryServiceImpl<GuardedTodoRepo, InMemHistoryRepo>,
+    recurrence_service: RecurrenceServiceImpl<DefaultTodoService, InMemRecurrenceRepo>,
+    reminder_service: ReminderServiceImpl<GuardedTodoRepo, InMemReminderRepo>,
+    shared_metrics: Option<SharedMetrics>,
+    prometheus: Option<Arc<PrometheusMetrics>>,
+    audit_log: SharedAuditLog,
+    queues: EventQueues,
+    // Creates the next occurrences of recurring todos and sends due reminders
+    background_jobs: LocalBoxFuture<'static, ()>,
+    push_metrics: LocalBoxFuture<'static, ()>,
+}
+
+async fn assemble(config: &Config, read_only: &ReadOnly) -> Result<Assembled, std::io::Error> {
+    let (todo_repo, list_repo, rule_repo, share_repo, history_repo, recurrence_repo, reminder_repo) =
+        match config.storage {
+            StorageBackend::InMem => (
+                match config.id_strategy {
+                    IdStrategy::Sequential => todo_repo::new(),
+                    IdStrategy::Uuid => {
+                        todo_repo::new().with_id_generator(Arc::new(uuid_ids::new()))
+                    }
+                },
+                list_repo::new(),
+                rule_repo::new(),
+                share_repo::new(),
+                history_repo::new(),
+                recurrence_repo::new(),
+                reminder_repo::new(),
+            ),
+        };
+    // Clones share the breaker, so every service sees storage as failing once it is
+    let guarded_repo = resilient_todo_repo::new(
+        todo_repo.clone(),
+        config.storage_resilience.clone(),
+        Arc::new(|duration| Box::pin(rt::time::sleep(duration))),
+    );
+    // Shared by all workers so that live update subscribers see every change; named for their
+    // queues' metrics and spill files
+    let mut subscribers: Vec<(String, Arc<dyn Subscriber + Send + Sync>)> =
+        vec![("log".to_string(), Arc::new(logging_subscriber::new()))];
+    if let Some(messaging_settings) = &config.messaging {
+        info!(
+            "Publishing todo events via [{:?}] to [{}]",
+            messaging_settings.backend, messaging_settings.topic
+        );
+        subscribers.push((
+            "messaging".to_string(),
+            messaging::publisher(messaging_settings).await?,
+        ));
+    }
+    let recorder = match &config.metrics {
+        Some(metrics_settings) => {
+            info!("Recording metrics via [{:?}]", metrics_settings.backend());
+            Some(metrics::recorder(metrics_settings)?)
+        }
+        None => None,
+    };
+    let shared_metrics = recorder.as_ref().map(|r| r.metrics.clone());
+    let prometheus = recorder.as_ref().and_then(|r| r.prometheus.clone());
+    let plugins = match &config.plugins_dir {
+        Some(plugins_dir) => plugins::load(plugins_dir)?,
+        None => plugins::Plugins::default(),
+    };
+    for description in &plugins.descriptions {
+        info!("Loaded plugin [{}]", description);
+    }
+    subscribers.extend(
+        plugins
+            .subscribers
+            .into_iter()
+            .enumerate()
+            .map(|(i, plugin)| (format!("plugin-{}", i + 1), plugin)),
+    );
+    info!("Delivering reminders via [{:?}]", config.notifier.backend());
+    let reminder_service = reminder_service::new(
+        guarded_repo.clone(),
+        reminder_repo,
+        notifications::jittered(
+            notifications::notifier(&config.notifier)?,
+            Duration::from_millis(config.notifier_jitter_ms),
+        ),
+    )
+    .with_policy(DispatchPolicy {
+        batch_size: config.reminder_batch_size,
+        concurrency: config.notifier_concurrency,
+        // Later than a check should make them, e.g. having been held back from the last one
+        overdue_after: chrono::Duration::from_std(scheduler::REMINDER_CHECK_INTERVAL)
+            .unwrap_or_else(|_| chrono::Duration::zero()),
+    });
+    // So that deleted todos' reminders are forgotten
+    subscribers.push(("reminders".to_string(), Arc::new(reminder_service.clone())));
+    info!(
+        "Queueing up to [{}] todo events per subscriber, then [{:?}]",
+        config.event_queues.capacity,
+        config.event_queues.overflow.policy()
+    );
+    let mut queued_subscribers: Vec<Arc<dyn Subscriber + Send + Sync>> = Vec::new();
+    let mut queues: EventQueues = Vec::new();
+    for (name, subscriber) in subscribers {
+        let (queue, drain) = event_queues::queued(
+            &name,
+            subscriber,
+            &config.event_queues,
+            shared_metrics.clone(),
+        )?;
+        // Spawned here rather than joined into the scheduler, so that events are delivered
+        // even by embedders that don't run it
+        rt::spawn(drain);
+        queued_subscribers.push(queue.clone());
+        queues.push(queue);
+    }
+    let (audit_sink, audit_log): (Arc<dyn AuditSink + Send + Sync>, SharedAuditLog) =
+        match &config.audit_log_path {
+            Some(path) => {
+                info!("Appending the audit log to [{}]", path);
+                let file_log = Arc::new(file_audit_log::open(Path::new(path))?);
+                (file_log.clone(), file_log)
+            }
+            None => {
+                let in_mem_log = Arc::new(audit_log::new(config.audit_log_size));
+                (in_mem_log.clone(), in_mem_log)
+            }
+        };
+    let todo_service =
+        todo_service::with_subscribers(timed(guarded_repo.clone()), queued_subscribers)
+            .with_rules(Arc::new(rule_repo.clone()))
+            .with_lists(Arc::new(list_repo.clone()))
+            .with_policy(config.task_policy.clone())
+            .with_validators(plugins.validators)
+            .with_audit(
+                vec![Arc::new(history_repo.clone()), audit_sink],
+                change_origin,
+            );
+    let todo_service = match config.flood_limit {
+        0 => todo_service,
+        max_changes => todo_service.with_flood_guard(flood::guard(FloodLimit {
+            max_changes,
+            per: Duration::from_secs(1),
+        })),
+    };
+    let todo_service = timed(todo_service);
+    if let Some(seed_file) = &config.seed_file {
+        seed::seed(seed_file, &guarded_repo, &todo_service).await?;
+    }
+    let list_service = list_service::new(list_repo, guarded_repo.clone());
+    let rule_service = rule_service::new(rule_repo);
+    let share_service = share_service::new(guarded_repo.clone(), share_repo);
+    let history_service = history_service::new(guarded_repo, history_repo);
+    let recurrence_service = recurrence_service::new(todo_service.clone(), recurrence_repo);
+    let push_metrics = recorder
+        .and_then(|r| r.push)
+        .unwrap_or_else(|| Box::pin(future::ready(())));
+    let background_jobs = Box::pin(
+        future::join(
+            scheduler::run(
+                recurrence_service.clone(),
+                todo_service.subscribe(),
+                scheduler::CHECK_INTERVAL,
+                read_only.clone(),
+            ),
+            scheduler::remind(
+                reminder_service.clone(),
+                scheduler::REMINDER_CHECK_INTERVAL,
+                read_only.clone(),
+            ),
+        )
+        .map(|_| ()),
+    );
+    Ok(Assembled {
+        todo_repo,
+        todo_service,
+        list_service,
+        rule_service,
+        share_service,
+        history_service,
+        recurrence_service,
+        reminder_service,
+        shared_metrics,
+        prometheus,
+        audit_log,
+        queues,
+        background_jobs,
+        push_metrics,
+    })
+}
+
+// Changes are attributed to the request being handled, if any
+fn change_origin() -> ChangeOrigin {
+    ChangeOrigin {
+        actor: request_id::current_client(),
+        request_id: request_id::current(),
+    }
+}
+
+// Applies an embedder's middleware, in order, to each worker's service
+struct Layers(Vec<Layer>);
+
+impl<S, B> Transform<S, ServiceRequest> for Layers
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = BoxedService;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<BoxedService, ()>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let layers = self.0.clone();
+        Box::pin(async move {
+            let mut service = boxed::service(service.map(ServiceResponse::map_into_boxed_body));
+            for layer in layers {
+                service = layer(service).await?;
+            }
+            Ok(service)
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use crate::bundle_hash::bundle_hash;
+
+/// A file under `static/`, as baked into the binary by build.rs
+pub struct Asset {
+    /// Relative to `static/`, with `/` separators
+    pub path: &'static str,
+    /// `path` with a hash of the contents before the extension, e.g. `app.0123456789abcdef.js`.
+    /// Pages link to these, so that they can be cached for good.
+    pub hashed_path: &'static str,
+    pub content_hash: &'static str,
+    pub mime_type: &'static str,
+    /// Pages have their links to other assets swapped for the hashed paths
+    pub data: &'static [u8],
+    /// Pre-compressed, for text assets that compress at all
+    pub gzip: Option<&'static [u8]>,
+    pub brotli: Option<&'static [u8]>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+/// By either its path or its hashed path
+pub fn get(path: &str) -> Option<&'static Asset> {
+    ASSETS
+        .iter()
+        .find(|a| a.path == path || a.hashed_path == path)
+}
+
+/// Hash of the static assets as they were baked when the binary was built
+pub static EXPECTED_BUNDLE_HASH: &str = env!("ASSET_BUNDLE_SHA256");
+
+/// Hash of the static assets as they are embedded in this binary
+pub fn embedded_bundle_hash() -> String {
+    bundle_hash(ASSETS.iter().map(|a| (a.path, a.data)))
+}
+
+/// Makes sure the assets served are the ones that were built, e.g. that the binary was not
+/// corrupted or patched in transit.
+pub fn verify() -> Result<String, String> {
+    let embedded = embedded_bundle_hash();
+    if embedded == EXPECTED_BUNDLE_HASH {
+        Ok(format!("bundle hash [{}]", embedded))
+    } else {
+        Err(format!(
+            "bundle hash [{}] does not match the built one [{}]",
+            embedded, EXPECTED_BUNDLE_HASH
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_hash() {
+        let files = vec![("b.js", &b"b"[..]), ("a.html", &b"a"[..])];
+        let hash = bundle_hash(files.clone());
+        assert_eq!(64, hash.len());
+        assert_eq!(hash, bundle_hash(files.into_iter().rev()));
+        assert_ne!(hash, bundle_hash(vec![("a.html", &b"ab"[..])]));
+    }
+
+    #[test]
+    fn test_baked() {
+        let bundle = get("swagger-ui-bundle.js").unwrap();
+        assert_eq!("text/javascript", bundle.mime_type);
+        assert!(std::ptr::eq(bundle, get(bundle.hashed_path).unwrap()));
+        assert!(bundle.hashed_path.starts_with("swagger-ui-bundle."));
+        assert!(bundle.hashed_path.ends_with(".js"));
+        assert!(bundle.gzip.unwrap().len() < bundle.data.len());
+        assert!(bundle.brotli.unwrap().len() < bundle.gzip.unwrap().len());
+        // Images are compressed already
+        assert!(get("favicon-16x16.png").unwrap().gzip.is_none());
+
+        let index = std::str::from_utf8(get("index.html").unwrap().data).unwrap();
+        assert!(index.contains(&format!("src=\"{}\"", bundle.hashed_path)));
+        assert!(!index.contains("src=\"swagger-ui-bundle.js\""));
+        assert_eq!(EXPECTED_BUNDLE_HASH, embedded_bundle_hash());
+    }
+}
@@ -0,0 +1,49 @@
+use crate::models::history as api_models;
+use crate::models::todo::TodoId;
+use async_trait::async_trait;
+use domain::services::history_service::{HistoryService, HistoryServiceLookupErr};
+use thiserror::Error;
+
+#[async_trait]
+pub trait HistoryController {
+    async fn history(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<Vec<api_models::TodoChange>, HistoryControllerLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct HistoryControllerImpl<A: HistoryService + Sync> {
+    history_service: A,
+}
+
+pub fn new<A: HistoryService + Sync>(history_service: A) -> HistoryControllerImpl<A> {
+    HistoryControllerImpl { history_service }
+}
+
+#[async_trait]
+impl<A: HistoryService + Sync> HistoryController for HistoryControllerImpl<A> {
+    async fn history(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<Vec<api_models::TodoChange>, HistoryControllerLookupErr> {
+        let changes = self.history_service.history(&todo_id.into()).await?;
+        Ok(changes.into_iter().map(|v| v.into()).collect())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HistoryControllerLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NotFound(TodoId),
+}
+
+impl From<HistoryServiceLookupErr> for HistoryControllerLookupErr {
+    fn from(e: HistoryServiceLookupErr) -> Self {
+        match e {
+            HistoryServiceLookupErr::NotFound(id) => {
+                HistoryControllerLookupErr::NotFound(id.into())
+            }
+        }
+    }
+}
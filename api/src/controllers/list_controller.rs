@@ -0,0 +1,130 @@
+use crate::controllers::todo_controller::TodoControllerLookupErr;
+use crate::models::list as api_models;
+use crate::models::todo::Todo;
+use async_trait::async_trait;
+use domain::services::list_service::{
+    ListService, ListServiceDataErr, ListServiceDeleteErr, ListServiceLookupErr,
+};
+use domain::services::todo_service::TodoServiceLookupErr;
+use thiserror::Error;
+
+#[async_trait]
+pub trait ListController {
+    async fn create(
+        &self,
+        list_data: &api_models::ListData,
+    ) -> Result<api_models::List, ListControllerDataErr>;
+    async fn get(
+        &self,
+        list_id: &api_models::ListId,
+    ) -> Result<api_models::List, ListControllerLookupErr>;
+    async fn list(&self) -> Vec<api_models::List>;
+    async fn delete(&self, list_id: &api_models::ListId) -> Result<(), ListControllerDeleteErr>;
+    async fn todos(
+        &self,
+        list_id: &api_models::ListId,
+    ) -> Result<Vec<Todo>, ListControllerLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct ListControllerImpl<A: ListService + Sync> {
+    list_service: A,
+}
+
+pub fn new<A: ListService + Sync>(list_service: A) -> ListControllerImpl<A> {
+    ListControllerImpl { list_service }
+}
+
+#[async_trait]
+impl<A: ListService + Sync> ListController for ListControllerImpl<A> {
+    async fn create(
+        &self,
+        list_data: &api_models::ListData,
+    ) -> Result<api_models::List, ListControllerDataErr> {
+        let as_domain_data = list_data.into();
+        let domain_list = self.list_service.create(&as_domain_data).await?;
+        Ok(domain_list.into())
+    }
+
+    async fn get(
+        &self,
+        list_id: &api_models::ListId,
+    ) -> Result<api_models::List, ListControllerLookupErr> {
+        let domain_list = self.list_service.get(&list_id.into()).await?;
+        Ok(domain_list.into())
+    }
+
+    async fn list(&self) -> Vec<api_models::List> {
+        let domain_lists = self.list_service.list().await;
+        domain_lists.into_iter().map(|v| v.into()).collect()
+    }
+
+    async fn delete(&self, list_id: &api_models::ListId) -> Result<(), ListControllerDeleteErr> {
+        Ok(self.list_service.delete(&list_id.into()).await?)
+    }
+
+    async fn todos(
+        &self,
+        list_id: &api_models::ListId,
+    ) -> Result<Vec<Todo>, ListControllerLookupErr> {
+        let domain_todos = self.list_service.todos(&list_id.into()).await?;
+        Ok(domain_todos.into_iter().map(|v| v.into()).collect())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ListControllerLookupErr {
+    #[error("No such list: [{0:?}]")]
+    NotFound(api_models::ListId),
+    #[error(transparent)]
+    TodosFailed(TodoControllerLookupErr),
+}
+
+#[derive(Error, Debug)]
+pub enum ListControllerDataErr {
+    #[error("Invalid list: {reason}")]
+    InvalidData { reason: String },
+}
+
+#[derive(Error, Debug)]
+pub enum ListControllerDeleteErr {
+    #[error("No such list: [{0:?}]")]
+    NotFound(api_models::ListId),
+    #[error("List [{0:?}] still has todos")]
+    NotEmpty(api_models::ListId),
+    #[error(transparent)]
+    TodosFailed(TodoControllerLookupErr),
+}
+
+impl From<ListServiceLookupErr> for ListControllerLookupErr {
+    fn from(e: ListServiceLookupErr) -> Self {
+        match e {
+            ListServiceLookupErr::NotFound(id) => ListControllerLookupErr::NotFound(id.into()),
+            ListServiceLookupErr::TodosFailed(e) => {
+                ListControllerLookupErr::TodosFailed(TodoServiceLookupErr::from(e).into())
+            }
+        }
+    }
+}
+
+impl From<ListServiceDataErr> for ListControllerDataErr {
+    fn from(e: ListServiceDataErr) -> Self {
+        match e {
+            ListServiceDataErr::InvalidData { reason } => {
+                ListControllerDataErr::InvalidData { reason }
+            }
+        }
+    }
+}
+
+impl From<ListServiceDeleteErr> for ListControllerDeleteErr {
+    fn from(e: ListServiceDeleteErr) -> Self {
+        match e {
+            ListServiceDeleteErr::NotFound(id) => ListControllerDeleteErr::NotFound(id.into()),
+            ListServiceDeleteErr::NotEmpty(id) => ListControllerDeleteErr::NotEmpty(id.into()),
+            ListServiceDeleteErr::TodosFailed(e) => {
+                ListControllerDeleteErr::TodosFailed(TodoServiceLookupErr::from(e).into())
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::models::recurrence as api_models;
+use crate::models::todo::TodoId;
+use async_trait::async_trait;
+use domain::services::recurrence_service::{
+    RecurrenceService, RecurrenceServiceLookupErr, RecurrenceServiceSetErr,
+};
+use thiserror::Error;
+
+#[async_trait]
+pub trait RecurrenceController {
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        recurrence: &api_models::Recurrence,
+    ) -> Result<(), RecurrenceControllerSetErr>;
+    async fn get(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<api_models::Recurrence, RecurrenceControllerLookupErr>;
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), RecurrenceControllerLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct RecurrenceControllerImpl<A: RecurrenceService + Sync> {
+    recurrence_service: A,
+}
+
+pub fn new<A: RecurrenceService + Sync>(recurrence_service: A) -> RecurrenceControllerImpl<A> {
+    RecurrenceControllerImpl { recurrence_service }
+}
+
+#[async_trait]
+impl<A: RecurrenceService + Sync> RecurrenceController for RecurrenceControllerImpl<A> {
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        recurrence: &api_models::Recurrence,
+    ) -> Result<(), RecurrenceControllerSetErr> {
+        Ok(self
+            .recurrence_service
+            .set(&todo_id.into(), &recurrence.into())
+            .await?)
+    }
+
+    async fn get(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<api_models::Recurrence, RecurrenceControllerLookupErr> {
+        let recurrence = self.recurrence_service.get(&todo_id.into()).await?;
+        Ok(recurrence.into())
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), RecurrenceControllerLookupErr> {
+        Ok(self.recurrence_service.delete(&todo_id.into()).await?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RecurrenceControllerLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Todo [{0:?}] doesn't recur")]
+    NotRecurring(TodoId),
+}
+
+impl From<RecurrenceServiceLookupErr> for RecurrenceControllerLookupErr {
+    fn from(e: RecurrenceServiceLookupErr) -> Self {
+        match e {
+            RecurrenceServiceLookupErr::NoSuchTodo(id) => {
+                RecurrenceControllerLookupErr::NoSuchTodo(id.into())
+            }
+            RecurrenceServiceLookupErr::NotRecurring(id) => {
+                RecurrenceControllerLookupErr::NotRecurring(id.into())
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RecurrenceControllerSetErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Invalid recurrence: {reason}")]
+    InvalidData { reason: String },
+}
+
+impl From<RecurrenceServiceSetErr> for RecurrenceControllerSetErr {
+    fn from(e: RecurrenceServiceSetErr) -> Self {
+        match e {
+            RecurrenceServiceSetErr::NoSuchTodo(id) => {
+                RecurrenceControllerSetErr::NoSuchTodo(id.into())
+            }
+            RecurrenceServiceSetErr::InvalidData { reason } => {
+                RecurrenceControllerSetErr::InvalidData { reason }
+            }
+        }
+    }
+}
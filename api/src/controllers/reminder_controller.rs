@@ -0,0 +1,98 @@
+use crate::models::reminder as api_models;
+use crate::models::todo::TodoId;
+use async_trait::async_trait;
+use domain::reminder::parse_remind_at;
+use domain::services::reminder_service::{ReminderService, ReminderServiceLookupErr};
+use thiserror::Error;
+
+#[async_trait]
+pub trait ReminderController {
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        reminder_data: &api_models::ReminderData,
+    ) -> Result<api_models::Reminder, ReminderControllerSetErr>;
+    async fn get(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<api_models::Reminder, ReminderControllerLookupErr>;
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), ReminderControllerLookupErr>;
+    async fn pending(&self) -> Vec<api_models::Reminder>;
+}
+
+#[derive(Clone)]
+pub struct ReminderControllerImpl<A: ReminderService + Sync> {
+    reminder_service: A,
+}
+
+pub fn new<A: ReminderService + Sync>(reminder_service: A) -> ReminderControllerImpl<A> {
+    ReminderControllerImpl { reminder_service }
+}
+
+#[async_trait]
+impl<A: ReminderService + Sync> ReminderController for ReminderControllerImpl<A> {
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        reminder_data: &api_models::ReminderData,
+    ) -> Result<api_models::Reminder, ReminderControllerSetErr> {
+        let remind_at = parse_remind_at(&reminder_data.remind_at)
+            .map_err(|reason| ReminderControllerSetErr::InvalidData { reason })?;
+        let reminder = self
+            .reminder_service
+            .set(&todo_id.into(), remind_at)
+            .await
+            .map_err(|_| ReminderControllerSetErr::NoSuchTodo(*todo_id))?;
+        Ok(reminder.into())
+    }
+
+    async fn get(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<api_models::Reminder, ReminderControllerLookupErr> {
+        let reminder = self.reminder_service.get(&todo_id.into()).await?;
+        Ok(reminder.into())
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), ReminderControllerLookupErr> {
+        Ok(self.reminder_service.delete(&todo_id.into()).await?)
+    }
+
+    async fn pending(&self) -> Vec<api_models::Reminder> {
+        self.reminder_service
+            .pending()
+            .await
+            .into_iter()
+            .map(|r| r.into())
+            .collect()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReminderControllerLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Todo [{0:?}] has no reminder")]
+    NoReminder(TodoId),
+}
+
+impl From<ReminderServiceLookupErr> for ReminderControllerLookupErr {
+    fn from(e: ReminderServiceLookupErr) -> Self {
+        match e {
+            ReminderServiceLookupErr::NoSuchTodo(id) => {
+                ReminderControllerLookupErr::NoSuchTodo(id.into())
+            }
+            ReminderServiceLookupErr::NoReminder(id) => {
+                ReminderControllerLookupErr::NoReminder(id.into())
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReminderControllerSetErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Invalid reminder: {reason}")]
+    InvalidData { reason: String },
+}
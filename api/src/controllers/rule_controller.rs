@@ -0,0 +1,166 @@
+use crate::models::rule as api_models;
+use async_trait::async_trait;
+use domain::services::rule_service::{RuleService, RuleServiceDataErr, RuleServiceLookupErr};
+use thiserror::Error;
+
+#[async_trait]
+pub trait RuleController {
+    async fn create(
+        &self,
+        rule_data: &api_models::RuleData,
+    ) -> Result<api_models::Rule, RuleControllerDataErr>;
+    async fn list(&self) -> Vec<api_models::Rule>;
+    async fn delete(&self, rule_id: &api_models::RuleId) -> Result<(), RuleControllerLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct RuleControllerImpl<A: RuleService + Sync> {
+    rule_service: A,
+}
+
+pub fn new<A: RuleService + Sync>(rule_service: A) -> RuleControllerImpl<A> {
+    RuleControllerImpl { rule_service }
+}
+
+#[async_trait]
+impl<A: RuleService + Sync> RuleController for RuleControllerImpl<A> {
+    async fn create(
+        &self,
+        rule_data: &api_models::RuleData,
+    ) -> Result<api_models::Rule, RuleControllerDataErr> {
+        let as_domain_data = rule_data.into();
+        let domain_rule = self.rule_service.create(&as_domain_data).await?;
+        Ok(domain_rule.into())
+    }
+
+    async fn list(&self) -> Vec<api_models::Rule> {
+        let domain_rules = self.rule_service.list().await;
+        domain_rules.into_iter().map(|v| v.into()).collect()
+    }
+
+    async fn delete(&self, rule_id: &api_models::RuleId) -> Result<(), RuleControllerLookupErr> {
+        let domain_id = rule_id.into();
+        Ok(self.rule_service.delete(&domain_id).await?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RuleControllerLookupErr {
+    #[error("No such rule: [{0:?}]")]
+    NotFound(api_models::RuleId),
+}
+
+impl From<RuleServiceLookupErr> for RuleControllerLookupErr {
+    fn from(e: RuleServiceLookupErr) -> Self {
+        match e {
+            RuleServiceLookupErr::NotFound(id) => RuleControllerLookupErr::NotFound(id.into()),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RuleControllerDataErr {
+    #[error("Invalid rule: {reason}")]
+    InvalidData { reason: String },
+}
+
+impl From<RuleServiceDataErr> for RuleControllerDataErr {
+    fn from(e: RuleServiceDataErr) -> Self {
+        match e {
+            RuleServiceDataErr::InvalidData { reason } => {
+                RuleControllerDataErr::InvalidData { reason }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::rule::{Rule, RuleData, RuleId};
+    use futures::executor::block_on;
+    use std::sync::*;
+
+    static NOT_FOUND_RULE_ID: api_models::RuleId = api_models::RuleId(999);
+
+    #[test]
+    fn test_create_ok() {
+        let mock_service = MockRuleService::new();
+        let controller = new(mock_service.clone());
+        let rule_data = api_models::RuleData {
+            name: "follow up".to_string(),
+            when: api_models::RuleCondition {
+                event: api_models::RuleTrigger::Updated,
+                task_contains: Some("bug".to_string()),
+            },
+            then: api_models::RuleAction {
+                create_todo: "Test {{task}}".to_string(),
+            },
+        };
+        match block_on(controller.create(&rule_data)) {
+            Ok(saved) => {
+                assert_eq!(api_models::RuleId(1), saved.id);
+                assert_eq!(rule_data.when, saved.when);
+                assert_eq!(rule_data.then, saved.then);
+                assert_eq!(1, *mock_service.create_called.lock().unwrap());
+            }
+            _ => panic!("creation failed"),
+        }
+    }
+
+    #[test]
+    fn test_delete_not_found() {
+        let mock_service = MockRuleService::new();
+        let controller = new(mock_service.clone());
+        match block_on(controller.delete(&NOT_FOUND_RULE_ID)) {
+            Err(RuleControllerLookupErr::NotFound(id)) => {
+                assert_eq!(NOT_FOUND_RULE_ID, id);
+                assert_eq!(1, *mock_service.delete_called.lock().unwrap());
+            }
+            _ => panic!("lookup failed"),
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockRuleService {
+        create_called: Arc<Mutex<usize>>,
+        delete_called: Arc<Mutex<usize>>,
+    }
+
+    impl MockRuleService {
+        fn new() -> MockRuleService {
+            MockRuleService {
+                create_called: Arc::new(Mutex::new(0)),
+                delete_called: Arc::new(Mutex::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl RuleService for MockRuleService {
+        async fn create(&self, rule_data: &RuleData) -> Result<Rule, RuleServiceDataErr> {
+            let mut mutex = self.create_called.lock().unwrap();
+            *mutex += 1;
+            Ok(Rule {
+                id: RuleId(1),
+                name: rule_data.name.clone(),
+                when: rule_data.when.clone(),
+                then: rule_data.then.clone(),
+            })
+        }
+
+        async fn list(&self) -> Vec<Rule> {
+            Vec::new()
+        }
+
+        async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleServiceLookupErr> {
+            let mut mutex = self.delete_called.lock().unwrap();
+            *mutex += 1;
+            if rule_id.0 == NOT_FOUND_RULE_ID.0 {
+                Err(RuleServiceLookupErr::NotFound(*rule_id))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::models::share as api_models;
+use crate::models::todo::{Todo, TodoId};
+use async_trait::async_trait;
+use domain::services::share_service::{ShareService, ShareServiceCreateErr, ShareServiceLookupErr};
+use thiserror::Error;
+
+#[async_trait]
+pub trait ShareController {
+    async fn create(
+        &self,
+        todo_id: &TodoId,
+        share_data: &api_models::ShareLinkData,
+    ) -> Result<api_models::ShareLink, ShareControllerCreateErr>;
+    async fn list(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<Vec<api_models::ShareLink>, ShareControllerLookupErr>;
+    async fn revoke(
+        &self,
+        todo_id: &TodoId,
+        token: &api_models::ShareToken,
+    ) -> Result<(), ShareControllerLookupErr>;
+    async fn shared(
+        &self,
+        token: &api_models::ShareToken,
+    ) -> Result<Todo, ShareControllerLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct ShareControllerImpl<A: ShareService + Sync> {
+    share_service: A,
+}
+
+pub fn new<A: ShareService + Sync>(share_service: A) -> ShareControllerImpl<A> {
+    ShareControllerImpl { share_service }
+}
+
+#[async_trait]
+impl<A: ShareService + Sync> ShareController for ShareControllerImpl<A> {
+    async fn create(
+        &self,
+        todo_id: &TodoId,
+        share_data: &api_models::ShareLinkData,
+    ) -> Result<api_models::ShareLink, ShareControllerCreateErr> {
+        let share = self
+            .share_service
+            .create(&todo_id.into(), &share_data.into())
+            .await?;
+        Ok(share.into())
+    }
+
+    async fn list(
+        &self,
+        todo_id: &TodoId,
+    ) -> Result<Vec<api_models::ShareLink>, ShareControllerLookupErr> {
+        let shares = self.share_service.list(&todo_id.into()).await?;
+        Ok(shares.into_iter().map(|v| v.into()).collect())
+    }
+
+    async fn revoke(
+        &self,
+        todo_id: &TodoId,
+        token: &api_models::ShareToken,
+    ) -> Result<(), ShareControllerLookupErr> {
+        Ok(self
+            .share_service
+            .revoke(&todo_id.into(), &token.into())
+            .await?)
+    }
+
+    async fn shared(
+        &self,
+        token: &api_models::ShareToken,
+    ) -> Result<Todo, ShareControllerLookupErr> {
+        let todo = self.share_service.shared(&token.into()).await?;
+        Ok(todo.into())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ShareControllerLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("No such share")]
+    NoSuchShare,
+}
+
+impl From<ShareServiceLookupErr> for ShareControllerLookupErr {
+    fn from(e: ShareServiceLookupErr) -> Self {
+        match e {
+            ShareServiceLookupErr::NoSuchTodo(id) => {
+                ShareControllerLookupErr::NoSuchTodo(id.into())
+            }
+            ShareServiceLookupErr::NoSuchShare => ShareControllerLookupErr::NoSuchShare,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ShareControllerCreateErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Invalid share: {reason}")]
+    InvalidData { reason: String },
+}
+
+impl From<ShareServiceCreateErr> for ShareControllerCreateErr {
+    fn from(e: ShareServiceCreateErr) -> Self {
+        match e {
+            ShareServiceCreateErr::NoSuchTodo(id) => {
+                ShareControllerCreateErr::NoSuchTodo(id.into())
+            }
+            ShareServiceCreateErr::InvalidData { reason } => {
+                ShareControllerCreateErr::InvalidData { reason }
+            }
+        }
+    }
+}
@@ -1,22 +1,78 @@
+use crate::models::list::ListId;
 use crate::models::todo as api_models;
 use async_trait::async_trait;
 use domain::services::todo_service::{
     TodoService, TodoServiceDataErr, TodoServiceLookupErr, TodoServiceUpdateErr,
 };
+use domain::todo::TodoSelection;
+use futures::stream::{BoxStream, StreamExt};
+use std::convert::TryFrom;
+use std::time::Duration;
+use thiserror::Error;
 
 #[async_trait]
 pub trait TodoController {
     async fn create(
         &self,
         todo_data: &api_models::TodoData,
-    ) -> Result<api_models::Todo, TodoControllerDataErr>;
+    ) -> Result<api_models::SavedTodo, TodoControllerDataErr>;
     async fn get(
         &self,
         todo_id: &api_models::TodoId,
     ) -> Result<api_models::Todo, TodoControllerLookupErr>;
-    async fn list(&self) -> Vec<api_models::Todo>;
-    async fn update(&self, todo: &api_models::Todo) -> Result<(), TodoControllerUpdateErr>;
+    async fn list(&self) -> Result<Vec<api_models::Todo>, TodoControllerLookupErr>;
+    /// Todos in id order, skipping the first `offset` and returning at most `limit`; controllers
+    /// that can should avoid listing every todo for it
+    async fn list_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<api_models::Todo>, TodoControllerLookupErr> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
+    /// Fails with a conflict when an expected version is given and the todo is at another one
+    async fn update(
+        &self,
+        todo: &api_models::Todo,
+        expected_version: Option<u64>,
+    ) -> Result<api_models::SavedTodo, TodoControllerUpdateErr>;
     async fn delete(&self, todo_id: &api_models::TodoId) -> Result<(), TodoControllerLookupErr>;
+    /// Dry runs of the above, which change nothing
+    async fn plan_create(
+        &self,
+        todo_data: &api_models::TodoData,
+    ) -> Result<api_models::TodoPlan, TodoControllerDataErr>;
+    async fn plan_update(
+        &self,
+        todo: &api_models::Todo,
+        expected_version: Option<u64>,
+    ) -> Result<api_models::TodoPlan, TodoControllerUpdateErr>;
+    async fn plan_delete(
+        &self,
+        todo_id: &api_models::TodoId,
+    ) -> Result<api_models::TodoPlan, TodoControllerLookupErr>;
+    /// Marks the selected todos done all at once
+    async fn complete(
+        &self,
+        selection: &api_models::CompleteTodos,
+    ) -> Result<api_models::CompletedTodos, TodoControllerCompleteErr>;
+    /// Puts the todos with the given ids first, returning every todo in the new manual order
+    async fn reorder(
+        &self,
+        order: &api_models::ReorderTodos,
+    ) -> Result<Vec<api_models::Todo>, TodoControllerReorderErr>;
+    /// An open todo that creating one from the data would duplicate, if any
+    async fn find_duplicate(
+        &self,
+        todo_data: &api_models::TodoData,
+    ) -> Result<Option<api_models::Todo>, TodoControllerLookupErr>;
+    fn subscribe(&self) -> BoxStream<'static, api_models::TodoEvent>;
 }
 
 #[derive(Clone)]
@@ -33,7 +89,7 @@ impl<A: TodoService + Sync> TodoController for TodoControllerImpl<A> {
     async fn create(
         &self,
         todo_data: &api_models::TodoData,
-    ) -> Result<api_models::Todo, TodoControllerDataErr> {
+    ) -> Result<api_models::SavedTodo, TodoControllerDataErr> {
         let as_domain_data = todo_data.into();
         let domain_todo = self.todo_service.create(&as_domain_data).await?;
         Ok(domain_todo.into())
@@ -48,51 +104,205 @@ impl<A: TodoService + Sync> TodoController for TodoControllerImpl<A> {
         Ok(domain_todo.into())
     }
 
-    async fn list(&self) -> Vec<api_models::Todo> {
-        let domain_todos = self.todo_service.list().await;
-        domain_todos.into_iter().map(|v| v.into()).collect()
+    async fn list(&self) -> Result<Vec<api_models::Todo>, TodoControllerLookupErr> {
+        let domain_todos = self.todo_service.list().await?;
+        Ok(domain_todos.into_iter().map(|v| v.into()).collect())
+    }
+
+    async fn list_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<api_models::Todo>, TodoControllerLookupErr> {
+        let domain_todos = self.todo_service.list_page(offset, limit).await?;
+        Ok(domain_todos.into_iter().map(|v| v.into()).collect())
     }
 
-    async fn update(&self, todo: &api_models::Todo) -> Result<(), TodoControllerUpdateErr> {
+    async fn update(
+        &self,
+        todo: &api_models::Todo,
+        expected_version: Option<u64>,
+    ) -> Result<api_models::SavedTodo, TodoControllerUpdateErr> {
         let as_domain_todo = todo.into();
-        Ok(self.todo_service.update(&as_domain_todo).await?)
+        let updated = self
+            .todo_service
+            .update(&as_domain_todo, expected_version)
+            .await?;
+        Ok(updated.into())
     }
 
     async fn delete(&self, todo_id: &api_models::TodoId) -> Result<(), TodoControllerLookupErr> {
         let domain_id = todo_id.into();
         Ok(self.todo_service.delete(&domain_id).await?)
     }
+
+    async fn plan_create(
+        &self,
+        todo_data: &api_models::TodoData,
+    ) -> Result<api_models::TodoPlan, TodoControllerDataErr> {
+        let as_domain_data = todo_data.into();
+        let plan = self.todo_service.plan_create(&as_domain_data).await?;
+        Ok(plan.into())
+    }
+
+    async fn plan_update(
+        &self,
+        todo: &api_models::Todo,
+        expected_version: Option<u64>,
+    ) -> Result<api_models::TodoPlan, TodoControllerUpdateErr> {
+        let as_domain_todo = todo.into();
+        let plan = self
+            .todo_service
+            .plan_update(&as_domain_todo, expected_version)
+            .await?;
+        Ok(plan.into())
+    }
+
+    async fn plan_delete(
+        &self,
+        todo_id: &api_models::TodoId,
+    ) -> Result<api_models::TodoPlan, TodoControllerLookupErr> {
+        let domain_id = todo_id.into();
+        let plan = self.todo_service.plan_delete(&domain_id).await?;
+        Ok(plan.into())
+    }
+
+    async fn complete(
+        &self,
+        selection: &api_models::CompleteTodos,
+    ) -> Result<api_models::CompletedTodos, TodoControllerCompleteErr> {
+        let as_domain_selection = TodoSelection::try_from(selection)
+            .map_err(TodoControllerCompleteErr::InvalidSelection)?;
+        let completed = self
+            .todo_service
+            .complete(&as_domain_selection)
+            .await
+            .map_err(|e| TodoControllerCompleteErr::DataErr(e.into()))?;
+        Ok(completed.into())
+    }
+
+    async fn reorder(
+        &self,
+        order: &api_models::ReorderTodos,
+    ) -> Result<Vec<api_models::Todo>, TodoControllerReorderErr> {
+        order
+            .validate()
+            .map_err(TodoControllerReorderErr::InvalidOrder)?;
+        let domain_ids: Vec<_> = order.ids.iter().map(|id| id.into()).collect();
+        let reordered = self
+            .todo_service
+            .reorder(&domain_ids)
+            .await
+            .map_err(|e| TodoControllerReorderErr::LookupErr(e.into()))?;
+        Ok(reordered.into_iter().map(|v| v.into()).collect())
+    }
+
+    async fn find_duplicate(
+        &self,
+        todo_data: &api_models::TodoData,
+    ) -> Result<Option<api_models::Todo>, TodoControllerLookupErr> {
+        let as_domain_data = todo_data.into();
+        let found = self.todo_service.find_duplicate(&as_domain_data).await?;
+        Ok(found.map(|v| v.into()))
+    }
+
+    fn subscribe(&self) -> BoxStream<'static, api_models::TodoEvent> {
+        self.todo_service.subscribe().map(|c| c.into()).boxed()
+    }
 }
 
+#[derive(Error, Debug)]
 pub enum TodoControllerUpdateErr {
+    #[error(transparent)]
     LookupErr(TodoControllerLookupErr),
+    #[error(transparent)]
     DataErr(TodoControllerDataErr),
+    #[error("Todo [{0:?}] has been updated since the expected version")]
+    Conflict(api_models::TodoId),
+    #[error(
+        "Todo [{id:?}] has been changed too often lately; retry in {}s",
+        .retry_after.as_secs()
+    )]
+    Flooded {
+        id: api_models::TodoId,
+        retry_after: Duration,
+    },
 }
 
+#[derive(Error, Debug)]
 pub enum TodoControllerLookupErr {
+    #[error("No such todo: [{0:?}]")]
     NotFound(api_models::TodoId),
+    #[error("Could not connect to the todos' storage: {0}")]
+    ConnectionError(String),
+    #[error("Another write to todo [{0:?}] got in the way")]
+    Conflict(api_models::TodoId),
+    #[error("The todos' storage timed out")]
+    Timeout,
+    #[error(transparent)]
+    Other(anyhow::Error),
 }
 
 impl From<TodoServiceLookupErr> for TodoControllerLookupErr {
     fn from(e: TodoServiceLookupErr) -> Self {
         match e {
             TodoServiceLookupErr::NotFound(id) => TodoControllerLookupErr::NotFound(id.into()),
+            TodoServiceLookupErr::ConnectionError(reason) => {
+                TodoControllerLookupErr::ConnectionError(reason)
+            }
+            TodoServiceLookupErr::Conflict(id) => TodoControllerLookupErr::Conflict(id.into()),
+            TodoServiceLookupErr::Timeout => TodoControllerLookupErr::Timeout,
+            TodoServiceLookupErr::Other(e) => TodoControllerLookupErr::Other(e),
         }
     }
 }
 
+#[derive(Error, Debug)]
 pub enum TodoControllerDataErr {
+    #[error("Invalid task: [{task}]")]
     InvalidData { task: String },
+    #[error("Rejected: {reason}")]
+    Rejected { reason: String },
+    #[error("Task [{task}] is against the policy: {reason}")]
+    Disallowed { task: String, reason: String },
+    #[error("No such list: [{0:?}]")]
+    NoSuchList(ListId),
+    #[error(transparent)]
+    Failed(TodoControllerLookupErr),
 }
 
 impl From<TodoServiceDataErr> for TodoControllerDataErr {
     fn from(e: TodoServiceDataErr) -> Self {
         match e {
             TodoServiceDataErr::InvalidData { task } => TodoControllerDataErr::InvalidData { task },
+            TodoServiceDataErr::Rejected { reason } => TodoControllerDataErr::Rejected { reason },
+            TodoServiceDataErr::Disallowed { task, reason } => {
+                TodoControllerDataErr::Disallowed { task, reason }
+            }
+            TodoServiceDataErr::NoSuchList(id) => TodoControllerDataErr::NoSuchList(id.into()),
+            TodoServiceDataErr::Failed(e) => TodoControllerDataErr::Failed(e.into()),
         }
     }
 }
 
+#[derive(Error, Debug)]
+pub enum TodoControllerCompleteErr {
+    // Neither or both of ids and a list were given
+    #[error("Invalid selection: {0}")]
+    InvalidSelection(String),
+    #[error(transparent)]
+    DataErr(TodoControllerDataErr),
+}
+
+#[derive(Error, Debug)]
+pub enum TodoControllerReorderErr {
+    // The same id was given more than once
+    #[error("Invalid order: {0}")]
+    InvalidOrder(String),
+    #[error(transparent)]
+    LookupErr(TodoControllerLookupErr),
+}
+
 impl From<TodoServiceUpdateErr> for TodoControllerUpdateErr {
     fn from(err: TodoServiceUpdateErr) -> Self {
         match err {
@@ -100,6 +310,11 @@ impl From<TodoServiceUpdateErr> for TodoControllerUpdateErr {
             TodoServiceUpdateErr::LookupErr(inner) => {
                 TodoControllerUpdateErr::LookupErr(inner.into())
             }
+            TodoServiceUpdateErr::Conflict(id) => TodoControllerUpdateErr::Conflict(id.into()),
+            TodoServiceUpdateErr::Flooded { id, retry_after } => TodoControllerUpdateErr::Flooded {
+                id: id.into(),
+                retry_after,
+            },
         }
     }
 }
@@ -107,7 +322,9 @@ impl From<TodoServiceUpdateErr> for TodoControllerUpdateErr {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use domain::todo::{Todo, TodoData, TodoId};
+    use domain::events::{TodoDeleted, TodoEvent};
+    use domain::todo::{Completed, SavedTodo, Todo, TodoData, TodoId, TodoPlan};
+    use futures::channel::mpsc::{unbounded, UnboundedReceiver};
     use futures::executor::block_on;
     use std::sync::*;
 
@@ -122,6 +339,8 @@ mod tests {
         let f_created = async {
             let todo_data = api_models::TodoData {
                 task: "say hello".to_string(),
+                done: false,
+                list_id: None,
             };
             controller.create(&todo_data).await
         };
@@ -141,6 +360,8 @@ mod tests {
         let f_created = async {
             let todo_data = api_models::TodoData {
                 task: INVALID_TASK.to_string(),
+                done: false,
+                list_id: None,
             };
             controller.create(&todo_data).await
         };
@@ -187,8 +408,12 @@ mod tests {
             vec![api_models::Todo {
                 id: api_models::TodoId(1),
                 task: RETRIEVED_TODO_TASK.to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
             }],
-            block_on(f_listed)
+            block_on(f_listed).unwrap()
         );
         assert_eq!(1, *mock_service.list_called.lock().unwrap());
     }
@@ -227,11 +452,16 @@ mod tests {
             let todo = api_models::Todo {
                 id: api_models::TodoId(1),
                 task: "hello world".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
             };
-            controller.update(&todo).await
+            controller.update(&todo, None).await
         };
         match block_on(f_updated) {
-            Ok(_) => {
+            Ok(updated) => {
+                assert_eq!(1, updated.warnings.len());
                 assert_eq!(1, *mock_service.update_called.lock().unwrap());
             }
             _ => panic!("lookup failed"),
@@ -244,10 +474,14 @@ mod tests {
         let controller = new(mock_service.clone());
         let f_updated = async {
             let todo = api_models::Todo {
-                id: NOT_FOUND_TODO_ID.into(),
+                id: NOT_FOUND_TODO_ID,
                 task: "hello world".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 0,
             };
-            controller.update(&todo).await
+            controller.update(&todo, None).await
         };
         match block_on(f_updated) {
             Err(TodoControllerUpdateErr::LookupErr(_)) => {
@@ -265,8 +499,12 @@ mod tests {
             let todo = api_models::Todo {
                 id: api_models::TodoId(1),
                 task: INVALID_TASK.to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
             };
-            controller.update(&todo).await
+            controller.update(&todo, None).await
         };
         match block_on(f_updated) {
             Err(TodoControllerUpdateErr::DataErr(_)) => {
@@ -276,6 +514,86 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_plan_delete() {
+        let mock_service = MockTodoService::new();
+        let controller = new(mock_service.clone());
+        match block_on(controller.plan_delete(&api_models::TodoId(1))) {
+            Ok(plan) => {
+                assert_eq!(
+                    vec![api_models::PlannedEvent {
+                        event: api_models::EventKind::Deleted,
+                        todo: None,
+                        id: Some(api_models::TodoId(1)),
+                    }],
+                    plan.events
+                );
+                assert_eq!(0, *mock_service.delete_called.lock().unwrap());
+            }
+            _ => panic!("planning failed"),
+        }
+    }
+
+    #[test]
+    fn test_complete() {
+        let controller = new(MockTodoService::new());
+        let by_ids = api_models::CompleteTodos {
+            ids: Some(vec![api_models::TodoId(1)]),
+            list_id: None,
+        };
+        match block_on(controller.complete(&by_ids)) {
+            Ok(completed) => assert_eq!(vec![api_models::TodoId(1)], completed.not_found),
+            _ => panic!("completing failed"),
+        }
+        let by_list = api_models::CompleteTodos {
+            ids: None,
+            list_id: Some(ListId(1)),
+        };
+        match block_on(controller.complete(&by_list)) {
+            Err(TodoControllerCompleteErr::DataErr(TodoControllerDataErr::NoSuchList(id))) => {
+                assert_eq!(ListId(1), id)
+            }
+            _ => panic!("completed a missing list"),
+        }
+        let neither = api_models::CompleteTodos::default();
+        match block_on(controller.complete(&neither)) {
+            Err(TodoControllerCompleteErr::InvalidSelection(_)) => {}
+            _ => panic!("completed nothing"),
+        }
+    }
+
+    #[test]
+    fn test_reorder() {
+        let controller = new(MockTodoService::new());
+        let order = |ids: Vec<u128>| api_models::ReorderTodos {
+            ids: ids.into_iter().map(api_models::TodoId).collect(),
+        };
+        assert!(block_on(controller.reorder(&order(vec![2, 1]))).is_ok());
+        match block_on(controller.reorder(&order(vec![2, NOT_FOUND_TODO_ID.0]))) {
+            Err(TodoControllerReorderErr::LookupErr(TodoControllerLookupErr::NotFound(id))) => {
+                assert_eq!(NOT_FOUND_TODO_ID, id)
+            }
+            _ => panic!("reordered a missing todo"),
+        }
+        match block_on(controller.reorder(&order(vec![2, 1, 2]))) {
+            Err(TodoControllerReorderErr::InvalidOrder(_)) => {}
+            _ => panic!("reordered with a repeated id"),
+        }
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let mock_service = MockTodoService::new();
+        let controller = new(mock_service);
+        let events: Vec<_> = block_on(controller.subscribe().collect());
+        assert_eq!(
+            vec![api_models::TodoEvent::Deleted {
+                id: api_models::TodoId(1)
+            }],
+            events
+        );
+    }
+
     #[derive(Clone)]
     struct MockTodoService {
         create_called: Arc<Mutex<usize>>,
@@ -299,7 +617,7 @@ mod tests {
 
     #[async_trait]
     impl TodoService for MockTodoService {
-        async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoServiceDataErr> {
+        async fn create(&self, todo_data: &TodoData) -> Result<SavedTodo, TodoServiceDataErr> {
             if todo_data.task == INVALID_TASK {
                 Err(TodoServiceDataErr::InvalidData {
                     task: todo_data.task.clone(),
@@ -310,8 +628,15 @@ mod tests {
                 let saved = Todo {
                     id: TodoId(1),
                     task: todo_data.task.clone(),
+                    done: todo_data.done,
+                    version: 1,
+                    list_id: None,
+                    position: 1,
                 };
-                Ok(saved)
+                Ok(SavedTodo {
+                    todo: saved,
+                    warnings: Vec::new(),
+                })
             }
         }
 
@@ -324,17 +649,39 @@ mod tests {
                 Ok(Todo {
                     id: *todo_id,
                     task: RETRIEVED_TODO_TASK.to_string(),
+                    done: false,
+                    version: 1,
+                    list_id: None,
+                    position: 0,
                 })
             }
         }
 
-        async fn list(&self) -> Vec<Todo> {
+        async fn list(&self) -> Result<Vec<Todo>, TodoServiceLookupErr> {
             let mut mutex = self.list_called.lock().unwrap();
             *mutex += 1;
-            vec![Todo {
+            Ok(vec![Todo {
                 id: TodoId(1),
                 task: RETRIEVED_TODO_TASK.to_string(),
-            }]
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
+            }])
+        }
+
+        async fn list_page(
+            &self,
+            offset: usize,
+            limit: usize,
+        ) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+            Ok(self
+                .list()
+                .await?
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .collect())
         }
 
         async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoServiceLookupErr> {
@@ -347,7 +694,11 @@ mod tests {
             }
         }
 
-        async fn update(&self, todo: &Todo) -> Result<(), TodoServiceUpdateErr> {
+        async fn update(
+            &self,
+            todo: &Todo,
+            _: Option<u64>,
+        ) -> Result<SavedTodo, TodoServiceUpdateErr> {
             let mut mutex = self.update_called.lock().unwrap();
             *mutex += 1;
             if todo.task == INVALID_TASK {
@@ -361,8 +712,62 @@ mod tests {
                     TodoServiceLookupErr::NotFound(todo.id),
                 ))
             } else {
-                Ok(())
+                Ok(SavedTodo {
+                    todo: todo.clone(),
+                    warnings: vec!["Todo [TodoId(2)] has the same task".to_string()],
+                })
             }
         }
+
+        async fn plan_create(&self, _: &TodoData) -> Result<TodoPlan, TodoServiceDataErr> {
+            unimplemented!()
+        }
+
+        async fn plan_update(
+            &self,
+            _: &Todo,
+            _: Option<u64>,
+        ) -> Result<TodoPlan, TodoServiceUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn plan_delete(&self, todo_id: &TodoId) -> Result<TodoPlan, TodoServiceLookupErr> {
+            Ok(TodoPlan {
+                todo: None,
+                events: vec![TodoEvent::Deleted(TodoDeleted { id: *todo_id })],
+                warnings: Vec::new(),
+            })
+        }
+
+        async fn complete(
+            &self,
+            selection: &TodoSelection,
+        ) -> Result<Completed, TodoServiceDataErr> {
+            match selection {
+                TodoSelection::Ids(ids) => Ok(Completed {
+                    completed: Vec::new(),
+                    not_found: ids.clone(),
+                    warnings: Vec::new(),
+                }),
+                TodoSelection::List(list_id) => Err(TodoServiceDataErr::NoSuchList(*list_id)),
+            }
+        }
+
+        async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+            match ids.iter().find(|id| id.0 == NOT_FOUND_TODO_ID.0) {
+                Some(id) => Err(TodoServiceLookupErr::NotFound(*id)),
+                None => Ok(Vec::new()),
+            }
+        }
+
+        async fn find_duplicate(&self, _: &TodoData) -> Result<Option<Todo>, TodoServiceLookupErr> {
+            unimplemented!()
+        }
+
+        fn subscribe(&self) -> UnboundedReceiver<TodoEvent> {
+            let (sender, receiver) = unbounded();
+            let _ = sender.unbounded_send(TodoEvent::Deleted(TodoDeleted { id: TodoId(1) }));
+            receiver
+        }
     }
 }
@@ -0,0 +1,198 @@
+use actix_cors::Cors;
+use actix_web::http::Method;
+use actix_web::middleware::Condition;
+use std::str::FromStr;
+
+pub static ANY_ORIGIN: &str = "*";
+pub static DEFAULT_METHODS: [&str; 4] = ["GET", "POST", "PUT", "DELETE"];
+pub static DEFAULT_HEADERS: [&str; 3] = ["content-type", "authorization", "x-request-id"];
+// Lets browsers skip the preflight for repeated calls within this long
+pub static DEFAULT_MAX_AGE_SECS: usize = 3600;
+// Response headers that scripts on other origins get to read
+static EXPOSED_HEADERS: [&str; 5] = ["x-request-id", "etag", "deprecation", "sunset", "link"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsSettings {
+    // Either exact origins, like "https://example.com", or just "*" for any origin
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<Method>,
+    // Lower-cased header names
+    pub allowed_headers: Vec<String>,
+    pub max_age_secs: usize,
+}
+
+/// Handles CORS requests, including preflights, when configured. Without settings, requests are
+/// passed through untouched, so browsers only allow same-origin calls.
+pub fn middleware(settings: &Option<CorsSettings>) -> Condition<Cors> {
+    match settings {
+        Some(settings) => Condition::new(true, cors(settings)),
+        None => Condition::new(false, Cors::default()),
+    }
+}
+
+fn cors(settings: &CorsSettings) -> Cors {
+    let mut cors = Cors::default()
+        .allowed_methods(settings.allowed_methods.clone())
+        .allowed_headers(settings.allowed_headers.iter().map(|h| h.as_str()))
+        .expose_headers(EXPOSED_HEADERS.iter().copied())
+        .max_age(settings.max_age_secs);
+    for origin in &settings.allowed_origins {
+        cors = if origin == ANY_ORIGIN {
+            cors.allow_any_origin()
+        } else {
+            cors.allowed_origin(origin)
+        };
+    }
+    cors
+}
+
+/// Splits a comma-separated list, checking that each origin is "*" or a scheme://host[:port]
+pub fn parse_origins(s: &str) -> Result<Vec<String>, String> {
+    split(s)
+        .map(|origin| {
+            let (scheme, host) = origin.split_once("://").unwrap_or(("", ""));
+            let sensible = origin == ANY_ORIGIN
+                || ((scheme == "http" || scheme == "https")
+                    && !host.is_empty()
+                    && !host.contains('/'));
+            if sensible {
+                Ok(origin.to_string())
+            } else {
+                Err(format!(
+                    "[{}] is not an origin like https://example.com, or *",
+                    origin
+                ))
+            }
+        })
+        .collect()
+}
+
+pub fn parse_methods(s: &str) -> Result<Vec<Method>, String> {
+    split(s)
+        .map(|method| {
+            Method::from_str(&method.to_uppercase())
+                .map_err(|_| format!("[{}] is not an HTTP method", method))
+        })
+        .collect()
+}
+
+pub fn parse_headers(s: &str) -> Result<Vec<String>, String> {
+    split(s)
+        .map(|header| {
+            let valid = header
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+            if valid {
+                Ok(header.to_lowercase())
+            } else {
+                Err(format!("[{}] is not a header name", header))
+            }
+        })
+        .collect()
+}
+
+fn split(s: &str) -> impl Iterator<Item = &str> {
+    s.split(',')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(vec!["https://example.com".to_string(), "*".to_string()]),
+            parse_origins("https://example.com, *,")
+        );
+        assert!(parse_origins("example.com").is_err());
+        assert!(parse_origins("https://example.com/app").is_err());
+        assert_eq!(
+            Ok(vec![Method::PUT, Method::PATCH]),
+            parse_methods("put,PATCH")
+        );
+        assert!(parse_methods("P UT").is_err());
+        assert_eq!(Ok(vec!["x-client".to_string()]), parse_headers("X-Client"));
+        assert!(parse_headers("x:client").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_preflight() {
+        let settings = CorsSettings {
+            allowed_origins: vec!["https://example.com".to_string()],
+            allowed_methods: parse_methods(&DEFAULT_METHODS.join(",")).unwrap(),
+            allowed_headers: vec!["content-type".to_string()],
+            max_age_secs: DEFAULT_MAX_AGE_SECS,
+        };
+        let app = init_service(
+            App::new()
+                .wrap(middleware(&Some(settings)))
+                .route("/tasks/{id}", web::put().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let preflight = |origin: &str| {
+            TestRequest::default()
+                .method(Method::OPTIONS)
+                .uri("/tasks/1")
+                .insert_header((header::ORIGIN, origin))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PUT"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+                .to_request()
+        };
+        let resp = call_service(&app, preflight("https://example.com")).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            "https://example.com",
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap()
+        );
+        assert_eq!(
+            "3600",
+            resp.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap()
+        );
+
+        let resp = call_service(&app, preflight("https://evil.example")).await;
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let req = TestRequest::put()
+            .uri("/tasks/1")
+            .insert_header((header::ORIGIN, "https://example.com"))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            "https://example.com",
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_disabled() {
+        let app = init_service(
+            App::new()
+                .wrap(middleware(&None))
+                .route("/tasks", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let req = TestRequest::get()
+            .uri("/tasks")
+            .insert_header((header::ORIGIN, "https://example.com"))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
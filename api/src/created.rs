@@ -0,0 +1,47 @@
+use actix_web::body::BoxBody;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use paperclip::actix::{CreatedJson, OperationModifier};
+use paperclip::v2::models::{DataType, DefaultOperationRaw, DefaultSchemaRaw, Either, Header};
+use paperclip::v2::schema::Apiv2Schema;
+use serde::Serialize;
+
+/// A 201 Created response with the JSON of what was created, and where it lives from now on
+pub struct Created<T> {
+    /// e.g. "/tasks/1"
+    pub location: String,
+    pub body: T,
+}
+
+impl<T: Serialize> Responder for Created<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        HttpResponse::Created()
+            .insert_header((header::LOCATION, self.location))
+            .json(self.body)
+    }
+}
+
+impl<T: Apiv2Schema> Apiv2Schema for Created<T> {}
+
+// Documented like paperclip's own CreatedJson, plus the Location header
+impl<T: Serialize + Apiv2Schema> OperationModifier for Created<T> {
+    fn update_response(op: &mut DefaultOperationRaw) {
+        CreatedJson::<T>::update_response(op);
+        if let Some(Either::Right(response)) = op.responses.get_mut("201") {
+            response.headers.insert(
+                "Location".to_string(),
+                Header {
+                    description: Some("Path of the created resource".to_string()),
+                    data_type: Some(DataType::String),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn update_definitions(map: &mut std::collections::BTreeMap<String, DefaultSchemaRaw>) {
+        CreatedJson::<T>::update_definitions(map);
+    }
+}
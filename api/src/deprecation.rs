@@ -0,0 +1,265 @@
+use crate::request_id;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::Method;
+use actix_web::Error;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use log::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A route that is scheduled for removal.
+#[derive(Debug, Clone)]
+pub struct DeprecatedRoute {
+    pub method: Method,
+    // Same pattern syntax as used when registering the route, e.g. `/tasks/{id}`
+    pub path: &'static str,
+    // HTTP-date, e.g. `Sat, 01 Feb 2020 00:00:00 GMT`
+    pub sunset: &'static str,
+    // Documentation on what to migrate to
+    pub link: Option<&'static str>,
+}
+
+/// Every route that is currently deprecated. Add an entry here when deprecating a route.
+pub fn deprecated_routes() -> Vec<DeprecatedRoute> {
+    vec![]
+}
+
+// Distinct clients tracked per route; calls from any further ones are counted under OTHER_CLIENTS
+const MAX_CLIENTS_PER_ROUTE: usize = 100;
+const OTHER_CLIENTS: &str = "other";
+
+/// Holds deprecated routes along with which clients are still calling them.
+#[derive(Clone)]
+pub struct DeprecationRegistry {
+    routes: Arc<Vec<DeprecatedRoute>>,
+    usage: Arc<Mutex<HashMap<usize, HashMap<String, u64>>>>,
+}
+
+pub fn new(routes: Vec<DeprecatedRoute>) -> DeprecationRegistry {
+    DeprecationRegistry {
+        routes: Arc::new(routes),
+        usage: Arc::new(Mutex::new(HashMap::new())),
+    }
+}
+
+pub struct RouteUsage {
+    pub route: DeprecatedRoute,
+    // (client identifier, number of calls), most active first
+    pub consumers: Vec<(String, u64)>,
+}
+
+impl DeprecationRegistry {
+    fn find(&self, method: &Method, path: &str) -> Option<usize> {
+        self.routes
+            .iter()
+            .position(|route| route.method == method && path_matches(route.path, path))
+    }
+
+    fn record_usage(&self, route_idx: usize, client: String) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let clients = usage.entry(route_idx).or_default();
+        // Client identifiers come from request headers, so there's no telling how many there are
+        let client = if clients.contains_key(&client) || clients.len() < MAX_CLIENTS_PER_ROUTE {
+            client
+        } else {
+            OTHER_CLIENTS.to_string()
+        };
+        *clients.entry(client).or_insert(0) += 1;
+    }
+
+    pub fn report(&self) -> Vec<RouteUsage> {
+        let usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        self.routes
+            .iter()
+            .enumerate()
+            .map(|(idx, route)| {
+                let mut consumers: Vec<_> = usage
+                    .get(&idx)
+                    .map(|clients| clients.iter().map(|(c, n)| (c.clone(), *n)).collect())
+                    .unwrap_or_default();
+                consumers.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                RouteUsage {
+                    route: route.clone(),
+                    consumers,
+                }
+            })
+            .collect()
+    }
+}
+
+// Matches `{param}` segments against anything
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern_segments: Vec<_> = pattern.trim_end_matches('/').split('/').collect();
+    let path_segments: Vec<_> = path.trim_end_matches('/').split('/').collect();
+    pattern_segments.len() == path_segments.len()
+        && pattern_segments
+            .iter()
+            .zip(path_segments.iter())
+            .all(|(p, s)| (p.starts_with('{') && p.ends_with('}')) || p == s)
+}
+
+/// Middleware that adds `Deprecation`, `Sunset` and `Link` headers to responses for deprecated
+/// routes, and records which clients are calling them.
+pub struct DeprecationHeaders {
+    registry: DeprecationRegistry,
+}
+
+impl DeprecationHeaders {
+    pub fn new(registry: DeprecationRegistry) -> DeprecationHeaders {
+        DeprecationHeaders { registry }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for DeprecationHeaders
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = DeprecationHeadersMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(DeprecationHeadersMiddleware {
+            service,
+            registry: self.registry.clone(),
+        })
+    }
+}
+
+pub struct DeprecationHeadersMiddleware<S> {
+    service: S,
+    registry: DeprecationRegistry,
+}
+
+impl<S, B> Service<ServiceRequest> for DeprecationHeadersMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match self.registry.find(req.method(), req.path()) {
+            None => Box::pin(self.service.call(req)),
+            Some(route_idx) => {
+                let route = self.registry.routes[route_idx].clone();
+                let client = request_id::client_identifier(&req);
+                warn!(
+                    "Deprecated route [{} {}] (sunset [{}]) called by [{}]",
+                    route.method, route.path, route.sunset, client
+                );
+                self.registry.record_usage(route_idx, client);
+                let f_res = self.service.call(req);
+                Box::pin(async move {
+                    let mut res = f_res.await?;
+                    let headers = res.headers_mut();
+                    headers.insert(
+                        HeaderName::from_static("deprecation"),
+                        HeaderValue::from_static("true"),
+                    );
+                    headers.insert(
+                        HeaderName::from_static("sunset"),
+                        HeaderValue::from_static(route.sunset),
+                    );
+                    if let Some(link) = route.link {
+                        if let Ok(link_value) =
+                            HeaderValue::from_str(&format!("<{}>; rel=\"deprecation\"", link))
+                        {
+                            headers.insert(HeaderName::from_static("link"), link_value);
+                        }
+                    }
+                    Ok(res)
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_id::CLIENT_ID_HEADER;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    fn test_routes() -> Vec<DeprecatedRoute> {
+        vec![DeprecatedRoute {
+            method: Method::GET,
+            path: "/old/{id}",
+            sunset: "Sat, 01 Feb 2020 00:00:00 GMT",
+            link: Some("https://example.com/migrate"),
+        }]
+    }
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("/tasks/{id}", "/tasks/1"));
+        assert!(path_matches("/tasks", "/tasks/"));
+        assert!(!path_matches("/tasks/{id}", "/tasks"));
+        assert!(!path_matches("/tasks/{id}", "/lists/1"));
+    }
+
+    #[actix_web::test]
+    async fn test_headers_and_usage() {
+        let registry = new(test_routes());
+        let app = init_service(
+            App::new()
+                .wrap(DeprecationHeaders::new(registry.clone()))
+                .route(
+                    "/old/{id}",
+                    web::get().to(|| async { HttpResponse::Ok().finish() }),
+                )
+                .route(
+                    "/new",
+                    web::get().to(|| async { HttpResponse::Ok().finish() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/old/1")
+            .insert_header((CLIENT_ID_HEADER, "billing-service"))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!("true", resp.headers().get("deprecation").unwrap());
+        assert_eq!(
+            "Sat, 01 Feb 2020 00:00:00 GMT",
+            resp.headers().get("sunset").unwrap()
+        );
+        assert_eq!(
+            "<https://example.com/migrate>; rel=\"deprecation\"",
+            resp.headers().get("link").unwrap()
+        );
+
+        let req = TestRequest::with_uri("/new").to_request();
+        let resp = call_service(&app, req).await;
+        assert!(resp.headers().get("deprecation").is_none());
+
+        let report = registry.report();
+        assert_eq!(1, report.len());
+        assert_eq!(
+            vec![("billing-service".to_string(), 1)],
+            report[0].consumers
+        );
+    }
+
+    #[test]
+    fn test_usage_capped() {
+        let registry = new(test_routes());
+        for i in 0..MAX_CLIENTS_PER_ROUTE + 2 {
+            registry.record_usage(0, format!("client-{}", i));
+        }
+        registry.record_usage(0, "client-0".to_string());
+        let consumers = &registry.report()[0].consumers;
+        assert_eq!(MAX_CLIENTS_PER_ROUTE + 1, consumers.len());
+        assert_eq!(("client-0".to_string(), 2), consumers[0]);
+        assert_eq!(("other".to_string(), 2), consumers[1]);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::models::todo::TodoPlan;
+use actix_web::body::BoxBody;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use paperclip::actix::OperationModifier;
+use paperclip::v2::models::{DefaultOperationRaw, DefaultSchemaRaw};
+use paperclip::v2::schema::Apiv2Schema;
+
+/// The response of a route that takes `?dry_run=true`: what it did, or the plan of what it would
+/// have done, as a 200
+pub enum OrPlan<T> {
+    Done(T),
+    Plan(TodoPlan),
+}
+
+impl<T: Responder> Responder for OrPlan<T>
+where
+    T::Body: 'static,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        match self {
+            OrPlan::Done(done) => done.respond_to(req).map_into_boxed_body(),
+            OrPlan::Plan(plan) => HttpResponse::Ok().json(plan),
+        }
+    }
+}
+
+impl<T: Apiv2Schema> Apiv2Schema for OrPlan<T> {}
+
+// Routes that already respond with a 200 keep documenting theirs, as the spec can only have one
+impl<T: OperationModifier> OperationModifier for OrPlan<T> {
+    fn update_response(op: &mut DefaultOperationRaw) {
+        T::update_response(op);
+        if !op.responses.contains_key("200") {
+            actix_web::web::Json::<TodoPlan>::update_response(op);
+        }
+    }
+
+    fn update_definitions(map: &mut std::collections::BTreeMap<String, DefaultSchemaRaw>) {
+        T::update_definitions(map);
+        actix_web::web::Json::<TodoPlan>::update_definitions(map);
+    }
+}
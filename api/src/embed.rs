@@ -0,0 +1,65 @@
+use crate::assets;
+use crate::models::todo::Todo;
+
+// Where the widget's template is among the static assets
+static TEMPLATE_PATH: &str = "embed/widget.html";
+
+/// Allows nothing but the page's own inline stylesheet: no scripts, images, fonts, forms or
+/// requests of any kind. Framing is left open, since that's the point.
+pub fn content_security_policy(nonce: &str) -> String {
+    format!(
+        "default-src 'none'; style-src 'nonce-{}'; base-uri 'none'; form-action 'none'",
+        nonce
+    )
+}
+
+/// A self-contained HTML page showing the todo, for embedding in an iframe
+pub fn render(todo: &Todo, nonce: &str) -> Result<String, String> {
+    let template = assets::get(TEMPLATE_PATH)
+        .ok_or_else(|| format!("[{}] is not among the static assets", TEMPLATE_PATH))?;
+    let template = std::str::from_utf8(template.data).map_err(|e| e.to_string())?;
+    // The task goes in last, so that it can't smuggle in placeholders
+    Ok(template
+        .replace("{{nonce}}", nonce)
+        .replace("{{done_class}}", if todo.done { "done" } else { "" })
+        .replace("{{status}}", if todo.done { "Done" } else { "To do" })
+        .replace("{{task}}", &escape_html(&todo.task)))
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::todo::TodoId;
+
+    #[test]
+    fn test_render() {
+        let todo = Todo {
+            id: TodoId(1),
+            task: "<script>alert('{{nonce}}')</script>".to_string(),
+            done: true,
+            version: 1,
+            list_id: None,
+            position: 1,
+        };
+        let html = render(&todo, "abc123").unwrap();
+        assert!(html.contains(r#"<style nonce="abc123">"#));
+        assert!(html.contains("&lt;script&gt;alert(&#39;{{nonce}}&#39;)&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains(r#"class="todo done""#));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::json_bodies;
+use crate::models::common::{ErrorCode, MalformedBody, Message};
+use crate::request_id;
+use actix_web::{error, Error, HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError};
+use std::fmt::{Debug, Display};
+
+/// For failures that no route documents, so that they too get a `Message` with a code
+pub fn internal<E: Debug + Display + 'static>(e: E) -> Error {
+    let response = HttpResponse::InternalServerError().json(&Message {
+        message: e.to_string(),
+        code: Some(ErrorCode::Internal),
+        warnings: Vec::new(),
+        request_id: request_id::current(),
+    });
+    error::InternalError::from_response(e, response).into()
+}
+
+/// Keeps the status of body, path and query extraction errors, e.g. 413 for oversized bodies
+pub fn malformed<E: error::ResponseError + 'static>(e: E) -> Error {
+    let response = HttpResponseBuilder::new(e.status_code()).json(&Message {
+        message: e.to_string(),
+        code: Some(ErrorCode::MalformedRequest),
+        warnings: Vec::new(),
+        request_id: request_id::current(),
+    });
+    error::InternalError::from_response(e, response).into()
+}
+
+/// As [[malformed]], also naming the field that couldn't be parsed and what it should have been
+pub fn malformed_json(e: error::JsonPayloadError, req: &HttpRequest) -> Error {
+    let serde_e = match &e {
+        error::JsonPayloadError::Deserialize(serde_e) if serde_e.is_data() => serde_e,
+        _ => return malformed(e),
+    };
+    // serde's own words, without where it was
+    let reason = serde_e.to_string();
+    let position = format!(" at line {} column {}", serde_e.line(), serde_e.column());
+    let reason = reason.strip_suffix(&position).unwrap_or(&reason);
+    let missing = reason
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.strip_suffix('`'));
+    let expected = match missing {
+        Some(_) => None,
+        None => reason
+            .rsplit_once(", expected ")
+            .map(|(_, expected)| expected.to_string()),
+    };
+    let field = json_bodies::kept(req)
+        .map(|body| json_bodies::field_at(&body, serde_e.line(), serde_e.column(), missing));
+    let response = HttpResponseBuilder::new(e.status_code()).json(&MalformedBody {
+        message: Message {
+            message: e.to_string(),
+            code: Some(ErrorCode::MalformedRequest),
+            warnings: Vec::new(),
+            request_id: request_id::current(),
+        },
+        field,
+        expected,
+    });
+    error::InternalError::from_response(e, response).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::todo::TodoData;
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{web, App};
+
+    #[actix_web::test]
+    async fn test_malformed() {
+        let e = malformed(error::JsonPayloadError::Overflow { limit: 10 });
+        let response = e.error_response();
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let message: Message = serde_json::from_slice(&body).unwrap();
+        assert_eq!(Some(ErrorCode::MalformedRequest), message.code);
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_json() {
+        let app = init_service(
+            App::new()
+                .wrap(json_bodies::KeepJsonBodies::new(1024))
+                .app_data(web::JsonConfig::default().error_handler(malformed_json))
+                .route(
+                    "/tasks",
+                    web::post().to(|_: web::Json<TodoData>| HttpResponse::Created()),
+                ),
+        )
+        .await;
+        let call = |body: &'static str| {
+            TestRequest::post()
+                .uri("/tasks")
+                .insert_header(("content-type", "application/json"))
+                .set_payload(body)
+                .to_request()
+        };
+        let resp = call_service(&app, call(r#"{"task": "Make the bed", "done": "yes"}"#)).await;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        let malformed: MalformedBody = read_body_json(resp).await;
+        assert_eq!(Some(ErrorCode::MalformedRequest), malformed.message.code);
+        assert_eq!(Some("done".to_string()), malformed.field);
+        assert_eq!(Some("a boolean".to_string()), malformed.expected);
+
+        let resp = call_service(&app, call(r#"{"done": true}"#)).await;
+        let malformed: MalformedBody = read_body_json(resp).await;
+        assert_eq!(Some("task".to_string()), malformed.field);
+        assert_eq!(None, malformed.expected);
+
+        // Not JSON at all, so nowhere in particular
+        let resp = call_service(&app, call("{")).await;
+        let malformed: MalformedBody = read_body_json(resp).await;
+        assert_eq!(None, malformed.field);
+    }
+}
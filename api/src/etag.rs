@@ -0,0 +1,164 @@
+use crate::errors;
+use crate::json;
+use actix_web::body::BoxBody;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use paperclip::actix::OperationModifier;
+use paperclip::v2::models::{DataType, DefaultOperationRaw, DefaultSchemaRaw, Either, Header};
+use paperclip::v2::schema::Apiv2Schema;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// A JSON response with an ETag, that is answered with a 304 Not Modified instead when the
+/// request's If-None-Match already has it, so that polling clients only download changes.
+pub struct ETagged<T> {
+    pub body: T,
+    // Hashed from the body when not set
+    pub etag: Option<String>,
+}
+
+/// Tagged with a hash of the body
+pub fn hashed<T>(body: T) -> ETagged<T> {
+    ETagged { body, etag: None }
+}
+
+/// Tagged with a version that is bumped on every change to the body, which makes for a strong
+/// ETag that writes can be made conditional on with If-Match
+pub fn versioned<T>(body: T, version: u64) -> ETagged<T> {
+    ETagged {
+        body,
+        etag: Some(version_tag(version)),
+    }
+}
+
+/// Weak, as the same JSON may be sent compressed or not
+pub fn of(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("W/\"{}\"", hex)
+}
+
+pub fn version_tag(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Whether an If-Match header allows writing over the given version. Strong comparison, as
+/// RFC 9110 requires, so weak tags never match.
+pub fn if_match_allows(if_match: &str, version: u64) -> bool {
+    let tag = version_tag(version);
+    if_match.trim() == "*" || if_match.split(',').any(|candidate| candidate.trim() == tag)
+}
+
+/// Weak comparison, as RFC 9110 requires for If-None-Match
+pub fn matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match.trim() == "*"
+        || if_none_match
+            .split(',')
+            .any(|candidate| opaque(candidate) == opaque(etag))
+}
+
+impl<T: Serialize> Responder for ETagged<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let body = match json::to_bytes(&self.body) {
+            Ok(body) => body,
+            Err(e) => return errors::internal(e).error_response(),
+        };
+        let etag = self.etag.unwrap_or_else(|| of(&body));
+        let not_modified = req
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| matches(v, &etag));
+        if not_modified {
+            HttpResponse::NotModified()
+                .insert_header((header::ETAG, etag))
+                .finish()
+        } else {
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .insert_header((header::ETAG, etag))
+                .body(body)
+        }
+    }
+}
+
+impl<T: Apiv2Schema> Apiv2Schema for ETagged<T> {}
+
+// Documented like a plain JSON response, plus the ETag header and the 304
+impl<T: Serialize + Apiv2Schema> OperationModifier for ETagged<T> {
+    fn update_response(op: &mut DefaultOperationRaw) {
+        actix_web::web::Json::<T>::update_response(op);
+        let etag_header = Header {
+            description: Some("Pass in If-None-Match to only get changes".to_string()),
+            data_type: Some(DataType::String),
+            ..Default::default()
+        };
+        if let Some(Either::Right(response)) = op.responses.get_mut("200") {
+            response.headers.insert("ETag".to_string(), etag_header);
+            let mut not_modified = response.clone();
+            not_modified.description = Some("Not Modified".to_string());
+            not_modified.schema = None;
+            op.responses
+                .insert("304".to_string(), Either::Right(not_modified));
+        }
+    }
+
+    fn update_definitions(map: &mut std::collections::BTreeMap<String, DefaultSchemaRaw>) {
+        actix_web::web::Json::<T>::update_definitions(map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_matches() {
+        let etag = of(b"[]");
+        assert!(etag.starts_with("W/\""));
+        assert_ne!(etag, of(b"[{}]"));
+        assert!(matches(&etag, &etag));
+        assert!(matches(&etag.replace("W/", ""), &etag));
+        assert!(matches(&format!("\"other\", {}", etag), &etag));
+        assert!(matches("*", &etag));
+        assert!(!matches("\"other\"", &etag));
+    }
+
+    #[test]
+    fn test_if_match_allows() {
+        assert!(if_match_allows("\"3\"", 3));
+        assert!(if_match_allows("\"1\", \"3\"", 3));
+        assert!(if_match_allows("*", 3));
+        assert!(!if_match_allows("\"2\"", 3));
+        assert!(!if_match_allows("W/\"3\"", 3));
+    }
+
+    #[actix_web::test]
+    async fn test_not_modified() {
+        let req = TestRequest::default().to_http_request();
+        let resp = hashed(vec![1, 2]).respond_to(&req);
+        assert_eq!(200, resp.status().as_u16());
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, etag.clone()))
+            .to_http_request();
+        let resp = hashed(vec![1, 2]).respond_to(&req);
+        assert_eq!(304, resp.status().as_u16());
+        assert_eq!(Some(&etag), resp.headers().get(header::ETAG));
+
+        let resp = hashed(vec![1, 2, 3]).respond_to(&req);
+        assert_eq!(200, resp.status().as_u16());
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "W/\"4\""))
+            .to_http_request();
+        let resp = versioned(vec![1, 2], 4).respond_to(&req);
+        assert_eq!(304, resp.status().as_u16());
+        assert_eq!("\"4\"", resp.headers().get(header::ETAG).unwrap());
+    }
+}
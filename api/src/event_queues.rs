@@ -0,0 +1,120 @@
+use crate::metrics::SharedMetrics;
+use domain::event_queue::{self, Overflow, QueuedSubscriber};
+use domain::events::Subscriber;
+use futures::future::BoxFuture;
+use infra::events::file_spill;
+use schemars::JsonSchema;
+use serde_derive::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How many events each subscriber's queue holds in memory
+pub const DEFAULT_CAPACITY: usize = 1024;
+/// How many events each subscriber's spill holds
+pub const DEFAULT_SPILL_MAX: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    Block,
+    DropOldest,
+    Spill,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(OverflowPolicy::Block),
+            "drop_oldest" => Ok(OverflowPolicy::DropOldest),
+            "spill" => Ok(OverflowPolicy::Spill),
+            _ => Err("expected one of [block, drop_oldest, spill]".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverflowSettings {
+    // Changes wait until their events fit
+    Block,
+    // A full queue's oldest event is dropped for each new one
+    DropOldest,
+    // Events that don't fit go to a file per subscriber in `dir`, holding up to `max_events`, past
+    // which the oldest are dropped
+    Spill { dir: String, max_events: usize },
+}
+
+impl OverflowSettings {
+    pub fn policy(&self) -> OverflowPolicy {
+        match self {
+            OverflowSettings::Block => OverflowPolicy::Block,
+            OverflowSettings::DropOldest => OverflowPolicy::DropOldest,
+            OverflowSettings::Spill { .. } => OverflowPolicy::Spill,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventQueueSettings {
+    pub capacity: usize,
+    pub overflow: OverflowSettings,
+}
+
+/// Puts `subscriber` behind a queue as configured; spawn the returned drain
+pub fn queued(
+    name: &str,
+    subscriber: Arc<dyn Subscriber + Send + Sync>,
+    settings: &EventQueueSettings,
+    metrics: Option<SharedMetrics>,
+) -> Result<(Arc<QueuedSubscriber>, BoxFuture<'static, ()>), std::io::Error> {
+    let overflow = match &settings.overflow {
+        OverflowSettings::Block => Overflow::Block,
+        OverflowSettings::DropOldest => Overflow::DropOldest,
+        OverflowSettings::Spill { dir, max_events } => {
+            std::fs::create_dir_all(dir)?;
+            let path = Path::new(dir).join(format!("{}.jsonl", name));
+            Overflow::Spill(Arc::new(file_spill::open(&path, *max_events)?))
+        }
+    };
+    Ok(event_queue::queued(
+        name,
+        subscriber,
+        settings.capacity,
+        overflow,
+        metrics,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::events::{TodoDeleted, TodoEvent};
+    use domain::todo::TodoId;
+    use infra::events::logging_subscriber;
+
+    #[actix_web::test]
+    async fn test_spill() {
+        let dir = std::env::temp_dir().join("todddo_test_event_queues");
+        let _ = std::fs::remove_dir_all(&dir);
+        let settings = EventQueueSettings {
+            capacity: 1,
+            overflow: OverflowSettings::Spill {
+                dir: dir.to_string_lossy().to_string(),
+                max_events: DEFAULT_SPILL_MAX,
+            },
+        };
+        let (queue, _drain) =
+            queued("log", Arc::new(logging_subscriber::new()), &settings, None).unwrap();
+        for id in 1..=3 {
+            queue
+                .notify(&TodoEvent::Deleted(TodoDeleted { id: TodoId(id) }))
+                .await;
+        }
+        let stats = queue.stats();
+        assert_eq!(1, stats.depth);
+        assert_eq!(2, stats.spilled);
+        assert!(dir.join("log.jsonl").is_file());
+    }
+}
@@ -0,0 +1,128 @@
+//! What requests that no route takes get: a 404, or a 405 with an Allow header when the path is
+//! served under other methods, each with a `Message` like every other error
+
+use crate::models::common::{ErrorCode, Message};
+use crate::request_id;
+use actix_web::dev::ResourceDef;
+use actix_web::http::{header, Method};
+use actix_web::{web, HttpRequest, HttpResponse};
+
+/// The methods each route is served under, for the Allow header, as actix keeps them to itself.
+/// Recorded by app_builder as it registers the routes; paths served but not recorded, e.g. from
+/// `AppBuilder::configure`, get a 405 without one.
+#[derive(Default)]
+pub struct AllowedMethods(Vec<(ResourceDef, Vec<Method>)>);
+
+impl AllowedMethods {
+    pub fn record(&mut self, pattern: &str, method: Method) {
+        match self
+            .0
+            .iter_mut()
+            .find(|(resource, _)| resource.pattern() == Some(pattern))
+        {
+            Some((_, methods)) => methods.push(method),
+            None => self.0.push((ResourceDef::new(pattern), vec![method])),
+        }
+    }
+
+    /// The methods the path is served under, in the order they were recorded; empty when unknown.
+    /// Like actix, takes the first route recorded that matches.
+    pub fn of(&self, path: &str) -> &[Method] {
+        self.0
+            .iter()
+            .find(|(resource, _)| resource.is_match(path))
+            .map(|(_, methods)| methods.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// The app's default service
+pub async fn unmatched(req: HttpRequest) -> HttpResponse {
+    let message = |message: String, code: ErrorCode| Message {
+        message,
+        code: Some(code),
+        warnings: Vec::new(),
+        request_id: request_id::current(),
+    };
+    // Routes are registered one method at a time, so a path that is served under another method
+    // still has a resource
+    if !req.resource_map().has_resource(req.path()) {
+        return HttpResponse::NotFound().json(message(
+            format!("No such route: [{}]", req.path()),
+            ErrorCode::RouteNotFound,
+        ));
+    }
+    let mut response = HttpResponse::MethodNotAllowed();
+    let allowed = req
+        .app_data::<web::Data<AllowedMethods>>()
+        .map(|allowed| allowed.of(req.path()))
+        .unwrap_or(&[]);
+    if !allowed.is_empty() {
+        let allowed: Vec<_> = allowed.iter().map(Method::as_str).collect();
+        response.insert_header((header::ALLOW, allowed.join(", ")));
+    }
+    response.json(message(
+        format!("[{}] is not allowed on [{}]", req.method(), req.path()),
+        ErrorCode::MethodNotAllowed,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::App;
+
+    fn allowed() -> AllowedMethods {
+        let mut allowed = AllowedMethods::default();
+        allowed.record("/tasks", Method::GET);
+        allowed.record("/tasks", Method::POST);
+        allowed.record("/tasks/reorder", Method::PATCH);
+        allowed.record("/tasks/{id}", Method::GET);
+        allowed.record("/tasks/{id}", Method::PUT);
+        allowed.record("/tasks/{id}", Method::DELETE);
+        allowed.record("/tasks/{id}/share/{token}", Method::DELETE);
+        allowed
+    }
+
+    #[actix_web::test]
+    async fn test_unmatched() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(allowed()))
+                .route("/tasks/{id}", web::get().to(HttpResponse::Ok))
+                .route("/tasks/{id}", web::put().to(HttpResponse::Ok))
+                .route("/tasks/{id}", web::delete().to(HttpResponse::NoContent))
+                .default_service(web::route().to(unmatched)),
+        )
+        .await;
+        let req = TestRequest::get().uri("/nope").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(404, resp.status().as_u16());
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let message: Message = serde_json::from_slice(&body).unwrap();
+        assert_eq!(Some(ErrorCode::RouteNotFound), message.code);
+
+        let req = TestRequest::post().uri("/tasks/1").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(405, resp.status().as_u16());
+        assert_eq!(
+            "GET, PUT, DELETE",
+            resp.headers().get(header::ALLOW).unwrap()
+        );
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let message: Message = serde_json::from_slice(&body).unwrap();
+        assert_eq!(Some(ErrorCode::MethodNotAllowed), message.code);
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let allowed = allowed();
+        assert_eq!(&[Method::GET, Method::POST], allowed.of("/tasks"));
+        // Not taken for an id
+        assert_eq!(&[Method::PATCH], allowed.of("/tasks/reorder"));
+        assert_eq!(&[Method::DELETE], allowed.of("/tasks/1/share/abc"));
+        assert!(allowed.of("/nope").is_empty());
+    }
+}
@@ -0,0 +1,572 @@
+use crate::assets;
+use crate::controllers::todo_controller::TodoControllerLookupErr;
+use crate::deprecation::{DeprecationRegistry, RouteUsage};
+use crate::handlers::todo_routes_handler::TodoRoutesLookupError;
+use crate::health_history::{self, Period};
+use crate::models::admin::*;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::history::{ExportedEvent, TodoChange};
+use crate::models::todo::Todo;
+use crate::read_only::ReadOnly;
+use crate::request_id;
+use actix_web::*;
+use chrono::{DateTime, Utc};
+use domain::event_queue::{QueueStats, QueuedSubscriber};
+use domain::history::{AuditLog, AuditLogErr};
+use domain::services::todo_service::TodoServiceLookupErr;
+use domain::todo::{TodoRepo, TodoRepoErr};
+use futures::stream;
+use infra::in_mem::todo_repo::{InMemTodoRepo, LockStats};
+use infra::metrics::prometheus_metrics::PrometheusMetrics;
+use log::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use std::sync::Arc;
+use thiserror::Error;
+
+pub type SharedAuditLog = Arc<dyn AuditLog + Send + Sync>;
+/// The queues in front of todo event subscribers
+pub type EventQueues = Vec<Arc<QueuedSubscriber>>;
+
+// Answered as the todo routes answer them
+fn storage_err(e: TodoRepoErr) -> TodoRoutesLookupError {
+    TodoControllerLookupErr::from(TodoServiceLookupErr::from(e)).into()
+}
+
+#[api_v2_operation(summary = "Build version", operation_id = "getVersion", tags(Admin))]
+pub async fn version() -> web::Json<VersionInfo> {
+    web::Json(VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        asset_bundle_hash: assets::EXPECTED_BUNDLE_HASH.to_string(),
+    })
+}
+
+// Not in the spec, being Prometheus' text format rather than JSON
+pub async fn metrics(prometheus: web::Data<Arc<PrometheusMetrics>>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(prometheus.render())
+}
+
+#[api_v2_operation(
+    summary = "Deprecated route usage",
+    description = "Lists deprecated routes, their sunset dates, and the clients still calling them, with those past the first 100 per route counted under "other". Requires the admin token",
+    operation_id = "listDeprecations",
+    tags(Admin)
+)]
+pub async fn deprecations(
+    registry: web::Data<DeprecationRegistry>,
+) -> web::Json<Vec<DeprecatedRouteReport>> {
+    let report = registry
+        .report()
+        .into_iter()
+        .map(deprecation_report)
+        .collect();
+    web::Json(report)
+}
+
+#[api_v2_operation(
+    summary = "Server diagnostics",
+    description = "Reports async runtime stats, repo lock contention, todo event queues and, when built with jemalloc, allocation stats. Requires the admin token",
+    operation_id = "getDiagnostics",
+    tags(Admin)
+)]
+pub async fn diagnostics(
+    repo: web::Data<InMemTodoRepo>,
+    event_queues: web::Data<EventQueues>,
+) -> web::Json<Diagnostics> {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    web::Json(Diagnostics {
+        runtime: RuntimeDiagnostics {
+            workers: metrics.num_workers(),
+            alive_tasks: metrics.num_alive_tasks(),
+            queue_depth: metrics.global_queue_depth(),
+        },
+        repo_lock: lock_diagnostics(repo.lock_stats()),
+        allocations: allocation_diagnostics(),
+        event_queues: event_queues
+            .iter()
+            .map(|queue| event_queue_diagnostics(queue.stats()))
+            .collect(),
+    })
+}
+
+#[api_v2_operation(
+    summary = "Readiness check history",
+    description = "Recent /readyz outcomes, oldest first, with consecutive checks of the same outcome collapsed into one period. Requires the admin token",
+    operation_id = "getHealthHistory",
+    tags(Admin)
+)]
+pub async fn health_history(
+    history: web::Data<health_history::HealthHistory>,
+) -> web::Json<HealthHistory> {
+    web::Json(HealthHistory {
+        size: history.size(),
+        periods: history.periods().into_iter().map(health_period).collect(),
+    })
+}
+
+#[api_v2_operation(
+    summary = "Read-only mode",
+    description = "Whether changes are being turned down with a 503 while reads still work. Requires the admin token",
+    operation_id = "getReadOnly",
+    tags(Admin)
+)]
+pub async fn read_only(read_only: web::Data<ReadOnly>) -> web::Json<ReadOnlyMode> {
+    web::Json(ReadOnlyMode {
+        read_only: read_only.is_on(),
+    })
+}
+
+#[api_v2_operation(
+    summary = "Turn read-only mode on or off",
+    description = "While on, every request that would change anything outside /admin gets a 503 with a READ_ONLY code, e.g. during a migration or an incident; it applies to this instance only. Requires the admin token",
+    operation_id = "setReadOnly",
+    tags(Admin)
+)]
+pub async fn set_read_only(
+    read_only: web::Data<ReadOnly>,
+    json: web::Json<ReadOnlyMode>,
+) -> Result<web::Json<ReadOnlyMode>, Error> {
+    read_only.set(json.read_only);
+    if json.read_only {
+        warn!("Read-only mode turned on; changes are turned down");
+    } else {
+        info!("Read-only mode turned off");
+    }
+    Ok(web::Json(*json))
+}
+
+#[api_v2_operation(
+    summary = "Todo stats",
+    description = "How many todos there are, and the highest id. Requires the admin token",
+    operation_id = "getTodoStats",
+    tags(Admin)
+)]
+pub async fn todo_stats(repo: web::Data<InMemTodoRepo>) -> Result<web::Json<TodoStats>, Error> {
+    let stats = repo.todo_stats().await.map_err(storage_err)?;
+    Ok(web::Json(TodoStats {
+        count: stats.count,
+        last_id: stats.last_id.map(|id| id.into()),
+    }))
+}
+
+#[api_v2_operation(
+    summary = "Dump every todo",
+    description = "Every todo, in id order, straight from storage. Requires the admin token",
+    operation_id = "dumpTodos",
+    tags(Admin)
+)]
+pub async fn dump_todos(repo: web::Data<InMemTodoRepo>) -> Result<web::Json<Vec<Todo>>, Error> {
+    let todos = repo.list().await.map_err(storage_err)?;
+    Ok(web::Json(todos.into_iter().map(|v| v.into()).collect()))
+}
+
+#[api_v2_operation(
+    summary = "Purge done todos",
+    description = "Deletes every done todo straight from storage, without emitting events or auditing the deletes. Requires the admin token",
+    operation_id = "purgeDoneTodos",
+    tags(Admin)
+)]
+pub async fn purge_done_todos(repo: web::Data<InMemTodoRepo>) -> Result<web::Json<Purged>, Error> {
+    let deleted = repo.purge_done().await.map_err(storage_err)?;
+    info!("Purged [{}] done todos", deleted);
+    Ok(web::Json(Purged { deleted }))
+}
+
+#[api_v2_operation(
+    summary = "Delete every todo",
+    description = "Deletes every todo straight from storage, without emitting events or auditing the deletes. Requires the admin token",
+    operation_id = "deleteAllTodos",
+    tags(Admin)
+)]
+pub async fn delete_all_todos(repo: web::Data<InMemTodoRepo>) -> Result<web::Json<Purged>, Error> {
+    let deleted = repo.delete_all().await.map_err(storage_err)?;
+    warn!("Deleted every todo, [{}] of them", deleted);
+    Ok(web::Json(Purged { deleted }))
+}
+
+#[api_v2_operation(
+    summary = "Audit log",
+    description = "Every create, update and delete of a todo, oldest first, with who made it and in which request. The in-memory log only keeps the latest changes. Requires the admin token",
+    operation_id = "getAuditLog",
+    tags(Admin)
+)]
+pub async fn audit(
+    audit_log: web::Data<SharedAuditLog>,
+    params: web::Query<AuditParams>,
+) -> Result<web::Json<Vec<TodoChange>>, Error> {
+    let since = match &params.since {
+        Some(since) => Some(
+            DateTime::parse_from_rfc3339(since)
+                .map_err(|e| AuditRoutesError::BadSince {
+                    reason: e.to_string(),
+                })?
+                .with_timezone(&Utc),
+        ),
+        None => None,
+    };
+    let changes = audit_log
+        .since(since)
+        .await
+        .map_err(AuditRoutesError::from)?;
+    Ok(web::Json(changes.into_iter().map(|v| v.into()).collect()))
+}
+
+#[api_v2_operation(
+    summary = "Export stream",
+    description = "Every change in the audit log after the `since` seq, oldest first, as newline-delimited JSON events with a schema version, for loading into a data warehouse incrementally. Requires the admin token",
+    operation_id = "streamExport",
+    tags(Admin)
+)]
+pub async fn export_stream(
+    audit_log: web::Data<SharedAuditLog>,
+    params: web::Query<ExportParams>,
+) -> Result<HttpResponse, Error> {
+    let changes = audit_log
+        .after(params.since.unwrap_or(0))
+        .await
+        .map_err(AuditRoutesError::from)?;
+    let lines = changes.into_iter().map(|change| {
+        let mut line = serde_json::to_vec(&ExportedEvent::from(change))?;
+        line.push(b'\n');
+        Ok::<_, Error>(web::Bytes::from(line))
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(stream::iter(lines)))
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Invalid since",
+    schema = "Message",
+    code = 410,
+    description = "Changes after since are no longer kept",
+    schema = "Message",
+    code = 500,
+    description = "The audit log could not be read",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum AuditRoutesError {
+    #[error("Bad since")]
+    BadSince { reason: String },
+    #[error("Truncated audit log")]
+    Truncated { oldest: u64 },
+    #[error("Unreadable audit log")]
+    Unreadable { reason: String },
+}
+
+impl error::ResponseError for AuditRoutesError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            AuditRoutesError::BadSince { reason } => HttpResponse::BadRequest().json(&Message {
+                message: format!("Invalid since, expected RFC 3339: {}", reason),
+                code: Some(ErrorCode::InvalidSince),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+            AuditRoutesError::Truncated { oldest } => HttpResponse::Gone().json(&Message {
+                message: format!(
+                    "Events after since are no longer kept; the oldest is [{}]",
+                    oldest
+                ),
+                code: Some(ErrorCode::ExportTruncated),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+            AuditRoutesError::Unreadable { reason } => {
+                HttpResponse::InternalServerError().json(&Message {
+                    message: format!("Could not read the audit log {}", reason),
+                    code: Some(ErrorCode::AuditLogUnreadable),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+        }
+    }
+}
+
+impl From<AuditLogErr> for AuditRoutesError {
+    fn from(e: AuditLogErr) -> Self {
+        match e {
+            AuditLogErr::Unreadable { reason } => AuditRoutesError::Unreadable { reason },
+            AuditLogErr::Truncated { oldest } => AuditRoutesError::Truncated { oldest },
+        }
+    }
+}
+
+#[cfg(feature = "profiling")]
+static DEFAULT_PROFILE_SECONDS: u64 = 10;
+#[cfg(feature = "profiling")]
+static MAX_PROFILE_SECONDS: u64 = 60;
+
+#[cfg(feature = "profiling")]
+#[api_v2_operation(
+    summary = "Capture a CPU profile",
+    description = "Profiles the whole process for the given number of seconds, then returns a flamegraph or pprof protobuf. Requires the admin token",
+    operation_id = "captureProfile",
+    tags(Admin)
+)]
+pub async fn profile(params: web::Query<ProfileParams>) -> Result<HttpResponse, Error> {
+    let seconds = params
+        .seconds
+        .unwrap_or(DEFAULT_PROFILE_SECONDS)
+        .min(MAX_PROFILE_SECONDS);
+    let format = params.format.unwrap_or(ProfileFormat::Flamegraph);
+    let body = crate::profiling::capture(std::time::Duration::from_secs(seconds), format)
+        .await
+        .map_err(crate::errors::internal)?;
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(body))
+}
+
+fn deprecation_report(v: RouteUsage) -> DeprecatedRouteReport {
+    DeprecatedRouteReport {
+        method: v.route.method.to_string(),
+        path: v.route.path.to_string(),
+        sunset: v.route.sunset.to_string(),
+        link: v.route.link.map(|l| l.to_string()),
+        consumers: v
+            .consumers
+            .into_iter()
+            .map(|(client, calls)| RouteConsumer { client, calls })
+            .collect(),
+    }
+}
+
+fn health_period(v: Period) -> HealthPeriod {
+    HealthPeriod {
+        ready: v.ready,
+        detail: v.detail,
+        since: v.since.to_rfc3339(),
+        last_checked: v.last_checked.to_rfc3339(),
+        checks: v.checks,
+    }
+}
+
+fn lock_diagnostics(v: LockStats) -> LockDiagnostics {
+    LockDiagnostics {
+        acquisitions: v.acquisitions,
+        contended: v.contended,
+    }
+}
+
+fn event_queue_diagnostics(v: QueueStats) -> EventQueueDiagnostics {
+    EventQueueDiagnostics {
+        subscriber: v.subscriber,
+        depth: v.depth,
+        spilled: v.spilled,
+        dropped: v.dropped,
+        blocked: v.blocked,
+    }
+}
+
+#[cfg(feature = "jemalloc")]
+fn allocation_diagnostics() -> Option<AllocationDiagnostics> {
+    use tikv_jemalloc_ctl::{epoch, stats};
+    // Stats are cached by jemalloc until the epoch is advanced
+    epoch::advance().ok()?;
+    Some(AllocationDiagnostics {
+        allocated: stats::allocated::read().ok()?,
+        resident: stats::resident::read().ok()?,
+    })
+}
+
+#[cfg(not(feature = "jemalloc"))]
+fn allocation_diagnostics() -> Option<AllocationDiagnostics> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::history::{ExportedEventKind, EXPORT_SCHEMA_VERSION};
+    use crate::{deprecation, read_only};
+    use actix_web::http::Method;
+    use domain::event_queue::{self, Overflow};
+    use domain::history::AuditSink;
+    use infra::events::logging_subscriber;
+    use infra::in_mem::{audit_log, todo_repo};
+
+    #[actix_web::test]
+    async fn test_deprecations() {
+        let registry = deprecation::new(vec![deprecation::DeprecatedRoute {
+            method: Method::GET,
+            path: "/old",
+            sunset: "Sat, 01 Feb 2020 00:00:00 GMT",
+            link: None,
+        }]);
+        let resp = deprecations(web::Data::new(registry)).await.0;
+        assert_eq!(1, resp.len());
+        assert_eq!("/old", &resp[0].path);
+        assert!(resp[0].consumers.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_diagnostics() {
+        let (queue, _drain) = event_queue::queued(
+            "log",
+            Arc::new(logging_subscriber::new()),
+            8,
+            Overflow::Block,
+            None,
+        );
+        let resp = diagnostics(
+            web::Data::new(todo_repo::new()),
+            web::Data::new(vec![queue]),
+        )
+        .await
+        .0;
+        assert_eq!(1, resp.runtime.workers);
+        assert_eq!(0, resp.repo_lock.acquisitions);
+        assert_eq!("log", resp.event_queues[0].subscriber);
+        assert_eq!(0, resp.event_queues[0].depth);
+    }
+
+    #[actix_web::test]
+    async fn test_audit() {
+        let in_mem_log = audit_log::new(10);
+        in_mem_log
+            .record(&domain::history::TodoChange {
+                todo_id: domain::todo::TodoId(1),
+                old: None,
+                new: None,
+                at: Utc::now(),
+                actor: Some("curl/8.0".to_string()),
+                request_id: Some("abc".to_string()),
+                rejected: None,
+            })
+            .await;
+        let shared: SharedAuditLog = Arc::new(in_mem_log);
+        let call = |since: Option<&str>| {
+            audit(
+                web::Data::new(shared.clone()),
+                web::Query(AuditParams {
+                    since: since.map(|s| s.to_string()),
+                }),
+            )
+        };
+        let changes = call(None).await.unwrap().0;
+        assert_eq!(1, changes.len());
+        assert_eq!(Some("abc".to_string()), changes[0].request_id);
+        assert!(call(Some("2999-01-01T00:00:00Z"))
+            .await
+            .unwrap()
+            .0
+            .is_empty());
+        assert!(call(Some("yesterday")).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_todo_maintenance() {
+        let repo = todo_repo::new();
+        for done in [false, true] {
+            repo.create(&domain::todo::TodoData {
+                task: "Make the bed".to_string(),
+                done,
+                list_id: None,
+            })
+            .await
+            .unwrap();
+        }
+        let repo = web::Data::new(repo);
+        let stats = todo_stats(repo.clone()).await.unwrap();
+        assert_eq!((2, Some(2)), (stats.count, stats.last_id.map(|id| id.0)));
+        let purged = purge_done_todos(repo.clone()).await.unwrap();
+        assert_eq!(1, purged.deleted);
+        let dumped = dump_todos(repo.clone()).await.unwrap();
+        assert_eq!(1, dumped.len());
+        assert!(!dumped[0].done);
+        let deleted = delete_all_todos(repo.clone()).await.unwrap();
+        assert_eq!(1, deleted.deleted);
+        assert!(repo.list().await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_read_only() {
+        let mode = read_only::new(false);
+        assert!(!read_only(web::Data::new(mode.clone())).await.read_only);
+        let set = set_read_only(
+            web::Data::new(mode.clone()),
+            web::Json(ReadOnlyMode { read_only: true }),
+        )
+        .await;
+        assert!(set.unwrap().read_only);
+        assert!(read_only(web::Data::new(mode.clone())).await.read_only);
+    }
+
+    #[actix_web::test]
+    async fn test_export_stream() {
+        let in_mem_log = audit_log::new(2);
+        let todo = domain::todo::Todo {
+            id: domain::todo::TodoId(1),
+            task: "Make the bed".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
+        };
+        let change = |old: Option<&domain::todo::Todo>, new: Option<&domain::todo::Todo>| {
+            domain::history::TodoChange {
+                todo_id: todo.id,
+                old: old.cloned(),
+                new: new.cloned(),
+                at: Utc::now(),
+                actor: None,
+                request_id: None,
+                rejected: None,
+            }
+        };
+        in_mem_log.record(&change(None, Some(&todo))).await;
+        in_mem_log.record(&change(Some(&todo), None)).await;
+        let shared: SharedAuditLog = Arc::new(in_mem_log.clone());
+        let call = |since: Option<u64>| {
+            export_stream(
+                web::Data::new(shared.clone()),
+                web::Query(ExportParams { since }),
+            )
+        };
+        let resp = call(None).await.unwrap();
+        assert_eq!(
+            Some("application/x-ndjson"),
+            resp.headers()
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+        );
+        let body = body::to_bytes(resp.into_body()).await.unwrap();
+        let events: Vec<ExportedEvent> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(2, events.len());
+        assert_eq!(
+            (1, ExportedEventKind::Created),
+            (events[0].seq, events[0].event)
+        );
+        assert_eq!(
+            (2, ExportedEventKind::Deleted),
+            (events[1].seq, events[1].event)
+        );
+        assert_eq!(EXPORT_SCHEMA_VERSION, events[0].schema_version);
+
+        let resp = call(Some(2)).await.unwrap();
+        assert!(body::to_bytes(resp.into_body()).await.unwrap().is_empty());
+        in_mem_log.record(&change(None, Some(&todo))).await;
+        in_mem_log.record(&change(None, Some(&todo))).await;
+        let resp = call(Some(1)).await.unwrap_err().error_response();
+        assert_eq!(410, resp.status().as_u16());
+    }
+
+    #[actix_web::test]
+    async fn test_health_history() {
+        let history = health_history::new(5);
+        history.record(true, "ready", chrono::Utc::now());
+        let resp = health_history(web::Data::new(history)).await.0;
+        assert_eq!(5, resp.size);
+        assert_eq!(1, resp.periods.len());
+        assert_eq!(1, resp.periods[0].checks);
+    }
+}
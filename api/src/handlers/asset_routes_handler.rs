@@ -0,0 +1,268 @@
+use crate::assets;
+use crate::assets::Asset;
+use crate::etag;
+use actix_files::HttpRange;
+use actix_web::http::header;
+use actix_web::*;
+
+// A year; hashed paths never change contents, so caches needn't ever check back
+static IMMUTABLE: &str = "public, max-age=31536000, immutable";
+// Pages are checked on every use, so that they pick up the hashed paths of new builds
+static REVALIDATE: &str = "no-cache";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+/// Serves a baked asset by its path or hashed path, pre-compressed when the client accepts it,
+/// with ETags and single byte ranges. Directories serve their `index.html`.
+pub async fn asset(req: HttpRequest, path: web::Path<String>) -> HttpResponse {
+    let mut path = path.into_inner();
+    if path.is_empty() || path.ends_with('/') {
+        path.push_str("index.html");
+    }
+    match assets::get(&path) {
+        Some(asset) => respond(&req, asset, path == asset.hashed_path),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+fn respond(req: &HttpRequest, asset: &Asset, hashed: bool) -> HttpResponse {
+    let header_str = |name| req.headers().get(name).and_then(|v| v.to_str().ok());
+    // Ranges are of the uncompressed bytes, and only while If-Range still names them
+    let range = header_str(header::RANGE).filter(|_| {
+        header_str(header::IF_RANGE)
+            .is_none_or(|if_range| if_range.trim() == tag(asset, Encoding::Identity))
+    });
+    let (encoding, body) = match range {
+        Some(_) => (Encoding::Identity, asset.data),
+        None => negotiate(req, asset),
+    };
+    let etag = tag(asset, encoding);
+    let not_modified = header_str(header::IF_NONE_MATCH).is_some_and(|v| etag::matches(v, &etag));
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header((header::ETAG, etag))
+        .insert_header((
+            header::CACHE_CONTROL,
+            if hashed { IMMUTABLE } else { REVALIDATE },
+        ))
+        .insert_header((header::VARY, "Accept-Encoding"))
+        .insert_header((header::ACCEPT_RANGES, "bytes"));
+    if not_modified {
+        return response.finish();
+    }
+    response.content_type(asset.mime_type);
+    match encoding {
+        Encoding::Gzip => {
+            response.insert_header((header::CONTENT_ENCODING, "gzip"));
+        }
+        Encoding::Brotli => {
+            response.insert_header((header::CONTENT_ENCODING, "br"));
+        }
+        // Keeps the compression middleware from compressing ranges
+        Encoding::Identity if range.is_some() => {
+            response.insert_header((header::CONTENT_ENCODING, "identity"));
+        }
+        Encoding::Identity => {}
+    }
+    let len = body.len() as u64;
+    let ranges = match range {
+        Some(range) => HttpRange::parse(range, len),
+        None => return response.body(body),
+    };
+    match ranges {
+        Ok(ranges) if ranges.len() == 1 => {
+            let range = ranges[0];
+            let start = range.start as usize;
+            let end = start + range.length as usize;
+            response
+                .status(http::StatusCode::PARTIAL_CONTENT)
+                .insert_header((
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end - 1, len),
+                ))
+                .body(&body[start..end])
+        }
+        // Several ranges would take a multipart body; the whole asset is simpler, and allowed
+        Ok(_) => response.body(body),
+        Err(_) => response
+            .status(http::StatusCode::RANGE_NOT_SATISFIABLE)
+            .insert_header((header::CONTENT_RANGE, format!("bytes */{}", len)))
+            .finish(),
+    }
+}
+
+// Strong, as the bytes are the same for as long as the hash is, which If-Range needs
+fn tag(asset: &Asset, encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Identity => format!("\"{}\"", asset.content_hash),
+        Encoding::Gzip => format!("\"{}-gzip\"", asset.content_hash),
+        Encoding::Brotli => format!("\"{}-br\"", asset.content_hash),
+    }
+}
+
+// Brotli before gzip, as it's the smaller of the two
+fn negotiate(req: &HttpRequest, asset: &Asset) -> (Encoding, &'static [u8]) {
+    match (asset.brotli, asset.gzip) {
+        (Some(brotli), _) if accepts(req, "br") => (Encoding::Brotli, brotli),
+        (_, Some(gzip)) if accepts(req, "gzip") => (Encoding::Gzip, gzip),
+        _ => (Encoding::Identity, asset.data),
+    }
+}
+
+// Named in Accept-Encoding without a q=0
+fn accepts(req: &HttpRequest, coding: &str) -> bool {
+    req.headers()
+        .get_all(header::ACCEPT_ENCODING)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|candidate| {
+            let mut params = candidate.split(';');
+            let named = params
+                .next()
+                .is_some_and(|name| name.trim().eq_ignore_ascii_case(coding));
+            let refused = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    == Some(0.0)
+            });
+            named && !refused
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+
+    #[actix_web::test]
+    async fn test_asset() {
+        let app = init_service(
+            App::new().service(
+                web::resource("/swagger/{path:.*}").route(
+                    web::route()
+                        .guard(guard::Any(guard::Get()).or(guard::Head()))
+                        .to(asset),
+                ),
+            ),
+        )
+        .await;
+        let bundle = assets::get("swagger-ui-bundle.js").unwrap();
+        let get = |path: &str| TestRequest::get().uri(&format!("/swagger/{}", path));
+        let header = |resp: &dev::ServiceResponse, name| {
+            resp.headers()
+                .get(name)
+                .map(|v: &header::HeaderValue| v.to_str().unwrap().to_string())
+        };
+
+        let resp = call_service(&app, get("").to_request()).await;
+        assert_eq!(200, resp.status().as_u16());
+        assert_eq!(
+            Some("text/html".to_string()),
+            header(&resp, header::CONTENT_TYPE)
+        );
+        assert_eq!(
+            Some(REVALIDATE.to_string()),
+            header(&resp, header::CACHE_CONTROL)
+        );
+
+        let resp = call_service(
+            &app,
+            get(bundle.hashed_path)
+                .insert_header((header::ACCEPT_ENCODING, "gzip, deflate, br"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            Some("br".to_string()),
+            header(&resp, header::CONTENT_ENCODING)
+        );
+        assert_eq!(
+            Some(IMMUTABLE.to_string()),
+            header(&resp, header::CACHE_CONTROL)
+        );
+        let etag = header(&resp, header::ETAG).unwrap();
+        assert_eq!(bundle.brotli.unwrap(), &read_body(resp).await[..]);
+
+        let resp = call_service(
+            &app,
+            get(bundle.path)
+                .insert_header((header::ACCEPT_ENCODING, "gzip, br;q=0"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            Some("gzip".to_string()),
+            header(&resp, header::CONTENT_ENCODING)
+        );
+        assert_eq!(bundle.gzip.unwrap(), &read_body(resp).await[..]);
+
+        let resp = call_service(
+            &app,
+            get(bundle.path)
+                .insert_header((header::ACCEPT_ENCODING, "br"))
+                .insert_header((header::IF_NONE_MATCH, etag))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(304, resp.status().as_u16());
+
+        let resp = call_service(
+            &app,
+            get(bundle.path)
+                .insert_header((header::ACCEPT_ENCODING, "br"))
+                .insert_header((header::RANGE, "bytes=10-19"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(206, resp.status().as_u16());
+        assert_eq!(
+            Some(format!("bytes 10-19/{}", bundle.data.len())),
+            header(&resp, header::CONTENT_RANGE)
+        );
+        assert_eq!(&bundle.data[10..20], &read_body(resp).await[..]);
+
+        // Changed since, so the whole of it
+        let resp = call_service(
+            &app,
+            get(bundle.path)
+                .insert_header((header::RANGE, "bytes=10-19"))
+                .insert_header((header::IF_RANGE, "\"0000000000000000\""))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(200, resp.status().as_u16());
+        assert_eq!(bundle.data, &read_body(resp).await[..]);
+
+        let resp = call_service(
+            &app,
+            get(bundle.path)
+                .insert_header((header::RANGE, format!("bytes={}-", bundle.data.len())))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(416, resp.status().as_u16());
+
+        let resp = call_service(
+            &app,
+            TestRequest::default()
+                .method(http::Method::HEAD)
+                .uri("/swagger/swagger-ui.css")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(200, resp.status().as_u16());
+        let resp = call_service(&app, get("nothing-here.js").to_request()).await;
+        assert_eq!(404, resp.status().as_u16());
+    }
+}
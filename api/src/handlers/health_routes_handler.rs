@@ -0,0 +1,90 @@
+use crate::health_history::HealthHistory;
+use crate::lifecycle::Readiness;
+use crate::models::common::{ErrorCode, Message};
+use crate::request_id;
+use actix_web::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use thiserror::Error;
+
+#[api_v2_operation(
+    summary = "Liveness check",
+    description = "Succeeds for as long as the server is up",
+    operation_id = "getLiveness",
+    tags(Health)
+)]
+pub async fn healthz() -> web::Json<Message> {
+    web::Json(Message {
+        message: "ok".to_string(),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    })
+}
+
+#[api_v2_operation(
+    summary = "Readiness check",
+    description = "Fails once shutdown has started, while requests are still served until draining. Outcomes are kept in the health history",
+    operation_id = "getReadiness",
+    tags(Health)
+)]
+pub async fn readyz(
+    readiness: web::Data<Readiness>,
+    history: web::Data<HealthHistory>,
+) -> Result<web::Json<Message>, NotReadyError> {
+    let result = if readiness.is_ready() {
+        Ok(web::Json(Message {
+            message: "ready".to_string(),
+            code: None,
+            warnings: Vec::new(),
+            request_id: None,
+        }))
+    } else {
+        Err(NotReadyError::ShuttingDown)
+    };
+    let detail = match &result {
+        Ok(message) => message.message.clone(),
+        Err(e) => e.to_string(),
+    };
+    history.record(result.is_ok(), &detail, chrono::Utc::now());
+    result
+}
+
+#[api_v2_errors(code = 503, description = "Shutting down", schema = "Message")]
+#[derive(Error, Debug)]
+pub enum NotReadyError {
+    #[error("Shutting down")]
+    ShuttingDown,
+}
+
+impl error::ResponseError for NotReadyError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::ServiceUnavailable().json(&Message {
+            message: self.to_string(),
+            code: Some(ErrorCode::NotReady),
+            warnings: Vec::new(),
+            request_id: request_id::current(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{health_history, lifecycle};
+
+    #[actix_web::test]
+    async fn test_readyz() {
+        let readiness = web::Data::new(lifecycle::readiness());
+        let history = web::Data::new(health_history::new(10));
+        assert!(readyz(readiness.clone(), history.clone()).await.is_ok());
+        readiness.set_not_ready();
+        match readyz(readiness, history.clone()).await {
+            Err(NotReadyError::ShuttingDown) => {}
+            other => panic!("Unexpected {:?}", other.map(|j| j.0)),
+        }
+        let periods = history.periods();
+        assert_eq!(2, periods.len());
+        assert!(periods[0].ready);
+        assert_eq!("Shutting down", &periods[1].detail);
+    }
+}
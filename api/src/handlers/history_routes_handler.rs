@@ -0,0 +1,55 @@
+use crate::controllers::history_controller::*;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::history::TodoChange;
+use crate::models::todo::TodoId;
+use crate::request_id;
+use actix_web::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use std::ops::Deref;
+
+#[api_v2_operation(
+    summary = "A todo's change history",
+    description = "Every change to the todo, oldest first, with who made it. Still served after the todo is deleted",
+    operation_id = "getTodoHistory",
+    tags(Todos)
+)]
+pub async fn history<A: HistoryController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+) -> Result<web::Json<Vec<TodoChange>>, HistoryRoutesLookupError> {
+    let controller = web.get_ref();
+    let changes = controller.history(id.deref()).await?;
+    Ok(web::Json(changes))
+}
+
+use thiserror::Error;
+
+#[api_v2_errors(code = 404, description = "No such todo", schema = "Message")]
+#[derive(Error, Debug)]
+pub enum HistoryRoutesLookupError {
+    #[error("No such task")]
+    NoSuchTask { id: TodoId },
+}
+
+impl error::ResponseError for HistoryRoutesLookupError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            HistoryRoutesLookupError::NoSuchTask { id } => {
+                HttpResponse::NotFound().json(&Message {
+                    message: format!("No such todo: [{:?}]", id),
+                    code: Some(ErrorCode::TodoNotFound),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+        }
+    }
+}
+
+impl From<HistoryControllerLookupErr> for HistoryRoutesLookupError {
+    fn from(e: HistoryControllerLookupErr) -> Self {
+        match e {
+            HistoryControllerLookupErr::NotFound(id) => HistoryRoutesLookupError::NoSuchTask { id },
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::controllers::list_controller::*;
+use crate::created::Created;
+use crate::handlers::todo_routes_handler::TodoRoutesLookupError;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::list::*;
+use crate::models::todo::Todo;
+use crate::request_id;
+use actix_web::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use std::ops::Deref;
+
+#[api_v2_operation(
+    summary = "List lists",
+    description = "Returns every list of todos, ordered by id",
+    operation_id = "listLists",
+    tags(Lists)
+)]
+pub async fn list<A: ListController + Send + Sync + 'static>(
+    web: web::Data<A>,
+) -> Result<web::Json<Vec<List>>, Error> {
+    let controller = web.get_ref();
+    let listed = controller.list().await;
+    Ok(web::Json(listed))
+}
+
+#[api_v2_operation(
+    summary = "Create a list",
+    description = "Creates a list that todos can be put in by setting their list_id. Responds with a 201 and the list's path in the Location header",
+    operation_id = "createList",
+    tags(Lists)
+)]
+pub async fn create<A: ListController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    json: web::Json<ListData>,
+) -> Result<Created<List>, ListRoutesDataError> {
+    let controller = web.get_ref();
+    let list = controller.create(json.deref()).await?;
+    Ok(Created {
+        location: format!("/lists/{}", list.id),
+        body: list,
+    })
+}
+
+#[api_v2_operation(summary = "Get a list", operation_id = "getList", tags(Lists))]
+pub async fn get<A: ListController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<ListId>,
+) -> Result<web::Json<List>, ListRoutesLookupError> {
+    let controller = web.get_ref();
+    let list = controller.get(id.deref()).await?;
+    Ok(web::Json(list))
+}
+
+#[api_v2_operation(
+    summary = "Delete a list",
+    description = "Fails with a 409 while any todo is still in the list",
+    operation_id = "deleteList",
+    tags(Lists)
+)]
+pub async fn delete<A: ListController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<ListId>,
+) -> Result<web::Json<Message>, ListRoutesDeleteError> {
+    let controller = web.get_ref();
+    controller.delete(id.deref()).await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully deleted: [{:?}]", id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    }))
+}
+
+#[api_v2_operation(
+    summary = "List a list's todos",
+    description = "Returns the todos in the list, ordered by id",
+    operation_id = "listListTodos",
+    tags(Lists)
+)]
+pub async fn todos<A: ListController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<ListId>,
+) -> Result<web::Json<Vec<Todo>>, ListRoutesLookupError> {
+    let controller = web.get_ref();
+    let todos = controller.todos(id.deref()).await?;
+    Ok(web::Json(todos))
+}
+
+use thiserror::Error;
+
+#[api_v2_errors(code = 400, description = "Invalid list data", schema = "Message")]
+#[derive(Error, Debug)]
+pub enum ListRoutesDataError {
+    #[error("Bad list data")]
+    BadList { reason: String },
+}
+
+#[api_v2_errors(
+    code = 404,
+    description = "No such list",
+    schema = "Message",
+    code = 500,
+    description = "The todos' storage failed",
+    schema = "Message",
+    code = 503,
+    description = "The todos' storage couldn't be reached in time",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum ListRoutesLookupError {
+    #[error("No such list")]
+    NoSuchList { id: ListId },
+    // Reading the list's todos failed
+    #[error(transparent)]
+    Todos(#[from] TodoRoutesLookupError),
+}
+
+#[api_v2_errors(
+    code = 404,
+    description = "No such list",
+    schema = "Message",
+    code = 409,
+    description = "Todos are still in the list",
+    schema = "Message",
+    code = 500,
+    description = "The todos' storage failed",
+    schema = "Message",
+    code = 503,
+    description = "The todos' storage couldn't be reached in time",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum ListRoutesDeleteError {
+    #[error(transparent)]
+    Lookup(#[from] ListRoutesLookupError),
+    #[error("List is not empty")]
+    NotEmpty { id: ListId },
+}
+
+impl error::ResponseError for ListRoutesDataError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ListRoutesDataError::BadList { reason } => HttpResponse::BadRequest().json(&Message {
+                message: invalid_list_message(reason),
+                code: Some(ErrorCode::ListInvalid),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+        }
+    }
+}
+
+impl error::ResponseError for ListRoutesLookupError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ListRoutesLookupError::NoSuchList { id } => HttpResponse::NotFound().json(&Message {
+                message: format!("No such list: [{:?}]", id),
+                code: Some(ErrorCode::ListNotFound),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+            ListRoutesLookupError::Todos(e) => e.error_response(),
+        }
+    }
+}
+
+impl error::ResponseError for ListRoutesDeleteError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ListRoutesDeleteError::Lookup(e) => e.error_response(),
+            ListRoutesDeleteError::NotEmpty { id } => HttpResponse::Conflict().json(&Message {
+                message: format!("List [{:?}] still has todos; move or delete them first", id),
+                code: Some(ErrorCode::ListNotEmpty),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+        }
+    }
+}
+
+impl From<ListControllerDataErr> for ListRoutesDataError {
+    fn from(e: ListControllerDataErr) -> Self {
+        match e {
+            ListControllerDataErr::InvalidData { reason } => {
+                ListRoutesDataError::BadList { reason }
+            }
+        }
+    }
+}
+
+impl From<ListControllerLookupErr> for ListRoutesLookupError {
+    fn from(e: ListControllerLookupErr) -> Self {
+        match e {
+            ListControllerLookupErr::NotFound(id) => ListRoutesLookupError::NoSuchList { id },
+            ListControllerLookupErr::TodosFailed(e) => TodoRoutesLookupError::from(e).into(),
+        }
+    }
+}
+
+impl From<ListControllerDeleteErr> for ListRoutesDeleteError {
+    fn from(e: ListControllerDeleteErr) -> Self {
+        match e {
+            ListControllerDeleteErr::NotFound(id) => {
+                ListRoutesLookupError::NoSuchList { id }.into()
+            }
+            ListControllerDeleteErr::NotEmpty(id) => ListRoutesDeleteError::NotEmpty { id },
+            ListControllerDeleteErr::TodosFailed(e) => {
+                ListRoutesLookupError::from(TodoRoutesLookupError::from(e)).into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::error::ResponseError;
+    use async_trait::async_trait;
+    use std::sync::*;
+
+    static NOT_FOUND_LIST_ID: ListId = ListId(999);
+    static NOT_EMPTY_LIST_ID: ListId = ListId(2);
+
+    #[actix_web::test]
+    async fn test_create() {
+        let mock_controller = MockListController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let list_data = ListData {
+            name: "Chores".to_string(),
+        };
+        let resp = create::<MockListController>(app_data, web::Json(list_data))
+            .await
+            .unwrap();
+        assert_eq!("Chores", &resp.body.name);
+        assert_eq!(format!("/lists/{}", resp.body.id.0), resp.location);
+        let times_called = *mock_controller.create_called.lock().unwrap();
+        assert_eq!(1, times_called);
+    }
+
+    #[actix_web::test]
+    async fn test_delete() {
+        let mock_controller = MockListController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let not_found = delete::<MockListController>(app_data.clone(), NOT_FOUND_LIST_ID.into())
+            .await
+            .unwrap_err();
+        assert_eq!(404, not_found.error_response().status().as_u16());
+        let not_empty = delete::<MockListController>(app_data, NOT_EMPTY_LIST_ID.into())
+            .await
+            .unwrap_err();
+        assert_eq!(409, not_empty.error_response().status().as_u16());
+        let times_called = *mock_controller.delete_called.lock().unwrap();
+        assert_eq!(2, times_called);
+    }
+
+    #[derive(Clone)]
+    struct MockListController {
+        create_called: Arc<Mutex<usize>>,
+        delete_called: Arc<Mutex<usize>>,
+    }
+
+    impl MockListController {
+        fn new() -> MockListController {
+            MockListController {
+                create_called: Arc::new(Mutex::new(0)),
+                delete_called: Arc::new(Mutex::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ListController for MockListController {
+        async fn create(&self, list_data: &ListData) -> Result<List, ListControllerDataErr> {
+            let mut mutex = self.create_called.lock().unwrap();
+            *mutex += 1;
+            Ok(List {
+                id: ListId(1),
+                name: list_data.name.clone(),
+            })
+        }
+
+        async fn get(&self, _: &ListId) -> Result<List, ListControllerLookupErr> {
+            unimplemented!()
+        }
+
+        async fn list(&self) -> Vec<List> {
+            Vec::new()
+        }
+
+        async fn delete(&self, list_id: &ListId) -> Result<(), ListControllerDeleteErr> {
+            let mut mutex = self.delete_called.lock().unwrap();
+            *mutex += 1;
+            if *list_id == NOT_FOUND_LIST_ID {
+                Err(ListControllerDeleteErr::NotFound(*list_id))
+            } else if *list_id == NOT_EMPTY_LIST_ID {
+                Err(ListControllerDeleteErr::NotEmpty(*list_id))
+            } else {
+                Ok(())
+            }
+        }
+
+        async fn todos(&self, _: &ListId) -> Result<Vec<Todo>, ListControllerLookupErr> {
+            unimplemented!()
+        }
+    }
+}
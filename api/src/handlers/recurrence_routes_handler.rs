@@ -0,0 +1,239 @@
+use crate::controllers::recurrence_controller::*;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::recurrence::*;
+use crate::models::todo::TodoId;
+use crate::request_id;
+use actix_web::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use std::ops::Deref;
+
+#[api_v2_operation(
+    summary = "Make a todo recur",
+    description = "Replaces any recurrence the todo already has. Once the todo is done, a new todo with the same task is created on the day the next occurrence is due",
+    operation_id = "setRecurrence",
+    tags(Todos)
+)]
+pub async fn set<A: RecurrenceController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+    json: web::Json<Recurrence>,
+) -> Result<web::Json<Message>, RecurrenceRoutesSetError> {
+    let controller = web.get_ref();
+    controller.set(id.deref(), json.deref()).await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully made recur: [{:?}]", id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    }))
+}
+
+#[api_v2_operation(
+    summary = "Get a todo's recurrence",
+    operation_id = "getRecurrence",
+    tags(Todos)
+)]
+pub async fn get<A: RecurrenceController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+) -> Result<web::Json<Recurrence>, RecurrenceRoutesLookupError> {
+    let controller = web.get_ref();
+    let recurrence = controller.get(id.deref()).await?;
+    Ok(web::Json(recurrence))
+}
+
+#[api_v2_operation(
+    summary = "Stop a todo recurring",
+    description = "Also cancels its next occurrence, if it's done and that is yet to be created",
+    operation_id = "deleteRecurrence",
+    tags(Todos)
+)]
+pub async fn delete<A: RecurrenceController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+) -> Result<web::Json<Message>, RecurrenceRoutesLookupError> {
+    let controller = web.get_ref();
+    controller.delete(id.deref()).await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully stopped recurring: [{:?}]", id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    }))
+}
+
+use thiserror::Error;
+
+#[api_v2_errors(
+    code = 404,
+    description = "No such todo, or it doesn't recur",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum RecurrenceRoutesLookupError {
+    #[error("No such task")]
+    NoSuchTask { id: TodoId },
+    #[error("Task doesn't recur")]
+    NotRecurring { id: TodoId },
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Invalid recurrence",
+    schema = "Message",
+    code = 404,
+    description = "No such todo",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum RecurrenceRoutesSetError {
+    #[error("Bad recurrence")]
+    BadRecurrence { reason: String },
+    #[error("No such task")]
+    NoSuchTask { id: TodoId },
+}
+
+impl error::ResponseError for RecurrenceRoutesLookupError {
+    fn error_response(&self) -> HttpResponse {
+        let (message, code) = match self {
+            RecurrenceRoutesLookupError::NoSuchTask { id } => {
+                (format!("No such todo: [{:?}]", id), ErrorCode::TodoNotFound)
+            }
+            RecurrenceRoutesLookupError::NotRecurring { id } => (
+                format!("Todo [{:?}] doesn't recur", id),
+                ErrorCode::RecurrenceNotFound,
+            ),
+        };
+        HttpResponse::NotFound().json(&Message {
+            message,
+            code: Some(code),
+            warnings: Vec::new(),
+            request_id: request_id::current(),
+        })
+    }
+}
+
+impl error::ResponseError for RecurrenceRoutesSetError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            RecurrenceRoutesSetError::BadRecurrence { reason } => {
+                HttpResponse::BadRequest().json(&Message {
+                    message: invalid_recurrence_message(reason),
+                    code: Some(ErrorCode::RecurrenceInvalid),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+            RecurrenceRoutesSetError::NoSuchTask { id } => {
+                RecurrenceRoutesLookupError::NoSuchTask { id: *id }.error_response()
+            }
+        }
+    }
+}
+
+impl From<RecurrenceControllerLookupErr> for RecurrenceRoutesLookupError {
+    fn from(e: RecurrenceControllerLookupErr) -> Self {
+        match e {
+            RecurrenceControllerLookupErr::NoSuchTodo(id) => {
+                RecurrenceRoutesLookupError::NoSuchTask { id }
+            }
+            RecurrenceControllerLookupErr::NotRecurring(id) => {
+                RecurrenceRoutesLookupError::NotRecurring { id }
+            }
+        }
+    }
+}
+
+impl From<RecurrenceControllerSetErr> for RecurrenceRoutesSetError {
+    fn from(e: RecurrenceControllerSetErr) -> Self {
+        match e {
+            RecurrenceControllerSetErr::NoSuchTodo(id) => {
+                RecurrenceRoutesSetError::NoSuchTask { id }
+            }
+            RecurrenceControllerSetErr::InvalidData { reason } => {
+                RecurrenceRoutesSetError::BadRecurrence { reason }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::error::ResponseError;
+    use async_trait::async_trait;
+
+    static NOT_FOUND_TODO_ID: TodoId = TodoId(999);
+    static NOT_RECURRING_TODO_ID: TodoId = TodoId(2);
+
+    #[actix_web::test]
+    async fn test_set() {
+        let app_data = web::Data::new(MockRecurrenceController);
+        let invalid = Recurrence {
+            repeat: Repeat::Weekdays,
+            on: Vec::new(),
+        };
+        let bad =
+            set::<MockRecurrenceController>(app_data.clone(), TodoId(1).into(), web::Json(invalid))
+                .await
+                .unwrap_err();
+        assert_eq!(400, bad.error_response().status().as_u16());
+        let daily = Recurrence {
+            repeat: Repeat::Daily,
+            on: Vec::new(),
+        };
+        let not_found =
+            set::<MockRecurrenceController>(app_data, NOT_FOUND_TODO_ID.into(), web::Json(daily))
+                .await
+                .unwrap_err();
+        assert_eq!(404, not_found.error_response().status().as_u16());
+    }
+
+    #[actix_web::test]
+    async fn test_get() {
+        let app_data = web::Data::new(MockRecurrenceController);
+        let recurrence = get::<MockRecurrenceController>(app_data.clone(), TodoId(1).into())
+            .await
+            .unwrap();
+        assert_eq!(Repeat::Daily, recurrence.repeat);
+        let not_recurring = get::<MockRecurrenceController>(app_data, NOT_RECURRING_TODO_ID.into())
+            .await
+            .unwrap_err();
+        assert_eq!(404, not_recurring.error_response().status().as_u16());
+    }
+
+    #[derive(Clone)]
+    struct MockRecurrenceController;
+
+    #[async_trait]
+    impl RecurrenceController for MockRecurrenceController {
+        async fn set(
+            &self,
+            todo_id: &TodoId,
+            recurrence: &Recurrence,
+        ) -> Result<(), RecurrenceControllerSetErr> {
+            if let Err(reason) = domain::recurrence::Recurrence::from(recurrence).validate() {
+                Err(RecurrenceControllerSetErr::InvalidData { reason })
+            } else if *todo_id == NOT_FOUND_TODO_ID {
+                Err(RecurrenceControllerSetErr::NoSuchTodo(*todo_id))
+            } else {
+                Ok(())
+            }
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Result<Recurrence, RecurrenceControllerLookupErr> {
+            if *todo_id == NOT_RECURRING_TODO_ID {
+                Err(RecurrenceControllerLookupErr::NotRecurring(*todo_id))
+            } else {
+                Ok(Recurrence {
+                    repeat: Repeat::Daily,
+                    on: Vec::new(),
+                })
+            }
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), RecurrenceControllerLookupErr> {
+            unimplemented!()
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::controllers::reminder_controller::*;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::reminder::*;
+use crate::models::todo::TodoId;
+use crate::request_id;
+use actix_web::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use std::ops::Deref;
+
+#[api_v2_operation(
+    summary = "Set a todo's reminder",
+    description = "Replaces any reminder the todo already has. It's sent through the configured notifier once due, unless the todo has been done by then",
+    operation_id = "setReminder",
+    tags(Reminders)
+)]
+pub async fn set<A: ReminderController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+    json: web::Json<ReminderData>,
+) -> Result<web::Json<Reminder>, ReminderRoutesSetError> {
+    let controller = web.get_ref();
+    let reminder = controller.set(id.deref(), json.deref()).await?;
+    Ok(web::Json(reminder))
+}
+
+#[api_v2_operation(
+    summary = "Get a todo's reminder",
+    description = "Only while it's pending; reminders are forgotten once sent",
+    operation_id = "getReminder",
+    tags(Reminders)
+)]
+pub async fn get<A: ReminderController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+) -> Result<web::Json<Reminder>, ReminderRoutesLookupError> {
+    let controller = web.get_ref();
+    let reminder = controller.get(id.deref()).await?;
+    Ok(web::Json(reminder))
+}
+
+#[api_v2_operation(
+    summary = "Cancel a todo's reminder",
+    operation_id = "deleteReminder",
+    tags(Reminders)
+)]
+pub async fn delete<A: ReminderController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+) -> Result<web::Json<Message>, ReminderRoutesLookupError> {
+    let controller = web.get_ref();
+    controller.delete(id.deref()).await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully cancelled reminder: [{:?}]", id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    }))
+}
+
+#[api_v2_operation(
+    summary = "List pending reminders",
+    description = "Soonest first",
+    operation_id = "listReminders",
+    tags(Reminders)
+)]
+pub async fn pending<A: ReminderController + Send + Sync + 'static>(
+    web: web::Data<A>,
+) -> web::Json<Vec<Reminder>> {
+    let controller = web.get_ref();
+    web::Json(controller.pending().await)
+}
+
+use thiserror::Error;
+
+#[api_v2_errors(
+    code = 404,
+    description = "No such todo, or it has no pending reminder",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum ReminderRoutesLookupError {
+    #[error("No such task")]
+    NoSuchTask { id: TodoId },
+    #[error("No pending reminder")]
+    NoReminder { id: TodoId },
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Invalid reminder",
+    schema = "Message",
+    code = 404,
+    description = "No such todo",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum ReminderRoutesSetError {
+    #[error("Bad reminder")]
+    BadReminder { reason: String },
+    #[error("No such task")]
+    NoSuchTask { id: TodoId },
+}
+
+impl error::ResponseError for ReminderRoutesLookupError {
+    fn error_response(&self) -> HttpResponse {
+        let (message, code) = match self {
+            ReminderRoutesLookupError::NoSuchTask { id } => {
+                (format!("No such todo: [{:?}]", id), ErrorCode::TodoNotFound)
+            }
+            ReminderRoutesLookupError::NoReminder { id } => (
+                format!("Todo [{:?}] has no pending reminder", id),
+                ErrorCode::ReminderNotFound,
+            ),
+        };
+        HttpResponse::NotFound().json(&Message {
+            message,
+            code: Some(code),
+            warnings: Vec::new(),
+            request_id: request_id::current(),
+        })
+    }
+}
+
+impl error::ResponseError for ReminderRoutesSetError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ReminderRoutesSetError::BadReminder { reason } => {
+                HttpResponse::BadRequest().json(&Message {
+                    message: invalid_reminder_message(reason),
+                    code: Some(ErrorCode::ReminderInvalid),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+            ReminderRoutesSetError::NoSuchTask { id } => {
+                ReminderRoutesLookupError::NoSuchTask { id: *id }.error_response()
+            }
+        }
+    }
+}
+
+impl From<ReminderControllerLookupErr> for ReminderRoutesLookupError {
+    fn from(e: ReminderControllerLookupErr) -> Self {
+        match e {
+            ReminderControllerLookupErr::NoSuchTodo(id) => {
+                ReminderRoutesLookupError::NoSuchTask { id }
+            }
+            ReminderControllerLookupErr::NoReminder(id) => {
+                ReminderRoutesLookupError::NoReminder { id }
+            }
+        }
+    }
+}
+
+impl From<ReminderControllerSetErr> for ReminderRoutesSetError {
+    fn from(e: ReminderControllerSetErr) -> Self {
+        match e {
+            ReminderControllerSetErr::NoSuchTodo(id) => ReminderRoutesSetError::NoSuchTask { id },
+            ReminderControllerSetErr::InvalidData { reason } => {
+                ReminderRoutesSetError::BadReminder { reason }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::error::ResponseError;
+    use async_trait::async_trait;
+
+    static NOT_FOUND_TODO_ID: TodoId = TodoId(999);
+    static REMIND_AT: &str = "2024-01-31T12:00:00+00:00";
+
+    #[actix_web::test]
+    async fn test_set() {
+        let app_data = web::Data::new(MockReminderController);
+        let reminder_data = |remind_at: &str| {
+            web::Json(ReminderData {
+                remind_at: remind_at.to_string(),
+            })
+        };
+        let reminder = set::<MockReminderController>(
+            app_data.clone(),
+            TodoId(1).into(),
+            reminder_data(REMIND_AT),
+        )
+        .await
+        .unwrap();
+        assert_eq!(REMIND_AT, reminder.remind_at);
+        let bad = set::<MockReminderController>(
+            app_data.clone(),
+            TodoId(1).into(),
+            reminder_data("soon"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(400, bad.error_response().status().as_u16());
+        let not_found = set::<MockReminderController>(
+            app_data,
+            NOT_FOUND_TODO_ID.into(),
+            reminder_data(REMIND_AT),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(404, not_found.error_response().status().as_u16());
+    }
+
+    #[derive(Clone)]
+    struct MockReminderController;
+
+    #[async_trait]
+    impl ReminderController for MockReminderController {
+        async fn set(
+            &self,
+            todo_id: &TodoId,
+            reminder_data: &ReminderData,
+        ) -> Result<Reminder, ReminderControllerSetErr> {
+            if let Err(reason) = domain::reminder::parse_remind_at(&reminder_data.remind_at) {
+                Err(ReminderControllerSetErr::InvalidData { reason })
+            } else if *todo_id == NOT_FOUND_TODO_ID {
+                Err(ReminderControllerSetErr::NoSuchTodo(*todo_id))
+            } else {
+                Ok(Reminder {
+                    todo_id: *todo_id,
+                    remind_at: reminder_data.remind_at.clone(),
+                })
+            }
+        }
+
+        async fn get(&self, _: &TodoId) -> Result<Reminder, ReminderControllerLookupErr> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), ReminderControllerLookupErr> {
+            unimplemented!()
+        }
+
+        async fn pending(&self) -> Vec<Reminder> {
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::controllers::rule_controller::*;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::rule::*;
+use crate::request_id;
+use actix_web::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use std::ops::Deref;
+
+#[api_v2_operation(
+    summary = "List rules",
+    description = "Returns every automation rule, in the order they are evaluated",
+    operation_id = "listRules",
+    tags(Rules)
+)]
+pub async fn list<A: RuleController + Send + Sync + 'static>(
+    web: web::Data<A>,
+) -> Result<web::Json<Vec<Rule>>, Error> {
+    let controller = web.get_ref();
+    let listed = controller.list().await;
+    Ok(web::Json(listed))
+}
+
+#[api_v2_operation(
+    summary = "Create a rule",
+    description = "Creates a rule that is evaluated against every todo event from now on. Todos created by rules do not trigger other rules",
+    operation_id = "createRule",
+    tags(Rules)
+)]
+pub async fn create<A: RuleController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    json: web::Json<RuleData>,
+) -> Result<web::Json<Rule>, RuleRoutesDataError> {
+    let controller = web.get_ref();
+    let rule = controller.create(json.deref()).await?;
+    Ok(web::Json(rule))
+}
+
+#[api_v2_operation(summary = "Delete a rule", operation_id = "deleteRule", tags(Rules))]
+pub async fn delete<A: RuleController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<RuleId>,
+) -> Result<web::Json<Message>, RuleRoutesLookupError> {
+    let controller = web.get_ref();
+    controller.delete(id.deref()).await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully deleted: [{:?}]", id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    }))
+}
+
+use thiserror::Error;
+
+#[api_v2_errors(code = 400, description = "Invalid rule data", schema = "Message")]
+#[derive(Error, Debug)]
+pub enum RuleRoutesDataError {
+    #[error("Bad rule data")]
+    BadRule { reason: String },
+}
+
+#[api_v2_errors(code = 404, description = "No such rule", schema = "Message")]
+#[derive(Error, Debug)]
+pub enum RuleRoutesLookupError {
+    #[error("No such rule")]
+    NoSuchRule { id: RuleId },
+}
+
+impl error::ResponseError for RuleRoutesDataError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            RuleRoutesDataError::BadRule { reason } => HttpResponse::BadRequest().json(&Message {
+                message: invalid_rule_message(reason),
+                code: Some(ErrorCode::RuleInvalid),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+        }
+    }
+}
+
+impl error::ResponseError for RuleRoutesLookupError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            RuleRoutesLookupError::NoSuchRule { id } => HttpResponse::NotFound().json(&Message {
+                message: format!("No such rule: [{:?}]", id),
+                code: Some(ErrorCode::RuleNotFound),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+        }
+    }
+}
+
+impl From<RuleControllerDataErr> for RuleRoutesDataError {
+    fn from(e: RuleControllerDataErr) -> Self {
+        match e {
+            RuleControllerDataErr::InvalidData { reason } => {
+                RuleRoutesDataError::BadRule { reason }
+            }
+        }
+    }
+}
+
+impl From<RuleControllerLookupErr> for RuleRoutesLookupError {
+    fn from(e: RuleControllerLookupErr) -> Self {
+        match e {
+            RuleControllerLookupErr::NotFound(id) => RuleRoutesLookupError::NoSuchRule { id },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::*;
+
+    static NOT_FOUND_RULE_ID: RuleId = RuleId(999);
+
+    fn rule_data() -> RuleData {
+        RuleData {
+            name: "follow up".to_string(),
+            when: RuleCondition {
+                event: RuleTrigger::Created,
+                task_contains: None,
+            },
+            then: RuleAction {
+                create_todo: "Follow up on {{task}}".to_string(),
+            },
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_create() {
+        let mock_controller = MockRuleController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let resp = create::<MockRuleController>(app_data, web::Json(rule_data()))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!("follow up", &resp.name);
+        let times_called = *mock_controller.create_called.lock().unwrap();
+        assert_eq!(1, times_called);
+    }
+
+    #[actix_web::test]
+    async fn test_delete_not_found() {
+        use actix_web::error::ResponseError;
+        let mock_controller = MockRuleController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let err = delete::<MockRuleController>(app_data, NOT_FOUND_RULE_ID.into())
+            .await
+            .unwrap_err();
+        assert_eq!(404, err.error_response().status().as_u16());
+        let times_called = *mock_controller.delete_called.lock().unwrap();
+        assert_eq!(1, times_called);
+    }
+
+    #[derive(Clone)]
+    struct MockRuleController {
+        create_called: Arc<Mutex<usize>>,
+        delete_called: Arc<Mutex<usize>>,
+    }
+
+    impl MockRuleController {
+        fn new() -> MockRuleController {
+            MockRuleController {
+                create_called: Arc::new(Mutex::new(0)),
+                delete_called: Arc::new(Mutex::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl RuleController for MockRuleController {
+        async fn create(&self, rule_data: &RuleData) -> Result<Rule, RuleControllerDataErr> {
+            let mut mutex = self.create_called.lock().unwrap();
+            *mutex += 1;
+            Ok(Rule {
+                id: RuleId(1),
+                name: rule_data.name.clone(),
+                when: rule_data.when.clone(),
+                then: rule_data.then.clone(),
+            })
+        }
+
+        async fn list(&self) -> Vec<Rule> {
+            Vec::new()
+        }
+
+        async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleControllerLookupErr> {
+            let mut mutex = self.delete_called.lock().unwrap();
+            *mutex += 1;
+            if *rule_id == NOT_FOUND_RULE_ID {
+                Err(RuleControllerLookupErr::NotFound(*rule_id))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::controllers::share_controller::*;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::share::*;
+use crate::models::todo::{Todo, TodoId};
+use crate::{embed, errors, request_id};
+use actix_web::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
+use std::ops::Deref;
+
+#[api_v2_operation(
+    summary = "Share a todo",
+    description = "Creates a link with an unguessable token that gives read-only access to the todo until it expires or is revoked",
+    operation_id = "createShareLink",
+    tags(Sharing)
+)]
+pub async fn create<A: ShareController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+    json: web::Json<ShareLinkData>,
+) -> Result<web::Json<ShareLink>, ShareRoutesCreateError> {
+    let controller = web.get_ref();
+    let link = controller.create(id.deref(), json.deref()).await?;
+    Ok(web::Json(link))
+}
+
+#[api_v2_operation(
+    summary = "List a todo's share links",
+    description = "Returns the share links of a todo that haven't expired or been revoked",
+    operation_id = "listShareLinks",
+    tags(Sharing)
+)]
+pub async fn list<A: ShareController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+) -> Result<web::Json<Vec<ShareLink>>, ShareRoutesLookupError> {
+    let controller = web.get_ref();
+    let links = controller.list(id.deref()).await?;
+    Ok(web::Json(links))
+}
+
+#[api_v2_operation(
+    summary = "Revoke a share link",
+    operation_id = "revokeShareLink",
+    tags(Sharing)
+)]
+pub async fn revoke<A: ShareController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    path: web::Path<ShareLinkPath>,
+) -> Result<web::Json<Message>, ShareRoutesLookupError> {
+    let controller = web.get_ref();
+    let todo_id = path.id;
+    controller
+        .revoke(&todo_id, &ShareToken(path.token.clone()))
+        .await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully revoked a share link of: [{:?}]", todo_id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    }))
+}
+
+#[api_v2_operation(
+    summary = "Read a shared todo",
+    description = "Returns the todo behind a share link, for anyone with the token",
+    operation_id = "getSharedTodo",
+    tags(Sharing)
+)]
+pub async fn shared<A: ShareController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    token: web::Path<ShareToken>,
+) -> Result<web::Json<Todo>, ShareRoutesLookupError> {
+    let controller = web.get_ref();
+    let todo = controller.shared(token.deref()).await?;
+    Ok(web::Json(todo))
+}
+
+#[api_v2_operation(
+    summary = "Embed a shared todo",
+    description = "An HTML page showing the todo behind a share link, for iframes. It loads nothing else, runs no scripts and sets no cookies",
+    operation_id = "embedSharedTodo",
+    tags(Sharing)
+)]
+pub async fn embed<A: ShareController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    token: web::Path<ShareToken>,
+) -> Result<HttpResponse, Error> {
+    let controller = web.get_ref();
+    let todo = controller
+        .shared(token.deref())
+        .await
+        .map_err(ShareRoutesLookupError::from)?;
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let html = embed::render(&todo, &nonce).map_err(errors::internal)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((
+            http::header::CONTENT_SECURITY_POLICY,
+            embed::content_security_policy(&nonce),
+        ))
+        .insert_header((http::header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header((http::header::REFERRER_POLICY, "no-referrer"))
+        // So that revoking the link takes effect right away
+        .insert_header((http::header::CACHE_CONTROL, "no-store"))
+        .body(html))
+}
+
+use thiserror::Error;
+
+#[api_v2_errors(
+    code = 404,
+    description = "No such todo, or share link",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum ShareRoutesLookupError {
+    #[error("No such task")]
+    NoSuchTask { id: TodoId },
+    // Also covers expired and revoked links
+    #[error("No such share link")]
+    NoSuchShare,
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Invalid share link data",
+    schema = "Message",
+    code = 404,
+    description = "No such todo",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum ShareRoutesCreateError {
+    #[error("Bad share link data")]
+    BadShare { reason: String },
+    #[error("No such task")]
+    NoSuchTask { id: TodoId },
+}
+
+impl error::ResponseError for ShareRoutesLookupError {
+    fn error_response(&self) -> HttpResponse {
+        let (message, code) = match self {
+            ShareRoutesLookupError::NoSuchTask { id } => {
+                (format!("No such todo: [{:?}]", id), ErrorCode::TodoNotFound)
+            }
+            ShareRoutesLookupError::NoSuchShare => (
+                "No such share link; it may have expired or been revoked".to_string(),
+                ErrorCode::ShareLinkNotFound,
+            ),
+        };
+        HttpResponse::NotFound().json(&Message {
+            message,
+            code: Some(code),
+            warnings: Vec::new(),
+            request_id: request_id::current(),
+        })
+    }
+}
+
+impl error::ResponseError for ShareRoutesCreateError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ShareRoutesCreateError::BadShare { reason } => {
+                HttpResponse::BadRequest().json(&Message {
+                    message: invalid_share_link_message(reason),
+                    code: Some(ErrorCode::ShareLinkInvalid),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+            ShareRoutesCreateError::NoSuchTask { id } => {
+                ShareRoutesLookupError::NoSuchTask { id: *id }.error_response()
+            }
+        }
+    }
+}
+
+impl From<ShareControllerLookupErr> for ShareRoutesLookupError {
+    fn from(e: ShareControllerLookupErr) -> Self {
+        match e {
+            ShareControllerLookupErr::NoSuchTodo(id) => ShareRoutesLookupError::NoSuchTask { id },
+            ShareControllerLookupErr::NoSuchShare => ShareRoutesLookupError::NoSuchShare,
+        }
+    }
+}
+
+impl From<ShareControllerCreateErr> for ShareRoutesCreateError {
+    fn from(e: ShareControllerCreateErr) -> Self {
+        match e {
+            ShareControllerCreateErr::NoSuchTodo(id) => ShareRoutesCreateError::NoSuchTask { id },
+            ShareControllerCreateErr::InvalidData { reason } => {
+                ShareRoutesCreateError::BadShare { reason }
+            }
+        }
+    }
+}
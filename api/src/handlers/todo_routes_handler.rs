@@ -1,136 +1,786 @@
 use crate::controllers::todo_controller::*;
-use crate::models::common::Message;
-use crate::models::todo::{Todo, TodoData, TodoId};
+use crate::created::Created;
+use crate::dry_run::OrPlan;
+use crate::errors;
+use crate::etag;
+use crate::etag::ETagged;
+use crate::import_export;
+use crate::models::common::{ErrorCode, Message};
+use crate::models::list::ListId;
+use crate::models::todo::*;
+use crate::request_id;
 use actix_web::*;
-use futures::future::{FutureExt, TryFutureExt};
-use futures_01::Future as Future01;
-use paperclip::actix::{api_v2_operation, api_v2_schema};
+use futures::{select, StreamExt};
+use log::*;
+use paperclip::actix::{api_v2_errors, api_v2_operation};
 use std::ops::Deref;
 
-#[api_v2_operation]
-pub fn list<A: TodoController + Send + Sync + 'static>(
+#[api_v2_operation(
+    summary = "List todos",
+    description = "Returns every todo, ordered by id, or by position with order=manual. With offset and limit, returns just that page of them",
+    operation_id = "listTodos",
+    tags(Todos)
+)]
+pub async fn list<A: TodoController + Send + Sync + 'static>(
     web: web::Data<A>,
-) -> impl Future01<Item = web::Json<Vec<Todo>>, Error = Error> {
-    let f_resp = async move {
-        let controller = web.get_ref();
-        let listed = controller.list().await;
-        Ok(web::Json(listed))
+    params: web::Query<ListParams>,
+    page: web::Query<PageParams>,
+) -> Result<ETagged<Vec<Todo>>, Error> {
+    let controller = web.get_ref();
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit.unwrap_or(usize::MAX);
+    let listed = if params.order == Some(TodoOrder::Manual) {
+        // Positions aren't indexed, so this page is cut from every todo
+        let mut listed = controller
+            .list()
+            .await
+            .map_err(TodoRoutesLookupError::from)?;
+        listed.sort_by_key(|t| t.position);
+        listed.into_iter().skip(offset).take(limit).collect()
+    } else {
+        controller
+            .list_page(offset, limit)
+            .await
+            .map_err(TodoRoutesLookupError::from)?
     };
-    f_resp.boxed().compat()
+    Ok(etag::hashed(listed))
 }
 
-#[api_v2_operation]
-pub fn create<A: TodoController + Send + Sync + 'static>(
+#[api_v2_operation(
+    summary = "Create a todo",
+    description = "Creates a todo from the given data; the task must not be empty. Responds with a 201 and the todo's path in the Location header, along with warnings about non-fatal issues, e.g. another open todo having the same task. With dedupe, such a todo is instead answered with a 409 and its path in the Location header, give or take case and whitespace. With dry_run, responds with a 200 and the plan of what creating it would do instead",
+    operation_id = "createTodo",
+    tags(Todos)
+)]
+pub async fn create<A: TodoController + Send + Sync + 'static>(
     web: web::Data<A>,
+    params: web::Query<DryRunParams>,
+    dedupe_params: web::Query<DedupeParams>,
     json: web::Json<TodoData>,
-) -> impl Future01<Item = web::Json<Todo>, Error = TodoRoutesError> {
-    let f_resp = async move {
-        let controller = web.get_ref();
-        let todo = controller.create(json.deref()).await?;
-        Ok(web::Json(todo))
-    };
-    f_resp.boxed().compat()
+) -> Result<OrPlan<Created<SavedTodo>>, TodoRoutesCreateError> {
+    let controller = web.get_ref();
+    if dedupe_params.dedupe {
+        if let Some(existing) = controller
+            .find_duplicate(json.deref())
+            .await
+            .map_err(TodoRoutesLookupError::from)?
+        {
+            return Err(TodoRoutesCreateError::Duplicate { id: existing.id });
+        }
+    }
+    if params.dry_run {
+        return Ok(OrPlan::Plan(controller.plan_create(json.deref()).await?));
+    }
+    let todo = controller.create(json.deref()).await?;
+    Ok(OrPlan::Done(Created {
+        location: format!("/tasks/{}", todo.id),
+        body: todo,
+    }))
 }
 
-#[api_v2_operation]
-pub fn get<A: TodoController + Send + Sync + 'static>(
+#[api_v2_operation(
+    summary = "Get a todo",
+    description = "The ETag is the todo's version, which updates can be made conditional on with If-Match",
+    operation_id = "getTodo",
+    tags(Todos)
+)]
+pub async fn get<A: TodoController + Send + Sync + 'static>(
     web: web::Data<A>,
     id: web::Path<TodoId>,
-) -> impl Future01<Item = web::Json<Todo>, Error = TodoRoutesError> {
-    let f_resp = async move {
-        let controller = web.get_ref();
-        let get_result = controller.get(id.deref().into()).await?;
-        Ok(web::Json(get_result))
-    };
-    f_resp.boxed().compat()
+) -> Result<ETagged<Todo>, TodoRoutesLookupError> {
+    let controller = web.get_ref();
+    let get_result = controller.get(id.deref()).await?;
+    let version = get_result.version;
+    Ok(etag::versioned(get_result, version))
 }
 
-#[api_v2_operation]
-pub fn delete<A: TodoController + Send + Sync + 'static>(
+#[api_v2_operation(
+    summary = "Delete a todo",
+    description = "With dry_run, responds with the plan of what deleting it would do instead",
+    operation_id = "deleteTodo",
+    tags(Todos)
+)]
+pub async fn delete<A: TodoController + Send + Sync + 'static>(
     web: web::Data<A>,
     id: web::Path<TodoId>,
-) -> impl Future01<Item = web::Json<Message>, Error = TodoRoutesError> {
-    let f_resp = async move {
-        let controller = web.get_ref();
-        let _ = controller.delete(id.deref()).await?;
-        Ok(web::Json(Message {
-            message: format!("Successfully deleted: [{:?}]", id),
-        }))
-    };
-    f_resp.boxed().compat()
+    params: web::Query<DryRunParams>,
+) -> Result<OrPlan<web::Json<Message>>, TodoRoutesLookupError> {
+    let controller = web.get_ref();
+    if params.dry_run {
+        return Ok(OrPlan::Plan(controller.plan_delete(id.deref()).await?));
+    }
+    controller.delete(id.deref()).await?;
+    Ok(OrPlan::Done(web::Json(Message {
+        message: format!("Successfully deleted: [{:?}]", id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    })))
 }
 
-#[api_v2_operation]
-pub fn update<A: TodoController + Send + Sync + 'static>(
+#[api_v2_operation(
+    summary = "Update a todo",
+    description = "Replaces the todo's data; the task must not be empty. With an If-Match of the todo's ETag, fails with a 412 instead if someone else has updated it since. With dry_run, responds with the plan of what updating it would do instead",
+    operation_id = "updateTodo",
+    tags(Todos)
+)]
+pub async fn update<A: TodoController + Send + Sync + 'static>(
     web: web::Data<A>,
     id: web::Path<TodoId>,
+    params: web::Query<DryRunParams>,
+    req: HttpRequest,
     json: web::Json<TodoData>,
-) -> impl Future01<Item = web::Json<Message>, Error = TodoRoutesError> {
-    let f_resp = async move {
-        let controller = web.get_ref();
-        let todo = Todo {
-            id: *id.deref(),
-            task: json.into_inner().task,
-        };
-        let _ = controller.update(&todo).await?;
-        Ok(web::Json(Message {
-            message: format!("Successfully updated: [{:?}]", id),
-        }))
+) -> Result<OrPlan<web::Json<Message>>, TodoRoutesUpdateError> {
+    let controller = web.get_ref();
+    let expected_version = expected_version(controller, &id, &req).await?;
+    let todo_data = json.into_inner();
+    let todo = Todo {
+        id: *id.deref(),
+        task: todo_data.task,
+        done: todo_data.done,
+        version: expected_version.unwrap_or_default(),
+        list_id: todo_data.list_id,
+        // Kept as it is by the repo; only reordering moves todos
+        position: 0,
+    };
+    if params.dry_run {
+        let plan = controller.plan_update(&todo, expected_version).await?;
+        return Ok(OrPlan::Plan(plan));
+    }
+    let updated = controller.update(&todo, expected_version).await?;
+    Ok(OrPlan::Done(web::Json(Message {
+        message: format!("Successfully updated: [{:?}]", id),
+        code: None,
+        warnings: updated.warnings,
+        request_id: None,
+    })))
+}
+
+// The version an If-Match allows updating, failing if the todo has moved on from it
+pub(crate) async fn expected_version<A: TodoController>(
+    controller: &A,
+    id: &TodoId,
+    req: &HttpRequest,
+) -> Result<Option<u64>, TodoRoutesUpdateError> {
+    match req.headers().get(http::header::IF_MATCH) {
+        Some(if_match) => {
+            let current = controller
+                .get(id)
+                .await
+                .map_err(TodoRoutesLookupError::from)?;
+            let allowed = if_match
+                .to_str()
+                .is_ok_and(|v| etag::if_match_allows(v, current.version));
+            if !allowed {
+                return Err(TodoRoutesUpdateError::PreconditionFailed { id: *id });
+            }
+            // Checked again by the repo, in case someone else writes in the meantime
+            Ok(Some(current.version))
+        }
+        None => Ok(None),
+    }
+}
+
+#[api_v2_operation(
+    summary = "Export todos",
+    description = "Downloads every todo as a CSV or JSON file",
+    operation_id = "exportTodos",
+    tags(Todos)
+)]
+pub async fn export<A: TodoController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    params: web::Query<TransferParams>,
+) -> Result<HttpResponse, Error> {
+    let format = params.format.unwrap_or(TransferFormat::Json);
+    let controller = web.get_ref();
+    let todos = controller
+        .list()
+        .await
+        .map_err(TodoRoutesLookupError::from)?;
+    let body = import_export::export(&todos, format).map_err(errors::internal)?;
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            http::header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"todos.{}\"", format.extension()),
+        ))
+        .body(body))
+}
+
+#[api_v2_operation(
+    summary = "Import todos",
+    description = "Creates a todo per row of an uploaded CSV (with a `task` header) or JSON array. Valid rows are created even when others are rejected; rejected rows are listed in the response. With dry_run, nothing is created, and the response lists the events the import would emit",
+    operation_id = "importTodos",
+    tags(Todos)
+)]
+pub async fn import<A: TodoController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    req: HttpRequest,
+    params: web::Query<TransferParams>,
+    dry_run_params: web::Query<DryRunParams>,
+    body: web::Bytes,
+) -> Result<web::Json<ImportReport>, TodoRoutesImportError> {
+    let format = params.format.unwrap_or_else(|| {
+        if req.content_type() == TransferFormat::Csv.content_type() {
+            TransferFormat::Csv
+        } else {
+            TransferFormat::Json
+        }
+    });
+    let rows = import_export::parse_import(&body, format)
+        .map_err(|reason| TodoRoutesImportError::Unreadable { reason })?;
+    let controller = web.get_ref();
+    let mut report = ImportReport {
+        created: Vec::new(),
+        errors: Vec::new(),
+        planned_events: Vec::new(),
     };
-    f_resp.boxed().compat()
+    for (idx, row) in rows.into_iter().enumerate() {
+        let created = match row {
+            // Rows are planned on their own, so e.g. duplicates between them aren't warned about
+            Ok(todo_data) if dry_run_params.dry_run => controller
+                .plan_create(&todo_data)
+                .await
+                .map(|plan| {
+                    report.planned_events.extend(plan.events);
+                    plan.todo
+                })
+                .map_err(data_err_message),
+            Ok(todo_data) => controller
+                .create(&todo_data)
+                .await
+                .map(|saved| Some(saved.into()))
+                .map_err(data_err_message),
+            Err(reason) => Err(reason),
+        };
+        match created {
+            Ok(todo) => report.created.extend(todo),
+            Err(message) => report.errors.push(ImportRowError {
+                row: idx + 1,
+                message,
+            }),
+        }
+    }
+    Ok(web::Json(report))
 }
 
-use failure::Fail;
+#[api_v2_operation(
+    summary = "Complete todos",
+    description = "Marks the todos with the given ids, or every todo in the given list, done all at once. Todos that are already done are left alone, and ids that no todo has are listed in the response rather than failing the request",
+    operation_id = "completeTodos",
+    tags(Todos)
+)]
+pub async fn complete<A: TodoController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    json: web::Json<CompleteTodos>,
+) -> Result<web::Json<CompletedTodos>, TodoRoutesCompleteError> {
+    let controller = web.get_ref();
+    let completed = controller.complete(json.deref()).await?;
+    Ok(web::Json(completed))
+}
 
-#[api_v2_schema]
-#[derive(Fail, Debug)]
-pub enum TodoRoutesError {
-    #[fail(display = "Bad task data")]
+#[api_v2_operation(
+    summary = "Reorder todos",
+    description = "Moves the todos with the given ids to the front of the manual order, in the order given; the rest follow in the order they were in. Responds with every todo in the new order. The todos that moved get new versions, and so new ETags",
+    operation_id = "reorderTodos",
+    tags(Todos)
+)]
+pub async fn reorder<A: TodoController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    json: web::Json<ReorderTodos>,
+) -> Result<web::Json<Vec<Todo>>, TodoRoutesReorderError> {
+    let controller = web.get_ref();
+    let reordered = controller.reorder(json.deref()).await?;
+    Ok(web::Json(reordered))
+}
+
+// Not part of the OpenAPI spec, which cannot describe WebSockets
+#[api_v2_operation(skip)]
+pub async fn subscribe<A: TodoController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let (response, mut session, msg_stream) = actix_ws::handle(&req, body)?;
+    let mut events = web.get_ref().subscribe().fuse();
+    let mut msg_stream = msg_stream.fuse();
+    rt::spawn(async move {
+        loop {
+            select! {
+                event = events.next() => match event.map(|e| serde_json::to_string(&e)) {
+                    Some(Ok(json)) => {
+                        if session.text(json).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Err(e)) => error!("Could not serialise todo event: {}", e),
+                    None => break,
+                },
+                msg = msg_stream.next() => match msg {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => break,
+                },
+            }
+        }
+        let _ = session.close(None).await;
+    });
+    Ok(response)
+}
+
+use thiserror::Error;
+
+// Errors are split per kind of operation so that each one only declares the error
+// responses it can actually return in the generated spec.
+
+#[api_v2_errors(code = 400, description = "Invalid todo data", schema = "Message")]
+#[derive(Error, Debug)]
+pub enum TodoRoutesDataError {
+    #[error("Bad task data")]
     BadTask { task: String },
-    #[fail(display = "No such task")]
+    #[error("Rejected task data")]
+    Rejected { reason: String },
+    #[error("Disallowed task")]
+    Disallowed { task: String, reason: String },
+    #[error("No such list")]
+    NoSuchList { id: ListId },
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Invalid todo data",
+    schema = "Message",
+    code = 409,
+    description = "With dedupe, an open todo already has the same task",
+    schema = "Message",
+    code = 500,
+    description = "The todos' storage failed",
+    schema = "Message",
+    code = 503,
+    description = "The todos' storage couldn't be reached in time",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum TodoRoutesCreateError {
+    #[error(transparent)]
+    Data(#[from] TodoRoutesDataError),
+    #[error("Duplicate task")]
+    Duplicate { id: TodoId },
+    #[error(transparent)]
+    Lookup(#[from] TodoRoutesLookupError),
+}
+
+#[api_v2_errors(
+    code = 404,
+    description = "No such todo",
+    schema = "Message",
+    code = 409,
+    description = "Another write to the todo got in the way",
+    schema = "Message",
+    code = 500,
+    description = "The todos' storage failed",
+    schema = "Message",
+    code = 503,
+    description = "The todos' storage couldn't be reached in time",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum TodoRoutesLookupError {
+    #[error("No such task")]
     NoSuchTask { id: TodoId },
+    #[error("Storage unavailable")]
+    Unavailable { reason: String },
+    #[error("Write conflict")]
+    Conflict { id: TodoId },
+    #[error("Storage failed")]
+    Failed,
 }
 
-use TodoRoutesError::*;
+#[api_v2_errors(
+    code = 400,
+    description = "Invalid todo data",
+    schema = "Message",
+    code = 404,
+    description = "No such todo",
+    schema = "Message",
+    code = 409,
+    description = "Another write to the todo got in the way",
+    schema = "Message",
+    code = 412,
+    description = "The todo has been updated since the version in If-Match",
+    schema = "Message",
+    code = 429,
+    description = "The todo has been changed too often lately",
+    schema = "Message",
+    code = 500,
+    description = "The todos' storage failed",
+    schema = "Message",
+    code = 503,
+    description = "The todos' storage couldn't be reached in time",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum TodoRoutesUpdateError {
+    #[error(transparent)]
+    Data(#[from] TodoRoutesDataError),
+    #[error(transparent)]
+    Lookup(#[from] TodoRoutesLookupError),
+    #[error("Todo has changed")]
+    PreconditionFailed { id: TodoId },
+    #[error("Todo is changing too often")]
+    Flooded { id: TodoId, retry_after_secs: u64 },
+}
 
-impl error::ResponseError for TodoRoutesError {
+#[api_v2_errors(
+    code = 400,
+    description = "Neither or both of ids and list_id given, or no such list",
+    schema = "Message",
+    code = 409,
+    description = "Another write to a todo got in the way",
+    schema = "Message",
+    code = 500,
+    description = "The todos' storage failed",
+    schema = "Message",
+    code = 503,
+    description = "The todos' storage couldn't be reached in time",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum TodoRoutesCompleteError {
+    #[error("Bad selection")]
+    BadSelection { reason: String },
+    #[error(transparent)]
+    Data(#[from] TodoRoutesDataError),
+    #[error(transparent)]
+    Lookup(#[from] TodoRoutesLookupError),
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "The same id was given more than once",
+    schema = "Message",
+    code = 404,
+    description = "No such todo",
+    schema = "Message",
+    code = 409,
+    description = "Another write to the todo got in the way",
+    schema = "Message",
+    code = 500,
+    description = "The todos' storage failed",
+    schema = "Message",
+    code = 503,
+    description = "The todos' storage couldn't be reached in time",
+    schema = "Message"
+)]
+#[derive(Error, Debug)]
+pub enum TodoRoutesReorderError {
+    #[error("Bad order")]
+    BadOrder { reason: String },
+    #[error(transparent)]
+    Lookup(#[from] TodoRoutesLookupError),
+}
+
+#[api_v2_errors(code = 400, description = "Unreadable import file", schema = "Message")]
+#[derive(Error, Debug)]
+pub enum TodoRoutesImportError {
+    #[error("Unreadable import file")]
+    Unreadable { reason: String },
+}
+
+fn disallowed_message(task: &str, reason: &str) -> String {
+    format!("Disallowed task: [{}]: {}", task, reason)
+}
+
+fn no_such_list_message(id: &ListId) -> String {
+    format!("No such list: [{:?}]", id)
+}
+
+// The message a 400 for the error would have
+fn data_err_message(e: TodoControllerDataErr) -> String {
+    match e {
+        TodoControllerDataErr::InvalidData { task } => invalid_task_message(&task),
+        TodoControllerDataErr::Rejected { reason } => format!("Rejected: {}", reason),
+        TodoControllerDataErr::Disallowed { task, reason } => disallowed_message(&task, &reason),
+        TodoControllerDataErr::NoSuchList(id) => no_such_list_message(&id),
+        TodoControllerDataErr::Failed(e) => TodoRoutesLookupError::from(e).message(),
+    }
+}
+
+impl error::ResponseError for TodoRoutesDataError {
     fn error_response(&self) -> HttpResponse {
         match self {
-            BadTask { task } => HttpResponse::BadRequest().json(&Message {
-                message: format!("Invalid task: [{}]", task),
+            TodoRoutesDataError::BadTask { task } => HttpResponse::BadRequest().json(&Message {
+                message: invalid_task_message(task),
+                code: Some(ErrorCode::TaskEmpty),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
             }),
-            NoSuchTask { id } => HttpResponse::NotFound().json(&Message {
-                message: format!("No such todo: [{:?}]", id),
+            TodoRoutesDataError::Rejected { reason } => HttpResponse::BadRequest().json(&Message {
+                message: format!("Rejected: {}", reason),
+                code: Some(ErrorCode::TodoRejected),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            }),
+            TodoRoutesDataError::Disallowed { task, reason } => {
+                HttpResponse::BadRequest().json(&Message {
+                    message: disallowed_message(task, reason),
+                    code: Some(ErrorCode::TaskDisallowed),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+            TodoRoutesDataError::NoSuchList { id } => HttpResponse::BadRequest().json(&Message {
+                message: no_such_list_message(id),
+                code: Some(ErrorCode::ListNotFound),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
             }),
         }
     }
+}
 
-    fn render_response(&self) -> HttpResponse {
-        self.error_response()
+impl error::ResponseError for TodoRoutesCreateError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            TodoRoutesCreateError::Data(e) => e.error_response(),
+            TodoRoutesCreateError::Lookup(e) => e.error_response(),
+            TodoRoutesCreateError::Duplicate { id } => HttpResponse::Conflict()
+                .insert_header((http::header::LOCATION, format!("/tasks/{}", id)))
+                .json(&Message {
+                    message: format!("Todo [{:?}] has the same task", id),
+                    code: Some(ErrorCode::TaskDuplicate),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                }),
+        }
     }
 }
 
-impl From<TodoControllerDataErr> for TodoRoutesError {
+impl From<TodoControllerDataErr> for TodoRoutesCreateError {
     fn from(e: TodoControllerDataErr) -> Self {
+        match routes_data_err(e) {
+            Ok(e) => e.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TodoRoutesLookupError {
+    // The message its response has
+    fn message(&self) -> String {
+        match self {
+            TodoRoutesLookupError::NoSuchTask { id } => format!("No such todo: [{:?}]", id),
+            TodoRoutesLookupError::Unavailable { reason } => {
+                format!("Storage is unavailable: {}", reason)
+            }
+            TodoRoutesLookupError::Conflict { id } => format!(
+                "Todo [{:?}] was written to by someone else at the same time; retry",
+                id
+            ),
+            TodoRoutesLookupError::Failed => {
+                "The todos' storage failed; the cause has been logged".to_string()
+            }
+        }
+    }
+}
+
+impl error::ResponseError for TodoRoutesLookupError {
+    fn error_response(&self) -> HttpResponse {
+        let (mut response, code) = match self {
+            TodoRoutesLookupError::NoSuchTask { .. } => {
+                (HttpResponse::NotFound(), ErrorCode::TodoNotFound)
+            }
+            TodoRoutesLookupError::Unavailable { .. } => (
+                HttpResponse::ServiceUnavailable(),
+                ErrorCode::StorageUnavailable,
+            ),
+            TodoRoutesLookupError::Conflict { .. } => {
+                (HttpResponse::Conflict(), ErrorCode::WriteConflict)
+            }
+            TodoRoutesLookupError::Failed => {
+                (HttpResponse::InternalServerError(), ErrorCode::Internal)
+            }
+        };
+        response.json(&Message {
+            message: self.message(),
+            code: Some(code),
+            warnings: Vec::new(),
+            request_id: request_id::current(),
+        })
+    }
+}
+
+impl error::ResponseError for TodoRoutesImportError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            TodoRoutesImportError::Unreadable { reason } => {
+                HttpResponse::BadRequest().json(&Message {
+                    message: format!("Unreadable import file: {}", reason),
+                    code: Some(ErrorCode::ImportUnreadable),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+        }
+    }
+}
+
+impl error::ResponseError for TodoRoutesUpdateError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            TodoRoutesUpdateError::Data(e) => e.error_response(),
+            TodoRoutesUpdateError::Lookup(e) => e.error_response(),
+            TodoRoutesUpdateError::PreconditionFailed { id } => HttpResponse::PreconditionFailed()
+                .json(&Message {
+                    message: format!(
+                        "Todo [{:?}] has been updated since; fetch it again and retry",
+                        id
+                    ),
+                    code: Some(ErrorCode::VersionConflict),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                }),
+            TodoRoutesUpdateError::Flooded {
+                id,
+                retry_after_secs,
+            } => HttpResponse::TooManyRequests()
+                .insert_header((http::header::RETRY_AFTER, retry_after_secs.to_string()))
+                .json(&Message {
+                    message: format!(
+                        "Todo [{:?}] has been changed too often; retry in {}s",
+                        id, retry_after_secs
+                    ),
+                    code: Some(ErrorCode::TooManyChanges),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                }),
+        }
+    }
+}
+
+impl error::ResponseError for TodoRoutesCompleteError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            TodoRoutesCompleteError::BadSelection { reason } => {
+                HttpResponse::BadRequest().json(&Message {
+                    message: reason.clone(),
+                    code: Some(ErrorCode::SelectionInvalid),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+            TodoRoutesCompleteError::Data(e) => e.error_response(),
+            TodoRoutesCompleteError::Lookup(e) => e.error_response(),
+        }
+    }
+}
+
+impl From<TodoControllerCompleteErr> for TodoRoutesCompleteError {
+    fn from(e: TodoControllerCompleteErr) -> Self {
         match e {
-            TodoControllerDataErr::InvalidData { task } => TodoRoutesError::BadTask { task },
+            TodoControllerCompleteErr::InvalidSelection(reason) => {
+                TodoRoutesCompleteError::BadSelection { reason }
+            }
+            TodoControllerCompleteErr::DataErr(e) => match routes_data_err(e) {
+                Ok(e) => e.into(),
+                Err(e) => e.into(),
+            },
         }
     }
 }
 
-impl From<TodoControllerLookupErr> for TodoRoutesError {
+impl error::ResponseError for TodoRoutesReorderError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            TodoRoutesReorderError::BadOrder { reason } => {
+                HttpResponse::BadRequest().json(&Message {
+                    message: reason.clone(),
+                    code: Some(ErrorCode::OrderInvalid),
+                    warnings: Vec::new(),
+                    request_id: request_id::current(),
+                })
+            }
+            TodoRoutesReorderError::Lookup(e) => e.error_response(),
+        }
+    }
+}
+
+impl From<TodoControllerReorderErr> for TodoRoutesReorderError {
+    fn from(e: TodoControllerReorderErr) -> Self {
+        match e {
+            TodoControllerReorderErr::InvalidOrder(reason) => {
+                TodoRoutesReorderError::BadOrder { reason }
+            }
+            TodoControllerReorderErr::LookupErr(e) => TodoRoutesLookupError::from(e).into(),
+        }
+    }
+}
+
+// Storage failing isn't the data's fault, so is answered like it is on lookups, rather than with
+// a 400
+fn routes_data_err(e: TodoControllerDataErr) -> Result<TodoRoutesDataError, TodoRoutesLookupError> {
+    match e {
+        TodoControllerDataErr::InvalidData { task } => Ok(TodoRoutesDataError::BadTask { task }),
+        TodoControllerDataErr::Rejected { reason } => Ok(TodoRoutesDataError::Rejected { reason }),
+        TodoControllerDataErr::Disallowed { task, reason } => {
+            Ok(TodoRoutesDataError::Disallowed { task, reason })
+        }
+        TodoControllerDataErr::NoSuchList(id) => Ok(TodoRoutesDataError::NoSuchList { id }),
+        TodoControllerDataErr::Failed(e) => Err(e.into()),
+    }
+}
+
+impl From<TodoControllerLookupErr> for TodoRoutesLookupError {
     fn from(e: TodoControllerLookupErr) -> Self {
         match e {
-            TodoControllerLookupErr::NotFound(id) => TodoRoutesError::NoSuchTask { id: id.into() },
+            TodoControllerLookupErr::NotFound(id) => TodoRoutesLookupError::NoSuchTask { id },
+            TodoControllerLookupErr::ConnectionError(reason) => {
+                TodoRoutesLookupError::Unavailable { reason }
+            }
+            TodoControllerLookupErr::Timeout => TodoRoutesLookupError::Unavailable {
+                reason: "timed out".to_string(),
+            },
+            TodoControllerLookupErr::Conflict(id) => TodoRoutesLookupError::Conflict { id },
+            // The causes can say more about the storage than clients should know
+            TodoControllerLookupErr::Other(e) => {
+                error!(
+                    "Storage failed on request [{}]: {:#}",
+                    request_id::current().unwrap_or_default(),
+                    e
+                );
+                TodoRoutesLookupError::Failed
+            }
         }
     }
 }
 
-impl From<TodoControllerUpdateErr> for TodoRoutesError {
+impl From<TodoControllerUpdateErr> for TodoRoutesUpdateError {
     fn from(e: TodoControllerUpdateErr) -> Self {
         match e {
-            TodoControllerUpdateErr::LookupErr(e) => e.into(),
-            TodoControllerUpdateErr::DataErr(e) => e.into(),
+            TodoControllerUpdateErr::LookupErr(e) => TodoRoutesLookupError::from(e).into(),
+            TodoControllerUpdateErr::DataErr(e) => match routes_data_err(e) {
+                Ok(e) => e.into(),
+                Err(e) => e.into(),
+            },
+            TodoControllerUpdateErr::Conflict(id) => {
+                TodoRoutesUpdateError::PreconditionFailed { id }
+            }
+            // Rounded up, so that retrying straight after isn't turned down again
+            TodoControllerUpdateErr::Flooded { id, retry_after } => {
+                TodoRoutesUpdateError::Flooded {
+                    id,
+                    retry_after_secs: retry_after.as_secs()
+                        + u64::from(retry_after.subsec_nanos() > 0),
+                }
+            }
         }
     }
 }
@@ -138,8 +788,8 @@ impl From<TodoControllerUpdateErr> for TodoRoutesError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use actix_web::test;
     use async_trait::async_trait;
+    use futures::stream::{self, BoxStream};
     use std::sync::*;
 
     static RETURNED_TASK: &str = "say hello";
@@ -148,93 +798,342 @@ mod tests {
         Todo {
             id: TodoId(1),
             task: RETURNED_TASK.to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
         }
     }
 
-    #[test]
-    fn test_create() {
+    fn dry_run(dry_run: bool) -> web::Query<DryRunParams> {
+        web::Query(DryRunParams { dry_run })
+    }
+
+    fn dedupe(dedupe: bool) -> web::Query<DedupeParams> {
+        web::Query(DedupeParams { dedupe })
+    }
+
+    fn done<T>(resp: OrPlan<T>) -> T {
+        match resp {
+            OrPlan::Done(done) => done,
+            OrPlan::Plan(_) => panic!("Unexpectedly dry run"),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_create() {
         let mock_controller = MockTodoController::new();
         let todo_data = TodoData {
             task: "say goodbye".to_string(),
+            done: false,
+            list_id: None,
         };
         let todo_json = web::Json(todo_data);
-        let req = test::TestRequest::default()
-            .data(mock_controller.clone())
-            .to_http_request();
-        let app_data = req.get_app_data().unwrap();
-        let resp = test::block_on(create::<MockTodoController>(app_data, todo_json))
-            .unwrap()
-            .0;
-        assert_eq!("say goodbye", &resp.task);
+        let app_data = web::Data::new(mock_controller.clone());
+        let resp = done(
+            create::<MockTodoController>(app_data, dry_run(false), dedupe(false), todo_json)
+                .await
+                .unwrap(),
+        );
+        assert_eq!("say goodbye", &resp.body.task);
+        assert_eq!(format!("/tasks/{}", resp.body.id.0), resp.location);
         let times_called = *mock_controller.create_called.lock().unwrap();
         assert_eq!(1, times_called);
     }
 
-    #[test]
-    fn test_get() {
+    #[actix_web::test]
+    async fn test_create_dedupe() {
+        use actix_web::error::ResponseError;
         let mock_controller = MockTodoController::new();
-        let req = test::TestRequest::default()
-            .data(mock_controller.clone())
-            .to_http_request();
-        let app_data = req.get_app_data().unwrap();
+        let app_data = web::Data::new(mock_controller.clone());
+        let todo_data = || TodoData {
+            task: RETURNED_TASK.to_string(),
+            done: false,
+            list_id: None,
+        };
+        let created = create::<MockTodoController>(
+            app_data.clone(),
+            dry_run(false),
+            dedupe(true),
+            web::Json(todo_data()),
+        )
+        .await;
+        let resp = match created {
+            Err(e @ TodoRoutesCreateError::Duplicate { .. }) => e.error_response(),
+            _ => panic!("created a duplicate todo"),
+        };
+        assert_eq!(409, resp.status().as_u16());
+        assert_eq!(
+            "/tasks/1",
+            resp.headers().get(http::header::LOCATION).unwrap()
+        );
+        assert_eq!(0, *mock_controller.create_called.lock().unwrap());
+
+        // Duplicates are only warned about without it
+        create::<MockTodoController>(
+            app_data,
+            dry_run(false),
+            dedupe(false),
+            web::Json(todo_data()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, *mock_controller.create_called.lock().unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_get() {
+        let mock_controller = MockTodoController::new();
+        let app_data = web::Data::new(mock_controller.clone());
         let id = TodoId(123);
-        let resp = test::block_on(get::<MockTodoController>(app_data, id.into()))
+        let resp = get::<MockTodoController>(app_data, id.into())
+            .await
             .unwrap()
-            .0;
+            .body;
         assert_eq!(id, resp.id);
         let times_called = *mock_controller.get_called.lock().unwrap();
         assert_eq!(1, times_called);
     }
 
-    #[test]
-    fn test_list() {
+    #[actix_web::test]
+    async fn test_list() {
         let mock_controller = MockTodoController::new();
-        let req = test::TestRequest::default()
-            .data(mock_controller.clone())
-            .to_http_request();
-        let app_data = req.get_app_data().unwrap();
-        let resp = test::block_on(list::<MockTodoController>(app_data))
+        let app_data = web::Data::new(mock_controller.clone());
+        let params = web::Query(ListParams { order: None });
+        let page = web::Query(PageParams {
+            offset: None,
+            limit: None,
+        });
+        let resp = list::<MockTodoController>(app_data.clone(), params, page)
+            .await
             .unwrap()
-            .0;
+            .body;
         assert_eq!(vec![expected_task()], resp);
         let times_called = *mock_controller.list_called.lock().unwrap();
         assert_eq!(1, times_called);
+        let params = web::Query(ListParams { order: None });
+        let page = web::Query(PageParams {
+            offset: Some(1),
+            limit: Some(10),
+        });
+        let resp = list::<MockTodoController>(app_data, params, page)
+            .await
+            .unwrap()
+            .body;
+        assert!(resp.is_empty());
     }
 
-    #[test]
-    fn test_delete() {
+    #[actix_web::test]
+    async fn test_delete() {
         let mock_controller = MockTodoController::new();
-        let req = test::TestRequest::default()
-            .data(mock_controller.clone())
-            .to_http_request();
-        let app_data = req.get_app_data().unwrap();
+        let app_data = web::Data::new(mock_controller.clone());
         let id = TodoId(123);
-        let _ = test::block_on(delete::<MockTodoController>(app_data, id.into()))
-            .unwrap()
-            .0;
+        let _ = done(
+            delete::<MockTodoController>(app_data, id.into(), dry_run(false))
+                .await
+                .unwrap(),
+        )
+        .0;
         let times_called = *mock_controller.delete_called.lock().unwrap();
         assert_eq!(1, times_called);
     }
 
-    #[test]
-    fn test_update() {
+    #[actix_web::test]
+    async fn test_complete() {
+        let app_data = web::Data::new(MockTodoController::new());
+        let json = web::Json(CompleteTodos {
+            ids: Some(vec![TodoId(1)]),
+            list_id: None,
+        });
+        let resp = complete::<MockTodoController>(app_data.clone(), json)
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![Todo {
+                done: true,
+                ..expected_task()
+            }],
+            resp.completed
+        );
+        let json = web::Json(CompleteTodos::default());
+        match complete::<MockTodoController>(app_data, json).await {
+            Err(TodoRoutesCompleteError::BadSelection { .. }) => {}
+            other => panic!("Unexpected {:?}", other.map(|j| j.0)),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_dry_runs() {
+        let mock_controller = MockTodoController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let todo_data = TodoData {
+            task: "say goodbye".to_string(),
+            done: false,
+            list_id: None,
+        };
+        let created = create::<MockTodoController>(
+            app_data.clone(),
+            dry_run(true),
+            dedupe(false),
+            web::Json(todo_data),
+        )
+        .await
+        .unwrap();
+        match created {
+            OrPlan::Plan(plan) => assert_eq!(
+                Some("say goodbye"),
+                plan.todo.as_ref().map(|t| t.task.as_str())
+            ),
+            OrPlan::Done(_) => panic!("Created on a dry run"),
+        }
+        let deleted = delete::<MockTodoController>(app_data, TodoId(123).into(), dry_run(true))
+            .await
+            .unwrap();
+        match deleted {
+            OrPlan::Plan(plan) => assert_eq!(1, plan.events.len()),
+            OrPlan::Done(_) => panic!("Deleted on a dry run"),
+        }
+        assert_eq!(0, *mock_controller.create_called.lock().unwrap());
+        assert_eq!(0, *mock_controller.delete_called.lock().unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_update() {
         let mock_controller = MockTodoController::new();
         let todo_data = TodoData {
             task: "say goodbye".to_string(),
+            done: false,
+            list_id: None,
         };
         let todo_json = web::Json(todo_data);
-        let req = test::TestRequest::default()
-            .data(mock_controller.clone())
-            .to_http_request();
-        let app_data = req.get_app_data().unwrap();
+        let app_data = web::Data::new(mock_controller.clone());
         let id = TodoId(123);
-        let _ = test::block_on(update::<MockTodoController>(app_data, id.into(), todo_json))
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        let _ = done(
+            update::<MockTodoController>(app_data, id.into(), dry_run(false), req, todo_json)
+                .await
+                .unwrap(),
+        )
+        .0;
+        let times_called = *mock_controller.update_called.lock().unwrap();
+        assert_eq!(1, times_called);
+    }
+
+    #[actix_web::test]
+    async fn test_update_if_match() {
+        let mock_controller = MockTodoController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let todo_json = || {
+            web::Json(TodoData {
+                task: "say goodbye".to_string(),
+                done: false,
+                list_id: None,
+            })
+        };
+        let if_match = |tag: &str| {
+            actix_web::test::TestRequest::default()
+                .insert_header((http::header::IF_MATCH, tag))
+                .to_http_request()
+        };
+        let stale = update::<MockTodoController>(
+            app_data.clone(),
+            TodoId(123).into(),
+            dry_run(false),
+            if_match("\"2\""),
+            todo_json(),
+        )
+        .await;
+        match stale {
+            Err(TodoRoutesUpdateError::PreconditionFailed { id }) => assert_eq!(TodoId(123), id),
+            Err(other) => panic!("Unexpected {:?}", other),
+            Ok(_) => panic!("Updated a stale version"),
+        }
+        assert_eq!(0, *mock_controller.update_called.lock().unwrap());
+        assert!(update::<MockTodoController>(
+            app_data,
+            TodoId(123).into(),
+            dry_run(false),
+            if_match("\"1\""),
+            todo_json(),
+        )
+        .await
+        .is_ok());
+        assert_eq!(1, *mock_controller.update_called.lock().unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_export() {
+        let mock_controller = MockTodoController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let params = web::Query(TransferParams {
+            format: Some(TransferFormat::Csv),
+        });
+        let resp = export::<MockTodoController>(app_data, params)
+            .await
+            .unwrap();
+        assert_eq!("text/csv", resp.headers().get("content-type").unwrap());
+        let times_called = *mock_controller.list_called.lock().unwrap();
+        assert_eq!(1, times_called);
+    }
+
+    #[actix_web::test]
+    async fn test_import() {
+        let mock_controller = MockTodoController::new();
+        let app_data = web::Data::new(mock_controller.clone());
+        let req = actix_web::test::TestRequest::default()
+            .insert_header(("content-type", "text/csv"))
+            .to_http_request();
+        let params = web::Query(TransferParams { format: None });
+        let body = web::Bytes::from_static(b"task\nsay hello\ntoo,many\n");
+        let resp = import::<MockTodoController>(app_data, req, params, dry_run(false), body)
+            .await
             .unwrap()
             .0;
-        let times_called = *mock_controller.update_called.lock().unwrap();
+        assert_eq!(1, resp.created.len());
+        assert_eq!(1, resp.errors.len());
+        assert_eq!(2, resp.errors[0].row);
+        let times_called = *mock_controller.create_called.lock().unwrap();
         assert_eq!(1, times_called);
     }
 
+    #[actix_web::test]
+    async fn test_error_responses() {
+        use actix_web::error::ResponseError;
+        let bad_task = TodoRoutesDataError::BadTask {
+            task: "".to_string(),
+        };
+        let no_such_task = TodoRoutesLookupError::NoSuchTask { id: TodoId(1) };
+        assert_eq!(400, bad_task.error_response().status().as_u16());
+        assert_eq!(404, no_such_task.error_response().status().as_u16());
+        let update_err: TodoRoutesUpdateError = no_such_task.into();
+        assert_eq!(404, update_err.error_response().status().as_u16());
+        let conflict = TodoRoutesUpdateError::PreconditionFailed { id: TodoId(1) };
+        assert_eq!(412, conflict.error_response().status().as_u16());
+
+        let statuses: Vec<u16> = vec![
+            TodoControllerLookupErr::ConnectionError("refused".to_string()),
+            TodoControllerLookupErr::Timeout,
+            TodoControllerLookupErr::Conflict(TodoId(1)),
+            TodoControllerLookupErr::Other(
+                anyhow::anyhow!("connection reset").context("Could not read todo"),
+            ),
+        ]
+        .into_iter()
+        .map(|e| {
+            TodoRoutesLookupError::from(e)
+                .error_response()
+                .status()
+                .as_u16()
+        })
+        .collect();
+        assert_eq!(vec![503, 503, 409, 500], statuses);
+        // The causes are only logged
+        let failed = TodoRoutesLookupError::from(TodoControllerLookupErr::Other(
+            anyhow::anyhow!("connection reset").context("Could not read todo"),
+        ));
+        assert!(!failed.message().contains("connection reset"));
+    }
+
     #[derive(Clone)]
     struct MockTodoController {
         create_called: Arc<Mutex<usize>>,
@@ -258,12 +1157,17 @@ mod tests {
 
     #[async_trait]
     impl TodoController for MockTodoController {
-        async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoControllerDataErr> {
+        async fn create(&self, todo_data: &TodoData) -> Result<SavedTodo, TodoControllerDataErr> {
             let mut mutex = self.create_called.lock().unwrap();
             *mutex += 1;
-            Ok(Todo {
+            Ok(SavedTodo {
                 id: TodoId(123),
                 task: todo_data.task.clone(),
+                done: todo_data.done,
+                version: 1,
+                list_id: todo_data.list_id,
+                position: 123,
+                warnings: Vec::new(),
             })
         }
 
@@ -273,19 +1177,35 @@ mod tests {
             Ok(Todo {
                 id: *todo_id,
                 task: RETURNED_TASK.to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 0,
             })
         }
 
-        async fn list(&self) -> Vec<Todo> {
+        async fn list(&self) -> Result<Vec<Todo>, TodoControllerLookupErr> {
             let mut mutex = self.list_called.lock().unwrap();
             *mutex += 1;
-            vec![expected_task()]
+            Ok(vec![expected_task()])
         }
 
-        async fn update(&self, _: &Todo) -> Result<(), TodoControllerUpdateErr> {
+        async fn update(
+            &self,
+            todo: &Todo,
+            _: Option<u64>,
+        ) -> Result<SavedTodo, TodoControllerUpdateErr> {
             let mut mutex = self.update_called.lock().unwrap();
             *mutex += 1;
-            Ok(())
+            Ok(SavedTodo {
+                id: todo.id,
+                task: todo.task.clone(),
+                done: todo.done,
+                version: todo.version + 1,
+                list_id: None,
+                position: 0,
+                warnings: Vec::new(),
+            })
         }
 
         async fn delete(&self, _: &TodoId) -> Result<(), TodoControllerLookupErr> {
@@ -293,5 +1213,86 @@ mod tests {
             *mutex += 1;
             Ok(())
         }
+
+        async fn plan_create(
+            &self,
+            todo_data: &TodoData,
+        ) -> Result<TodoPlan, TodoControllerDataErr> {
+            let todo = Todo {
+                id: TodoId(0),
+                task: todo_data.task.clone(),
+                done: todo_data.done,
+                version: 1,
+                list_id: todo_data.list_id,
+                position: 0,
+            };
+            Ok(TodoPlan {
+                todo: Some(todo.clone()),
+                events: vec![PlannedEvent {
+                    event: EventKind::Created,
+                    todo: Some(todo),
+                    id: None,
+                }],
+                warnings: Vec::new(),
+            })
+        }
+
+        async fn plan_update(
+            &self,
+            _: &Todo,
+            _: Option<u64>,
+        ) -> Result<TodoPlan, TodoControllerUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn plan_delete(&self, todo_id: &TodoId) -> Result<TodoPlan, TodoControllerLookupErr> {
+            Ok(TodoPlan {
+                todo: None,
+                events: vec![PlannedEvent {
+                    event: EventKind::Deleted,
+                    todo: None,
+                    id: Some(*todo_id),
+                }],
+                warnings: Vec::new(),
+            })
+        }
+
+        async fn complete(
+            &self,
+            selection: &CompleteTodos,
+        ) -> Result<CompletedTodos, TodoControllerCompleteErr> {
+            match &selection.ids {
+                Some(ids) => Ok(CompletedTodos {
+                    completed: ids
+                        .iter()
+                        .map(|id| Todo {
+                            id: *id,
+                            done: true,
+                            ..expected_task()
+                        })
+                        .collect(),
+                    not_found: Vec::new(),
+                    warnings: Vec::new(),
+                }),
+                None => Err(TodoControllerCompleteErr::InvalidSelection(
+                    "Invalid selection".to_string(),
+                )),
+            }
+        }
+
+        async fn reorder(&self, _: &ReorderTodos) -> Result<Vec<Todo>, TodoControllerReorderErr> {
+            unimplemented!()
+        }
+
+        async fn find_duplicate(
+            &self,
+            todo_data: &TodoData,
+        ) -> Result<Option<Todo>, TodoControllerLookupErr> {
+            Ok(Some(expected_task()).filter(|todo| todo.task == todo_data.task))
+        }
+
+        fn subscribe(&self) -> BoxStream<'static, TodoEvent> {
+            stream::empty().boxed()
+        }
     }
 }
@@ -0,0 +1,149 @@
+//! The v2 representation of todos, adapted from what the same controllers return for /tasks
+
+use crate::controllers::history_controller::HistoryController;
+use crate::controllers::todo_controller::TodoController;
+use crate::created::Created;
+use crate::etag;
+use crate::etag::ETagged;
+use crate::handlers::todo_routes_handler::{
+    expected_version, TodoRoutesCreateError, TodoRoutesLookupError, TodoRoutesUpdateError,
+};
+use crate::models::common::Message;
+use crate::models::todo::{PageParams, Todo, TodoData, TodoId};
+use crate::models::v2::{SavedTodoV2, TodoDataV2, TodoV2};
+use actix_web::*;
+use paperclip::actix::api_v2_operation;
+use std::ops::Deref;
+
+#[api_v2_operation(
+    summary = "List todos (v2)",
+    description = "Returns every todo, ordered by id. With offset and limit, returns just that page of them",
+    operation_id = "listTodosV2",
+    tags(TodosV2)
+)]
+pub async fn list<
+    A: TodoController + Send + Sync + 'static,
+    H: HistoryController + Send + Sync + 'static,
+>(
+    web: web::Data<A>,
+    histories: web::Data<H>,
+    page: web::Query<PageParams>,
+) -> Result<ETagged<Vec<TodoV2>>, Error> {
+    let controller = web.get_ref();
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit.unwrap_or(usize::MAX);
+    let mut listed = Vec::new();
+    let page = controller
+        .list_page(offset, limit)
+        .await
+        .map_err(TodoRoutesLookupError::from)?;
+    for todo in page {
+        listed.push(dated(histories.get_ref(), todo).await);
+    }
+    Ok(etag::hashed(listed))
+}
+
+#[api_v2_operation(
+    summary = "Create a todo (v2)",
+    description = "Creates a todo from the given data; the task must not be empty. Responds with a 201 and the todo's path in the Location header, along with warnings about non-fatal issues",
+    operation_id = "createTodoV2",
+    tags(TodosV2)
+)]
+pub async fn create<
+    A: TodoController + Send + Sync + 'static,
+    H: HistoryController + Send + Sync + 'static,
+>(
+    web: web::Data<A>,
+    histories: web::Data<H>,
+    json: web::Json<TodoDataV2>,
+) -> Result<Created<SavedTodoV2>, TodoRoutesCreateError> {
+    let controller = web.get_ref();
+    let saved = controller.create(&json.into_inner().into()).await?;
+    let warnings = saved.warnings.clone();
+    let todo = dated(histories.get_ref(), saved.into()).await;
+    Ok(Created {
+        location: format!("/v2/tasks/{}", todo.id),
+        body: SavedTodoV2 { todo, warnings },
+    })
+}
+
+#[api_v2_operation(
+    summary = "Get a todo (v2)",
+    description = "The ETag is the todo's version, which updates can be made conditional on with If-Match",
+    operation_id = "getTodoV2",
+    tags(TodosV2)
+)]
+pub async fn get<
+    A: TodoController + Send + Sync + 'static,
+    H: HistoryController + Send + Sync + 'static,
+>(
+    web: web::Data<A>,
+    histories: web::Data<H>,
+    id: web::Path<TodoId>,
+) -> Result<ETagged<TodoV2>, TodoRoutesLookupError> {
+    let controller = web.get_ref();
+    let todo = controller.get(id.deref()).await?;
+    let version = todo.version;
+    Ok(etag::versioned(
+        dated(histories.get_ref(), todo).await,
+        version,
+    ))
+}
+
+#[api_v2_operation(
+    summary = "Update a todo (v2)",
+    description = "Replaces the todo's data; the task must not be empty. With an If-Match of the todo's ETag, fails with a 412 instead if someone else has updated it since",
+    operation_id = "updateTodoV2",
+    tags(TodosV2)
+)]
+pub async fn update<A: TodoController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+    req: HttpRequest,
+    json: web::Json<TodoDataV2>,
+) -> Result<web::Json<Message>, TodoRoutesUpdateError> {
+    let controller = web.get_ref();
+    let expected_version = expected_version(controller, &id, &req).await?;
+    let todo_data = TodoData::from(json.into_inner());
+    let todo = Todo {
+        id: *id.deref(),
+        task: todo_data.task,
+        done: todo_data.done,
+        version: expected_version.unwrap_or_default(),
+        list_id: todo_data.list_id,
+        // Kept as it is by the repo; only reordering moves todos
+        position: 0,
+    };
+    let updated = controller.update(&todo, expected_version).await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully updated: [{:?}]", id),
+        code: None,
+        warnings: updated.warnings,
+        request_id: None,
+    }))
+}
+
+#[api_v2_operation(
+    summary = "Delete a todo (v2)",
+    operation_id = "deleteTodoV2",
+    tags(TodosV2)
+)]
+pub async fn delete<A: TodoController + Send + Sync + 'static>(
+    web: web::Data<A>,
+    id: web::Path<TodoId>,
+) -> Result<web::Json<Message>, TodoRoutesLookupError> {
+    let controller = web.get_ref();
+    controller.delete(id.deref()).await?;
+    Ok(web::Json(Message {
+        message: format!("Successfully deleted: [{:?}]", id),
+        code: None,
+        warnings: Vec::new(),
+        request_id: None,
+    }))
+}
+
+// Todos whose history can't be found are served undated rather than failing
+async fn dated<H: HistoryController>(histories: &H, todo: Todo) -> TodoV2 {
+    let history = histories.history(&todo.id).await.unwrap_or_default();
+    TodoV2::from(todo).dated(&history)
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+pub static DEFAULT_SIZE: usize = 100;
+
+/// A run of consecutive readiness checks that all had the same outcome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Period {
+    pub ready: bool,
+    pub detail: String,
+    pub since: DateTime<Utc>,
+    pub last_checked: DateTime<Utc>,
+    pub checks: u64,
+}
+
+/// The last few readiness check outcomes, oldest first.
+///
+/// Checks are collapsed into periods of the same outcome, so that frequent probes of a steady
+/// instance don't push out older history, while a flapping one shows up as alternating periods.
+#[derive(Clone)]
+pub struct HealthHistory {
+    size: usize,
+    periods: Arc<Mutex<VecDeque<Period>>>,
+}
+
+pub fn new(size: usize) -> HealthHistory {
+    HealthHistory {
+        size,
+        periods: Arc::new(Mutex::new(VecDeque::with_capacity(size))),
+    }
+}
+
+impl HealthHistory {
+    pub fn record(&self, ready: bool, detail: &str, at: DateTime<Utc>) {
+        let mut periods = self.periods.lock().unwrap_or_else(|e| e.into_inner());
+        match periods.back_mut() {
+            Some(last) if last.ready == ready && last.detail == detail => {
+                last.last_checked = at;
+                last.checks += 1;
+            }
+            _ => {
+                if periods.len() >= self.size {
+                    periods.pop_front();
+                }
+                periods.push_back(Period {
+                    ready,
+                    detail: detail.to_string(),
+                    since: at,
+                    last_checked: at,
+                    checks: 1,
+                });
+            }
+        }
+    }
+
+    pub fn periods(&self) -> Vec<Period> {
+        let periods = self.periods.lock().unwrap_or_else(|e| e.into_inner());
+        periods.iter().cloned().collect()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_record() {
+        let history = new(2);
+        let start = Utc::now();
+        history.record(true, "ready", start);
+        history.record(true, "ready", start + Duration::seconds(1));
+        let periods = history.periods();
+        assert_eq!(1, periods.len());
+        assert_eq!(2, periods[0].checks);
+        assert_eq!(start, periods[0].since);
+        assert_eq!(start + Duration::seconds(1), periods[0].last_checked);
+
+        history.record(false, "Shutting down", start + Duration::seconds(2));
+        history.record(true, "ready", start + Duration::seconds(3));
+        let periods = history.periods();
+        assert_eq!(2, periods.len());
+        assert_eq!(
+            vec![false, true],
+            periods.iter().map(|p| p.ready).collect::<Vec<_>>()
+        );
+        assert_eq!(start + Duration::seconds(3), periods[1].since);
+    }
+}
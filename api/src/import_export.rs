@@ -0,0 +1,96 @@
+use crate::models::todo::{Todo, TodoData, TransferFormat};
+use serde_json::Value;
+
+/// Encodes todos as a downloadable file, CSV having an `id,task,done,version,list_id,position` header
+pub fn export(todos: &[Todo], format: TransferFormat) -> Result<Vec<u8>, String> {
+    match format {
+        TransferFormat::Json => serde_json::to_vec(todos).map_err(|e| e.to_string()),
+        TransferFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for todo in todos {
+                writer.serialize(todo).map_err(|e| e.to_string())?;
+            }
+            writer.into_inner().map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Parses an uploaded file into one result per row, so that bad rows can be reported without
+/// failing the whole import. Only fails outright when the file as a whole can't be read.
+///
+/// CSV needs a `task` header and may have `done` and `list_id` ones; other columns (e.g. `id` and `version`
+/// from an export) are ignored.
+pub fn parse_import(
+    body: &[u8],
+    format: TransferFormat,
+) -> Result<Vec<Result<TodoData, String>>, String> {
+    match format {
+        TransferFormat::Json => {
+            let rows: Vec<Value> = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+            Ok(rows
+                .into_iter()
+                .map(|row| serde_json::from_value(row).map_err(|e| e.to_string()))
+                .collect())
+        }
+        TransferFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(body);
+            let has_task = reader
+                .headers()
+                .map_err(|e| e.to_string())?
+                .iter()
+                .any(|h| h == "task");
+            if !has_task {
+                return Err("missing a [task] header".to_string());
+            }
+            Ok(reader
+                .deserialize()
+                .map(|row| row.map_err(|e| e.to_string()))
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::todo::TodoId;
+
+    #[test]
+    fn test_export_csv() {
+        let todos = vec![Todo {
+            id: TodoId(1),
+            task: "Make the bed, then tea".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
+        }];
+        let exported = export(&todos, TransferFormat::Csv).unwrap();
+        assert_eq!(
+            "id,task,done,version,list_id,position\n1,\"Make the bed, then tea\",false,1,,1\n",
+            String::from_utf8(exported).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_import_csv() {
+        let rows = parse_import(b"id,task,done\n1,hello,true\n2\n", TransferFormat::Csv).unwrap();
+        assert_eq!(2, rows.len());
+        assert_eq!("hello", &rows[0].as_ref().unwrap().task);
+        assert!(rows[0].as_ref().unwrap().done);
+        assert!(rows[1].is_err());
+        assert!(parse_import(b"name\nhello\n", TransferFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn test_parse_import_json() {
+        let rows = parse_import(
+            br#"[{"task": "hello"}, {"tsk": "typo"}]"#,
+            TransferFormat::Json,
+        )
+        .unwrap();
+        assert_eq!("hello", &rows[0].as_ref().unwrap().task);
+        assert!(rows[1].is_err());
+        assert!(parse_import(b"{}", TransferFormat::Json).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! A stand-in for the HTTP client that calls a todo controller directly, so that code written
+//! against `client::TodoApi` can be unit tested against the real domain logic without a server
+
+use crate::controllers::todo_controller;
+use crate::controllers::todo_controller::{TodoController, TodoControllerImpl};
+use crate::handlers::todo_routes_handler::{
+    TodoRoutesCompleteError, TodoRoutesCreateError, TodoRoutesLookupError, TodoRoutesReorderError,
+    TodoRoutesUpdateError,
+};
+use crate::models::common::Message;
+use crate::models::todo::*;
+use actix_web::body::MessageBody;
+use actix_web::ResponseError;
+use async_trait::async_trait;
+use client::{ClientError, TodoApi};
+use domain::services::todo_service;
+use domain::services::todo_service::TodoServiceImpl;
+use infra::in_mem::todo_repo;
+use infra::in_mem::todo_repo::InMemTodoRepo;
+
+/// Answers as the server would, errors included, minus anything done by middleware
+pub struct InProcessClient<A> {
+    controller: A,
+}
+
+pub fn new<A: TodoController + Send + Sync>(controller: A) -> InProcessClient<A> {
+    InProcessClient { controller }
+}
+
+/// Backed by fresh in-memory storage, with the default validation and no rules or lists
+pub fn in_memory() -> InProcessClient<TodoControllerImpl<TodoServiceImpl<InMemTodoRepo>>> {
+    new(todo_controller::new(todo_service::new(todo_repo::new())))
+}
+
+#[async_trait]
+impl<A: TodoController + Send + Sync> TodoApi for InProcessClient<A> {
+    async fn list_todos(&self) -> Result<Vec<Todo>, ClientError> {
+        self.controller
+            .list()
+            .await
+            .map_err(|e| api_err(TodoRoutesLookupError::from(e)))
+    }
+
+    async fn list_todos_in_manual_order(&self) -> Result<Vec<Todo>, ClientError> {
+        let mut listed = self
+            .controller
+            .list()
+            .await
+            .map_err(|e| api_err(TodoRoutesLookupError::from(e)))?;
+        listed.sort_by_key(|t| t.position);
+        Ok(listed)
+    }
+
+    async fn list_todos_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, ClientError> {
+        self.controller
+            .list_page(offset, limit)
+            .await
+            .map_err(|e| api_err(TodoRoutesLookupError::from(e)))
+    }
+
+    async fn get_todo(&self, id: TodoId) -> Result<Todo, ClientError> {
+        match self.controller.get(&id).await {
+            Ok(todo) => Ok(todo),
+            Err(e) => Err(api_err(TodoRoutesLookupError::from(e))),
+        }
+    }
+
+    async fn create_todo(&self, todo_data: &TodoData) -> Result<SavedTodo, ClientError> {
+        match self.controller.create(todo_data).await {
+            Ok(saved) => Ok(saved),
+            Err(e) => Err(api_err(TodoRoutesCreateError::from(e))),
+        }
+    }
+
+    async fn update_todo(&self, todo: &Todo) -> Result<Message, ClientError> {
+        self.update(todo, None).await
+    }
+
+    async fn update_todo_if_unchanged(&self, todo: &Todo) -> Result<Message, ClientError> {
+        self.update(todo, Some(todo.version)).await
+    }
+
+    async fn delete_todo(&self, id: TodoId) -> Result<Message, ClientError> {
+        match self.controller.delete(&id).await {
+            Ok(()) => Ok(Message {
+                message: format!("Successfully deleted: [{:?}]", id),
+                code: None,
+                warnings: Vec::new(),
+                request_id: None,
+            }),
+            Err(e) => Err(api_err(TodoRoutesLookupError::from(e))),
+        }
+    }
+
+    async fn complete_todos(
+        &self,
+        selection: &CompleteTodos,
+    ) -> Result<CompletedTodos, ClientError> {
+        match self.controller.complete(selection).await {
+            Ok(completed) => Ok(completed),
+            Err(e) => Err(api_err(TodoRoutesCompleteError::from(e))),
+        }
+    }
+
+    async fn reorder_todos(&self, order: &ReorderTodos) -> Result<Vec<Todo>, ClientError> {
+        match self.controller.reorder(order).await {
+            Ok(reordered) => Ok(reordered),
+            Err(e) => Err(api_err(TodoRoutesReorderError::from(e))),
+        }
+    }
+
+    async fn plan_create_todo(&self, todo_data: &TodoData) -> Result<TodoPlan, ClientError> {
+        match self.controller.plan_create(todo_data).await {
+            Ok(plan) => Ok(plan),
+            Err(e) => Err(api_err(TodoRoutesCreateError::from(e))),
+        }
+    }
+
+    async fn plan_update_todo(&self, todo: &Todo) -> Result<TodoPlan, ClientError> {
+        match self.controller.plan_update(&sent(todo), None).await {
+            Ok(plan) => Ok(plan),
+            Err(e) => Err(api_err(TodoRoutesUpdateError::from(e))),
+        }
+    }
+
+    async fn plan_delete_todo(&self, id: TodoId) -> Result<TodoPlan, ClientError> {
+        match self.controller.plan_delete(&id).await {
+            Ok(plan) => Ok(plan),
+            Err(e) => Err(api_err(TodoRoutesLookupError::from(e))),
+        }
+    }
+}
+
+impl<A: TodoController + Send + Sync> InProcessClient<A> {
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Message, ClientError> {
+        let mut sent = sent(todo);
+        sent.version = expected_version.unwrap_or_default();
+        match self.controller.update(&sent, expected_version).await {
+            Ok(updated) => Ok(Message {
+                message: format!("Successfully updated: [{:?}]", todo.id),
+                code: None,
+                warnings: updated.warnings,
+                request_id: None,
+            }),
+            Err(e) => Err(api_err(TodoRoutesUpdateError::from(e))),
+        }
+    }
+}
+
+// Only what the HTTP client would send of the todo; the rest is left to the server
+fn sent(todo: &Todo) -> Todo {
+    Todo {
+        id: todo.id,
+        task: todo.task.clone(),
+        done: todo.done,
+        version: 0,
+        list_id: todo.list_id,
+        position: 0,
+    }
+}
+
+// The error the HTTP client would get for the route's error response
+fn api_err<E: ResponseError>(e: E) -> ClientError {
+    let response = e.error_response();
+    let status = response.status().as_u16();
+    // Error responses are built with their whole body at hand
+    let body = response.into_body().try_into_bytes().unwrap_or_default();
+    let (message, code) = match serde_json::from_slice::<Message>(&body) {
+        Ok(m) => (m.message, m.code),
+        Err(_) => (String::from_utf8_lossy(&body).into_owned(), None),
+    };
+    ClientError::Api {
+        status,
+        message,
+        code,
+        request_id: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::ErrorCode;
+
+    // Written against the trait, as code under test would be
+    async fn finish<C: TodoApi>(api: &C, id: TodoId) -> Result<Message, ClientError> {
+        let mut todo = api.get_todo(id).await?;
+        todo.done = true;
+        api.update_todo_if_unchanged(&todo).await
+    }
+
+    #[actix_web::test]
+    async fn test_in_process() {
+        let api = in_memory();
+        let todo_data = TodoData {
+            task: "Write tests".to_string(),
+            done: false,
+            list_id: None,
+        };
+        let created: Todo = api.create_todo(&todo_data).await.unwrap().into();
+        finish(&api, created.id).await.unwrap();
+        let finished = api.get_todo(created.id).await.unwrap();
+        assert!(finished.done);
+        assert_eq!(vec![finished], api.list_todos().await.unwrap());
+
+        // A stale version, as when someone else has updated the todo since
+        match api.update_todo_if_unchanged(&created).await {
+            Err(ClientError::Api {
+                status: 412,
+                code: Some(ErrorCode::VersionConflict),
+                ..
+            }) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+        match api.get_todo(TodoId(created.id.0 + 1)).await {
+            Err(ClientError::Api {
+                status: 404,
+                message,
+                code: Some(ErrorCode::TodoNotFound),
+                ..
+            }) => assert_eq!(
+                format!("No such todo: [{:?}]", TodoId(created.id.0 + 1)),
+                message
+            ),
+            other => panic!("Unexpected {:?}", other),
+        }
+        let empty = TodoData {
+            task: "".to_string(),
+            ..todo_data
+        };
+        match api.create_todo(&empty).await {
+            Err(ClientError::Api {
+                status: 400,
+                code: Some(ErrorCode::TaskEmpty),
+                ..
+            }) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Serialisation of the bodies of the busiest responses, the todo lists. With the `fast-json`
+//! feature this goes through simd-json, which writes the same bytes as serde_json. It is off by
+//! default as, unlike its parsing, simd-json's serialising has benchmarked slower than
+//! serde_json's on x86_64; `benches/json.rs` compares the two.
+//!
+//! With the `arena` feature, bodies are written to a per-thread bumpalo arena that is reset after
+//! each one, so that the memory grown to fit the largest bodies is reused by the next request
+//! instead of being reallocated as the body grows. The conversions from domain models that come
+//! before move their strings rather than copying them, which leaves the body as the only sizeable
+//! allocation of a list request. It is off by default as it has benchmarked no faster.
+
+use actix_web::web::Bytes;
+use serde::Serialize;
+use std::io;
+
+#[cfg(feature = "arena")]
+thread_local! {
+    static ARENA: std::cell::RefCell<bumpalo::Bump> = std::cell::RefCell::new(bumpalo::Bump::new());
+}
+
+#[cfg(not(feature = "fast-json"))]
+fn write<W: io::Write, T: Serialize>(writer: W, value: &T) -> Result<(), String> {
+    serde_json::to_writer(writer, value).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "fast-json")]
+fn write<W: io::Write, T: Serialize>(writer: W, value: &T) -> Result<(), String> {
+    simd_json::serde::to_writer(writer, value).map_err(|e| e.to_string())
+}
+
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let mut body = Vec::with_capacity(128);
+    write(&mut body, value)?;
+    Ok(body)
+}
+
+#[cfg(not(feature = "arena"))]
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Bytes, String> {
+    to_vec(value).map(Bytes::from)
+}
+
+#[cfg(feature = "arena")]
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Bytes, String> {
+    ARENA.with(|arena| {
+        let mut arena = arena.borrow_mut();
+        let bytes = {
+            let mut body = bumpalo::collections::Vec::with_capacity_in(128, &arena);
+            write(&mut body, value).map(|()| Bytes::copy_from_slice(&body))
+        };
+        arena.reset();
+        bytes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::todo::{Todo, TodoId};
+
+    // ETags are hashed from the bytes, so they must not change with the feature
+    #[test]
+    fn test_same_as_serde_json() {
+        let tasks = [
+            "Plain",
+            "",
+            "Quotes \" and \\ backslashes / slashes",
+            "Control \n\r\t\u{8}\u{c}\u{0}\u{1f} characters",
+            "Unicode: héllo, 日本語, 🦀, \u{2028}",
+            &"Long ".repeat(1_000),
+        ];
+        let todos: Vec<Todo> = tasks
+            .iter()
+            .enumerate()
+            .map(|(idx, task)| Todo {
+                id: TodoId(idx as u128 + 1),
+                task: task.to_string(),
+                done: idx % 2 == 0,
+                version: u64::MAX - idx as u64,
+                list_id: None,
+                position: idx as u64,
+            })
+            .collect();
+        assert_eq!(serde_json::to_vec(&todos).unwrap(), to_vec(&todos).unwrap());
+        // Twice, so that the second reuses the arena, when there is one
+        for _ in 0..2 {
+            assert_eq!(
+                serde_json::to_vec(&todos).unwrap(),
+                to_bytes(&todos).unwrap().to_vec()
+            );
+        }
+        let empty: Vec<Todo> = Vec::new();
+        assert_eq!(b"[]".to_vec(), to_vec(&empty).unwrap());
+    }
+}
@@ -0,0 +1,246 @@
+//! Keeps a copy of JSON request bodies, so that errors parsing them can say which field was wrong:
+//! serde only says where in the body it gave up
+
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::PayloadError;
+use actix_web::http::header;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{Error, HttpMessage, HttpRequest};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use futures::stream::{self, Stream, StreamExt};
+use std::pin::Pin;
+use std::rc::Rc;
+
+// What's kept in the request's extensions
+#[derive(Clone)]
+struct KeptBody(Bytes);
+
+/// The request's JSON body, unless it was bigger than the limit
+pub fn kept(req: &HttpRequest) -> Option<Bytes> {
+    req.extensions()
+        .get::<KeptBody>()
+        .map(|kept| kept.0.clone())
+}
+
+/// Middleware that reads JSON bodies of up to `limit` bytes ahead of the handler, keeping a copy;
+/// bigger ones are passed on as they came, for the JSON extractor to turn down
+pub struct KeepJsonBodies {
+    limit: usize,
+}
+
+impl KeepJsonBodies {
+    pub fn new(limit: usize) -> KeepJsonBodies {
+        KeepJsonBodies { limit }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for KeepJsonBodies
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = KeepJsonBodiesMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(KeepJsonBodiesMiddleware {
+            service: Rc::new(service),
+            limit: self.limit,
+        })
+    }
+}
+
+pub struct KeepJsonBodiesMiddleware<S> {
+    service: Rc<S>,
+    limit: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for KeepJsonBodiesMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limit = self.limit;
+        Box::pin(async move {
+            if is_json(&req) {
+                let mut payload = req.take_payload();
+                let mut body = BytesMut::new();
+                let mut rest = None;
+                while let Some(chunk) = payload.next().await {
+                    match chunk {
+                        Ok(chunk) if body.len() + chunk.len() <= limit => {
+                            body.extend_from_slice(&chunk)
+                        }
+                        // Passed on after what was read, for the extractor to fail on
+                        unread => {
+                            rest = Some(stream::once(async { unread }).chain(payload));
+                            break;
+                        }
+                    }
+                }
+                let body = body.freeze();
+                match rest {
+                    Some(rest) => {
+                        let read = stream::once(async { Ok(body) });
+                        let stream: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+                            Box::pin(read.chain(rest));
+                        req.set_payload(Payload::from(stream));
+                    }
+                    None => {
+                        req.extensions_mut().insert(KeptBody(body.clone()));
+                        req.set_payload(Payload::from(body));
+                    }
+                }
+            }
+            service.call(req).await
+        })
+    }
+}
+
+fn is_json(req: &ServiceRequest) -> bool {
+    match req.headers().get(header::CONTENT_TYPE) {
+        Some(content_type) => content_type
+            .to_str()
+            .map(|s| s.starts_with("application/json") || s.contains("+json"))
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+enum Frame {
+    // The key being read, if any yet
+    Object(Option<String>),
+    Array(usize),
+}
+
+/// Where in the body the given 1-based line and column are, e.g. `done` or `ids[2]`, as serde
+/// reports them on errors; empty at the top level. With `missing`, names that field in the object
+/// at that point instead.
+pub fn field_at(body: &[u8], line: usize, column: usize, missing: Option<&str>) -> String {
+    let line_start: usize = body
+        .split(|b| *b == b'\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len() + 1)
+        .sum();
+    let end = (line_start + column.saturating_sub(1)).min(body.len());
+    let mut frames = Vec::new();
+    let mut expecting_key = false;
+    let mut i = 0;
+    while i < end {
+        match body[i] {
+            b'{' => {
+                frames.push(Frame::Object(None));
+                expecting_key = true;
+            }
+            b'[' => frames.push(Frame::Array(0)),
+            b'}' | b']' => {
+                frames.pop();
+            }
+            b',' => match frames.last_mut() {
+                Some(Frame::Object(_)) => expecting_key = true,
+                Some(Frame::Array(index)) => *index += 1,
+                None => {}
+            },
+            b'"' => {
+                let start = i + 1;
+                i = start;
+                while i < body.len() && body[i] != b'"' {
+                    i += if body[i] == b'\\' { 2 } else { 1 };
+                }
+                if expecting_key {
+                    let key = String::from_utf8_lossy(&body[start..i.min(body.len())]);
+                    if let Some(Frame::Object(current)) = frames.last_mut() {
+                        *current = Some(key.to_string());
+                    }
+                    expecting_key = false;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if let (Some(missing), Some(Frame::Object(current))) = (missing, frames.last_mut()) {
+        *current = Some(missing.to_string());
+    }
+    let mut path = String::new();
+    for frame in frames {
+        match frame {
+            Frame::Object(Some(key)) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&key);
+            }
+            Frame::Object(None) => {}
+            Frame::Array(index) => path.push_str(&format!("[{}]", index)),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::todo::{ReorderTodos, TodoData};
+    use actix_web::test::{call_and_read_body, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use serde::de::DeserializeOwned;
+    use std::fmt::Debug;
+
+    // Where serde gives up on the body
+    fn field_of<T: DeserializeOwned + Debug>(body: &str, missing: Option<&str>) -> String {
+        let e = serde_json::from_str::<T>(body).unwrap_err();
+        field_at(body.as_bytes(), e.line(), e.column(), missing)
+    }
+
+    #[test]
+    fn test_field_at() {
+        let body = r#"{"task": "Make, \"the\" bed", "done": "yes"}"#;
+        assert_eq!("done", field_of::<TodoData>(body, None));
+        let body = r#"{"task": "Make the bed", "done": {"yes": true}}"#;
+        assert_eq!("done", field_of::<TodoData>(body, None));
+        let body = "{\n  \"ids\": [1,\n  \"x\"]\n}";
+        assert_eq!("ids[1]", field_of::<ReorderTodos>(body, None));
+        let body = r#"{"done": true}"#;
+        assert_eq!("task", field_of::<TodoData>(body, Some("task")));
+        assert_eq!("", field_of::<Vec<TodoData>>("{}", None));
+        assert_eq!(
+            "[0].task",
+            field_of::<Vec<TodoData>>(r#"[{"task": 1}]"#, None)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_keep_json_bodies() {
+        let app = init_service(App::new().wrap(KeepJsonBodies::new(8)).route(
+            "/",
+            web::post().to(|req: HttpRequest, body: Bytes| async move {
+                let kept = kept(&req).map(|kept| kept.to_vec()).unwrap_or_default();
+                assert!(kept.is_empty() || kept == body);
+                HttpResponse::Ok().body(format!("{}:{}", body.len(), kept.len()))
+            }),
+        ))
+        .await;
+        let call = |body: &'static str| {
+            TestRequest::post()
+                .uri("/")
+                .insert_header((header::CONTENT_TYPE, "application/json"))
+                .set_payload(body)
+                .to_request()
+        };
+        assert_eq!("4:4", call_and_read_body(&app, call("[1 ]")).await);
+        // Too big to keep, but passed on whole
+        assert_eq!("12:0", call_and_read_body(&app, call("[1, 2, 3, 4]")).await);
+    }
+}
@@ -1,85 +1,120 @@
-#![feature(async_await)]
-
 pub mod handlers {
+    pub mod admin_routes_handler;
+    pub mod asset_routes_handler;
+    pub mod health_routes_handler;
+    pub mod history_routes_handler;
+    pub mod list_routes_handler;
+    pub mod recurrence_routes_handler;
+    pub mod reminder_routes_handler;
+    pub mod rule_routes_handler;
+    pub mod share_routes_handler;
     pub mod todo_routes_handler;
+    pub mod v2_todo_routes_handler;
 }
 
 pub mod controllers {
+    pub mod history_controller;
+    pub mod list_controller;
+    pub mod recurrence_controller;
+    pub mod reminder_controller;
+    pub mod rule_controller;
+    pub mod share_controller;
     pub mod todo_controller;
 }
 
-pub mod models {
-    pub mod common;
-    pub mod todo;
-}
+pub use models;
 
-use crate::controllers::todo_controller;
-use crate::controllers::todo_controller::TodoControllerImpl;
-use actix_web::middleware::Logger;
-use actix_web::*;
-use domain::services::todo_service;
-use domain::services::todo_service::TodoServiceImpl;
-use handlers::todo_routes_handler;
-use infra::in_mem::todo_repo;
-use infra::in_mem::todo_repo::InMemTodoRepo;
-use log::*;
-use paperclip::actix::{
-    // use this instead of actix_web::web
-    web,
-    // extension trait for actix_web::App and proc-macro attributes
-    OpenApiExt,
-};
+pub mod admin_token;
+pub mod app_builder;
+pub mod assets;
+mod bundle_hash;
+pub mod config;
+pub mod cors;
+pub mod created;
+pub mod deprecation;
+pub mod dry_run;
+pub mod embed;
+pub mod errors;
+pub mod etag;
+pub mod event_queues;
+pub mod fallback;
+pub mod health_history;
+pub mod import_export;
+pub mod in_process;
+pub mod json;
+pub mod json_bodies;
+pub mod lifecycle;
+pub mod messaging;
+pub mod metrics;
+pub mod notifications;
+pub mod plugins;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+pub mod read_only;
+pub mod request_id;
+pub mod scheduler;
+pub mod seed;
+pub mod self_check;
+pub mod server_timing;
+pub mod spec;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod tls;
 
-static WEB_BIND_ADDR_KEY: &str = "WEB_BIND_ADDR";
+use crate::config::Config;
+use crate::lifecycle::Readiness;
+use actix_web::dev::Server;
+use actix_web::rt;
+use futures::future::{self, LocalBoxFuture};
+use std::net::SocketAddr;
+use std::time::Duration;
 
-// This allows us to use a generated (via build.rs) file
-// that bakes these static files into our binary.
-use std::collections::HashMap;
-include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+pub use crate::app_builder::AppBuilder;
 
-pub fn run_server() -> Result<(), std::io::Error> {
-    let todo_repo = todo_repo::new();
-    type Controller = TodoControllerImpl<TodoServiceImpl<InMemTodoRepo>>;
-    let server = HttpServer::new(move || {
-        let todo_service = todo_service::new(todo_repo.clone());
-        let todo_controller = todo_controller::new(todo_service);
-        App::new()
-            .wrap(Logger::default())
-            .wrap(middleware::Compress::default())
-            .data(todo_controller)
-            .service(actix_web_static_files::ResourceFiles::new(
-                "/swagger",
-                generate(),
-            ))
-            .wrap_api()
-            .with_json_spec_at("/api/spec")
-            .route(
-                "/tasks",
-                web::get().to_async(todo_routes_handler::list::<Controller>),
-            )
-            .route(
-                "/tasks",
-                web::post().to_async(todo_routes_handler::create::<Controller>),
-            )
-            .route(
-                "/tasks/{id}",
-                web::get().to_async(todo_routes_handler::get::<Controller>),
-            )
-            .route(
-                "/tasks/{id}",
-                web::delete().to_async(todo_routes_handler::delete::<Controller>),
-            )
-            .route(
-                "/tasks/{id}",
-                web::put().to_async(todo_routes_handler::update::<Controller>),
-            )
-            .build()
-    });
+pub async fn run_server(config: Config) -> Result<(), std::io::Error> {
+    let drain_delay = Duration::from_secs(config.drain_delay_secs);
+    let built = build_server(config).await?;
+    rt::spawn(built.scheduler);
+    let mut handles = vec![built.server.handle()];
+    handles.extend(built.redirect_server.as_ref().map(|s| s.handle()));
+    rt::spawn(lifecycle::drain_on_shutdown_signal(
+        built.readiness,
+        drain_delay,
+        handles,
+    ));
+    match built.redirect_server {
+        None => built.server.await,
+        Some(redirect_server) => future::try_join(built.server, redirect_server)
+            .await
+            .map(|_| ()),
+    }
+}
 
-    let bind_to = std::env::var(WEB_BIND_ADDR_KEY).unwrap_or("127.0.0.1:8080".to_string());
-    info!(
-        "Binding to [{}], change by setting the {} env var.",
-        bind_to, WEB_BIND_ADDR_KEY
-    );
-    Ok(server.bind(bind_to)?.run()?)
+/// Servers that are already listening, but only serve once awaited or spawned
+pub struct BuiltServer {
+    pub server: Server,
+    /// Redirects plaintext HTTP to HTTPS, when configured
+    pub redirect_server: Option<Server>,
+    /// Where `server` listens; differs from the configured address when binding to port 0
+    pub addrs: Vec<SocketAddr>,
+    /// What /readyz reports
+    pub readiness: Readiness,
+    /// Creates the next occurrences of recurring todos, sends due reminders and pushes metrics
+    /// when configured to; spawn it alongside `server`
+    pub scheduler: LocalBoxFuture<'static, ()>,
+}
+
+/// Binds the app as configured, without handling signals: stop it through `Server::handle`.
+/// Must be called from within an actix runtime.
+pub async fn build_server(config: Config) -> Result<BuiltServer, std::io::Error> {
+    if let Some(secret) = &config.id_secret {
+        // Process-wide, as ids are (de)serialized with no access to the app's state
+        models::ids::install(models::ids::codec(secret)).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "ids are already encoded with a secret in this process",
+            )
+        })?;
+    }
+    AppBuilder::new(config).build().await
 }
@@ -0,0 +1,76 @@
+use actix_web::dev::ServerHandle;
+use actix_web::rt::signal::ctrl_c;
+use actix_web::rt::time::sleep;
+use futures::future;
+use log::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Whether this instance wants traffic; turned off as soon as shutdown starts.
+#[derive(Clone)]
+pub struct Readiness(Arc<AtomicBool>);
+
+pub fn readiness() -> Readiness {
+    Readiness(Arc::new(AtomicBool::new(true)))
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set_not_ready(&self) {
+        self.0.store(false, Ordering::Relaxed)
+    }
+}
+
+/// On SIGTERM or Ctrl-C, fails readiness checks for `drain_delay` while still serving, giving
+/// load balancers time to deregister the instance, then gracefully stops the servers.
+pub async fn drain_on_shutdown_signal(
+    readiness: Readiness,
+    drain_delay: Duration,
+    servers: Vec<ServerHandle>,
+) {
+    shutdown_signal().await;
+    info!(
+        "Shutting down; failing readiness checks for [{}s] before draining",
+        drain_delay.as_secs()
+    );
+    readiness.set_not_ready();
+    sleep(drain_delay).await;
+    for server in servers {
+        server.stop(true).await;
+    }
+}
+
+async fn shutdown_signal() {
+    let interrupt = Box::pin(async {
+        let _ = ctrl_c().await;
+    });
+    #[cfg(unix)]
+    {
+        use actix_web::rt::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            let terminate = Box::pin(async move {
+                terminate.recv().await;
+            });
+            future::select(interrupt, terminate).await;
+            return;
+        }
+    }
+    interrupt.await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness() {
+        let readiness = readiness();
+        assert!(readiness.is_ready());
+        readiness.clone().set_not_ready();
+        assert!(!readiness.is_ready());
+    }
+}
@@ -0,0 +1,77 @@
+use domain::events::Subscriber;
+use schemars::JsonSchema;
+use serde_derive::Deserialize;
+use std::io::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessagingBackend {
+    Kafka,
+    Nats,
+}
+
+impl MessagingBackend {
+    /// The cargo feature that has to be enabled for this backend to be usable
+    pub fn feature(self) -> &'static str {
+        match self {
+            MessagingBackend::Kafka => "kafka",
+            MessagingBackend::Nats => "nats",
+        }
+    }
+
+    pub fn is_enabled(self) -> bool {
+        match self {
+            MessagingBackend::Kafka => cfg!(feature = "kafka"),
+            MessagingBackend::Nats => cfg!(feature = "nats"),
+        }
+    }
+}
+
+impl FromStr for MessagingBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kafka" => Ok(MessagingBackend::Kafka),
+            "nats" => Ok(MessagingBackend::Nats),
+            _ => Err("expected one of [kafka, nats]".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MessagingSettings {
+    pub backend: MessagingBackend,
+    // Comma-separated brokers for Kafka, a server URL for NATS
+    pub url: String,
+    // Kafka topic or NATS subject
+    pub topic: String,
+}
+
+/// Connects to the configured broker, returning a subscriber that publishes todo events to it
+pub async fn publisher(
+    settings: &MessagingSettings,
+) -> Result<Arc<dyn Subscriber + Send + Sync>, Error> {
+    match settings.backend {
+        #[cfg(feature = "kafka")]
+        MessagingBackend::Kafka => {
+            let publisher = infra::messaging::kafka_publisher::new(&settings.url, &settings.topic)
+                .map_err(|e| Error::other(e.to_string()))?;
+            Ok(Arc::new(publisher))
+        }
+        #[cfg(feature = "nats")]
+        MessagingBackend::Nats => {
+            let publisher = infra::messaging::nats_publisher::new(&settings.url, &settings.topic)
+                .await
+                .map_err(|e| Error::other(e.to_string()))?;
+            Ok(Arc::new(publisher))
+        }
+        #[allow(unreachable_patterns)]
+        backend => Err(Error::other(format!(
+            "Built without the [{}] feature needed for messaging",
+            backend.feature()
+        ))),
+    }
+}
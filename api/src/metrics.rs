@@ -0,0 +1,243 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use domain::metrics::Metrics;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use infra::metrics::prometheus_metrics::{self, PrometheusMetrics};
+use infra::metrics::statsd_metrics;
+use schemars::JsonSchema;
+use serde_derive::Deserialize;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often totals are pushed to an OTLP collector
+pub const OTLP_PUSH_INTERVAL: Duration = Duration::from_secs(15);
+/// What the service is called in OTLP's resource attributes
+pub static SERVICE_NAME: &str = "todddo";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsBackend {
+    Prometheus,
+    Statsd,
+    Otlp,
+}
+
+impl MetricsBackend {
+    /// The cargo feature that has to be enabled for this backend to be usable, if any
+    pub fn feature(self) -> Option<&'static str> {
+        match self {
+            MetricsBackend::Prometheus | MetricsBackend::Statsd => None,
+            MetricsBackend::Otlp => Some("otlp"),
+        }
+    }
+
+    pub fn is_enabled(self) -> bool {
+        match self {
+            MetricsBackend::Prometheus | MetricsBackend::Statsd => true,
+            MetricsBackend::Otlp => cfg!(feature = "otlp"),
+        }
+    }
+}
+
+impl FromStr for MetricsBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prometheus" => Ok(MetricsBackend::Prometheus),
+            "statsd" => Ok(MetricsBackend::Statsd),
+            "otlp" => Ok(MetricsBackend::Otlp),
+            _ => Err("expected one of [prometheus, statsd, otlp]".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MetricsSettings {
+    // Scraped from /metrics
+    Prometheus,
+    // `addr` is the agent's, e.g. localhost:8125
+    Statsd { addr: String },
+    // `url` is the collector's, e.g. http://localhost:4318
+    Otlp { url: String },
+}
+
+impl MetricsSettings {
+    pub fn backend(&self) -> MetricsBackend {
+        match self {
+            MetricsSettings::Prometheus => MetricsBackend::Prometheus,
+            MetricsSettings::Statsd { .. } => MetricsBackend::Statsd,
+            MetricsSettings::Otlp { .. } => MetricsBackend::Otlp,
+        }
+    }
+}
+
+pub type SharedMetrics = Arc<dyn Metrics + Send + Sync>;
+
+/// Where the configured backend's measurements go, and what it needs besides
+pub struct Recorder {
+    pub metrics: SharedMetrics,
+    /// Served on /metrics for Prometheus to scrape
+    pub prometheus: Option<Arc<PrometheusMetrics>>,
+    /// Pushes totals to a collector; spawn it alongside the server
+    pub push: Option<LocalBoxFuture<'static, ()>>,
+}
+
+/// Sets up the configured metrics backend
+pub fn recorder(settings: &MetricsSettings) -> Result<Recorder, std::io::Error> {
+    match settings {
+        MetricsSettings::Prometheus => {
+            let prometheus = Arc::new(prometheus_metrics::new());
+            Ok(Recorder {
+                metrics: prometheus.clone(),
+                prometheus: Some(prometheus),
+                push: None,
+            })
+        }
+        MetricsSettings::Statsd { addr } => Ok(Recorder {
+            metrics: Arc::new(statsd_metrics::new(addr)?),
+            prometheus: None,
+            push: None,
+        }),
+        #[cfg(feature = "otlp")]
+        MetricsSettings::Otlp { url } => {
+            let otlp = Arc::new(infra::metrics::otlp_metrics::new(url, SERVICE_NAME));
+            Ok(Recorder {
+                metrics: otlp.clone(),
+                prometheus: None,
+                push: Some(Box::pin(push(otlp, OTLP_PUSH_INTERVAL))),
+            })
+        }
+        #[allow(unreachable_patterns)]
+        other => Err(std::io::Error::other(format!(
+            "Built without the [{}] feature needed for metrics",
+            other.backend().feature().unwrap_or_default()
+        ))),
+    }
+}
+
+// Runs forever, the first push being a period in
+#[cfg(feature = "otlp")]
+async fn push(otlp: Arc<infra::metrics::otlp_metrics::OtlpMetrics>, every: Duration) {
+    let mut ticks = actix_web::rt::time::interval(every);
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        if let Err(e) = otlp.push().await {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+/// Counts requests and times them, by method, route pattern and status, when metrics are
+/// configured
+pub struct RecordMetrics {
+    metrics: Option<SharedMetrics>,
+}
+
+impl RecordMetrics {
+    pub fn new(metrics: Option<SharedMetrics>) -> RecordMetrics {
+        RecordMetrics { metrics }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RecordMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RecordMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RecordMetricsMiddleware {
+            service,
+            metrics: self.metrics.clone(),
+        })
+    }
+}
+
+pub struct RecordMetricsMiddleware<S> {
+    service: S,
+    metrics: Option<SharedMetrics>,
+}
+
+impl<S, B> Service<ServiceRequest> for RecordMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let metrics = match &self.metrics {
+            Some(metrics) => metrics.clone(),
+            None => return Box::pin(self.service.call(req)),
+        };
+        let start = Instant::now();
+        let method = req.method().to_string();
+        // Patterns rather than paths, so that there's a series per route rather than per todo
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+        let f_res = self.service.call(req);
+        Box::pin(async move {
+            let res = f_res.await;
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            let status = status.as_u16().to_string();
+            let labels = [
+                ("method", method.as_str()),
+                ("route", route.as_str()),
+                ("status", status.as_str()),
+            ];
+            metrics.count("http_requests", &labels, 1);
+            metrics.time("http_request_duration", &labels, start.elapsed());
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_record_metrics() {
+        let prometheus = Arc::new(prometheus_metrics::new());
+        let app = init_service(
+            App::new()
+                .wrap(RecordMetrics::new(Some(prometheus.clone())))
+                .route(
+                    "/tasks/{id}",
+                    web::get().to(|| async { HttpResponse::Ok().finish() }),
+                ),
+        )
+        .await;
+        for uri in ["/tasks/1", "/tasks/2", "/nothing"] {
+            call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+        }
+        let rendered = prometheus.render();
+        assert!(rendered.contains(
+            "http_requests_total{method=\"GET\",route=\"/tasks/{id}\",status=\"200\"} 2\n"
+        ));
+        assert!(rendered.contains(
+            "http_requests_total{method=\"GET\",route=\"unmatched\",status=\"404\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "http_request_duration_seconds_count{method=\"GET\",route=\"/tasks/{id}\",status=\"200\"} 2\n"
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+use domain::events::Subscriber;
+use domain::todo::TodoValidator;
+use std::io::Error;
+use std::sync::Arc;
+
+/// Plugins loaded from a directory, hooked into the todo service as validators and subscribers
+#[derive(Default)]
+pub struct Plugins {
+    pub validators: Vec<Arc<dyn TodoValidator + Send + Sync>>,
+    pub subscribers: Vec<Arc<dyn Subscriber + Send + Sync>>,
+    // e.g. "spellcheck (validate, on_event)", for logging and the self-check
+    pub descriptions: Vec<String>,
+}
+
+#[cfg(feature = "plugins")]
+pub fn load(dir: &str) -> Result<Plugins, Error> {
+    let loaded = infra::plugins::wasm_plugin::load_dir(std::path::Path::new(dir))
+        .map_err(|e| Error::other(format!("{:#}", e)))?;
+    let mut plugins = Plugins::default();
+    for plugin in loaded {
+        let plugin = Arc::new(plugin);
+        plugins
+            .descriptions
+            .push(format!("{} ({})", plugin.name(), plugin.hooks().join(", ")));
+        plugins.validators.push(plugin.clone());
+        plugins.subscribers.push(plugin);
+    }
+    Ok(plugins)
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn load(_dir: &str) -> Result<Plugins, Error> {
+    Err(Error::other(
+        "Built without the [plugins] feature needed for plugins",
+    ))
+}
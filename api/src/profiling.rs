@@ -0,0 +1,29 @@
+use crate::models::admin::ProfileFormat;
+use actix_web::rt::time::sleep;
+use pprof::protos::Message;
+use pprof::ProfilerGuardBuilder;
+use std::time::Duration;
+
+// Samples per second
+static FREQUENCY: i32 = 100;
+
+/// Samples the CPU usage of the whole process for `duration`, returning the encoded profile.
+pub async fn capture(duration: Duration, format: ProfileFormat) -> Result<Vec<u8>, String> {
+    let guard = ProfilerGuardBuilder::default()
+        .frequency(FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(|e| e.to_string())?;
+    sleep(duration).await;
+    let report = guard.report().build().map_err(|e| e.to_string())?;
+    let mut body = Vec::new();
+    match format {
+        ProfileFormat::Flamegraph => report.flamegraph(&mut body).map_err(|e| e.to_string())?,
+        ProfileFormat::Pprof => report
+            .pprof()
+            .map_err(|e| e.to_string())?
+            .encode(&mut body)
+            .map_err(|e| e.to_string())?,
+    }
+    Ok(body)
+}
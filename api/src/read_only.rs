@@ -0,0 +1,137 @@
+use crate::models::common::{ErrorCode, Message};
+use crate::request_id;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::{Error, HttpResponse};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Whether changes are being turned down, e.g. during a migration; shared by every worker, and
+/// flipped at runtime through the admin endpoint
+#[derive(Clone)]
+pub struct ReadOnly(Arc<AtomicBool>);
+
+pub fn new(on: bool) -> ReadOnly {
+    ReadOnly(Arc::new(AtomicBool::new(on)))
+}
+
+impl ReadOnly {
+    pub fn is_on(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, on: bool) {
+        self.0.store(on, Ordering::Relaxed)
+    }
+}
+
+// Reads pass, as do admin routes, so that read-only mode can be turned off again
+fn turned_down(method: &Method, path: &str) -> bool {
+    let reads = [Method::GET, Method::HEAD, Method::OPTIONS];
+    !reads.contains(method) && !path.starts_with("/admin/")
+}
+
+/// Middleware that answers requests that would change anything with a 503 while read-only mode
+/// is on
+pub struct ReadOnlyGuard {
+    read_only: ReadOnly,
+}
+
+impl ReadOnlyGuard {
+    pub fn new(read_only: ReadOnly) -> ReadOnlyGuard {
+        ReadOnlyGuard { read_only }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ReadOnlyGuard
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ReadOnlyGuardMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ReadOnlyGuardMiddleware {
+            service,
+            read_only: self.read_only.clone(),
+        })
+    }
+}
+
+pub struct ReadOnlyGuardMiddleware<S> {
+    service: S,
+    read_only: ReadOnly,
+}
+
+impl<S, B> Service<ServiceRequest> for ReadOnlyGuardMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.read_only.is_on() && turned_down(req.method(), req.path()) {
+            let response = HttpResponse::ServiceUnavailable().json(&Message {
+                message: "The server is read-only for maintenance; try again later".to_string(),
+                code: Some(ErrorCode::ReadOnly),
+                warnings: Vec::new(),
+                request_id: request_id::current(),
+            });
+            let (req, _) = req.into_parts();
+            return Box::pin(async move {
+                Ok(ServiceResponse::new(req, response).map_into_right_body())
+            });
+        }
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use actix_web::{test, web, App};
+
+    #[actix_web::test]
+    async fn test_read_only_guard() {
+        let read_only = new(true);
+        let app = test::init_service(
+            App::new()
+                .wrap(ReadOnlyGuard::new(read_only.clone()))
+                .route("/tasks", web::get().to(HttpResponse::Ok))
+                .route("/tasks", web::post().to(HttpResponse::Created))
+                .route("/admin/read-only", web::put().to(HttpResponse::Ok)),
+        )
+        .await;
+        let call = |method: Method, path: &str| {
+            test::TestRequest::default()
+                .method(method)
+                .uri(path)
+                .to_request()
+        };
+        let listed = test::call_service(&app, call(Method::GET, "/tasks")).await;
+        assert_eq!(200, listed.status().as_u16());
+        let created = test::call_service(&app, call(Method::POST, "/tasks")).await;
+        assert_eq!(503, created.status().as_u16());
+        let body = to_bytes(created.into_body()).await.unwrap();
+        let message: Message = serde_json::from_slice(&body).unwrap();
+        assert_eq!(Some(ErrorCode::ReadOnly), message.code);
+        let admin = test::call_service(&app, call(Method::PUT, "/admin/read-only")).await;
+        assert_eq!(200, admin.status().as_u16());
+        read_only.set(false);
+        let created = test::call_service(&app, call(Method::POST, "/tasks")).await;
+        assert_eq!(201, created.status().as_u16());
+    }
+}
@@ -0,0 +1,184 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Logger;
+use actix_web::{Error, HttpMessage};
+use futures::future::{ok, LocalBoxFuture, Ready};
+
+pub static REQUEST_ID_HEADER: &str = "x-request-id";
+pub static CLIENT_ID_HEADER: &str = "x-client-id";
+// Incoming ids longer than this, or with anything but printable ASCII, are replaced
+static MAX_INCOMING_LEN: usize = 128;
+// actix's default format, plus the request id
+static ACCESS_LOG_FORMAT: &str =
+    r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T request_id=%{request_id}xi"#;
+
+tokio::task_local! {
+    static CURRENT: RequestId;
+    static CURRENT_CLIENT: String;
+}
+
+/// Identifies a request across the access log, other log lines and error bodies
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// The id of the request being handled, if any. Works from any layer (controllers, services,
+/// subscribers) as long as it runs within the request, rather than in a spawned task.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.0.clone()).ok()
+}
+
+/// Who made the request being handled, as given by `client_identifier`
+pub fn current_client() -> Option<String> {
+    CURRENT_CLIENT.try_with(|client| client.clone()).ok()
+}
+
+/// The `X-Client-Id` header, falling back to the user agent and then the address
+pub(crate) fn client_identifier(req: &ServiceRequest) -> String {
+    let header_value = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    header_value(CLIENT_ID_HEADER)
+        .or_else(|| header_value("user-agent"))
+        .or_else(|| {
+            req.connection_info()
+                .realip_remote_addr()
+                .map(|r| r.to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Access logger that includes the request id; must be wrapped inside `RequestIds`
+pub fn access_logger() -> Logger {
+    Logger::new(ACCESS_LOG_FORMAT).custom_request_replace("request_id", |req| {
+        req.extensions()
+            .get::<RequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_else(|| "-".to_string())
+    })
+}
+
+fn incoming(req: &ServiceRequest) -> Option<RequestId> {
+    let given = req.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?;
+    let sensible = !given.is_empty()
+        && given.len() <= MAX_INCOMING_LEN
+        && given.bytes().all(|b| b.is_ascii_graphic());
+    if sensible {
+        Some(RequestId(given.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Middleware that gives every request an id, propagating a sensible `X-Request-Id` header or
+/// generating one, and echoes it in the response's `X-Request-Id` header.
+pub struct RequestIds;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIds
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdsMiddleware { service })
+    }
+}
+
+pub struct RequestIdsMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id =
+            incoming(&req).unwrap_or_else(|| RequestId(uuid::Uuid::new_v4().simple().to_string()));
+        req.extensions_mut().insert(request_id.clone());
+        let client = client_identifier(&req);
+        // Inner services can do some of their work before returning a future, e.g. the logger
+        let f_res = CURRENT.sync_scope(request_id.clone(), || {
+            CURRENT_CLIENT.sync_scope(client.clone(), || self.service.call(req))
+        });
+        let f_res = CURRENT_CLIENT.scope(client, f_res);
+        Box::pin(CURRENT.scope(request_id.clone(), async move {
+            let mut res = f_res.await?;
+            if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App};
+
+    #[actix_web::test]
+    async fn test_request_ids() {
+        let app = init_service(App::new().wrap(RequestIds).route(
+            "/",
+            web::get().to(|| async { current().unwrap_or_default() }),
+        ))
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header((REQUEST_ID_HEADER, "abc-123"))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!("abc-123", resp.headers().get(REQUEST_ID_HEADER).unwrap());
+        assert_eq!("abc-123", read_body(resp).await);
+
+        for given in &[None, Some("has spaces"), Some("")] {
+            let mut req = TestRequest::default();
+            if let Some(given) = given {
+                req = req.insert_header((REQUEST_ID_HEADER, *given));
+            }
+            let resp = call_service(&app, req.to_request()).await;
+            let generated = resp.headers().get(REQUEST_ID_HEADER).unwrap().clone();
+            assert_eq!(32, generated.len());
+            assert_eq!(generated.as_bytes(), &read_body(resp).await[..]);
+        }
+        assert_eq!(None, current());
+    }
+
+    #[actix_web::test]
+    async fn test_current_client() {
+        let app = init_service(App::new().wrap(RequestIds).route(
+            "/",
+            web::get().to(|| async { current_client().unwrap_or_default() }),
+        ))
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header((CLIENT_ID_HEADER, "billing"))
+            .insert_header(("user-agent", "curl/8.0"))
+            .to_request();
+        assert_eq!("billing", read_body(call_service(&app, req).await).await);
+        let req = TestRequest::default()
+            .insert_header(("user-agent", "curl/8.0"))
+            .to_request();
+        assert_eq!("curl/8.0", read_body(call_service(&app, req).await).await);
+        assert_eq!(None, current_client());
+    }
+}
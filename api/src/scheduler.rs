@@ -0,0 +1,92 @@
+use crate::read_only::ReadOnly;
+use actix_web::rt::time::interval;
+use domain::events::TodoEvent;
+use domain::services::recurrence_service::RecurrenceService;
+use domain::services::reminder_service::ReminderService;
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::future;
+use futures::stream::{self, StreamExt};
+use log::*;
+use std::time::Duration;
+
+/// How often due occurrences of recurring todos are checked for
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often due reminders are checked for; at most this late
+pub const REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+enum Wake {
+    Event(TodoEvent),
+    Tick,
+    Stop,
+}
+
+/// Schedules the next occurrence of recurring todos as they are done, and creates those that are
+/// due every `every`, starting straight away. Runs until `events` ends.
+///
+/// No todos are created while `read_only` is on; those that fall due meanwhile are created at the
+/// first check after it's turned off. Events are still handled, as they're of changes already made.
+pub async fn run<A: RecurrenceService>(
+    recurrence_service: A,
+    events: UnboundedReceiver<TodoEvent>,
+    every: Duration,
+    read_only: ReadOnly,
+) {
+    let ticks = stream::unfold(interval(every), |mut ticks| async move {
+        ticks.tick().await;
+        Some((Wake::Tick, ticks))
+    });
+    // The ticks never end, so the end of the events is marked to know when to stop
+    let events = events
+        .map(Wake::Event)
+        .chain(stream::once(future::ready(Wake::Stop)));
+    let mut wakes = stream::select(events, ticks.boxed_local());
+    while let Some(wake) = wakes.next().await {
+        match wake {
+            Wake::Event(event) => recurrence_service.handle(&event, today()).await,
+            Wake::Tick if read_only.is_on() => {
+                debug!("Not creating recurring todos while in read-only mode")
+            }
+            Wake::Tick => {
+                for materialized in recurrence_service.materialize(today()).await {
+                    match materialized {
+                        Ok(todo) => info!("Created recurring todo [{:?}]", todo.id),
+                        Err(occurrence) => warn!(
+                            "Dropped the occurrence after [{:?}], due [{}], as it's no longer valid",
+                            occurrence.after, occurrence.due
+                        ),
+                    }
+                }
+            }
+            Wake::Stop => break,
+        }
+    }
+}
+
+/// Sends the reminders that are due every `every`, starting straight away. Runs forever.
+///
+/// None are sent while `read_only` is on, as sending them marks them as sent; they're sent, overdue,
+/// once it's turned off.
+pub async fn remind<A: ReminderService>(reminder_service: A, every: Duration, read_only: ReadOnly) {
+    let mut ticks = interval(every);
+    loop {
+        ticks.tick().await;
+        if read_only.is_on() {
+            debug!("Not sending reminders while in read-only mode");
+            continue;
+        }
+        for (reminder, outcome) in reminder_service.dispatch(chrono::Utc::now()).await {
+            match outcome {
+                Ok(()) => info!("Sent the reminder for todo [{:?}]", reminder.todo_id),
+                // Not retried, so that a broken notifier doesn't pile reminders up
+                Err(e) => warn!(
+                    "Could not send the reminder for todo [{:?}]: {}",
+                    reminder.todo_id, e
+                ),
+            }
+        }
+    }
+}
+
+fn today() -> chrono::NaiveDate {
+    chrono::Local::now().date_naive()
+}
@@ -0,0 +1,131 @@
+//! Todos created at startup from a file, for demos, tests and staging environments
+
+use crate::import_export;
+use crate::models::todo::TransferFormat;
+use domain::services::todo_service::TodoService;
+use domain::todo::{TodoData, TodoRepo};
+use log::*;
+use std::io::{Error, ErrorKind};
+
+/// Reads the todos in a seed file: a JSON array of todo data, as `POST /tasks/import` takes or
+/// `GET /tasks/export` gives, or CSV when the path ends in `.csv`. Fails on the first row that
+/// can't be read, naming it.
+pub fn read(path: &str) -> Result<Vec<TodoData>, Error> {
+    let body = std::fs::read(path).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("Could not read seed file [{}]: {}", path, e),
+        )
+    })?;
+    let format = if path.ends_with(".csv") {
+        TransferFormat::Csv
+    } else {
+        TransferFormat::Json
+    };
+    let rows = import_export::parse_import(&body, format).map_err(|e| invalid(path, e))?;
+    rows.into_iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            row.map(|todo_data| TodoData::from(&todo_data))
+                .map_err(|e| invalid(path, format!("row [{}]: {}", idx + 1, e)))
+        })
+        .collect()
+}
+
+/// Creates the seed file's todos through the service, as if through the API, unless storage
+/// has todos already, e.g. from a previous start. Each is checked before any is created, so a
+/// bad seed file leaves storage as it was; rows are checked on their own though, so e.g. rows
+/// with the same task can still fail part way with `TASK_UNIQUE` on.
+pub async fn seed<R, S>(path: &str, todo_repo: &R, todo_service: &S) -> Result<(), Error>
+where
+    R: TodoRepo + Sync,
+    S: TodoService + Sync,
+{
+    let todos = read(path)?;
+    let existing = todo_repo
+        .list_page(0, 1)
+        .await
+        .map_err(|e| Error::other(format!("Could not check for todos before seeding: {}", e)))?;
+    if !existing.is_empty() {
+        info!("Not seeding from [{}], as there are todos already", path);
+        return Ok(());
+    }
+    for (idx, todo_data) in todos.iter().enumerate() {
+        todo_service
+            .plan_create(todo_data)
+            .await
+            .map_err(|e| invalid(path, format!("row [{}]: {}", idx + 1, e)))?;
+    }
+    for (idx, todo_data) in todos.iter().enumerate() {
+        todo_service
+            .create(todo_data)
+            .await
+            .map_err(|e| invalid(path, format!("row [{}]: {}", idx + 1, e)))?;
+    }
+    info!("Seeded [{}] todos from [{}]", todos.len(), path);
+    Ok(())
+}
+
+fn invalid(path: &str, reason: String) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Invalid seed file [{}]: {}", path, reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::services::todo_service;
+    use futures::executor::block_on;
+    use infra::in_mem::todo_repo;
+
+    fn seed_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_read() {
+        let path = seed_file(
+            "todddo_test_seed.json",
+            r#"[{"task": "Make the bed"}, {"task": "Do the dishes", "done": true}]"#,
+        );
+        let todos = read(&path).unwrap();
+        assert_eq!(2, todos.len());
+        assert!(todos[1].done);
+        let path = seed_file("todddo_test_seed.csv", "task\nMake the bed\n");
+        assert_eq!("Make the bed", read(&path).unwrap()[0].task);
+        let path = seed_file(
+            "todddo_test_seed_typo.json",
+            r#"[{"task": "ok"}, {"tsk": "typo"}]"#,
+        );
+        let message = read(&path).unwrap_err().to_string();
+        assert!(message.contains("row [2]"), "{}", message);
+        assert!(read("/nonexistent/seed.json").is_err());
+    }
+
+    #[test]
+    fn test_seed() {
+        let repo = todo_repo::new();
+        let service = todo_service::new(repo.clone());
+        let invalid = seed_file(
+            "todddo_test_seed_invalid.json",
+            r#"[{"task": "Make the bed"}, {"task": ""}]"#,
+        );
+        let message = block_on(seed(&invalid, &repo, &service))
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("row [2]"), "{}", message);
+        assert!(block_on(repo.list()).unwrap().is_empty());
+        let valid = seed_file(
+            "todddo_test_seed_valid.json",
+            r#"[{"task": "Make the bed"}]"#,
+        );
+        block_on(seed(&valid, &repo, &service)).unwrap();
+        // Not again, now that there are todos
+        block_on(seed(&valid, &repo, &service)).unwrap();
+        assert_eq!(1, block_on(repo.list()).unwrap().len());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::config::{Config, LogFormat, StorageBackend};
+use crate::{assets, messaging, metrics, notifications, plugins, seed, tls};
+use serde_derive::Serialize;
+use std::fmt;
+use std::net::TcpListener;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 2024-01-01T00:00:00Z; a clock behind this has most likely not been synced
+static EARLIEST_SANE_TIME: Duration = Duration::from_secs(1_704_067_200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Results of the checks run before serving.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Failed)
+    }
+
+    /// Prints to stdout, as a single JSON object when logging as JSON
+    pub fn print(&self, log_format: LogFormat) {
+        match log_format {
+            LogFormat::Json => println!("{}", serde_json::json!({ "self_check": self })),
+            LogFormat::Text => print!("{}", self),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Self-check:")?;
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Skipped => "skipped",
+                CheckStatus::Failed => "FAILED",
+            };
+            writeln!(f, "  {:<12} {:<8} {}", check.name, status, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs every check. Invalid config never gets this far, so the config check only summarises it.
+pub async fn run(config: &Config) -> Report {
+    let checks = vec![
+        check(
+            "config",
+            Ok(format!(
+                "binding to [{}], storing in [{:?}]",
+                config.bind_addr, config.storage
+            )),
+        ),
+        check("bind", check_bind(&config.bind_addr)),
+        storage_check(config.storage),
+        migrations_check(config.storage),
+        check("clock", check_clock(SystemTime::now())),
+        skipped("paths", "nothing is written to disk"),
+        check("assets", assets::verify()),
+        tls_check(config),
+        messaging_check(config).await,
+        metrics_check(config),
+        notifier_check(config),
+        plugins_check(config),
+        seed_check(config),
+    ];
+    Report { checks }
+}
+
+fn check(name: &'static str, result: Result<String, String>) -> Check {
+    match result {
+        Ok(detail) => Check {
+            name,
+            status: CheckStatus::Ok,
+            detail,
+        },
+        Err(detail) => Check {
+            name,
+            status: CheckStatus::Failed,
+            detail,
+        },
+    }
+}
+
+fn skipped(name: &'static str, detail: &str) -> Check {
+    Check {
+        name,
+        status: CheckStatus::Skipped,
+        detail: detail.to_string(),
+    }
+}
+
+// The listener is dropped straight away, freeing the address for the server
+fn check_bind(bind_addr: &str) -> Result<String, String> {
+    TcpListener::bind(bind_addr)
+        .map(|_| format!("[{}] is available", bind_addr))
+        .map_err(|e| format!("cannot bind [{}]: {}", bind_addr, e))
+}
+
+fn storage_check(storage: StorageBackend) -> Check {
+    match storage {
+        StorageBackend::InMem => check(
+            "storage",
+            Ok("in-memory, nothing to connect to".to_string()),
+        ),
+    }
+}
+
+fn migrations_check(storage: StorageBackend) -> Check {
+    match storage {
+        StorageBackend::InMem => skipped("migrations", "in-memory storage has no schema"),
+    }
+}
+
+fn check_clock(now: SystemTime) -> Result<String, String> {
+    match now.duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) if since_epoch >= EARLIEST_SANE_TIME => {
+            Ok(format!("{}s since the epoch", since_epoch.as_secs()))
+        }
+        _ => Err("system clock is in the past; is it synced?".to_string()),
+    }
+}
+
+fn tls_check(config: &Config) -> Check {
+    match &config.tls {
+        Some(tls_settings) => check(
+            "tls",
+            tls::load_rustls_config(tls_settings)
+                .map(|_| format!("loaded [{}]", tls_settings.cert_path))
+                .map_err(|e| e.to_string()),
+        ),
+        None => skipped("tls", "not configured"),
+    }
+}
+
+async fn messaging_check(config: &Config) -> Check {
+    match &config.messaging {
+        Some(messaging_settings) => check(
+            "messaging",
+            messaging::publisher(messaging_settings)
+                .await
+                .map(|_| format!("connected to [{}]", messaging_settings.url))
+                .map_err(|e| e.to_string()),
+        ),
+        None => skipped("messaging", "not configured"),
+    }
+}
+
+// StatsD is sent to over UDP and OTLP is pushed to later, so only the address is checked
+fn metrics_check(config: &Config) -> Check {
+    match &config.metrics {
+        Some(metrics_settings) => check(
+            "metrics",
+            metrics::recorder(metrics_settings)
+                .map(|_| format!("recording via [{:?}]", metrics_settings.backend()))
+                .map_err(|e| e.to_string()),
+        ),
+        None => skipped("metrics", "not configured"),
+    }
+}
+
+// Only sets the notifier up; nothing is sent until a reminder is due
+fn notifier_check(config: &Config) -> Check {
+    check(
+        "notifier",
+        notifications::notifier(&config.notifier)
+            .map(|_| format!("delivering reminders via [{:?}]", config.notifier.backend()))
+            .map_err(|e| e.to_string()),
+    )
+}
+
+fn plugins_check(config: &Config) -> Check {
+    match &config.plugins_dir {
+        Some(plugins_dir) => check(
+            "plugins",
+            plugins::load(plugins_dir)
+                .map(|p| format!("loaded [{}]", p.descriptions.join(", ")))
+                .map_err(|e| e.to_string()),
+        ),
+        None => skipped("plugins", "not configured"),
+    }
+}
+
+// Only reads the file; whether its todos are valid is checked against storage at startup
+fn seed_check(config: &Config) -> Check {
+    match &config.seed_file {
+        Some(seed_file) => check(
+            "seed",
+            seed::read(seed_file)
+                .map(|todos| format!("[{}] todos in [{}]", todos.len(), seed_file))
+                .map_err(|e| e.to_string()),
+        ),
+        None => skipped("seed", "not configured"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_clock() {
+        assert!(check_clock(SystemTime::now()).is_ok());
+        assert!(check_clock(UNIX_EPOCH + Duration::from_secs(60)).is_err());
+    }
+
+    #[test]
+    fn test_check_bind() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken_addr = taken.local_addr().unwrap().to_string();
+        assert!(check_bind(&taken_addr).is_err());
+        assert!(check_bind("127.0.0.1:0").is_ok());
+    }
+
+    #[test]
+    fn test_report_passed() {
+        let report = Report {
+            checks: vec![
+                check("clock", Ok("fine".to_string())),
+                skipped("tls", "not configured"),
+            ],
+        };
+        assert!(report.passed());
+        let report = Report {
+            checks: vec![check("bind", Err("taken".to_string()))],
+        };
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,422 @@
+//! The opt-in `Server-Timing` header: how long a request spent in the handler, controller, service
+//! and repo, as recorded by the `Timed` decorators around the todo stack
+
+use crate::controllers::todo_controller::*;
+use crate::models::todo as api_models;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::Error;
+use async_trait::async_trait;
+use domain::events::TodoEvent;
+use domain::services::todo_service::{
+    TodoService, TodoServiceDataErr, TodoServiceLookupErr, TodoServiceUpdateErr,
+};
+use domain::todo::*;
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::future::{ok, BoxFuture, LocalBoxFuture, Ready};
+use futures::stream::BoxStream;
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+pub static SERVER_TIMING_HEADER: &str = "server-timing";
+
+tokio::task_local! {
+    static CURRENT: RefCell<Timings>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Controller,
+    Service,
+    Repo,
+}
+
+// Time spent in each layer, including the layers below it
+#[derive(Default, Debug)]
+struct Timings {
+    controller: Duration,
+    service: Duration,
+    repo: Duration,
+}
+
+impl Timings {
+    fn add(&mut self, layer: Layer, elapsed: Duration) {
+        match layer {
+            Layer::Controller => self.controller += elapsed,
+            Layer::Service => self.service += elapsed,
+            Layer::Repo => self.repo += elapsed,
+        }
+    }
+
+    // Each layer's own time, so that they add up to the total
+    fn header_value(&self, total: Duration) -> String {
+        let entries = [
+            ("handler", total.saturating_sub(self.controller)),
+            ("controller", self.controller.saturating_sub(self.service)),
+            ("service", self.service.saturating_sub(self.repo)),
+            ("repo", self.repo),
+            ("total", total),
+        ];
+        entries
+            .iter()
+            .map(|(name, d)| format!("{};dur={:.3}", name, d.as_secs_f64() * 1000.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Runs `f`, adding how long it took to `layer`'s time for the request being handled, if it is
+/// being timed
+pub async fn time<F: Future>(layer: Layer, f: F) -> F::Output {
+    if CURRENT.try_with(|_| ()).is_err() {
+        return f.await;
+    }
+    let start = Instant::now();
+    let output = f.await;
+    let elapsed = start.elapsed();
+    let _ = CURRENT.try_with(|timings| timings.borrow_mut().add(layer, elapsed));
+    output
+}
+
+/// Decorates a todo controller, service or repo so that calls to it are timed as its layer
+#[derive(Clone)]
+pub struct Timed<A> {
+    inner: A,
+}
+
+pub fn timed<A>(inner: A) -> Timed<A> {
+    Timed { inner }
+}
+
+#[async_trait]
+impl<A: TodoRepo + Send + Sync> TodoRepo for Timed<A> {
+    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
+        time(Layer::Repo, self.inner.create(todo_data)).await
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+        time(Layer::Repo, self.inner.get(todo_id)).await
+    }
+
+    async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+        time(Layer::Repo, self.inner.list()).await
+    }
+
+    async fn list_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, TodoRepoErr> {
+        time(Layer::Repo, self.inner.list_page(offset, limit)).await
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoRepoErr> {
+        time(Layer::Repo, self.inner.delete(todo_id)).await
+    }
+
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Todo, TodoRepoUpdateErr> {
+        time(Layer::Repo, self.inner.update(todo, expected_version)).await
+    }
+
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+        time(Layer::Repo, self.inner.complete(selection)).await
+    }
+
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+        time(Layer::Repo, self.inner.reorder(ids)).await
+    }
+
+    async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+        time(Layer::Repo, self.inner.find_by_task(task)).await
+    }
+
+    async fn purge_done(&self) -> Result<usize, TodoRepoErr> {
+        time(Layer::Repo, self.inner.purge_done()).await
+    }
+
+    async fn delete_all(&self) -> Result<usize, TodoRepoErr> {
+        time(Layer::Repo, self.inner.delete_all()).await
+    }
+
+    async fn todo_stats(&self) -> Result<TodoRepoStats, TodoRepoErr> {
+        time(Layer::Repo, self.inner.todo_stats()).await
+    }
+}
+
+// Timed as a whole, as the repo's share of the request
+#[async_trait]
+impl<A: TodoRepo + TodoUnitOfWork + Send + Sync> TodoUnitOfWork for Timed<A> {
+    type Tx = A::Tx;
+
+    async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+    where
+        T: Send,
+        E: Send,
+        F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+    {
+        time(Layer::Repo, self.inner.transact(work)).await
+    }
+}
+
+#[async_trait]
+impl<A: TodoService + Send + Sync> TodoService for Timed<A> {
+    async fn create(&self, todo_data: &TodoData) -> Result<SavedTodo, TodoServiceDataErr> {
+        time(Layer::Service, self.inner.create(todo_data)).await
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoServiceLookupErr> {
+        time(Layer::Service, self.inner.get(todo_id)).await
+    }
+
+    async fn list(&self) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+        time(Layer::Service, self.inner.list()).await
+    }
+
+    async fn list_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+        time(Layer::Service, self.inner.list_page(offset, limit)).await
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoServiceLookupErr> {
+        time(Layer::Service, self.inner.delete(todo_id)).await
+    }
+
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<SavedTodo, TodoServiceUpdateErr> {
+        time(Layer::Service, self.inner.update(todo, expected_version)).await
+    }
+
+    async fn plan_create(&self, todo_data: &TodoData) -> Result<TodoPlan, TodoServiceDataErr> {
+        time(Layer::Service, self.inner.plan_create(todo_data)).await
+    }
+
+    async fn plan_update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<TodoPlan, TodoServiceUpdateErr> {
+        time(
+            Layer::Service,
+            self.inner.plan_update(todo, expected_version),
+        )
+        .await
+    }
+
+    async fn plan_delete(&self, todo_id: &TodoId) -> Result<TodoPlan, TodoServiceLookupErr> {
+        time(Layer::Service, self.inner.plan_delete(todo_id)).await
+    }
+
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoServiceDataErr> {
+        time(Layer::Service, self.inner.complete(selection)).await
+    }
+
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+        time(Layer::Service, self.inner.reorder(ids)).await
+    }
+
+    async fn find_duplicate(
+        &self,
+        todo_data: &TodoData,
+    ) -> Result<Option<Todo>, TodoServiceLookupErr> {
+        time(Layer::Service, self.inner.find_duplicate(todo_data)).await
+    }
+
+    fn subscribe(&self) -> UnboundedReceiver<TodoEvent> {
+        self.inner.subscribe()
+    }
+}
+
+#[async_trait]
+impl<A: TodoController + Send + Sync> TodoController for Timed<A> {
+    async fn create(
+        &self,
+        todo_data: &api_models::TodoData,
+    ) -> Result<api_models::SavedTodo, TodoControllerDataErr> {
+        time(Layer::Controller, self.inner.create(todo_data)).await
+    }
+
+    async fn get(
+        &self,
+        todo_id: &api_models::TodoId,
+    ) -> Result<api_models::Todo, TodoControllerLookupErr> {
+        time(Layer::Controller, self.inner.get(todo_id)).await
+    }
+
+    async fn list(&self) -> Result<Vec<api_models::Todo>, TodoControllerLookupErr> {
+        time(Layer::Controller, self.inner.list()).await
+    }
+
+    async fn list_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<api_models::Todo>, TodoControllerLookupErr> {
+        time(Layer::Controller, self.inner.list_page(offset, limit)).await
+    }
+
+    async fn update(
+        &self,
+        todo: &api_models::Todo,
+        expected_version: Option<u64>,
+    ) -> Result<api_models::SavedTodo, TodoControllerUpdateErr> {
+        time(Layer::Controller, self.inner.update(todo, expected_version)).await
+    }
+
+    async fn delete(&self, todo_id: &api_models::TodoId) -> Result<(), TodoControllerLookupErr> {
+        time(Layer::Controller, self.inner.delete(todo_id)).await
+    }
+
+    async fn plan_create(
+        &self,
+        todo_data: &api_models::TodoData,
+    ) -> Result<api_models::TodoPlan, TodoControllerDataErr> {
+        time(Layer::Controller, self.inner.plan_create(todo_data)).await
+    }
+
+    async fn plan_update(
+        &self,
+        todo: &api_models::Todo,
+        expected_version: Option<u64>,
+    ) -> Result<api_models::TodoPlan, TodoControllerUpdateErr> {
+        time(
+            Layer::Controller,
+            self.inner.plan_update(todo, expected_version),
+        )
+        .await
+    }
+
+    async fn plan_delete(
+        &self,
+        todo_id: &api_models::TodoId,
+    ) -> Result<api_models::TodoPlan, TodoControllerLookupErr> {
+        time(Layer::Controller, self.inner.plan_delete(todo_id)).await
+    }
+
+    async fn complete(
+        &self,
+        selection: &api_models::CompleteTodos,
+    ) -> Result<api_models::CompletedTodos, TodoControllerCompleteErr> {
+        time(Layer::Controller, self.inner.complete(selection)).await
+    }
+
+    async fn reorder(
+        &self,
+        order: &api_models::ReorderTodos,
+    ) -> Result<Vec<api_models::Todo>, TodoControllerReorderErr> {
+        time(Layer::Controller, self.inner.reorder(order)).await
+    }
+
+    async fn find_duplicate(
+        &self,
+        todo_data: &api_models::TodoData,
+    ) -> Result<Option<api_models::Todo>, TodoControllerLookupErr> {
+        time(Layer::Controller, self.inner.find_duplicate(todo_data)).await
+    }
+
+    fn subscribe(&self) -> BoxStream<'static, api_models::TodoEvent> {
+        self.inner.subscribe()
+    }
+}
+
+/// Middleware that times every request, adding a `Server-Timing` header with each layer's share
+pub struct ServerTiming;
+
+impl<S, B> Transform<S, ServiceRequest> for ServerTiming
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ServerTimingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ServerTimingMiddleware { service })
+    }
+}
+
+pub struct ServerTimingMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for ServerTimingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let f_res = self.service.call(req);
+        Box::pin(CURRENT.scope(RefCell::new(Timings::default()), async move {
+            let mut res = f_res.await?;
+            let value = CURRENT.with(|timings| timings.borrow().header_value(start.elapsed()));
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(SERVER_TIMING_HEADER), value);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App};
+
+    #[test]
+    fn test_header_value() {
+        let timings = Timings {
+            controller: Duration::from_millis(8),
+            service: Duration::from_millis(5),
+            repo: Duration::from_millis(2),
+        };
+        assert_eq!(
+            "handler;dur=2.000, controller;dur=3.000, service;dur=3.000, repo;dur=2.000, total;dur=10.000",
+            timings.header_value(Duration::from_millis(10))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_server_timing() {
+        let app = init_service(App::new().wrap(ServerTiming).route(
+            "/",
+            web::get().to(|| async {
+                time(
+                    Layer::Repo,
+                    actix_web::rt::time::sleep(Duration::from_millis(5)),
+                )
+                .await;
+                "done"
+            }),
+        ))
+        .await;
+        let resp = call_service(&app, TestRequest::default().to_request()).await;
+        let value = resp.headers().get(SERVER_TIMING_HEADER).unwrap();
+        let repo = value
+            .to_str()
+            .unwrap()
+            .split(", ")
+            .find_map(|entry| entry.strip_prefix("repo;dur="))
+            .unwrap();
+        assert!(repo.parse::<f64>().unwrap() >= 5.0);
+        // Untimed outside of requests
+        assert_eq!("done", time(Layer::Repo, async { "done" }).await);
+    }
+}
@@ -0,0 +1,60 @@
+use paperclip::v2::models::{DefaultApiRaw, Info, Tag};
+
+/// Top-level metadata for the generated spec served at `/api/spec`
+pub fn api_spec() -> DefaultApiRaw {
+    DefaultApiRaw {
+        info: Info {
+            title: "todddo".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            description: Some("A simple todo API".to_string()),
+            ..Default::default()
+        },
+        tags: vec![
+        Tag {
+            name: "Todos".to_string(),
+            description: Some("Creating, reading, updating and deleting todos".to_string()),
+            external_docs: None,
+        },
+        Tag {
+            name: "TodosV2".to_string(),
+            description: Some(
+                "Todos with string ids, a status and timestamps; being soft launched alongside the \
+                 v1 ones"
+                    .to_string(),
+            ),
+            external_docs: None,
+        },
+        Tag {
+            name: "Lists".to_string(),
+            description: Some("Named collections of todos".to_string()),
+            external_docs: None,
+        },
+        Tag {
+            name: "Rules".to_string(),
+            description: Some("Automation rules evaluated on todo events".to_string()),
+            external_docs: None,
+        },
+        Tag {
+            name: "Reminders".to_string(),
+            description: Some("Notifications sent when todos are due".to_string()),
+            external_docs: None,
+        },
+        Tag {
+            name: "Sharing".to_string(),
+            description: Some("Read-only links to todos".to_string()),
+            external_docs: None,
+        },
+        Tag {
+            name: "Health".to_string(),
+            description: Some("Liveness and readiness checks for orchestrators".to_string()),
+            external_docs: None,
+        },
+        Tag {
+            name: "Admin".to_string(),
+            description: Some("Operational endpoints".to_string()),
+            external_docs: None,
+        },
+    ],
+        ..Default::default()
+    }
+}
@@ -0,0 +1,44 @@
+#[cfg(feature = "test-support")]
+pub mod mocks;
+
+use crate::config;
+use actix_web::dev::ServerHandle;
+use actix_web::rt;
+
+/// The full app, with fresh in-memory storage, listening on a random local port
+pub struct TestServer {
+    /// e.g. "http://127.0.0.1:41234", without a trailing slash
+    pub base_url: String,
+    handle: ServerHandle,
+}
+
+/// Boots the app as `run_server` would, for black-box tests of routing, serialisation and
+/// error mapping. Must be called from within an actix runtime, e.g. in an `#[actix_web::test]`.
+pub async fn spawn_test_server() -> TestServer {
+    let args = vec![
+        "todddo",
+        "--bind",
+        "127.0.0.1:0",
+        "--workers",
+        "1",
+        "--shutdown-timeout",
+        "1",
+    ];
+    // Fixed args that are always valid, and no env vars or config file to get in the way
+    let config = config::load_from(args, |_| None).expect("test server config is valid");
+    let built = crate::build_server(config)
+        .await
+        .expect("test server could bind to a random port");
+    let base_url = format!("http://{}", built.addrs[0]);
+    let handle = built.server.handle();
+    rt::spawn(built.scheduler);
+    rt::spawn(built.server);
+    TestServer { base_url, handle }
+}
+
+impl TestServer {
+    /// Stops accepting connections and waits for in-flight requests to finish
+    pub async fn stop(self) {
+        self.handle.stop(true).await
+    }
+}
@@ -0,0 +1,41 @@
+use crate::controllers::todo_controller::{self, TodoControllerImpl};
+use domain::mocks::todo_repo::MockTodoRepo;
+use domain::mocks::todo_service::{self, MockTodoService};
+
+/// The real controller and service over a [[MockTodoRepo]], for tests of handlers or of a custom
+/// `TodoController` that wraps this one: answers are converted as the app's would be, and
+/// failures queued on the repo come out as the controller's own errors
+pub type MockTodoController = TodoControllerImpl<MockTodoService>;
+
+pub fn todo_controller(repo: MockTodoRepo) -> MockTodoController {
+    todo_controller::new(todo_service::new(repo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controllers::todo_controller::{TodoController, TodoControllerLookupErr};
+    use crate::models::todo::{TodoData, TodoId};
+    use domain::mocks::todo_repo;
+    use domain::todo::TodoRepoErr;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_todo_controller() {
+        let repo = todo_repo::new();
+        let controller = todo_controller(repo.clone());
+        let todo_data = TodoData {
+            task: "Make the bed".to_string(),
+            done: false,
+            list_id: None,
+        };
+        let created = block_on(controller.create(&todo_data)).unwrap();
+        assert_eq!(TodoId(1), created.id);
+        repo.fail_next(TodoRepoErr::Timeout);
+        match block_on(controller.get(&created.id)) {
+            Err(TodoControllerLookupErr::Timeout) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert_eq!((1, 1), (repo.calls("create"), repo.calls("get")));
+    }
+}
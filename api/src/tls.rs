@@ -0,0 +1,133 @@
+use actix_web::http::header;
+use actix_web::http::uri::Authority;
+use actix_web::{HttpRequest, HttpResponse};
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls_pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind};
+
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    pub cert_path: String,
+    pub key_path: String,
+    // When set, a plaintext listener is bound here that redirects everything to HTTPS
+    pub redirect_from: Option<String>,
+}
+
+pub fn load_rustls_config(settings: &TlsSettings) -> Result<ServerConfig, Error> {
+    let cert_chain = certs(&mut BufReader::new(File::open(&settings.cert_path)?))
+        .map_err(|_| invalid_data(format!("Could not read certs in [{}]", settings.cert_path)))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    let key = read_private_key(&settings.key_path)?;
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .map_err(|e| invalid_data(format!("Invalid cert/key pair: {}", e)))
+}
+
+// Tries PKCS8 first, then falls back to RSA (PKCS1) keys
+fn read_private_key(key_path: &str) -> Result<PrivateKey, Error> {
+    let pkcs8_keys = pkcs8_private_keys(&mut BufReader::new(File::open(key_path)?))
+        .map_err(|_| invalid_data(format!("Could not read keys in [{}]", key_path)))?;
+    let mut keys = if pkcs8_keys.is_empty() {
+        rsa_private_keys(&mut BufReader::new(File::open(key_path)?))
+            .map_err(|_| invalid_data(format!("Could not read keys in [{}]", key_path)))?
+    } else {
+        pkcs8_keys
+    };
+    if keys.is_empty() {
+        Err(invalid_data(format!(
+            "No private keys found in [{}]",
+            key_path
+        )))
+    } else {
+        Ok(PrivateKey(keys.remove(0)))
+    }
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Extracts the port from a bind address such as `0.0.0.0:8443`
+pub fn port_of(bind_addr: &str) -> Option<u16> {
+    bind_addr.rsplit(':').next()?.parse().ok()
+}
+
+pub fn redirect_to_https(req: &HttpRequest, https_port: u16) -> HttpResponse {
+    // Parsed rather than split on ':', so that IPv6 hosts, e.g. `[::1]:8080`, are kept whole
+    let host = req
+        .connection_info()
+        .host()
+        .parse::<Authority>()
+        .map(|authority| authority.host().to_string())
+        .unwrap_or_else(|_| "localhost".to_string());
+    let authority = if https_port == 443 {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, https_port)
+    };
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    HttpResponse::PermanentRedirect()
+        .insert_header((
+            header::LOCATION,
+            format!("https://{}{}", authority, path_and_query),
+        ))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_port_of() {
+        assert_eq!(Some(8443), port_of("127.0.0.1:8443"));
+        assert_eq!(None, port_of("localhost"));
+    }
+
+    #[test]
+    fn test_redirect_to_https() {
+        let req = TestRequest::with_uri("/tasks?x=1")
+            .insert_header((header::HOST, "example.com:8080"))
+            .to_http_request();
+        let resp = redirect_to_https(&req, 8443);
+        assert_eq!(308, resp.status().as_u16());
+        assert_eq!(
+            "https://example.com:8443/tasks?x=1",
+            resp.headers().get(header::LOCATION).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_redirect_to_https_default_port() {
+        let req = TestRequest::with_uri("/")
+            .insert_header((header::HOST, "example.com"))
+            .to_http_request();
+        let resp = redirect_to_https(&req, 443);
+        assert_eq!(
+            "https://example.com/",
+            resp.headers().get(header::LOCATION).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_redirect_to_https_ipv6() {
+        let req = TestRequest::with_uri("/")
+            .insert_header((header::HOST, "[::1]:8080"))
+            .to_http_request();
+        let resp = redirect_to_https(&req, 8443);
+        assert_eq!(
+            "https://[::1]:8443/",
+            resp.headers().get(header::LOCATION).unwrap()
+        );
+    }
+}
@@ -0,0 +1,52 @@
+// On its own, as the id secret applies to the whole test process
+use actix_web::rt;
+use client::models::todo::{Todo, TodoData, TodoId};
+use client::ClientError;
+
+#[actix_web::test]
+async fn test_opaque_ids() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |key| {
+        Some("s3cret".to_string()).filter(|_| key == "ID_SECRET")
+    })
+    .unwrap();
+    let built = api::build_server(config).await.unwrap();
+    let base_url = format!("http://{}", built.addrs[0]);
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+
+    // The client shares the process-wide codec, so it reads and writes the same ids
+    let client = client::new(&base_url);
+    let todo_data = TodoData {
+        task: "Make the bed".to_string(),
+        done: false,
+        list_id: None,
+    };
+    let created: Todo = client.create_todo(&todo_data).await.unwrap().into();
+    assert_eq!(created, client.get_todo(created.id).await.unwrap());
+
+    let http = reqwest::Client::new();
+    let body = http
+        .get(format!("{}/tasks", base_url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(body.contains(&format!("\"id\":\"{}\"", created.id)));
+    assert_ne!(created.id.0.to_string(), created.id.to_string());
+    // The sequential number no longer works
+    let status = http
+        .get(format!("{}/tasks/{}", base_url, created.id.0))
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(404, status.as_u16());
+    match client.get_todo(TodoId(created.id.0 + 1)).await {
+        Err(ClientError::Api { status: 404, .. }) => {}
+        other => panic!("Unexpected {:?}", other),
+    }
+    handle.stop(true).await;
+}
@@ -0,0 +1,930 @@
+use api::test_support::spawn_test_server;
+use client::models::common::{ErrorCode, Message};
+use client::models::list::{ListData, ListId};
+use client::models::recurrence::{Day, Recurrence, Repeat};
+use client::models::reminder::ReminderData;
+use client::models::rule::{RuleAction, RuleCondition, RuleData, RuleTrigger};
+use client::models::share::ShareLinkData;
+use client::models::todo::{
+    CompleteTodos, EventKind, ImportReport, ReorderTodos, Todo, TodoData, TodoId,
+};
+use client::models::v2::{SavedTodoV2, TodoStatus, TodoV2};
+use client::ClientError;
+
+fn todo_data(task: &str) -> TodoData {
+    TodoData {
+        task: task.to_string(),
+        done: false,
+        list_id: None,
+    }
+}
+
+#[actix_web::test]
+async fn test_todo_lifecycle() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let created: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    assert_eq!("Make the bed", created.task);
+    let done = Todo {
+        done: true,
+        ..created.clone()
+    };
+    client.update_todo_if_unchanged(&done).await.unwrap();
+    // Stale, as the update bumped the version
+    match client.update_todo_if_unchanged(&done).await {
+        Err(ClientError::Api { status: 412, .. }) => {}
+        other => panic!("Unexpected {:?}", other),
+    }
+    let done = Todo {
+        version: created.version + 1,
+        ..done
+    };
+    assert_eq!(done, client.get_todo(created.id).await.unwrap());
+    assert_eq!(vec![done], client.list_todos().await.unwrap());
+    client.delete_todo(created.id).await.unwrap();
+    assert!(client.list_todos().await.unwrap().is_empty());
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_error_mapping() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    match client.get_todo(TodoId(42)).await {
+        Err(ClientError::Api {
+            status, request_id, ..
+        }) => {
+            assert_eq!(404, status);
+            assert!(request_id.is_some());
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    match client.create_todo(&todo_data("")).await {
+        Err(ClientError::Api { status, .. }) => assert_eq!(400, status),
+        other => panic!("Unexpected: {:?}", other),
+    }
+    let bad_template = RuleData {
+        name: "follow up".to_string(),
+        when: RuleCondition {
+            event: RuleTrigger::Created,
+            task_contains: None,
+        },
+        then: RuleAction {
+            create_todo: "Follow up on {{title}}".to_string(),
+        },
+    };
+    match client.create_rule(&bad_template).await {
+        Err(ClientError::Api {
+            status, message, ..
+        }) => {
+            assert_eq!(400, status);
+            assert!(message.contains("title"), "{}", message);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_error_codes() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    fn code<T>(result: Result<T, ClientError>) -> Option<ErrorCode> {
+        match result {
+            Err(ClientError::Api { code, .. }) => code,
+            Ok(_) => panic!("Unexpectedly succeeded"),
+            Err(other) => panic!("Unexpected: {:?}", other),
+        }
+    }
+
+    assert_eq!(
+        Some(ErrorCode::TodoNotFound),
+        code(client.get_todo(TodoId(42)).await)
+    );
+    assert_eq!(
+        Some(ErrorCode::TaskEmpty),
+        code(client.create_todo(&todo_data("")).await)
+    );
+    let todo: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    client.update_todo_if_unchanged(&todo).await.unwrap();
+    assert_eq!(
+        Some(ErrorCode::VersionConflict),
+        code(client.update_todo_if_unchanged(&todo).await)
+    );
+    let malformed = reqwest::Client::new()
+        .post(format!("{}/tasks", server.base_url))
+        .header("content-type", "application/json")
+        .body("{\"task\":")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, malformed.status().as_u16());
+    let message: Message = malformed.json().await.unwrap();
+    assert_eq!(Some(ErrorCode::MalformedRequest), message.code);
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_warnings() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let first = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap();
+    assert!(first.warnings.is_empty());
+    let second = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![format!("Todo [{:?}] has the same task", first.id)],
+        second.warnings
+    );
+    let done = Todo {
+        done: true,
+        ..first.into()
+    };
+    let updated = client.update_todo(&done).await.unwrap();
+    assert!(updated.warnings.is_empty());
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_dry_runs() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    client
+        .create_rule(&RuleData {
+            name: "follow up".to_string(),
+            when: RuleCondition {
+                event: RuleTrigger::Created,
+                task_contains: None,
+            },
+            then: RuleAction {
+                create_todo: "Follow up on {{task}}".to_string(),
+            },
+        })
+        .await
+        .unwrap();
+    let plan = client
+        .plan_create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap();
+    let planned_tasks: Vec<_> = plan
+        .events
+        .iter()
+        .map(|e| (e.event, e.todo.as_ref().unwrap().task.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (EventKind::Created, "Make the bed"),
+            (EventKind::Created, "Follow up on Make the bed")
+        ],
+        planned_tasks
+    );
+    assert!(client.list_todos().await.unwrap().is_empty());
+    let created: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    let todos = client.list_todos().await.unwrap();
+    let done = Todo {
+        done: true,
+        ..created.clone()
+    };
+    let plan = client.plan_update_todo(&done).await.unwrap();
+    assert_eq!(Some(created.version + 1), plan.todo.map(|t| t.version));
+    let plan = client.plan_delete_todo(created.id).await.unwrap();
+    assert_eq!(Some(created.id), plan.events[0].id);
+    match client.plan_delete_todo(TodoId(42)).await {
+        Err(ClientError::Api { status, .. }) => assert_eq!(404, status),
+        other => panic!("Unexpected: {:?}", other),
+    }
+    let import = reqwest::Client::new()
+        .post(format!("{}/tasks/import?dry_run=true", server.base_url))
+        .header("content-type", "text/csv")
+        .body("task\nDo the dishes\n\n")
+        .send()
+        .await
+        .unwrap()
+        .json::<ImportReport>()
+        .await
+        .unwrap();
+    assert_eq!(1, import.created.len());
+    assert_eq!(2, import.planned_events.len());
+    assert_eq!(
+        todos,
+        client.list_todos().await.unwrap(),
+        "Dry runs changed the todos"
+    );
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_lists() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let list = client
+        .create_list(&ListData {
+            name: "Chores".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(list, client.get_list(list.id).await.unwrap());
+    let in_list: Todo = client
+        .create_todo(&TodoData {
+            list_id: Some(list.id),
+            ..todo_data("Make the bed")
+        })
+        .await
+        .unwrap()
+        .into();
+    client
+        .create_todo(&todo_data("Do the dishes"))
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![in_list.clone()],
+        client.list_todos_in(list.id).await.unwrap()
+    );
+    match client
+        .create_todo(&TodoData {
+            list_id: Some(ListId(42)),
+            ..todo_data("Make tea")
+        })
+        .await
+    {
+        Err(ClientError::Api { status, code, .. }) => {
+            assert_eq!(400, status);
+            assert_eq!(Some(ErrorCode::ListNotFound), code);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    match client.delete_list(list.id).await {
+        Err(ClientError::Api { status, code, .. }) => {
+            assert_eq!(409, status);
+            assert_eq!(Some(ErrorCode::ListNotEmpty), code);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    client.delete_todo(in_list.id).await.unwrap();
+    client.delete_list(list.id).await.unwrap();
+    assert!(client.list_lists().await.unwrap().is_empty());
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_complete_todos() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let list = client
+        .create_list(&ListData {
+            name: "Chores".to_string(),
+        })
+        .await
+        .unwrap();
+    let open: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    let in_list: Todo = client
+        .create_todo(&TodoData {
+            list_id: Some(list.id),
+            ..todo_data("Do the dishes")
+        })
+        .await
+        .unwrap()
+        .into();
+    let completed = client
+        .complete_todos(&CompleteTodos {
+            ids: Some(vec![open.id, TodoId(42)]),
+            list_id: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(vec![open.id], ids(&completed.completed));
+    assert_eq!(vec![TodoId(42)], completed.not_found);
+    assert!(client.get_todo(open.id).await.unwrap().done);
+    let completed = client
+        .complete_todos(&CompleteTodos {
+            ids: None,
+            list_id: Some(list.id),
+        })
+        .await
+        .unwrap();
+    assert_eq!(vec![in_list.id], ids(&completed.completed));
+    for selection in [
+        CompleteTodos::default(),
+        CompleteTodos {
+            ids: None,
+            list_id: Some(ListId(42)),
+        },
+    ] {
+        match client.complete_todos(&selection).await {
+            Err(ClientError::Api { status: 400, .. }) => {}
+            other => panic!("Unexpected: {:?}", other),
+        }
+    }
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_dedupe() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let created: Todo = client
+        .create_todo(&todo_data("Buy milk"))
+        .await
+        .unwrap()
+        .into();
+    let http = reqwest::Client::new();
+    let duplicate = http
+        .post(format!("{}/tasks?dedupe=true", server.base_url))
+        .json(&todo_data("  buy  MILK"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(409, duplicate.status().as_u16());
+    assert_eq!(
+        format!("/tasks/{}", created.id),
+        duplicate.headers()["location"].to_str().unwrap()
+    );
+    let message: Message = duplicate.json().await.unwrap();
+    assert_eq!(Some(ErrorCode::TaskDuplicate), message.code);
+    assert_eq!(1, client.list_todos().await.unwrap().len());
+
+    // Done todos don't count
+    let mut done = created.clone();
+    done.done = true;
+    client.update_todo(&done).await.unwrap();
+    let created = http
+        .post(format!("{}/tasks?dedupe=true", server.base_url))
+        .json(&todo_data("Buy milk"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, created.status().as_u16());
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_reorder_todos() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let mut created = Vec::new();
+    for task in ["Make the bed", "Do the dishes", "Water the plants"] {
+        let todo: Todo = client.create_todo(&todo_data(task)).await.unwrap().into();
+        created.push(todo.id);
+    }
+    let reordered = client
+        .reorder_todos(&ReorderTodos {
+            ids: vec![created[2], created[0]],
+        })
+        .await
+        .unwrap();
+    let expected = vec![created[2], created[0], created[1]];
+    assert_eq!(expected, ids(&reordered));
+    assert_eq!(
+        expected,
+        ids(&client.list_todos_in_manual_order().await.unwrap())
+    );
+    assert_eq!(created, ids(&client.list_todos().await.unwrap()));
+    assert_eq!(
+        created[1..],
+        ids(&client.list_todos_page(1, 5).await.unwrap())[..]
+    );
+    // Reordering bumps the versions of the todos that moved, and updates don't move todos
+    let mut todo = client.get_todo(created[0]).await.unwrap();
+    assert_eq!((2, 2), (todo.version, todo.position));
+    todo.done = true;
+    client.update_todo(&todo).await.unwrap();
+    assert_eq!(
+        expected,
+        ids(&client.list_todos_in_manual_order().await.unwrap())
+    );
+
+    let code = |result: Result<Vec<Todo>, ClientError>| match result {
+        Err(ClientError::Api { code, .. }) => code,
+        other => panic!("Unexpected: {:?}", other),
+    };
+    assert_eq!(
+        Some(ErrorCode::OrderInvalid),
+        code(
+            client
+                .reorder_todos(&ReorderTodos {
+                    ids: vec![created[1], created[1]],
+                })
+                .await
+        )
+    );
+    assert_eq!(
+        Some(ErrorCode::TodoNotFound),
+        code(
+            client
+                .reorder_todos(&ReorderTodos {
+                    ids: vec![created[1], TodoId(42)],
+                })
+                .await
+        )
+    );
+    assert_eq!(
+        expected,
+        ids(&client.list_todos_in_manual_order().await.unwrap())
+    );
+    server.stop().await;
+}
+
+fn ids(todos: &[Todo]) -> Vec<TodoId> {
+    todos.iter().map(|t| t.id).collect()
+}
+
+#[actix_web::test]
+async fn test_v2_todos() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let http = reqwest::Client::new();
+    let resp = http
+        .post(format!("{}/v2/tasks", server.base_url))
+        .header("Content-Type", "application/json")
+        .body(r#"{"task": "Make the bed"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, resp.status().as_u16());
+    let saved: SavedTodoV2 = serde_json::from_str(&resp.text().await.unwrap()).unwrap();
+    assert_eq!(TodoStatus::Open, saved.todo.status);
+    assert!(saved.todo.created_at.is_some());
+    assert_eq!(saved.todo.created_at, saved.todo.updated_at);
+
+    // The same todo, in both representations
+    let id = saved.todo.id;
+    assert!(!client.get_todo(id).await.unwrap().done);
+    let resp = http
+        .put(format!("{}/v2/tasks/{}", server.base_url, id))
+        .header("Content-Type", "application/json")
+        .body(r#"{"task": "Make the bed", "status": "done"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, resp.status().as_u16());
+    assert!(client.get_todo(id).await.unwrap().done);
+    let body = http
+        .get(format!("{}/v2/tasks/{}", server.base_url, id))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(body.contains(&format!("\"id\":\"{}\"", id)));
+    let fetched: TodoV2 = serde_json::from_str(&body).unwrap();
+    assert_eq!(TodoStatus::Done, fetched.status);
+    assert_eq!(2, fetched.version);
+    assert!(fetched.updated_at >= fetched.created_at);
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_recurrence() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let todo: Todo = client
+        .create_todo(&todo_data("Water the plants"))
+        .await
+        .unwrap()
+        .into();
+    let recurrence = Recurrence {
+        repeat: Repeat::Weekdays,
+        on: vec![Day::Mon, Day::Thu],
+    };
+    client.set_recurrence(todo.id, &recurrence).await.unwrap();
+    assert_eq!(recurrence, client.get_recurrence(todo.id).await.unwrap());
+    match client
+        .set_recurrence(
+            todo.id,
+            &Recurrence {
+                repeat: Repeat::Weekdays,
+                on: Vec::new(),
+            },
+        )
+        .await
+    {
+        Err(ClientError::Api { status, code, .. }) => {
+            assert_eq!(400, status);
+            assert_eq!(Some(ErrorCode::RecurrenceInvalid), code);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    client.delete_recurrence(todo.id).await.unwrap();
+    match client.get_recurrence(todo.id).await {
+        Err(ClientError::Api { status, code, .. }) => {
+            assert_eq!(404, status);
+            assert_eq!(Some(ErrorCode::RecurrenceNotFound), code);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    match client.get_recurrence(TodoId(42)).await {
+        Err(ClientError::Api { status, code, .. }) => {
+            assert_eq!(404, status);
+            assert_eq!(Some(ErrorCode::TodoNotFound), code);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_reminders() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let todo: Todo = client
+        .create_todo(&todo_data("Water the plants"))
+        .await
+        .unwrap()
+        .into();
+    // Far enough off not to be sent during the test
+    let reminder = client
+        .set_reminder(
+            todo.id,
+            &ReminderData {
+                remind_at: "2999-01-31T13:00:00+01:00".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!("2999-01-31T12:00:00+00:00", reminder.remind_at);
+    assert_eq!(reminder, client.get_reminder(todo.id).await.unwrap());
+    assert_eq!(vec![reminder], client.list_reminders().await.unwrap());
+    match client
+        .set_reminder(
+            todo.id,
+            &ReminderData {
+                remind_at: "tomorrow".to_string(),
+            },
+        )
+        .await
+    {
+        Err(ClientError::Api { status, code, .. }) => {
+            assert_eq!(400, status);
+            assert_eq!(Some(ErrorCode::ReminderInvalid), code);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    client.delete_reminder(todo.id).await.unwrap();
+    match client.get_reminder(todo.id).await {
+        Err(ClientError::Api { status, code, .. }) => {
+            assert_eq!(404, status);
+            assert_eq!(Some(ErrorCode::ReminderNotFound), code);
+        }
+        other => panic!("Unexpected: {:?}", other),
+    }
+    // Deleting the todo also forgets its reminder
+    client
+        .set_reminder(
+            todo.id,
+            &ReminderData {
+                remind_at: "2999-01-31T12:00:00Z".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    client.delete_todo(todo.id).await.unwrap();
+    assert!(client.list_reminders().await.unwrap().is_empty());
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_shared_validation() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    fn server_message<T>(result: Result<T, ClientError>) -> String {
+        match result {
+            Err(ClientError::Api { message, .. }) => message,
+            Ok(_) => panic!("Accepted invalid data"),
+            Err(other) => panic!("Unexpected: {:?}", other),
+        }
+    }
+
+    let empty_task = todo_data("");
+    assert_eq!(
+        empty_task.validate().unwrap_err(),
+        server_message(client.create_todo(&empty_task).await)
+    );
+    let unnamed_rule = RuleData {
+        name: "".to_string(),
+        when: RuleCondition {
+            event: RuleTrigger::Created,
+            task_contains: None,
+        },
+        then: RuleAction {
+            create_todo: "Follow up".to_string(),
+        },
+    };
+    assert_eq!(
+        unnamed_rule.validate().unwrap_err(),
+        server_message(client.create_rule(&unnamed_rule).await)
+    );
+    let todo: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    assert!(todo_data("Make the bed").validate().is_ok());
+    let expired = ShareLinkData {
+        expires_in_secs: Some(0),
+    };
+    assert_eq!(
+        expired.validate().unwrap_err(),
+        server_message(client.share_todo(todo.id, &expired).await)
+    );
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_fresh_storage() {
+    let first = spawn_test_server().await;
+    client::new(&first.base_url)
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap();
+    let second = spawn_test_server().await;
+    assert!(client::new(&second.base_url)
+        .list_todos()
+        .await
+        .unwrap()
+        .is_empty());
+    first.stop().await;
+    second.stop().await;
+}
+
+#[actix_web::test]
+async fn test_share_links() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let todo: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    let link = client
+        .share_todo(
+            todo.id,
+            &ShareLinkData {
+                expires_in_secs: Some(3600),
+            },
+        )
+        .await
+        .unwrap();
+    assert!(link.expires_at.is_some());
+    assert_eq!(format!("/shared/{}", link.token.0), link.path);
+    assert_eq!(todo, client.get_shared_todo(&link.token).await.unwrap());
+    assert_eq!(
+        vec![link.clone()],
+        client.list_share_links(todo.id).await.unwrap()
+    );
+
+    client
+        .revoke_share_link(todo.id, &link.token)
+        .await
+        .unwrap();
+    match client.get_shared_todo(&link.token).await {
+        Err(ClientError::Api { status, .. }) => assert_eq!(404, status),
+        other => panic!("Unexpected: {:?}", other),
+    }
+    match client
+        .share_todo(TodoId(42), &ShareLinkData::default())
+        .await
+    {
+        Err(ClientError::Api { status, .. }) => assert_eq!(404, status),
+        other => panic!("Unexpected: {:?}", other),
+    }
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_todo_history() {
+    let server = spawn_test_server().await;
+    let http = reqwest::Client::builder()
+        .user_agent("routes-test")
+        .build()
+        .unwrap();
+    let client = client::with_http_client(&server.base_url, http);
+    let created: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    client
+        .update_todo(&Todo {
+            done: true,
+            ..created.clone()
+        })
+        .await
+        .unwrap();
+    client.delete_todo(created.id).await.unwrap();
+
+    let history = client.todo_history(created.id).await.unwrap();
+    assert_eq!(3, history.len());
+    assert_eq!(None, history[0].old);
+    assert_eq!(Some(created.clone()), history[1].old);
+    assert_eq!(Some(2), history[1].new.as_ref().map(|t| t.version));
+    assert_eq!(None, history[2].new);
+    assert!(history
+        .iter()
+        .all(|c| c.actor.as_deref() == Some("routes-test")));
+    match client.todo_history(TodoId(42)).await {
+        Err(ClientError::Api { status, .. }) => assert_eq!(404, status),
+        other => panic!("Unexpected: {:?}", other),
+    }
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_share_embed() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let todo: Todo = client
+        .create_todo(&todo_data("Make the <b>bed</b>"))
+        .await
+        .unwrap()
+        .into();
+    let link = client
+        .share_todo(todo.id, &ShareLinkData::default())
+        .await
+        .unwrap();
+    let resp = reqwest::get(format!("{}{}/embed", server.base_url, link.path))
+        .await
+        .unwrap();
+    assert_eq!(200, resp.status().as_u16());
+    let csp = resp.headers()["content-security-policy"].to_str().unwrap();
+    assert!(csp.starts_with("default-src 'none'"), "{}", csp);
+    assert!(resp.headers().get("set-cookie").is_none());
+    let html = resp.text().await.unwrap();
+    assert!(html.contains("Make the &lt;b&gt;bed&lt;/b&gt;"), "{}", html);
+
+    client
+        .revoke_share_link(todo.id, &link.token)
+        .await
+        .unwrap();
+    let resp = reqwest::get(format!("{}{}/embed", server.base_url, link.path))
+        .await
+        .unwrap();
+    assert_eq!(404, resp.status().as_u16());
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_create_location() {
+    let server = spawn_test_server().await;
+    let resp = reqwest::Client::new()
+        .post(format!("{}/tasks", server.base_url))
+        .header("content-type", "application/json")
+        .body(r#"{"task":"Make the bed"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, resp.status().as_u16());
+    let location = resp.headers()["location"].to_str().unwrap().to_string();
+    let created: Todo = serde_json::from_str(&resp.text().await.unwrap()).unwrap();
+    assert_eq!(format!("/tasks/{}", created.id.0), location);
+    let fetched = client::new(&server.base_url)
+        .get_todo(created.id)
+        .await
+        .unwrap();
+    assert_eq!(created, fetched);
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_conditional_gets() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let todo: Todo = client
+        .create_todo(&todo_data("Make the bed"))
+        .await
+        .unwrap()
+        .into();
+    let http = reqwest::Client::new();
+    for (i, path) in ["/tasks".to_string(), format!("/tasks/{}", todo.id.0)]
+        .iter()
+        .enumerate()
+    {
+        let url = format!("{}{}", server.base_url, path);
+        let resp = http.get(&url).send().await.unwrap();
+        assert_eq!(200, resp.status().as_u16());
+        let etag = resp.headers()["etag"].clone();
+        let resp = http
+            .get(&url)
+            .header("if-none-match", etag.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(304, resp.status().as_u16(), "{}", path);
+        assert!(resp.text().await.unwrap().is_empty());
+
+        client
+            .update_todo(&Todo {
+                task: format!("Make the bed, take {}", i + 2),
+                ..todo.clone()
+            })
+            .await
+            .unwrap();
+        let resp = http
+            .get(&url)
+            .header("if-none-match", etag.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(200, resp.status().as_u16(), "{}", path);
+        assert_ne!(etag, resp.headers()["etag"]);
+    }
+    server.stop().await;
+}
+
+// Reordering changes positions, which GET /tasks/{id} serves, so it mustn't get a 304
+#[actix_web::test]
+async fn test_conditional_get_after_reorder() {
+    let server = spawn_test_server().await;
+    let client = client::new(&server.base_url);
+    let mut created = Vec::new();
+    for task in ["Make the bed", "Do the dishes"] {
+        let todo: Todo = client.create_todo(&todo_data(task)).await.unwrap().into();
+        created.push(todo.id);
+    }
+    let http = reqwest::Client::new();
+    let url = format!("{}/tasks/{}", server.base_url, created[0].0);
+    let etag = http.get(&url).send().await.unwrap().headers()["etag"].clone();
+    client
+        .reorder_todos(&ReorderTodos {
+            ids: vec![created[1]],
+        })
+        .await
+        .unwrap();
+    let resp = http
+        .get(&url)
+        .header("if-none-match", etag.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, resp.status().as_u16());
+    assert_ne!(etag, resp.headers()["etag"]);
+    let todo: Todo = resp.json().await.unwrap();
+    assert_eq!(2, todo.position);
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn test_static_assets() {
+    let server = spawn_test_server().await;
+    let http = reqwest::Client::new();
+    let index = http
+        .get(format!("{}/swagger/", server.base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("no-cache", index.headers()["cache-control"]);
+    let index = index.text().await.unwrap();
+    let bundle = index
+        .split("src=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    assert!(bundle.starts_with("swagger-ui-bundle.") && bundle != "swagger-ui-bundle.js");
+
+    let url = format!("{}/swagger/{}", server.base_url, bundle);
+    let resp = http
+        .get(&url)
+        .header("accept-encoding", "gzip, br")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, resp.status().as_u16());
+    assert_eq!("br", resp.headers()["content-encoding"]);
+    assert_eq!(
+        "public, max-age=31536000, immutable",
+        resp.headers()["cache-control"]
+    );
+
+    // Not compressed again on the way out
+    let resp = http
+        .get(&url)
+        .header("accept-encoding", "gzip, br")
+        .header("range", "bytes=0-99")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(206, resp.status().as_u16());
+    assert_eq!(100, resp.bytes().await.unwrap().len());
+    server.stop().await;
+}
@@ -0,0 +1,314 @@
+use actix_web::middleware::DefaultHeaders;
+use actix_web::{rt, web};
+use api::app_builder::DefaultTodoController;
+use api::controllers::todo_controller;
+use api::controllers::todo_controller::{
+    TodoController, TodoControllerCompleteErr, TodoControllerDataErr, TodoControllerLookupErr,
+    TodoControllerReorderErr, TodoControllerUpdateErr,
+};
+use api::models::common::{ErrorCode, Message};
+use api::models::todo::{
+    CompleteTodos, CompletedTodos, ReorderTodos, SavedTodo, Todo, TodoData, TodoEvent, TodoId,
+    TodoPlan,
+};
+use api::AppBuilder;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+
+#[actix_web::test]
+async fn test_build_server() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |_| None).unwrap();
+    let built = api::build_server(config).await.unwrap();
+    assert_eq!(1, built.addrs.len());
+    assert_ne!(0, built.addrs[0].port());
+    assert!(built.redirect_server.is_none());
+    let handle = built.server.handle();
+    let running = rt::spawn(built.server);
+    let client = client::new(&format!("http://{}", built.addrs[0]));
+    assert_eq!("ok", client.healthz().await.unwrap().message);
+    handle.stop(true).await;
+    assert!(running.await.unwrap().is_ok());
+    assert!(client.healthz().await.is_err());
+}
+
+#[actix_web::test]
+async fn test_uuid_ids() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |key| {
+        Some("uuid".to_string()).filter(|_| key == "ID_STRATEGY")
+    })
+    .unwrap();
+    let built = api::build_server(config).await.unwrap();
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+    let client = client::new(&format!("http://{}", built.addrs[0]));
+    let todo_data = TodoData {
+        task: "Make the bed".to_string(),
+        done: false,
+        list_id: None,
+    };
+    let created: Todo = client.create_todo(&todo_data).await.unwrap().into();
+    // Rendered as a UUID, which reads back as the same id
+    assert!(created.id.0 > u128::from(u64::MAX));
+    assert_eq!(36, created.id.to_string().len());
+    assert_eq!(created, client.get_todo(created.id).await.unwrap());
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_admin_todo_maintenance() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |key| {
+        Some("secret".to_string()).filter(|_| key == "ADMIN_TOKEN")
+    })
+    .unwrap();
+    let built = api::build_server(config).await.unwrap();
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+    let client = client::new(&format!("http://{}", built.addrs[0]));
+    for (task, done) in [("Make the bed", false), ("Do the dishes", true)] {
+        let todo_data = TodoData {
+            task: task.to_string(),
+            done,
+            list_id: None,
+        };
+        client.create_todo(&todo_data).await.unwrap();
+    }
+    assert!(client.todo_stats("wrong").await.is_err());
+    assert_eq!(2, client.todo_stats("secret").await.unwrap().count);
+    assert_eq!(1, client.purge_done_todos("secret").await.unwrap().deleted);
+    let listed = client.list_todos().await.unwrap();
+    assert_eq!(listed, client.dump_todos("secret").await.unwrap());
+    assert_eq!("Make the bed", listed[0].task);
+    assert_eq!(1, client.delete_all_todos("secret").await.unwrap().deleted);
+    assert!(client.list_todos().await.unwrap().is_empty());
+    handle.stop(true).await;
+}
+
+// Shouts every task it creates, and otherwise leaves things to the default controller
+struct ShoutingController(DefaultTodoController);
+
+#[async_trait]
+impl TodoController for ShoutingController {
+    async fn create(&self, todo_data: &TodoData) -> Result<SavedTodo, TodoControllerDataErr> {
+        self.0.create(&shout(todo_data)).await
+    }
+    async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoControllerLookupErr> {
+        self.0.get(todo_id).await
+    }
+    async fn list(&self) -> Result<Vec<Todo>, TodoControllerLookupErr> {
+        self.0.list().await
+    }
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<SavedTodo, TodoControllerUpdateErr> {
+        self.0.update(todo, expected_version).await
+    }
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoControllerLookupErr> {
+        self.0.delete(todo_id).await
+    }
+    async fn plan_create(&self, todo_data: &TodoData) -> Result<TodoPlan, TodoControllerDataErr> {
+        self.0.plan_create(&shout(todo_data)).await
+    }
+    async fn plan_update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<TodoPlan, TodoControllerUpdateErr> {
+        self.0.plan_update(todo, expected_version).await
+    }
+    async fn plan_delete(&self, todo_id: &TodoId) -> Result<TodoPlan, TodoControllerLookupErr> {
+        self.0.plan_delete(todo_id).await
+    }
+    async fn complete(
+        &self,
+        selection: &CompleteTodos,
+    ) -> Result<CompletedTodos, TodoControllerCompleteErr> {
+        self.0.complete(selection).await
+    }
+    async fn reorder(&self, order: &ReorderTodos) -> Result<Vec<Todo>, TodoControllerReorderErr> {
+        self.0.reorder(order).await
+    }
+    async fn find_duplicate(
+        &self,
+        todo_data: &TodoData,
+    ) -> Result<Option<Todo>, TodoControllerLookupErr> {
+        self.0.find_duplicate(&shout(todo_data)).await
+    }
+    fn subscribe(&self) -> BoxStream<'static, TodoEvent> {
+        self.0.subscribe()
+    }
+}
+
+fn shout(todo_data: &TodoData) -> TodoData {
+    TodoData {
+        task: todo_data.task.to_uppercase(),
+        done: todo_data.done,
+        list_id: todo_data.list_id,
+    }
+}
+
+#[actix_web::test]
+async fn test_app_builder() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |_| None).unwrap();
+    let built = AppBuilder::new(config)
+        .todo_controller(|todo_service| {
+            ShoutingController(todo_controller::new(todo_service.clone()))
+        })
+        .configure(|cfg| {
+            cfg.route("/extra", web::get().to(|| async { "extra" }));
+        })
+        .wrap(|| DefaultHeaders::new().add(("X-Embedded", "yes")))
+        .build()
+        .await
+        .unwrap();
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+    let base_url = format!("http://{}", built.addrs[0]);
+
+    let extra = reqwest::get(format!("{}/extra", base_url)).await.unwrap();
+    assert_eq!(Some("yes"), extra.headers()["X-Embedded"].to_str().ok());
+    assert_eq!("extra", extra.text().await.unwrap());
+    let client = client::new(&base_url);
+    let data = TodoData {
+        task: "Make the bed".to_string(),
+        done: false,
+        list_id: None,
+    };
+    assert_eq!(
+        "MAKE THE BED",
+        client.create_todo(&data).await.unwrap().task
+    );
+    // Built-in routes get the middleware too
+    let healthz = reqwest::get(format!("{}/healthz", base_url)).await.unwrap();
+    assert!(healthz.headers().contains_key("X-Embedded"));
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_server_timing() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |key| {
+        Some("true".to_string()).filter(|_| key == "SERVER_TIMING")
+    })
+    .unwrap();
+    let built = AppBuilder::new(config).build().await.unwrap();
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+    let base_url = format!("http://{}", built.addrs[0]);
+
+    let listed = reqwest::get(format!("{}/tasks", base_url)).await.unwrap();
+    let timing = listed.headers()["server-timing"].to_str().unwrap();
+    let layers: Vec<_> = timing
+        .split(", ")
+        .map(|entry| entry.split(";dur=").next().unwrap())
+        .collect();
+    assert_eq!(
+        vec!["handler", "controller", "service", "repo", "total"],
+        layers
+    );
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_max_json_bytes() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |key| {
+        Some("64".to_string()).filter(|_| key == "MAX_JSON_BYTES")
+    })
+    .unwrap();
+    let built = api::build_server(config).await.unwrap();
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+    let client = client::new(&format!("http://{}", built.addrs[0]));
+    let todo_data = TodoData {
+        task: "Make the bed".repeat(10),
+        done: false,
+        list_id: None,
+    };
+    match client.create_todo(&todo_data).await {
+        Err(client::ClientError::Api { status, code, .. }) => {
+            assert_eq!(413, status);
+            assert_eq!(Some(ErrorCode::MalformedRequest), code);
+        }
+        other => panic!("Unexpected {:?}", other),
+    }
+    assert!(client.list_todos().await.unwrap().is_empty());
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_unmatched_routes() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |key| {
+        Some("secret".to_string()).filter(|_| key == "ADMIN_TOKEN")
+    })
+    .unwrap();
+    let built = api::build_server(config).await.unwrap();
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+    let base_url = format!("http://{}", built.addrs[0]);
+    let http = reqwest::Client::new();
+    // With the admin token, so that the admin routes get as far as matching methods
+    let call = |method: &str, path: &str| {
+        let method = reqwest::Method::from_bytes(method.as_bytes()).unwrap();
+        http.request(method, format!("{}{}", base_url, path))
+            .bearer_auth("secret")
+            .send()
+    };
+
+    let missing = call("GET", "/nope").await.unwrap();
+    assert_eq!(404, missing.status().as_u16());
+    let message: Message = missing.json().await.unwrap();
+    assert_eq!(Some(ErrorCode::RouteNotFound), message.code);
+
+    // Allowed in the order they're registered in
+    let served = [
+        ("/tasks", "GET, POST"),
+        ("/tasks/reorder", "PATCH"),
+        ("/tasks/1", "GET, DELETE, PUT"),
+        ("/tasks/1/share", "POST, GET"),
+        ("/swagger/index.html", "GET, HEAD"),
+        ("/api/spec", "GET"),
+        ("/admin/read-only", "GET, PUT"),
+    ];
+    for (path, allowed) in served {
+        let other = if allowed.contains("PATCH") {
+            "POST"
+        } else {
+            "PATCH"
+        };
+        let resp = call(other, path).await.unwrap();
+        assert_eq!(405, resp.status().as_u16(), "{} {}", other, path);
+        assert_eq!(allowed, resp.headers()["allow"], "{}", path);
+    }
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_prometheus_metrics() {
+    let args = vec!["todddo", "--bind", "127.0.0.1:0", "--workers", "1"];
+    let config = api::config::load_from(args, |key| {
+        Some("prometheus".to_string()).filter(|_| key == "METRICS_BACKEND")
+    })
+    .unwrap();
+    let built = AppBuilder::new(config).build().await.unwrap();
+    let handle = built.server.handle();
+    rt::spawn(built.server);
+    let base_url = format!("http://{}", built.addrs[0]);
+
+    reqwest::get(format!("{}/tasks/1", base_url)).await.unwrap();
+    let scraped = reqwest::get(format!("{}/metrics", base_url))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(scraped
+        .contains("http_requests_total{method=\"GET\",route=\"/tasks/{id}\",status=\"404\"} 1\n"));
+    handle.stop(true).await;
+}
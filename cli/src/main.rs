@@ -0,0 +1,190 @@
+mod output;
+
+use crate::output::OutputFormat;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use client::models::ids;
+use client::models::todo::{Todo, TodoData, TodoId};
+use client::{ClientError, TodoApiClient};
+use std::{env, process};
+
+static SERVER_URL_KEY: &str = "TODDDO_URL";
+// Only read from the environment, to keep it out of shell histories
+static ID_SECRET_KEY: &str = "TODDDO_ID_SECRET";
+static DEFAULT_SERVER_URL: &str = "http://127.0.0.1:8080";
+
+fn main() {
+    // Before parsing args, so that ids are validated against it
+    if let Some(secret) = env::var(ID_SECRET_KEY).ok().filter(|s| !s.is_empty()) {
+        let _ = ids::install(ids::codec(&secret));
+    }
+    let matches = cli().get_matches();
+    let client = client::new(matches.value_of("server").unwrap_or(DEFAULT_SERVER_URL));
+    // Validated by clap
+    let format = matches
+        .value_of("output")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(OutputFormat::Table);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Could not start the async runtime: {}", e);
+            process::exit(1);
+        });
+    match runtime.block_on(run(&client, format, &matches)) {
+        Ok(printed) => println!("{}", printed),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn cli<'a, 'b>() -> App<'a, 'b> {
+    let id_arg = || {
+        Arg::with_name("id")
+            .help("Id of the todo")
+            .required(true)
+            .validator(|s| s.parse::<TodoId>().map(|_| ()))
+    };
+    let task_arg = || {
+        Arg::with_name("task")
+            .help("What needs doing")
+            .required(true)
+    };
+    App::new("todddo")
+        .about("Manages todos on a todddo server")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("server")
+                .short("s")
+                .long("server")
+                .value_name("URL")
+                .help("Base URL of the server")
+                .env(SERVER_URL_KEY)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FORMAT")
+                .possible_values(&["table", "json"])
+                .default_value("table")
+                .help("How to print results"),
+        )
+        .subcommand(SubCommand::with_name("list").about("Lists every todo"))
+        .subcommand(
+            SubCommand::with_name("add")
+                .about("Adds a todo")
+                .arg(task_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("done")
+                .about("Marks a todo as done")
+                .arg(id_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("rm")
+                .about("Deletes a todo")
+                .arg(id_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("edit")
+                .about("Changes the task of a todo")
+                .arg(id_arg())
+                .arg(task_arg()),
+        )
+}
+
+async fn run(
+    client: &TodoApiClient,
+    format: OutputFormat,
+    matches: &ArgMatches<'_>,
+) -> Result<String, ClientError> {
+    match matches.subcommand() {
+        ("list", _) => Ok(output::todos(&client.list_todos().await?, format)),
+        ("add", Some(args)) => {
+            let todo_data = TodoData {
+                task: task(args),
+                done: false,
+                list_id: None,
+            };
+            let created = client.create_todo(&todo_data).await?;
+            print_warnings(&created.warnings);
+            Ok(output::todos(&[created.into()], format))
+        }
+        ("done", Some(args)) => {
+            update(client, format, id(args), |todo| Todo { done: true, ..todo }).await
+        }
+        ("edit", Some(args)) => {
+            update(client, format, id(args), |todo| Todo {
+                task: task(args),
+                ..todo
+            })
+            .await
+        }
+        ("rm", Some(args)) => Ok(output::message(
+            &client.delete_todo(id(args)).await?,
+            format,
+        )),
+        // SubcommandRequiredElseHelp means clap has already printed help for anything else
+        _ => unreachable!(),
+    }
+}
+
+// The API only replaces whole todos, so the current one is fetched first
+async fn update<F: FnOnce(Todo) -> Todo>(
+    client: &TodoApiClient,
+    format: OutputFormat,
+    id: TodoId,
+    f: F,
+) -> Result<String, ClientError> {
+    let updated = f(client.get_todo(id).await?);
+    // Fails rather than clobbering changes made since the todo was fetched
+    let message = client.update_todo_if_unchanged(&updated).await?;
+    print_warnings(&message.warnings);
+    let updated = Todo {
+        version: updated.version + 1,
+        ..updated
+    };
+    Ok(output::todos(&[updated], format))
+}
+
+// On stderr, so that the output stays parseable
+fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+}
+
+fn id(args: &ArgMatches) -> TodoId {
+    // Validated by clap
+    args.value_of("id")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(TodoId(0))
+}
+
+fn task(args: &ArgMatches) -> String {
+    args.value_of("task").unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        let matches = cli()
+            .get_matches_from_safe(vec!["todddo", "-o", "json", "edit", "3", "Have tea"])
+            .unwrap();
+        assert_eq!(Some("json"), matches.value_of("output"));
+        let (name, args) = matches.subcommand();
+        assert_eq!("edit", name);
+        assert_eq!(TodoId(3), id(args.unwrap()));
+        assert_eq!("Have tea", task(args.unwrap()));
+        assert!(cli()
+            .get_matches_from_safe(vec!["todddo", "done", "three"])
+            .is_err());
+    }
+}
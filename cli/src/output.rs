@@ -0,0 +1,90 @@
+use client::models::common::Message;
+use client::models::todo::Todo;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!("unknown output format [{}]", other)),
+        }
+    }
+}
+
+pub fn todos(todos: &[Todo], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(&todos),
+        OutputFormat::Table => table(todos),
+    }
+}
+
+pub fn message(message: &Message, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(message),
+        OutputFormat::Table => message.message.clone(),
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    // Only API models are printed, and those always serialise
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn table(todos: &[Todo]) -> String {
+    let ids: Vec<String> = todos.iter().map(|t| t.id.to_string()).collect();
+    let id_width = ids.iter().map(|id| id.len()).max().unwrap_or(0).max(2);
+    let mut lines = vec![format!("{:<w$}  DONE  TASK", "ID", w = id_width)];
+    for (id, todo) in ids.iter().zip(todos) {
+        let done = if todo.done { "[x]" } else { "[ ]" };
+        lines.push(format!(
+            "{:<w$}  {:<4}  {}",
+            id,
+            done,
+            todo.task,
+            w = id_width
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client::models::todo::TodoId;
+
+    #[test]
+    fn test_table() {
+        let listed = vec![
+            Todo {
+                id: TodoId(1),
+                task: "Make the bed".to_string(),
+                done: true,
+                version: 1,
+                list_id: None,
+                position: 1,
+            },
+            Todo {
+                id: TodoId(100),
+                task: "Have tea".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 100,
+            },
+        ];
+        assert_eq!(
+            "ID   DONE  TASK\n1    [x]   Make the bed\n100  [ ]   Have tea",
+            todos(&listed, OutputFormat::Table)
+        );
+        assert_eq!("ID  DONE  TASK", todos(&[], OutputFormat::Table));
+    }
+}
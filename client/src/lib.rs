@@ -0,0 +1,609 @@
+//! An async client for the todddo HTTP API, using the same models as the server
+
+use async_trait::async_trait;
+use models::admin::{
+    DeprecatedRouteReport, Diagnostics, HealthHistory, ProfileFormat, Purged, TodoStats,
+    VersionInfo,
+};
+use models::common::{ErrorCode, Message};
+use models::history::TodoChange;
+use models::list::{List, ListData, ListId};
+use models::recurrence::Recurrence;
+use models::reminder::{Reminder, ReminderData};
+use models::rule::{Rule, RuleData, RuleId};
+use models::share::{ShareLink, ShareLinkData, ShareToken};
+use models::todo::{
+    CompleteTodos, CompletedTodos, ImportReport, ReorderTodos, SavedTodo, Todo, TodoData, TodoId,
+    TodoPlan, TransferFormat,
+};
+use reqwest::header;
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+pub use models;
+
+/// Covers every endpoint except the `/tasks/ws` live updates, which need a WebSocket client
+#[derive(Clone)]
+pub struct TodoApiClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+pub fn new(base_url: &str) -> TodoApiClient {
+    with_http_client(base_url, reqwest::Client::new())
+}
+
+/// For configuring timeouts, proxies, TLS roots and the like
+pub fn with_http_client(base_url: &str, http: reqwest::Client) -> TodoApiClient {
+    TodoApiClient {
+        base_url: base_url.trim_end_matches('/').to_string(),
+        http,
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    // The server's own message, e.g. "No such todo: [TodoId(3)]"
+    #[error("{message} (HTTP {status})")]
+    Api {
+        status: u16,
+        message: String,
+        // For branching on, unlike the message; None for errors that aren't the server's own
+        code: Option<ErrorCode>,
+        // For finding the request in the server's logs
+        request_id: Option<String>,
+    },
+    #[error("Could not talk to the server: {0}")]
+    Transport(#[from] reqwest::Error),
+}
+
+/// The todo endpoints, for code that should run both against a server and, in tests, against an
+/// in-process double such as `api::in_process::InProcessClient`
+#[async_trait]
+pub trait TodoApi {
+    async fn list_todos(&self) -> Result<Vec<Todo>, ClientError>;
+
+    async fn list_todos_in_manual_order(&self) -> Result<Vec<Todo>, ClientError>;
+
+    async fn list_todos_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, ClientError>;
+
+    async fn get_todo(&self, id: TodoId) -> Result<Todo, ClientError>;
+
+    async fn create_todo(&self, todo_data: &TodoData) -> Result<SavedTodo, ClientError>;
+
+    async fn update_todo(&self, todo: &Todo) -> Result<Message, ClientError>;
+
+    async fn update_todo_if_unchanged(&self, todo: &Todo) -> Result<Message, ClientError>;
+
+    async fn delete_todo(&self, id: TodoId) -> Result<Message, ClientError>;
+
+    async fn complete_todos(
+        &self,
+        selection: &CompleteTodos,
+    ) -> Result<CompletedTodos, ClientError>;
+
+    async fn reorder_todos(&self, order: &ReorderTodos) -> Result<Vec<Todo>, ClientError>;
+
+    async fn plan_create_todo(&self, todo_data: &TodoData) -> Result<TodoPlan, ClientError>;
+
+    async fn plan_update_todo(&self, todo: &Todo) -> Result<TodoPlan, ClientError>;
+
+    async fn plan_delete_todo(&self, id: TodoId) -> Result<TodoPlan, ClientError>;
+}
+
+impl TodoApiClient {
+    pub async fn list_todos(&self) -> Result<Vec<Todo>, ClientError> {
+        json(self.http.get(self.url("/tasks"))).await
+    }
+
+    /// Every todo by position, as set by reorder_todos
+    pub async fn list_todos_in_manual_order(&self) -> Result<Vec<Todo>, ClientError> {
+        let request = self
+            .http
+            .get(self.url("/tasks"))
+            .query(&[("order", "manual")]);
+        json(request).await
+    }
+
+    /// Up to `limit` todos by id, after skipping `offset` of them
+    pub async fn list_todos_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Todo>, ClientError> {
+        let request = self
+            .http
+            .get(self.url("/tasks"))
+            .query(&[("offset", offset), ("limit", limit)]);
+        json(request).await
+    }
+
+    pub async fn get_todo(&self, id: TodoId) -> Result<Todo, ClientError> {
+        json(self.http.get(self.todo_url(id))).await
+    }
+
+    /// The created todo comes with warnings about non-fatal issues; `.into()` drops them
+    pub async fn create_todo(&self, todo_data: &TodoData) -> Result<SavedTodo, ClientError> {
+        json(self.http.post(self.url("/tasks")).json(todo_data)).await
+    }
+
+    pub async fn update_todo(&self, todo: &Todo) -> Result<Message, ClientError> {
+        let todo_data = TodoData {
+            task: todo.task.clone(),
+            done: todo.done,
+            list_id: todo.list_id,
+        };
+        json(self.http.put(self.todo_url(todo.id)).json(&todo_data)).await
+    }
+
+    /// Like update_todo, but fails with a 412 instead if the todo is no longer at `todo.version`,
+    /// i.e. someone else has updated it since it was fetched
+    pub async fn update_todo_if_unchanged(&self, todo: &Todo) -> Result<Message, ClientError> {
+        let todo_data = TodoData {
+            task: todo.task.clone(),
+            done: todo.done,
+            list_id: todo.list_id,
+        };
+        let request = self
+            .http
+            .put(self.todo_url(todo.id))
+            .header(header::IF_MATCH, format!("\"{}\"", todo.version))
+            .json(&todo_data);
+        json(request).await
+    }
+
+    pub async fn delete_todo(&self, id: TodoId) -> Result<Message, ClientError> {
+        json(self.http.delete(self.todo_url(id))).await
+    }
+
+    /// Marks the todos with the given ids, or every todo in a list, done all at once. Ids that no
+    /// todo has are listed in `not_found` rather than failing the call.
+    pub async fn complete_todos(
+        &self,
+        selection: &CompleteTodos,
+    ) -> Result<CompletedTodos, ClientError> {
+        json(self.http.post(self.url("/tasks/complete")).json(selection)).await
+    }
+
+    /// Puts the todos with the given ids first, in that order, returning every todo in the new
+    /// manual order
+    pub async fn reorder_todos(&self, order: &ReorderTodos) -> Result<Vec<Todo>, ClientError> {
+        json(self.http.patch(self.url("/tasks/reorder")).json(order)).await
+    }
+
+    /// Dry run of create_todo: what creating it would do, e.g. the follow-ups rules would create
+    pub async fn plan_create_todo(&self, todo_data: &TodoData) -> Result<TodoPlan, ClientError> {
+        let request = self
+            .http
+            .post(self.url("/tasks"))
+            .query(&[("dry_run", true)]);
+        json(request.json(todo_data)).await
+    }
+
+    /// Dry run of update_todo
+    pub async fn plan_update_todo(&self, todo: &Todo) -> Result<TodoPlan, ClientError> {
+        let todo_data = TodoData {
+            task: todo.task.clone(),
+            done: todo.done,
+            list_id: todo.list_id,
+        };
+        let request = self
+            .http
+            .put(self.todo_url(todo.id))
+            .query(&[("dry_run", true)]);
+        json(request.json(&todo_data)).await
+    }
+
+    /// Dry run of delete_todo
+    pub async fn plan_delete_todo(&self, id: TodoId) -> Result<TodoPlan, ClientError> {
+        json(
+            self.http
+                .delete(self.todo_url(id))
+                .query(&[("dry_run", true)]),
+        )
+        .await
+    }
+
+    /// Oldest first; still served after the todo is deleted
+    pub async fn todo_history(&self, id: TodoId) -> Result<Vec<TodoChange>, ClientError> {
+        json(self.http.get(format!("{}/history", self.todo_url(id)))).await
+    }
+
+    /// Fails with a 404 with a `RECURRENCE_NOT_FOUND` code if the todo exists but doesn't recur
+    pub async fn get_recurrence(&self, id: TodoId) -> Result<Recurrence, ClientError> {
+        json(self.http.get(self.recurrence_url(id))).await
+    }
+
+    pub async fn set_recurrence(
+        &self,
+        id: TodoId,
+        recurrence: &Recurrence,
+    ) -> Result<Message, ClientError> {
+        json(self.http.put(self.recurrence_url(id)).json(recurrence)).await
+    }
+
+    pub async fn delete_recurrence(&self, id: TodoId) -> Result<Message, ClientError> {
+        json(self.http.delete(self.recurrence_url(id))).await
+    }
+
+    /// Soonest first
+    pub async fn list_reminders(&self) -> Result<Vec<Reminder>, ClientError> {
+        json(self.http.get(self.url("/reminders"))).await
+    }
+
+    /// Fails with a 404 with a `REMINDER_NOT_FOUND` code if the todo exists but its reminder has
+    /// been sent or cancelled
+    pub async fn get_reminder(&self, id: TodoId) -> Result<Reminder, ClientError> {
+        json(self.http.get(self.reminder_url(id))).await
+    }
+
+    pub async fn set_reminder(
+        &self,
+        id: TodoId,
+        reminder_data: &ReminderData,
+    ) -> Result<Reminder, ClientError> {
+        json(self.http.put(self.reminder_url(id)).json(reminder_data)).await
+    }
+
+    pub async fn delete_reminder(&self, id: TodoId) -> Result<Message, ClientError> {
+        json(self.http.delete(self.reminder_url(id))).await
+    }
+
+    pub async fn export_todos(&self, format: TransferFormat) -> Result<String, ClientError> {
+        let request = self
+            .http
+            .get(self.url(&format!("/tasks/export?format={}", format.extension())));
+        Ok(send(request).await?.text().await?)
+    }
+
+    /// `body` is CSV with a `task` (and optionally a `done`) header, or a JSON array of todo data
+    pub async fn import_todos(
+        &self,
+        body: String,
+        format: TransferFormat,
+    ) -> Result<ImportReport, ClientError> {
+        let request = self
+            .http
+            .post(self.url("/tasks/import"))
+            .header(header::CONTENT_TYPE, format.content_type())
+            .body(body);
+        json(request).await
+    }
+
+    pub async fn share_todo(
+        &self,
+        id: TodoId,
+        share_data: &ShareLinkData,
+    ) -> Result<ShareLink, ClientError> {
+        json(self.http.post(self.share_url(id)).json(share_data)).await
+    }
+
+    pub async fn list_share_links(&self, id: TodoId) -> Result<Vec<ShareLink>, ClientError> {
+        json(self.http.get(self.share_url(id))).await
+    }
+
+    pub async fn revoke_share_link(
+        &self,
+        id: TodoId,
+        token: &ShareToken,
+    ) -> Result<Message, ClientError> {
+        let url = format!("{}/{}", self.share_url(id), token.0);
+        json(self.http.delete(url)).await
+    }
+
+    /// Works without knowing the todo's id, until the link expires or is revoked
+    pub async fn get_shared_todo(&self, token: &ShareToken) -> Result<Todo, ClientError> {
+        json(self.http.get(self.url(&format!("/shared/{}", token.0)))).await
+    }
+
+    pub async fn list_lists(&self) -> Result<Vec<List>, ClientError> {
+        json(self.http.get(self.url("/lists"))).await
+    }
+
+    /// Todos are put in the list by setting their `list_id`
+    pub async fn create_list(&self, list_data: &ListData) -> Result<List, ClientError> {
+        json(self.http.post(self.url("/lists")).json(list_data)).await
+    }
+
+    pub async fn get_list(&self, id: ListId) -> Result<List, ClientError> {
+        json(self.http.get(self.list_url(id))).await
+    }
+
+    /// Fails with a 409 while any todo is still in the list
+    pub async fn delete_list(&self, id: ListId) -> Result<Message, ClientError> {
+        json(self.http.delete(self.list_url(id))).await
+    }
+
+    pub async fn list_todos_in(&self, id: ListId) -> Result<Vec<Todo>, ClientError> {
+        json(self.http.get(format!("{}/tasks", self.list_url(id)))).await
+    }
+
+    pub async fn list_rules(&self) -> Result<Vec<Rule>, ClientError> {
+        json(self.http.get(self.url("/rules"))).await
+    }
+
+    pub async fn create_rule(&self, rule_data: &RuleData) -> Result<Rule, ClientError> {
+        json(self.http.post(self.url("/rules")).json(rule_data)).await
+    }
+
+    pub async fn delete_rule(&self, id: RuleId) -> Result<Message, ClientError> {
+        json(self.http.delete(self.url(&format!("/rules/{}", id)))).await
+    }
+
+    pub async fn healthz(&self) -> Result<Message, ClientError> {
+        json(self.http.get(self.url("/healthz"))).await
+    }
+
+    /// Fails with a 503 once the server has started shutting down
+    pub async fn readyz(&self) -> Result<Message, ClientError> {
+        json(self.http.get(self.url("/readyz"))).await
+    }
+
+    pub async fn version(&self) -> Result<VersionInfo, ClientError> {
+        json(self.http.get(self.url("/version"))).await
+    }
+
+    pub async fn deprecations(&self) -> Result<Vec<DeprecatedRouteReport>, ClientError> {
+        json(self.http.get(self.url("/admin/deprecations"))).await
+    }
+
+    pub async fn diagnostics(&self) -> Result<Diagnostics, ClientError> {
+        json(self.http.get(self.url("/admin/diagnostics"))).await
+    }
+
+    pub async fn health_history(&self) -> Result<HealthHistory, ClientError> {
+        json(self.http.get(self.url("/admin/health/history"))).await
+    }
+
+    /// `since` is RFC 3339; every change the server still has is returned when not given
+    pub async fn audit_log(
+        &self,
+        admin_token: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<TodoChange>, ClientError> {
+        let mut request = self.http.get(self.url("/admin/audit"));
+        if let Some(since) = since {
+            request = request.query(&[("since", since)]);
+        }
+        json(request.bearer_auth(admin_token)).await
+    }
+
+    pub async fn todo_stats(&self, admin_token: &str) -> Result<TodoStats, ClientError> {
+        json(
+            self.http
+                .get(self.url("/admin/tasks/stats"))
+                .bearer_auth(admin_token),
+        )
+        .await
+    }
+
+    pub async fn dump_todos(&self, admin_token: &str) -> Result<Vec<Todo>, ClientError> {
+        json(
+            self.http
+                .get(self.url("/admin/tasks/dump"))
+                .bearer_auth(admin_token),
+        )
+        .await
+    }
+
+    /// Deletes every done todo without emitting events or auditing
+    pub async fn purge_done_todos(&self, admin_token: &str) -> Result<Purged, ClientError> {
+        json(
+            self.http
+                .post(self.url("/admin/tasks/purge-done"))
+                .bearer_auth(admin_token),
+        )
+        .await
+    }
+
+    /// Deletes every todo without emitting events or auditing
+    pub async fn delete_all_todos(&self, admin_token: &str) -> Result<Purged, ClientError> {
+        json(
+            self.http
+                .delete(self.url("/admin/tasks"))
+                .bearer_auth(admin_token),
+        )
+        .await
+    }
+
+    /// Only served when the server is built with the `profiling` feature
+    pub async fn profile(
+        &self,
+        admin_token: &str,
+        seconds: u64,
+        format: ProfileFormat,
+    ) -> Result<Vec<u8>, ClientError> {
+        let format = match format {
+            ProfileFormat::Flamegraph => "flamegraph",
+            ProfileFormat::Pprof => "pprof",
+        };
+        let request = self
+            .http
+            .get(self.url(&format!(
+                "/admin/profile?seconds={}&format={}",
+                seconds, format
+            )))
+            .bearer_auth(admin_token);
+        Ok(send(request).await?.bytes().await?.to_vec())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn todo_url(&self, id: TodoId) -> String {
+        self.url(&format!("/tasks/{}", id))
+    }
+
+    fn list_url(&self, id: ListId) -> String {
+        self.url(&format!("/lists/{}", id))
+    }
+
+    fn recurrence_url(&self, id: TodoId) -> String {
+        format!("{}/recurrence", self.todo_url(id))
+    }
+
+    fn reminder_url(&self, id: TodoId) -> String {
+        format!("{}/reminder", self.todo_url(id))
+    }
+
+    fn share_url(&self, id: TodoId) -> String {
+        format!("{}/share", self.todo_url(id))
+    }
+}
+
+#[async_trait]
+impl TodoApi for TodoApiClient {
+    async fn list_todos(&self) -> Result<Vec<Todo>, ClientError> {
+        TodoApiClient::list_todos(self).await
+    }
+
+    async fn list_todos_in_manual_order(&self) -> Result<Vec<Todo>, ClientError> {
+        TodoApiClient::list_todos_in_manual_order(self).await
+    }
+
+    async fn list_todos_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, ClientError> {
+        TodoApiClient::list_todos_page(self, offset, limit).await
+    }
+
+    async fn get_todo(&self, id: TodoId) -> Result<Todo, ClientError> {
+        TodoApiClient::get_todo(self, id).await
+    }
+
+    async fn create_todo(&self, todo_data: &TodoData) -> Result<SavedTodo, ClientError> {
+        TodoApiClient::create_todo(self, todo_data).await
+    }
+
+    async fn update_todo(&self, todo: &Todo) -> Result<Message, ClientError> {
+        TodoApiClient::update_todo(self, todo).await
+    }
+
+    async fn update_todo_if_unchanged(&self, todo: &Todo) -> Result<Message, ClientError> {
+        TodoApiClient::update_todo_if_unchanged(self, todo).await
+    }
+
+    async fn delete_todo(&self, id: TodoId) -> Result<Message, ClientError> {
+        TodoApiClient::delete_todo(self, id).await
+    }
+
+    async fn complete_todos(
+        &self,
+        selection: &CompleteTodos,
+    ) -> Result<CompletedTodos, ClientError> {
+        TodoApiClient::complete_todos(self, selection).await
+    }
+
+    async fn reorder_todos(&self, order: &ReorderTodos) -> Result<Vec<Todo>, ClientError> {
+        TodoApiClient::reorder_todos(self, order).await
+    }
+
+    async fn plan_create_todo(&self, todo_data: &TodoData) -> Result<TodoPlan, ClientError> {
+        TodoApiClient::plan_create_todo(self, todo_data).await
+    }
+
+    async fn plan_update_todo(&self, todo: &Todo) -> Result<TodoPlan, ClientError> {
+        TodoApiClient::plan_update_todo(self, todo).await
+    }
+
+    async fn plan_delete_todo(&self, id: TodoId) -> Result<TodoPlan, ClientError> {
+        TodoApiClient::plan_delete_todo(self, id).await
+    }
+}
+
+async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, ClientError> {
+    Ok(send(request).await?.json().await?)
+}
+
+async fn send(request: RequestBuilder) -> Result<Response, ClientError> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let request_id = response
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    // Error bodies are a Message, except e.g. for bodies that actix couldn't parse
+    let body = response.text().await?;
+    let (message, code) = match serde_json::from_str::<Message>(&body) {
+        Ok(m) => (m.message, m.code),
+        Err(_) => (body, None),
+    };
+    Err(ClientError::Api {
+        status: status.as_u16(),
+        message,
+        code,
+        request_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Answers a single request with the given status and JSON body
+    async fn serve_once(status: &'static str, body: &'static str) -> TodoApiClient {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 4096];
+            let _ = socket.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        new(&format!("http://{}/", addr))
+    }
+
+    #[tokio::test]
+    async fn test_list_todos() {
+        let client = serve_once(
+            "200 OK",
+            r#"[{"id":1,"task":"Make the bed","done":true,"version":1}]"#,
+        )
+        .await;
+        assert_eq!(
+            vec![Todo {
+                id: TodoId(1),
+                task: "Make the bed".to_string(),
+                done: true,
+                version: 1,
+                list_id: None,
+                position: 0,
+            }],
+            client.list_todos().await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let client = serve_once(
+            "404 Not Found",
+            r#"{"message":"No such todo","code":"TODO_NOT_FOUND"}"#,
+        )
+        .await;
+        match client.get_todo(TodoId(3)).await {
+            Err(ClientError::Api {
+                status,
+                message,
+                code,
+                ..
+            }) => {
+                assert_eq!(404, status);
+                assert_eq!("No such todo", message);
+                assert_eq!(Some(ErrorCode::TodoNotFound), code);
+            }
+            other => panic!("Unexpected: {:?}", other.map(|_| ())),
+        }
+    }
+}
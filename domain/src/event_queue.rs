@@ -0,0 +1,341 @@
+//! Bounded queues in front of subscribers, so that a slow one (a webhook, a search indexer..)
+//! neither holds up the change that emitted an event nor piles events up in memory
+
+use crate::events::{Subscriber, TodoEvent};
+use crate::metrics::Metrics;
+use async_trait::async_trait;
+use futures::future::{poll_fn, BoxFuture};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// What happens to an event when its subscriber's queue is full
+#[derive(Clone)]
+pub enum Overflow {
+    /// The change that emitted it waits for room
+    Block,
+    /// The oldest queued event makes room for it
+    DropOldest,
+    /// It waits in the spill, as do later events until the spill has been drained
+    Spill(Arc<dyn Spill + Send + Sync>),
+}
+
+// Where events that didn't fit in memory go, oldest first; implementations live in infra, and log
+// their own failures. Called with the queue locked, so neither may wait on I/O
+pub trait Spill {
+    fn push(&self, event: &TodoEvent) -> Spilled;
+    /// Pending while the oldest event is still being read back, waking `cx` once it has been.
+    /// None once empty, or when the oldest event couldn't be read back; it's taken out of the
+    /// spill either way
+    fn poll_pop(&self, cx: &mut Context) -> Poll<Option<TodoEvent>>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// What came of spilling an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spilled {
+    Spilled,
+    /// The spill was full, so its oldest event was dropped to make room
+    DroppedOldest,
+    /// It couldn't be spilled, so it was dropped instead
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueStats {
+    pub subscriber: String,
+    /// Events waiting in memory
+    pub depth: usize,
+    /// Events waiting in the spill
+    pub spilled: usize,
+    /// Events dropped, in total
+    pub dropped: u64,
+    /// Times a change had to wait for room, in total
+    pub blocked: u64,
+}
+
+struct State {
+    events: VecDeque<TodoEvent>,
+    drainer: Option<Waker>,
+    // Changes waiting for room
+    waiting: Vec<Waker>,
+    closed: bool,
+    dropped: u64,
+    blocked: u64,
+}
+
+/// Queues events for a subscriber that is notified by the drain returned alongside it
+pub struct QueuedSubscriber {
+    name: String,
+    capacity: usize,
+    overflow: Overflow,
+    metrics: Option<Arc<dyn Metrics + Send + Sync>>,
+    state: Arc<Mutex<State>>,
+}
+
+/// The drain runs until the queued subscriber is dropped and its queue emptied; spawn it
+pub fn queued(
+    name: &str,
+    subscriber: Arc<dyn Subscriber + Send + Sync>,
+    capacity: usize,
+    overflow: Overflow,
+    metrics: Option<Arc<dyn Metrics + Send + Sync>>,
+) -> (Arc<QueuedSubscriber>, BoxFuture<'static, ()>) {
+    let state = Arc::new(Mutex::new(State {
+        events: VecDeque::with_capacity(capacity),
+        drainer: None,
+        waiting: Vec::new(),
+        closed: false,
+        dropped: 0,
+        blocked: 0,
+    }));
+    let spill = match &overflow {
+        Overflow::Spill(spill) => Some(spill.clone()),
+        _ => None,
+    };
+    let drain_state = state.clone();
+    let drain = Box::pin(async move {
+        while let Some(event) = poll_fn(|cx| next(&drain_state, capacity, &spill, cx)).await {
+            subscriber.notify(&event).await;
+        }
+    });
+    let queued = Arc::new(QueuedSubscriber {
+        name: name.to_string(),
+        capacity,
+        overflow,
+        metrics,
+        state,
+    });
+    (queued, drain)
+}
+
+impl QueuedSubscriber {
+    pub fn stats(&self) -> QueueStats {
+        let state = lock(&self.state);
+        QueueStats {
+            subscriber: self.name.clone(),
+            depth: state.events.len(),
+            spilled: match &self.overflow {
+                Overflow::Spill(spill) => spill.len(),
+                _ => 0,
+            },
+            dropped: state.dropped,
+            blocked: state.blocked,
+        }
+    }
+
+    fn offer(&self, event: &TodoEvent, waited: &mut bool, cx: &mut Context) -> Poll<()> {
+        let mut state = lock(&self.state);
+        let spilling = match &self.overflow {
+            Overflow::Spill(spill) => !spill.is_empty(),
+            _ => false,
+        };
+        if state.events.len() < self.capacity && !spilling {
+            state.events.push_back(event.clone());
+            wake_drainer(&mut state);
+            return Poll::Ready(());
+        }
+        match &self.overflow {
+            Overflow::Block => {
+                if !*waited {
+                    *waited = true;
+                    state.blocked += 1;
+                    self.count("event_queue_blocked");
+                }
+                state.waiting.push(cx.waker().clone());
+                return Poll::Pending;
+            }
+            Overflow::DropOldest => {
+                state.events.pop_front();
+                state.events.push_back(event.clone());
+                state.dropped += 1;
+                self.count("event_queue_dropped");
+            }
+            Overflow::Spill(spill) => match spill.push(event) {
+                Spilled::Spilled => self.count("event_queue_spilled"),
+                Spilled::DroppedOldest => {
+                    self.count("event_queue_spilled");
+                    state.dropped += 1;
+                    self.count("event_queue_dropped");
+                }
+                Spilled::Failed => {
+                    state.dropped += 1;
+                    self.count("event_queue_dropped");
+                }
+            },
+        }
+        wake_drainer(&mut state);
+        Poll::Ready(())
+    }
+
+    fn count(&self, name: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.count(name, &[("subscriber", &self.name)], 1);
+        }
+    }
+}
+
+#[async_trait]
+impl Subscriber for QueuedSubscriber {
+    async fn notify(&self, event: &TodoEvent) {
+        let mut waited = false;
+        poll_fn(|cx| self.offer(event, &mut waited, cx)).await
+    }
+}
+
+impl Drop for QueuedSubscriber {
+    fn drop(&mut self) {
+        let mut state = lock(&self.state);
+        state.closed = true;
+        wake_drainer(&mut state);
+    }
+}
+
+// Spilled events are moved back into memory as room is made, so that they keep their order
+fn next(
+    state: &Mutex<State>,
+    capacity: usize,
+    spill: &Option<Arc<dyn Spill + Send + Sync>>,
+    cx: &mut Context,
+) -> Poll<Option<TodoEvent>> {
+    let mut state = lock(state);
+    if let Some(spill) = spill {
+        while state.events.len() < capacity && !spill.is_empty() {
+            match spill.poll_pop(cx) {
+                Poll::Ready(Some(event)) => state.events.push_back(event),
+                Poll::Ready(None) => {}
+                // Woken again once it has been read back
+                Poll::Pending => break,
+            }
+        }
+    }
+    match state.events.pop_front() {
+        Some(event) => {
+            for waker in state.waiting.drain(..) {
+                waker.wake();
+            }
+            Poll::Ready(Some(event))
+        }
+        // Not while events are still being read back from the spill
+        None if state.closed && !matches!(spill, Some(spill) if !spill.is_empty()) => {
+            Poll::Ready(None)
+        }
+        None => {
+            state.drainer = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+fn wake_drainer(state: &mut State) {
+    if let Some(waker) = state.drainer.take() {
+        waker.wake();
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::TodoDeleted;
+    use crate::todo::TodoId;
+    use futures::executor::block_on;
+    use futures::future::FutureExt;
+
+    #[derive(Clone, Default)]
+    struct Recorded(Arc<Mutex<Vec<u128>>>);
+
+    #[async_trait]
+    impl Subscriber for Recorded {
+        async fn notify(&self, event: &TodoEvent) {
+            if let TodoEvent::Deleted(deleted) = event {
+                self.0.lock().unwrap().push(deleted.id.0);
+            }
+        }
+    }
+
+    // Holds up to its max
+    struct InMemSpill(Mutex<VecDeque<TodoEvent>>, usize);
+
+    impl Spill for InMemSpill {
+        fn push(&self, event: &TodoEvent) -> Spilled {
+            let mut events = self.0.lock().unwrap();
+            events.push_back(event.clone());
+            if events.len() > self.1 {
+                events.pop_front();
+                Spilled::DroppedOldest
+            } else {
+                Spilled::Spilled
+            }
+        }
+        fn poll_pop(&self, _: &mut Context) -> Poll<Option<TodoEvent>> {
+            Poll::Ready(self.0.lock().unwrap().pop_front())
+        }
+        fn len(&self) -> usize {
+            self.0.lock().unwrap().len()
+        }
+    }
+
+    fn deleted(id: u128) -> TodoEvent {
+        TodoEvent::Deleted(TodoDeleted { id: TodoId(id) })
+    }
+
+    // Notifies every id, then drains the queue
+    fn run(overflow: Overflow, ids: &[u128]) -> (Vec<u128>, QueueStats) {
+        let recorded = Recorded::default();
+        let (queued, drain) = queued("test", Arc::new(recorded.clone()), 2, overflow, None);
+        for id in ids {
+            block_on(queued.notify(&deleted(*id)));
+        }
+        let stats = queued.stats();
+        drop(queued);
+        block_on(drain);
+        let delivered = recorded.0.lock().unwrap().clone();
+        (delivered, stats)
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let (delivered, stats) = run(Overflow::DropOldest, &[1, 2, 3, 4]);
+        assert_eq!(vec![3, 4], delivered);
+        assert_eq!(2, stats.depth);
+        assert_eq!(2, stats.dropped);
+    }
+
+    #[test]
+    fn test_spill() {
+        let spill = || Arc::new(InMemSpill(Mutex::default(), 2));
+        let (delivered, stats) = run(Overflow::Spill(spill()), &[1, 2, 3, 4]);
+        assert_eq!(vec![1, 2, 3, 4], delivered);
+        assert_eq!(2, stats.spilled);
+        assert_eq!(0, stats.dropped);
+        // Once the spill is full too, its oldest events are dropped
+        let (delivered, stats) = run(Overflow::Spill(spill()), &[1, 2, 3, 4, 5]);
+        assert_eq!(vec![1, 2, 4, 5], delivered);
+        assert_eq!(1, stats.dropped);
+    }
+
+    #[test]
+    fn test_block() {
+        let recorded = Recorded::default();
+        let (queued, mut drain) =
+            queued("test", Arc::new(recorded.clone()), 1, Overflow::Block, None);
+        block_on(queued.notify(&deleted(1)));
+        let second = deleted(2);
+        let mut blocked = queued.notify(&second);
+        assert!(blocked.as_mut().now_or_never().is_none());
+        assert_eq!(1, queued.stats().blocked);
+        // Delivering the first makes room for the second
+        assert!(drain.as_mut().now_or_never().is_none());
+        assert!(blocked.now_or_never().is_some());
+        drop(queued);
+        block_on(drain);
+        assert_eq!(vec![1, 2], *recorded.0.lock().unwrap());
+    }
+}
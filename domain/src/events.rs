@@ -0,0 +1,95 @@
+use crate::todo::*;
+
+#[cfg(feature = "services")]
+use async_trait::async_trait;
+#[cfg(feature = "services")]
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+#[cfg(feature = "services")]
+use std::sync::{Arc, Mutex};
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TodoCreated {
+    pub todo: Todo,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TodoUpdated {
+    pub todo: Todo,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TodoDeleted {
+    pub id: TodoId,
+}
+
+// Something that happened to a [[Todo]], emitted by the service once it has been persisted
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TodoEvent {
+    Created(TodoCreated),
+    Updated(TodoUpdated),
+    Deleted(TodoDeleted),
+}
+
+// Gets told about every [[TodoEvent]]; implementations live in infra (logging, webhooks, queues..)
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait Subscriber {
+    async fn notify(&self, event: &TodoEvent);
+}
+
+/// Fans events out to in-process streams, e.g. for pushing live updates to clients
+#[cfg(feature = "services")]
+#[derive(Clone)]
+pub struct Broadcaster {
+    senders: Arc<Mutex<Vec<UnboundedSender<TodoEvent>>>>,
+}
+
+#[cfg(feature = "services")]
+pub fn broadcaster() -> Broadcaster {
+    Broadcaster {
+        senders: Arc::new(Mutex::new(Vec::new())),
+    }
+}
+
+#[cfg(feature = "services")]
+impl Broadcaster {
+    /// Returns a stream of every event notified from now on
+    pub fn subscribe(&self) -> UnboundedReceiver<TodoEvent> {
+        let (sender, receiver) = unbounded();
+        self.senders
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+        receiver
+    }
+}
+
+#[cfg(feature = "services")]
+#[async_trait]
+impl Subscriber for Broadcaster {
+    // Streams that have been dropped are forgotten here
+    async fn notify(&self, event: &TodoEvent) {
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.retain(|s| s.unbounded_send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(all(test, feature = "services"))]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_broadcaster() {
+        let broadcaster = broadcaster();
+        let first = broadcaster.subscribe();
+        let second = broadcaster.subscribe();
+        drop(second);
+        let event = TodoEvent::Deleted(TodoDeleted { id: TodoId(1) });
+        block_on(broadcaster.notify(&event));
+        assert_eq!(1, broadcaster.senders.lock().unwrap().len());
+        drop(broadcaster);
+        assert_eq!(vec![event], block_on(first.collect::<Vec<_>>()));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::todo::TodoId;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Beyond this many tracked todos, those not changed recently are forgotten
+static PRUNE_ABOVE: usize = 1024;
+
+/// At most `max_changes` to the same todo within any `per`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct FloodLimit {
+    pub max_changes: usize,
+    pub per: Duration,
+}
+
+/// Why a change was turned down
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Flooded {
+    /// When the todo can be changed again
+    pub retry_after: Duration,
+    /// Whether this is the first change turned down since the todo was last changed, so that
+    /// storms can be reported once rather than for every change
+    pub first: bool,
+}
+
+/// Keeps pathological clients from changing the same todo over and over, which would otherwise
+/// flood everything downstream of the service (subscribers, webhooks, the audit log..) with
+/// events. Clones share what they've seen.
+#[derive(Clone)]
+pub struct FloodGuard {
+    limit: FloodLimit,
+    recent: Arc<Mutex<HashMap<TodoId, Recent>>>,
+}
+
+#[derive(Default)]
+struct Recent {
+    // Oldest first, within the limit's window
+    changes: VecDeque<Instant>,
+    flooded: bool,
+}
+
+pub fn guard(limit: FloodLimit) -> FloodGuard {
+    FloodGuard {
+        limit,
+        recent: Arc::new(Mutex::new(HashMap::new())),
+    }
+}
+
+impl FloodGuard {
+    pub fn limit(&self) -> FloodLimit {
+        self.limit
+    }
+
+    /// Counts a change to the todo at `now`, unless it would be one too many
+    pub fn check(&self, todo_id: &TodoId, now: Instant) -> Result<(), Flooded> {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() > PRUNE_ABOVE {
+            recent.retain(|_, r| {
+                r.changes
+                    .back()
+                    .is_some_and(|last| now.duration_since(*last) < self.limit.per)
+            });
+        }
+        let todo_recent = recent.entry(*todo_id).or_default();
+        while let Some(oldest) = todo_recent.changes.front() {
+            if now.duration_since(*oldest) < self.limit.per {
+                break;
+            }
+            todo_recent.changes.pop_front();
+        }
+        match todo_recent.changes.front() {
+            Some(oldest) if todo_recent.changes.len() >= self.limit.max_changes => {
+                let first = !todo_recent.flooded;
+                todo_recent.flooded = true;
+                Err(Flooded {
+                    retry_after: self.limit.per - now.duration_since(*oldest),
+                    first,
+                })
+            }
+            _ => {
+                todo_recent.changes.push_back(now);
+                todo_recent.flooded = false;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let flood_guard = guard(FloodLimit {
+            max_changes: 2,
+            per: Duration::from_secs(1),
+        });
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        assert!(flood_guard.check(&TodoId(1), at(0)).is_ok());
+        assert!(flood_guard.check(&TodoId(1), at(100)).is_ok());
+        // Other todos are counted separately
+        assert!(flood_guard.check(&TodoId(2), at(100)).is_ok());
+        assert_eq!(
+            Err(Flooded {
+                retry_after: Duration::from_millis(800),
+                first: true
+            }),
+            flood_guard.check(&TodoId(1), at(200))
+        );
+        assert_eq!(
+            Err(Flooded {
+                retry_after: Duration::from_millis(700),
+                first: false
+            }),
+            flood_guard.check(&TodoId(1), at(300))
+        );
+        assert!(flood_guard.check(&TodoId(1), at(1000)).is_ok());
+        assert!(flood_guard.check(&TodoId(1), at(1050)).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::todo::*;
+
+#[cfg(feature = "services")]
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+/// One mutation of a [[Todo]]: `old` is empty for creations, and `new` for deletions. Mutations
+/// that were turned down are recorded too, with `new` being what was asked for.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TodoChange {
+    pub todo_id: TodoId,
+    pub old: Option<Todo>,
+    pub new: Option<Todo>,
+    pub at: DateTime<Utc>,
+    /// Who made the change, when known
+    pub actor: Option<String>,
+    /// The request that made the change, when known
+    pub request_id: Option<String>,
+    /// Why the change wasn't made, if it wasn't
+    pub rejected: Option<String>,
+}
+
+/// A [[TodoChange]] with its position in the audit log, which starts at 1 and never changes
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SequencedChange {
+    pub seq: u64,
+    pub change: TodoChange,
+}
+
+/// Where the change being made comes from
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct ChangeOrigin {
+    pub actor: Option<String>,
+    pub request_id: Option<String>,
+}
+
+// Where the service records every [[TodoChange]]; implementations live in infra
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait AuditSink {
+    async fn record(&self, change: &TodoChange);
+}
+
+// Changes by todo, for the history of each
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait TodoHistoryRepo: AuditSink {
+    /// Oldest first; kept after the todo is deleted
+    async fn history(&self, todo_id: &TodoId) -> Vec<TodoChange>;
+}
+
+// Every change to every todo, in the order they were made. Backends may only keep the latest.
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait AuditLog: AuditSink {
+    /// Oldest first, from `since` onwards when given
+    async fn since(&self, since: Option<DateTime<Utc>>) -> Result<Vec<TodoChange>, AuditLogErr>;
+
+    /// Oldest first, those after position `seq`; 0 gets every change still kept. Fails when
+    /// changes after `seq` have been dropped, rather than silently skipping them.
+    async fn after(&self, seq: u64) -> Result<Vec<SequencedChange>, AuditLogErr>;
+}
+
+#[derive(Error, Debug)]
+pub enum AuditLogErr {
+    #[error("Could not read the audit log: {reason}")]
+    Unreadable { reason: String },
+    #[error("Changes before [{oldest}] are no longer kept")]
+    Truncated { oldest: u64 },
+}
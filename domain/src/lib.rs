@@ -1,7 +1,33 @@
-#![feature(async_await)]
-
+#[cfg(feature = "services")]
 pub mod services {
+    pub mod history_service;
+    pub mod list_service;
+    pub mod recurrence_service;
+    pub mod reminder_service;
+    pub mod rule_service;
+    pub mod share_service;
+    pub mod todo_service;
+}
+
+#[cfg(feature = "test-support")]
+pub mod mocks {
+    pub mod todo_repo;
     pub mod todo_service;
 }
 
+#[cfg(feature = "services")]
+pub mod event_queue;
+pub mod events;
+#[cfg(feature = "services")]
+pub mod flood;
+pub mod history;
+pub mod list;
+pub mod metrics;
+pub mod recurrence;
+pub mod reminder;
+pub mod rule;
+pub mod share;
+pub mod template;
 pub mod todo;
+#[cfg(feature = "services")]
+pub mod validation;
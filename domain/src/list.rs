@@ -0,0 +1,45 @@
+#[cfg(feature = "services")]
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[derive(PartialEq, Eq, Ord, PartialOrd, Debug, Copy, Clone, Hash)]
+pub struct ListId(pub u64);
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ListData {
+    pub name: String,
+}
+
+impl ListData {
+    /// Returns why the list can't be created
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            Err("name must not be empty".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A named collection of [[Todo]]s, which point to it with their `list_id`
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct List {
+    pub id: ListId,
+    pub name: String,
+}
+
+// The algebra for a [[List]] repository, dealing w/ persistence
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait ListRepo {
+    async fn create(&self, list_data: &ListData) -> List;
+    async fn get(&self, list_id: &ListId) -> Result<List, ListRepoErr>;
+    async fn list(&self) -> Vec<List>;
+    async fn delete(&self, list_id: &ListId) -> Result<(), ListRepoErr>;
+}
+
+#[derive(Error, Debug)]
+pub enum ListRepoErr {
+    #[error("No such list: [{0:?}]")]
+    NotFound(ListId),
+}
@@ -0,0 +1,11 @@
+use std::time::Duration;
+
+/// Labels on a measurement, e.g. `[("method", "GET"), ("status", "200")]`
+pub type Labels<'a> = [(&'a str, &'a str)];
+
+// Where counters and timings go; implementations live in infra (Prometheus, StatsD, OTLP..).
+// Names carry no unit or suffix, as each backend adds its own, e.g. `_total` or `_seconds`.
+pub trait Metrics {
+    fn count(&self, name: &str, labels: &Labels, value: u64);
+    fn time(&self, name: &str, labels: &Labels, duration: Duration);
+}
@@ -0,0 +1,272 @@
+use crate::todo::*;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Keeps todos in memory like a real repo would, and can be told to fail, for tests of code that
+/// uses a [[TodoRepo]]. Clones share their todos, failures and counts, so a test can keep one
+/// while the code under test uses another.
+#[derive(Clone, Default)]
+pub struct MockTodoRepo {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    todos: BTreeMap<TodoId, Todo>,
+    last_id: u128,
+    last_position: u64,
+    // Taken, oldest first, by the calls that can fail
+    failures: VecDeque<TodoRepoErr>,
+    calls: HashMap<&'static str, usize>,
+}
+
+pub fn new() -> MockTodoRepo {
+    MockTodoRepo::default()
+}
+
+impl MockTodoRepo {
+    /// Starts off with the given todos; new ones get ids and positions after theirs
+    pub fn with_todos(self, todos: Vec<Todo>) -> MockTodoRepo {
+        {
+            let mut state = self.lock();
+            for todo in todos {
+                state.last_id = state.last_id.max(todo.id.0);
+                state.last_position = state.last_position.max(todo.position);
+                state.todos.insert(todo.id, todo);
+            }
+        }
+        self
+    }
+
+    /// Fails the next call with the given error, without touching any todos; queued errors are
+    /// used up in order
+    pub fn fail_next(&self, e: TodoRepoErr) {
+        self.lock().failures.push_back(e);
+    }
+
+    /// How many times the method with the given name has been called, e.g. "get"
+    pub fn calls(&self, method: &str) -> usize {
+        self.lock().calls.get(method).copied().unwrap_or(0)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn call(&self, method: &'static str) -> MutexGuard<'_, State> {
+        let mut state = self.lock();
+        *state.calls.entry(method).or_insert(0) += 1;
+        state
+    }
+}
+
+#[async_trait]
+impl TodoRepo for MockTodoRepo {
+    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
+        let mut state = self.call("create");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(e);
+        }
+        state.last_id += 1;
+        state.last_position += 1;
+        let todo = Todo {
+            id: TodoId(state.last_id),
+            task: todo_data.task.clone(),
+            done: todo_data.done,
+            version: 1,
+            list_id: todo_data.list_id,
+            position: state.last_position,
+        };
+        state.todos.insert(todo.id, todo.clone());
+        Ok(todo)
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+        let mut state = self.call("get");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(e);
+        }
+        state
+            .todos
+            .get(todo_id)
+            .cloned()
+            .ok_or(TodoRepoErr::NotFound(*todo_id))
+    }
+
+    async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+        let mut state = self.call("list");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(e);
+        }
+        Ok(state.todos.values().cloned().collect())
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoRepoErr> {
+        let mut state = self.call("delete");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(e);
+        }
+        match state.todos.remove(todo_id) {
+            Some(_) => Ok(()),
+            None => Err(TodoRepoErr::NotFound(*todo_id)),
+        }
+    }
+
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Todo, TodoRepoUpdateErr> {
+        let mut state = self.call("update");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(TodoRepoUpdateErr::Failed(e));
+        }
+        let existing = match state.todos.get_mut(&todo.id) {
+            Some(existing) => existing,
+            None => return Err(TodoRepoUpdateErr::NotFound(todo.id)),
+        };
+        if expected_version.is_some_and(|v| v != existing.version) {
+            return Err(TodoRepoUpdateErr::VersionConflict(todo.id));
+        }
+        *existing = Todo {
+            version: existing.version + 1,
+            position: existing.position,
+            ..todo.clone()
+        };
+        Ok(existing.clone())
+    }
+
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+        let mut state = self.call("complete");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(e);
+        }
+        let ids: Vec<TodoId> = match selection {
+            TodoSelection::Ids(ids) => ids.clone(),
+            TodoSelection::List(list_id) => state
+                .todos
+                .values()
+                .filter(|todo| todo.list_id == Some(*list_id))
+                .map(|todo| todo.id)
+                .collect(),
+        };
+        let mut completed = Completed::default();
+        for id in ids {
+            match state.todos.get_mut(&id) {
+                Some(todo) if todo.done => {}
+                Some(todo) => {
+                    todo.done = true;
+                    todo.version += 1;
+                    completed.completed.push(todo.clone());
+                }
+                None => completed.not_found.push(id),
+            }
+        }
+        Ok(completed)
+    }
+
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+        let mut state = self.call("reorder");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(e);
+        }
+        if let Some(missing) = ids.iter().find(|id| !state.todos.contains_key(id)) {
+            return Err(TodoRepoErr::NotFound(*missing));
+        }
+        let mut rest: Vec<&Todo> = state
+            .todos
+            .values()
+            .filter(|todo| !ids.contains(&todo.id))
+            .collect();
+        rest.sort_by_key(|todo| todo.position);
+        let mut order: Vec<TodoId> = Vec::with_capacity(state.todos.len());
+        for id in ids.iter().copied().chain(rest.iter().map(|todo| todo.id)) {
+            if !order.contains(&id) {
+                order.push(id);
+            }
+        }
+        let mut reordered = Vec::with_capacity(order.len());
+        for (i, id) in order.into_iter().enumerate() {
+            if let Some(todo) = state.todos.get_mut(&id) {
+                let position = i as u64 + 1;
+                if todo.position != position {
+                    todo.position = position;
+                    todo.version += 1;
+                }
+                reordered.push(todo.clone());
+            }
+        }
+        Ok(reordered)
+    }
+
+    async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+        let mut state = self.call("find_by_task");
+        if let Some(e) = state.failures.pop_front() {
+            return Err(e);
+        }
+        Ok(state
+            .todos
+            .values()
+            .filter(|todo| same_task(&todo.task, task))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Work runs straight against the mock, so it isn't isolated from other calls nor rolled back when
+/// it fails
+#[async_trait]
+impl TodoUnitOfWork for MockTodoRepo {
+    type Tx = MockTodoRepo;
+
+    async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+    where
+        T: Send,
+        E: Send,
+        F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+    {
+        drop(self.call("transact"));
+        work(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::repo_tests;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_contract() {
+        block_on(repo_tests::run(new));
+    }
+
+    #[test]
+    fn test_fail_next() {
+        let repo = new().with_todos(vec![Todo {
+            id: TodoId(7),
+            task: "Make the bed".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
+        }]);
+        repo.fail_next(TodoRepoErr::Timeout);
+        match block_on(repo.get(&TodoId(7))) {
+            Err(TodoRepoErr::Timeout) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert!(block_on(repo.get(&TodoId(7))).is_ok());
+        assert_eq!(2, repo.calls("get"));
+        assert_eq!(0, repo.calls("delete"));
+        let created = block_on(repo.create(&TodoData {
+            task: "Do the dishes".to_string(),
+            done: false,
+            list_id: None,
+        }))
+        .unwrap();
+        assert_eq!((TodoId(8), 2), (created.id, created.position));
+    }
+}
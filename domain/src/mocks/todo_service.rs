@@ -0,0 +1,47 @@
+use crate::mocks::todo_repo::MockTodoRepo;
+use crate::services::todo_service::{self, TodoServiceImpl};
+
+/// The real service over a [[MockTodoRepo]], so that validation, events and error mapping are as
+/// they'd be in the app, and failures queued on the repo come out as the service's own errors
+pub type MockTodoService = TodoServiceImpl<MockTodoRepo>;
+
+pub fn new(repo: MockTodoRepo) -> MockTodoService {
+    todo_service::new(repo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::todo_repo;
+    use crate::services::todo_service::{TodoService, TodoServiceDataErr, TodoServiceLookupErr};
+    use crate::todo::{TodoData, TodoId, TodoRepoErr};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_repo_failures() {
+        let repo = todo_repo::new();
+        let service = new(repo.clone());
+        repo.fail_next(TodoRepoErr::ConnectionError("refused".to_string()));
+        match block_on(service.get(&TodoId(1))) {
+            Err(TodoServiceLookupErr::ConnectionError(reason)) => assert_eq!("refused", reason),
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert_eq!(1, repo.calls("get"));
+        repo.fail_next(TodoRepoErr::Timeout);
+        assert!(matches!(
+            block_on(service.list()),
+            Err(TodoServiceLookupErr::Timeout)
+        ));
+        repo.fail_next(TodoRepoErr::Timeout);
+        let todo_data = TodoData {
+            task: "Make the bed".to_string(),
+            done: false,
+            list_id: None,
+        };
+        match block_on(service.create(&todo_data)) {
+            Err(TodoServiceDataErr::Failed(TodoServiceLookupErr::Timeout)) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert!(block_on(service.list()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::todo::*;
+
+#[cfg(feature = "services")]
+use async_trait::async_trait;
+use chrono::{Datelike, Duration, NaiveDate};
+// So that e.g. the models crate needn't depend on chrono for it
+pub use chrono::Weekday;
+
+/// How a [[Todo]] comes back once done
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    /// On the given days of the week, like cron's day-of-week field
+    Weekdays(Vec<Weekday>),
+}
+
+impl Recurrence {
+    /// Returns why the todo can't recur like this
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Recurrence::Weekdays(days) if days.is_empty() => {
+                Err("weekdays must repeat on at least one day".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The day the next occurrence is due, for a todo done on `done_on`
+    pub fn next_after(&self, done_on: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => done_on + Duration::days(1),
+            Recurrence::Weekly => done_on + Duration::days(7),
+            Recurrence::Weekdays(days) => (1..=7)
+                .map(|n| done_on + Duration::days(n))
+                .find(|day| days.contains(&day.weekday()))
+                .unwrap_or(done_on + Duration::days(7)),
+        }
+    }
+}
+
+/// The next occurrence of a recurring todo, waiting to be created as a new todo
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Occurrence {
+    /// The todo that was done
+    pub after: TodoId,
+    pub due: NaiveDate,
+    pub todo_data: TodoData,
+    /// For the new todo, so that it recurs in turn
+    pub recurrence: Recurrence,
+}
+
+// The algebra for a repository of recurrences and the occurrences they schedule
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait RecurrenceRepo {
+    /// Replaces any the todo already has
+    async fn set(&self, todo_id: &TodoId, recurrence: &Recurrence);
+    async fn get(&self, todo_id: &TodoId) -> Option<Recurrence>;
+    /// Returns whether the todo had one
+    async fn remove(&self, todo_id: &TodoId) -> bool;
+    /// Replaces any already scheduled after the same todo
+    async fn schedule(&self, occurrence: &Occurrence);
+    async fn unschedule(&self, after: &TodoId);
+    /// Removes and returns the occurrences due on or before `today`
+    async fn take_due(&self, today: NaiveDate) -> Vec<Occurrence>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_after() {
+        // A Wednesday
+        let done_on = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        assert_eq!(day(1), Recurrence::Daily.next_after(done_on));
+        assert_eq!(day(7), Recurrence::Weekly.next_after(done_on));
+        let weekdays = Recurrence::Weekdays(vec![Weekday::Mon, Weekday::Wed]);
+        assert_eq!(day(5), weekdays.next_after(done_on));
+        let same_day = Recurrence::Weekdays(vec![Weekday::Wed]);
+        assert_eq!(day(7), same_day.next_after(done_on));
+        assert!(Recurrence::Weekdays(Vec::new()).validate().is_err());
+    }
+}
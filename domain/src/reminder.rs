@@ -0,0 +1,85 @@
+use crate::todo::*;
+
+#[cfg(feature = "services")]
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+
+/// Tells whoever is interested that a [[Todo]] needs doing
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Reminder {
+    pub todo_id: TodoId,
+    pub remind_at: DateTime<Utc>,
+}
+
+/// Which due reminders are sent first, in the order they go
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Priority {
+    /// Due for longer than dispatching should take, e.g. having been held back or missed while down
+    Overdue,
+    Due,
+}
+
+impl Reminder {
+    pub fn priority(&self, now: DateTime<Utc>, overdue_after: Duration) -> Priority {
+        if now - self.remind_at > overdue_after {
+            Priority::Overdue
+        } else {
+            Priority::Due
+        }
+    }
+}
+
+/// Parses an RFC 3339 time to remind at, returning why it can't be used
+pub fn parse_remind_at(remind_at: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(remind_at)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("remind_at must be an RFC 3339 time: {}", e))
+}
+
+// Delivers due reminders; implementations live in infra (logging, webhooks, email..)
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait Notifier {
+    /// Returns why the reminder couldn't be delivered
+    async fn notify(&self, reminder: &Reminder, todo: &Todo) -> Result<(), String>;
+}
+
+// The algebra for a [[Reminder]] repository, dealing w/ persistence
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait ReminderRepo {
+    /// Replaces any the todo already has
+    async fn set(&self, reminder: &Reminder);
+    async fn get(&self, todo_id: &TodoId) -> Option<Reminder>;
+    /// Returns whether the todo had one
+    async fn remove(&self, todo_id: &TodoId) -> bool;
+    /// Soonest first
+    async fn list(&self) -> Vec<Reminder>;
+    /// Removes and returns the reminders due at or before `now`, soonest first
+    async fn take_due(&self, now: DateTime<Utc>) -> Vec<Reminder>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority() {
+        let now = Utc::now();
+        let reminder = |ago| Reminder {
+            todo_id: TodoId(1),
+            remind_at: now - Duration::seconds(ago),
+        };
+        let overdue_after = Duration::seconds(10);
+        assert_eq!(Priority::Overdue, reminder(11).priority(now, overdue_after));
+        assert_eq!(Priority::Due, reminder(10).priority(now, overdue_after));
+        assert!(Priority::Overdue < Priority::Due);
+    }
+
+    #[test]
+    fn test_parse_remind_at() {
+        let parsed = parse_remind_at("2024-01-31T12:00:00+01:00").unwrap();
+        assert_eq!("2024-01-31T11:00:00+00:00", parsed.to_rfc3339());
+        assert!(parse_remind_at("tomorrow").is_err());
+    }
+}
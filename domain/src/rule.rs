@@ -0,0 +1,226 @@
+use crate::events::*;
+use crate::template;
+use crate::template::{Sequences, TemplateContext, TemplateErr};
+use crate::todo::*;
+
+#[cfg(feature = "services")]
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use thiserror::Error;
+
+/// Variables that action templates can use on top of the built-in placeholders
+pub static TEMPLATE_VARS: [&str; 2] = ["id", "task"];
+
+#[derive(PartialEq, Eq, Ord, PartialOrd, Debug, Copy, Clone, Hash)]
+pub struct RuleId(pub u64);
+
+/// The kind of [[TodoEvent]] a rule reacts to
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Trigger {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// When a rule fires. Deleted events carry no task, so `task_contains` never matches them.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Condition {
+    pub event: Trigger,
+    /// Case-insensitive
+    pub task_contains: Option<String>,
+}
+
+/// What a rule does once it fires
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Action {
+    /// The task is a template; `{{id}}` and `{{task}}` are those of the triggering todo
+    CreateTodo { task: String },
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct RuleData {
+    pub name: String,
+    pub when: Condition,
+    pub then: Action,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Rule {
+    pub id: RuleId,
+    pub name: String,
+    pub when: Condition,
+    pub then: Action,
+}
+
+impl RuleData {
+    /// Returns why the rule can't be created
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if self.when.task_contains.as_deref() == Some("") {
+            return Err("task_contains must not be empty when given".to_string());
+        }
+        match &self.then {
+            Action::CreateTodo { task } if task.is_empty() => {
+                Err("the task to create must not be empty".to_string())
+            }
+            action => action
+                .validate()
+                .map_err(|e| format!("the task to create has an {}", e)),
+        }
+    }
+}
+
+impl Action {
+    pub fn validate(&self) -> Result<(), TemplateErr> {
+        match self {
+            Action::CreateTodo { task } => template::parse(task, &TEMPLATE_VARS).map(|_| ()),
+        }
+    }
+}
+
+impl Rule {
+    /// The todo to create in reaction to the event, if the rule fires for it. It goes in the same
+    /// list as the todo that triggered it.
+    pub fn follow_up(
+        &self,
+        event: &TodoEvent,
+        today: NaiveDate,
+        sequences: &Sequences,
+    ) -> Option<TodoData> {
+        let (id, task, list_id) = match (event, self.when.event) {
+            (TodoEvent::Created(e), Trigger::Created) => {
+                (e.todo.id, Some(e.todo.task.as_str()), e.todo.list_id)
+            }
+            (TodoEvent::Updated(e), Trigger::Updated) => {
+                (e.todo.id, Some(e.todo.task.as_str()), e.todo.list_id)
+            }
+            (TodoEvent::Deleted(e), Trigger::Deleted) => (e.id, None, None),
+            _ => return None,
+        };
+        let matches = match (&self.when.task_contains, task) {
+            (None, _) => true,
+            (Some(needle), Some(task)) => task.to_lowercase().contains(&needle.to_lowercase()),
+            (Some(_), None) => false,
+        };
+        if !matches {
+            return None;
+        }
+        match &self.then {
+            Action::CreateTodo { task: template } => {
+                let vars = [
+                    ("id", id.0.to_string()),
+                    ("task", task.unwrap_or_default().to_string()),
+                ];
+                let context = TemplateContext {
+                    today,
+                    vars: &vars,
+                    sequences,
+                    seq_scope: &format!("rule-{}", self.id.0),
+                };
+                // Templates are validated when rules are created
+                let template = template::parse(template, &TEMPLATE_VARS).ok()?;
+                Some(TodoData {
+                    task: template.expand(&context),
+                    done: false,
+                    list_id,
+                })
+            }
+        }
+    }
+}
+
+// The algebra for a [[Rule]] repository, dealing w/ persistence
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait RuleRepo {
+    async fn create(&self, rule_data: &RuleData) -> Rule;
+    async fn list(&self) -> Vec<Rule>;
+    async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleRepoErr>;
+}
+
+#[derive(Error, Debug)]
+pub enum RuleRepoErr {
+    #[error("No such rule: [{0:?}]")]
+    NotFound(RuleId),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(event: Trigger, task_contains: Option<&str>) -> Rule {
+        Rule {
+            id: RuleId(1),
+            name: "follow up".to_string(),
+            when: Condition {
+                event,
+                task_contains: task_contains.map(|s| s.to_string()),
+            },
+            then: Action::CreateTodo {
+                task: "Follow up #{{seq}} on {{task}} (#{{id}}, {{date}})".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_follow_up() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let sequences = Sequences::default();
+        let updated = TodoEvent::Updated(TodoUpdated {
+            todo: Todo {
+                id: TodoId(3),
+                task: "Fix Bug in parser".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 3,
+            },
+        });
+        assert_eq!(
+            Some(TodoData {
+                task: "Follow up #1 on Fix Bug in parser (#3, 2024-01-31)".to_string(),
+                done: false,
+                list_id: None,
+            }),
+            rule(Trigger::Updated, Some("bug")).follow_up(&updated, today, &sequences)
+        );
+        assert_eq!(
+            None,
+            rule(Trigger::Updated, Some("docs")).follow_up(&updated, today, &sequences)
+        );
+        assert_eq!(
+            None,
+            rule(Trigger::Created, None).follow_up(&updated, today, &sequences)
+        );
+
+        let deleted = TodoEvent::Deleted(TodoDeleted { id: TodoId(3) });
+        assert_eq!(
+            None,
+            rule(Trigger::Deleted, Some("bug")).follow_up(&deleted, today, &sequences)
+        );
+        assert_eq!(
+            Some(TodoData {
+                task: "Follow up #2 on  (#3, 2024-01-31)".to_string(),
+                done: false,
+                list_id: None,
+            }),
+            rule(Trigger::Deleted, None).follow_up(&deleted, today, &sequences)
+        );
+    }
+
+    #[test]
+    fn test_validate_action() {
+        assert!(rule(Trigger::Created, None).then.validate().is_ok());
+        let unknown = Action::CreateTodo {
+            task: "Follow up on {{title}}".to_string(),
+        };
+        assert_eq!(
+            Err(TemplateErr::Unknown {
+                placeholder: "title".to_string()
+            }),
+            unknown.validate()
+        );
+    }
+}
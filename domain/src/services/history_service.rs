@@ -0,0 +1,148 @@
+use crate::history::*;
+use crate::todo::*;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[async_trait]
+pub trait HistoryService {
+    /// Every change to the todo, oldest first, including its deletion
+    async fn history(&self, todo_id: &TodoId) -> Result<Vec<TodoChange>, HistoryServiceLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct HistoryServiceImpl<A: TodoRepo + Sync, B: TodoHistoryRepo + Sync> {
+    todo_repo: A,
+    history_repo: B,
+}
+
+pub fn new<A: TodoRepo + Sync, B: TodoHistoryRepo + Sync>(
+    todo_repo: A,
+    history_repo: B,
+) -> HistoryServiceImpl<A, B> {
+    HistoryServiceImpl {
+        todo_repo,
+        history_repo,
+    }
+}
+
+#[async_trait]
+impl<A: TodoRepo + Sync, B: TodoHistoryRepo + Sync> HistoryService for HistoryServiceImpl<A, B> {
+    async fn history(&self, todo_id: &TodoId) -> Result<Vec<TodoChange>, HistoryServiceLookupErr> {
+        let changes = self.history_repo.history(todo_id).await;
+        // Todos from before auditing started have no changes, but still exist
+        if changes.is_empty() && self.todo_repo.get(todo_id).await.is_err() {
+            return Err(HistoryServiceLookupErr::NotFound(*todo_id));
+        }
+        Ok(changes)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HistoryServiceLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NotFound(TodoId),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use futures::executor::block_on;
+
+    static EXISTING_TODO_ID: TodoId = TodoId(1);
+    static DELETED_TODO_ID: TodoId = TodoId(2);
+
+    #[test]
+    fn test_history() {
+        let service = new(MockTodoRepo, MockHistoryRepo);
+        match block_on(service.history(&EXISTING_TODO_ID)) {
+            Ok(changes) => assert!(changes.is_empty()),
+            Err(_) => panic!("No history for an existing todo"),
+        }
+        match block_on(service.history(&DELETED_TODO_ID)) {
+            Ok(changes) => assert_eq!(1, changes.len()),
+            Err(_) => panic!("No history for a deleted todo"),
+        }
+        match block_on(service.history(&TodoId(3))) {
+            Err(HistoryServiceLookupErr::NotFound(id)) => assert_eq!(TodoId(3), id),
+            Ok(_) => panic!("History for a todo that never existed"),
+        }
+    }
+
+    struct MockTodoRepo;
+
+    #[async_trait]
+    impl TodoRepo for MockTodoRepo {
+        async fn create(&self, _: &TodoData) -> Result<Todo, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+            if *todo_id == EXISTING_TODO_ID {
+                Ok(Todo {
+                    id: *todo_id,
+                    task: "Make the bed".to_string(),
+                    done: false,
+                    version: 1,
+                    list_id: None,
+                    position: 0,
+                })
+            } else {
+                Err(TodoRepoErr::NotFound(*todo_id))
+            }
+        }
+
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn update(&self, _: &Todo, _: Option<u64>) -> Result<Todo, TodoRepoUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn complete(&self, _: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn reorder(&self, _: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn find_by_task(&self, _: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+    }
+
+    struct MockHistoryRepo;
+
+    #[async_trait]
+    impl AuditSink for MockHistoryRepo {
+        async fn record(&self, _: &TodoChange) {
+            unimplemented!()
+        }
+    }
+
+    #[async_trait]
+    impl TodoHistoryRepo for MockHistoryRepo {
+        async fn history(&self, todo_id: &TodoId) -> Vec<TodoChange> {
+            if *todo_id == DELETED_TODO_ID {
+                vec![TodoChange {
+                    todo_id: *todo_id,
+                    old: None,
+                    new: None,
+                    at: Utc::now(),
+                    actor: None,
+                    request_id: None,
+                    rejected: None,
+                }]
+            } else {
+                Vec::new()
+            }
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use crate::list::*;
+use crate::todo::*;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[async_trait]
+pub trait ListService {
+    async fn create(&self, list_data: &ListData) -> Result<List, ListServiceDataErr>;
+    async fn get(&self, list_id: &ListId) -> Result<List, ListServiceLookupErr>;
+    async fn list(&self) -> Vec<List>;
+    /// Fails while any todo is still in the list, rather than orphaning it
+    async fn delete(&self, list_id: &ListId) -> Result<(), ListServiceDeleteErr>;
+    /// The todos in the list, ordered by id
+    async fn todos(&self, list_id: &ListId) -> Result<Vec<Todo>, ListServiceLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct ListServiceImpl<A: ListRepo + Sync, B: TodoRepo + Sync> {
+    list_repo: A,
+    todo_repo: B,
+}
+
+pub fn new<A: ListRepo + Sync, B: TodoRepo + Sync>(
+    list_repo: A,
+    todo_repo: B,
+) -> ListServiceImpl<A, B> {
+    ListServiceImpl {
+        list_repo,
+        todo_repo,
+    }
+}
+
+#[async_trait]
+impl<A, B> ListService for ListServiceImpl<A, B>
+where
+    A: ListRepo + Clone + Send + Sync + 'static,
+    B: TodoRepo + TodoUnitOfWork + Sync,
+{
+    async fn create(&self, list_data: &ListData) -> Result<List, ListServiceDataErr> {
+        list_data
+            .validate()
+            .map_err(|reason| ListServiceDataErr::InvalidData { reason })?;
+        Ok(self.list_repo.create(list_data).await)
+    }
+
+    async fn get(&self, list_id: &ListId) -> Result<List, ListServiceLookupErr> {
+        Ok(self.list_repo.get(list_id).await?)
+    }
+
+    async fn list(&self) -> Vec<List> {
+        self.list_repo.list().await
+    }
+
+    async fn delete(&self, list_id: &ListId) -> Result<(), ListServiceDeleteErr> {
+        let list_id = *list_id;
+        let list_repo = self.list_repo.clone();
+        // Under one unit of work, so that no todo can be put in the list between the check and the
+        // delete; the todo service checks the list under one too when it does
+        self.todo_repo
+            .transact(move |tx| {
+                Box::pin(async move {
+                    list_repo.get(&list_id).await?;
+                    let todos = tx.list().await.map_err(ListServiceDeleteErr::TodosFailed)?;
+                    if todos.iter().any(|todo| todo.list_id == Some(list_id)) {
+                        return Err(ListServiceDeleteErr::NotEmpty(list_id));
+                    }
+                    Ok(list_repo.delete(&list_id).await?)
+                })
+            })
+            .await
+    }
+
+    async fn todos(&self, list_id: &ListId) -> Result<Vec<Todo>, ListServiceLookupErr> {
+        self.list_repo.get(list_id).await?;
+        let mut todos = self
+            .todo_repo
+            .list()
+            .await
+            .map_err(ListServiceLookupErr::TodosFailed)?;
+        todos.retain(|todo| todo.list_id == Some(*list_id));
+        Ok(todos)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ListServiceLookupErr {
+    #[error("No such list: [{0:?}]")]
+    NotFound(ListId),
+    // Reading the list's todos failed
+    #[error(transparent)]
+    TodosFailed(TodoRepoErr),
+}
+
+#[derive(Error, Debug)]
+pub enum ListServiceDataErr {
+    #[error("Invalid list: {reason}")]
+    InvalidData { reason: String },
+}
+
+#[derive(Error, Debug)]
+pub enum ListServiceDeleteErr {
+    #[error("No such list: [{0:?}]")]
+    NotFound(ListId),
+    #[error("List [{0:?}] still has todos")]
+    NotEmpty(ListId),
+    #[error(transparent)]
+    TodosFailed(TodoRepoErr),
+}
+
+impl From<ListRepoErr> for ListServiceLookupErr {
+    fn from(repo_err: ListRepoErr) -> Self {
+        match repo_err {
+            ListRepoErr::NotFound(id) => ListServiceLookupErr::NotFound(id),
+        }
+    }
+}
+
+impl From<ListRepoErr> for ListServiceDeleteErr {
+    fn from(repo_err: ListRepoErr) -> Self {
+        match repo_err {
+            ListRepoErr::NotFound(id) => ListServiceDeleteErr::NotFound(id),
+        }
+    }
+}
+
+impl From<ListServiceLookupErr> for ListServiceDeleteErr {
+    fn from(err: ListServiceLookupErr) -> Self {
+        match err {
+            ListServiceLookupErr::NotFound(id) => ListServiceDeleteErr::NotFound(id),
+            ListServiceLookupErr::TodosFailed(e) => ListServiceDeleteErr::TodosFailed(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::todo_service::{self, TodoService, TodoServiceDataErr};
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+    use futures::future::{BoxFuture, FutureExt};
+    use std::sync::{Arc, Mutex};
+
+    static CHORES: ListId = ListId(1);
+    static EMPTY: ListId = ListId(2);
+
+    #[test]
+    fn test_create_invalid() {
+        let service = new(MockListRepo, MockTodoRepo);
+        let list_data = ListData {
+            name: " ".to_string(),
+        };
+        match block_on(service.create(&list_data)) {
+            Err(ListServiceDataErr::InvalidData { reason }) => {
+                assert_eq!("name must not be empty", &reason)
+            }
+            Ok(_) => panic!("Blank name accepted"),
+        }
+    }
+
+    #[test]
+    fn test_todos() {
+        let service = new(MockListRepo, MockTodoRepo);
+        match block_on(service.todos(&CHORES)) {
+            Ok(todos) => assert_eq!(
+                vec![TodoId(1)],
+                todos.iter().map(|t| t.id).collect::<Vec<_>>()
+            ),
+            Err(_) => panic!("No todos for an existing list"),
+        }
+        match block_on(service.todos(&ListId(3))) {
+            Err(ListServiceLookupErr::NotFound(id)) => assert_eq!(ListId(3), id),
+            _ => panic!("Todos for a list that doesn't exist"),
+        }
+    }
+
+    #[test]
+    fn test_delete() {
+        let service = new(MockListRepo, MockTodoRepo);
+        match block_on(service.delete(&CHORES)) {
+            Err(ListServiceDeleteErr::NotEmpty(id)) => assert_eq!(CHORES, id),
+            _ => panic!("Deleted a list with todos in it"),
+        }
+        assert!(block_on(service.delete(&EMPTY)).is_ok());
+        match block_on(service.delete(&ListId(3))) {
+            Err(ListServiceDeleteErr::NotFound(id)) => assert_eq!(ListId(3), id),
+            _ => panic!("Deleted a list that doesn't exist"),
+        }
+    }
+
+    // A todo created in the list while it's being deleted mustn't be left in a deleted list
+    #[test]
+    fn test_delete_racing_create() {
+        let (release, held) = oneshot::channel();
+        let lists = HeldLists {
+            lists: Arc::new(Mutex::new(vec![CHORES])),
+            held: Arc::new(Mutex::new(Some(held))),
+        };
+        let todos = LockingTodoRepo::default();
+        let service = new(lists.clone(), todos.clone());
+        let todo_service = todo_service::new(todos.clone()).with_lists(Arc::new(lists));
+        // Held off from deleting the list, after finding it empty
+        let mut deleting = service.delete(&CHORES);
+        assert!(deleting.as_mut().now_or_never().is_none());
+        let todo_data = TodoData {
+            task: "Make the bed".to_string(),
+            done: false,
+            list_id: Some(CHORES),
+        };
+        // The list is still there to be checked, but the todo can't be written until the delete is
+        // done
+        let mut creating = todo_service.create(&todo_data);
+        assert!(creating.as_mut().now_or_never().is_none());
+        release.send(()).unwrap();
+        assert!(block_on(deleting).is_ok());
+        match block_on(creating) {
+            Err(TodoServiceDataErr::NoSuchList(id)) => assert_eq!(CHORES, id),
+            other => panic!("Unexpected {:?}", other.map(|saved| saved.todo)),
+        }
+        assert!(block_on(todos.list()).unwrap().is_empty());
+    }
+
+    #[derive(Clone)]
+    struct MockListRepo;
+
+    #[async_trait]
+    impl ListRepo for MockListRepo {
+        async fn create(&self, _: &ListData) -> List {
+            unimplemented!()
+        }
+
+        async fn get(&self, list_id: &ListId) -> Result<List, ListRepoErr> {
+            if *list_id == CHORES || *list_id == EMPTY {
+                Ok(List {
+                    id: *list_id,
+                    name: "Chores".to_string(),
+                })
+            } else {
+                Err(ListRepoErr::NotFound(*list_id))
+            }
+        }
+
+        async fn list(&self) -> Vec<List> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, _: &ListId) -> Result<(), ListRepoErr> {
+            Ok(())
+        }
+    }
+
+    struct MockTodoRepo;
+
+    #[async_trait]
+    impl TodoRepo for MockTodoRepo {
+        async fn create(&self, _: &TodoData) -> Result<Todo, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn get(&self, _: &TodoId) -> Result<Todo, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+            let todo = |id: u64, list_id| Todo {
+                id: TodoId(id.into()),
+                task: "Make the bed".to_string(),
+                done: false,
+                version: 1,
+                list_id,
+                position: id,
+            };
+            Ok(vec![todo(1, Some(CHORES)), todo(2, None)])
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn update(&self, _: &Todo, _: Option<u64>) -> Result<Todo, TodoRepoUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn complete(&self, _: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn reorder(&self, _: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn find_by_task(&self, _: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+    }
+
+    #[async_trait]
+    impl TodoUnitOfWork for MockTodoRepo {
+        type Tx = MockTodoRepo;
+
+        async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+        where
+            T: Send,
+            E: Send,
+            F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+        {
+            work(self).await
+        }
+    }
+
+    // Deletes wait until the held receiver is released
+    #[derive(Clone)]
+    struct HeldLists {
+        lists: Arc<Mutex<Vec<ListId>>>,
+        held: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+    }
+
+    #[async_trait]
+    impl ListRepo for HeldLists {
+        async fn create(&self, _: &ListData) -> List {
+            unimplemented!()
+        }
+
+        async fn get(&self, list_id: &ListId) -> Result<List, ListRepoErr> {
+            if self.lists.lock().unwrap().contains(list_id) {
+                Ok(List {
+                    id: *list_id,
+                    name: "Chores".to_string(),
+                })
+            } else {
+                Err(ListRepoErr::NotFound(*list_id))
+            }
+        }
+
+        async fn list(&self) -> Vec<List> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, list_id: &ListId) -> Result<(), ListRepoErr> {
+            let held = self.held.lock().unwrap().take();
+            if let Some(held) = held {
+                let _ = held.await;
+            }
+            self.lists.lock().unwrap().retain(|id| id != list_id);
+            Ok(())
+        }
+    }
+
+    // Writes wait for units of work, which hold its lock throughout, but aren't rolled back
+    #[derive(Clone, Default)]
+    struct LockingTodoRepo {
+        todos: Arc<Mutex<Vec<Todo>>>,
+        lock: Arc<futures::lock::Mutex<()>>,
+        in_unit_of_work: bool,
+    }
+
+    #[async_trait]
+    impl TodoRepo for LockingTodoRepo {
+        async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
+            let _lock = match self.in_unit_of_work {
+                true => None,
+                false => Some(self.lock.lock().await),
+            };
+            let mut todos = self.todos.lock().unwrap();
+            let todo = Todo {
+                id: TodoId(todos.len() as u128 + 1),
+                task: todo_data.task.clone(),
+                done: todo_data.done,
+                version: 1,
+                list_id: todo_data.list_id,
+                position: todos.len() as u64 + 1,
+            };
+            todos.push(todo.clone());
+            Ok(todo)
+        }
+
+        async fn get(&self, _: &TodoId) -> Result<Todo, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+            Ok(self.todos.lock().unwrap().clone())
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn update(&self, _: &Todo, _: Option<u64>) -> Result<Todo, TodoRepoUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn complete(&self, _: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn reorder(&self, _: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            let mut todos = self.todos.lock().unwrap().clone();
+            todos.retain(|todo| todo.task == task);
+            Ok(todos)
+        }
+    }
+
+    #[async_trait]
+    impl TodoUnitOfWork for LockingTodoRepo {
+        type Tx = LockingTodoRepo;
+
+        async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+        where
+            T: Send,
+            E: Send,
+            F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+        {
+            let _lock = self.lock.lock().await;
+            let tx = LockingTodoRepo {
+                in_unit_of_work: true,
+                ..self.clone()
+            };
+            work(&tx).await
+        }
+    }
+}
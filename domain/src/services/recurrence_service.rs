@@ -0,0 +1,325 @@
+use crate::events::*;
+use crate::recurrence::*;
+use crate::services::todo_service::TodoService;
+use crate::todo::*;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use thiserror::Error;
+
+#[async_trait]
+pub trait RecurrenceService {
+    /// Replaces any recurrence the todo already has
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        recurrence: &Recurrence,
+    ) -> Result<(), RecurrenceServiceSetErr>;
+    async fn get(&self, todo_id: &TodoId) -> Result<Recurrence, RecurrenceServiceLookupErr>;
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), RecurrenceServiceLookupErr>;
+    /// Schedules the next occurrence when a recurring todo is done, and cancels it when the todo
+    /// is undone again
+    async fn handle(&self, event: &TodoEvent, today: NaiveDate);
+    /// Creates a todo for every occurrence due by `today`, which recurs in turn. Occurrences that
+    /// are no longer valid, e.g. as their list has since been deleted, are dropped and returned
+    /// as errors.
+    async fn materialize(&self, today: NaiveDate) -> Vec<Result<Todo, Occurrence>>;
+}
+
+/// Creates todos through the todo service, so that they are validated and emit events like any
+/// other
+#[derive(Clone)]
+pub struct RecurrenceServiceImpl<A: TodoService + Sync, B: RecurrenceRepo + Sync> {
+    todo_service: A,
+    recurrence_repo: B,
+}
+
+pub fn new<A: TodoService + Sync, B: RecurrenceRepo + Sync>(
+    todo_service: A,
+    recurrence_repo: B,
+) -> RecurrenceServiceImpl<A, B> {
+    RecurrenceServiceImpl {
+        todo_service,
+        recurrence_repo,
+    }
+}
+
+#[async_trait]
+impl<A: TodoService + Sync, B: RecurrenceRepo + Sync> RecurrenceService
+    for RecurrenceServiceImpl<A, B>
+{
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        recurrence: &Recurrence,
+    ) -> Result<(), RecurrenceServiceSetErr> {
+        recurrence
+            .validate()
+            .map_err(|reason| RecurrenceServiceSetErr::InvalidData { reason })?;
+        self.todo_service
+            .get(todo_id)
+            .await
+            .map_err(|_| RecurrenceServiceSetErr::NoSuchTodo(*todo_id))?;
+        self.recurrence_repo.set(todo_id, recurrence).await;
+        Ok(())
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Result<Recurrence, RecurrenceServiceLookupErr> {
+        match self.recurrence_repo.get(todo_id).await {
+            Some(recurrence) => Ok(recurrence),
+            None => Err(self.lookup_err(todo_id).await),
+        }
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), RecurrenceServiceLookupErr> {
+        if self.recurrence_repo.remove(todo_id).await {
+            self.recurrence_repo.unschedule(todo_id).await;
+            Ok(())
+        } else {
+            Err(self.lookup_err(todo_id).await)
+        }
+    }
+
+    async fn handle(&self, event: &TodoEvent, today: NaiveDate) {
+        match event {
+            TodoEvent::Updated(TodoUpdated { todo }) if todo.done => {
+                if let Some(recurrence) = self.recurrence_repo.get(&todo.id).await {
+                    let occurrence = Occurrence {
+                        after: todo.id,
+                        due: recurrence.next_after(today),
+                        todo_data: TodoData {
+                            task: todo.task.clone(),
+                            done: false,
+                            list_id: todo.list_id,
+                        },
+                        recurrence,
+                    };
+                    self.recurrence_repo.schedule(&occurrence).await;
+                }
+            }
+            TodoEvent::Updated(TodoUpdated { todo }) => {
+                self.recurrence_repo.unschedule(&todo.id).await
+            }
+            // The next occurrence still comes if a done todo is cleaned up
+            TodoEvent::Deleted(TodoDeleted { id }) => {
+                self.recurrence_repo.remove(id).await;
+            }
+            TodoEvent::Created(_) => {}
+        }
+    }
+
+    async fn materialize(&self, today: NaiveDate) -> Vec<Result<Todo, Occurrence>> {
+        let mut materialized = Vec::new();
+        for occurrence in self.recurrence_repo.take_due(today).await {
+            match self.todo_service.create(&occurrence.todo_data).await {
+                Ok(saved) => {
+                    // Moves on to the new todo, so that redoing the old one doesn't schedule again
+                    self.recurrence_repo.remove(&occurrence.after).await;
+                    self.recurrence_repo
+                        .set(&saved.todo.id, &occurrence.recurrence)
+                        .await;
+                    materialized.push(Ok(saved.todo));
+                }
+                Err(_) => materialized.push(Err(occurrence)),
+            }
+        }
+        materialized
+    }
+}
+
+impl<A: TodoService + Sync, B: RecurrenceRepo + Sync> RecurrenceServiceImpl<A, B> {
+    async fn lookup_err(&self, todo_id: &TodoId) -> RecurrenceServiceLookupErr {
+        match self.todo_service.get(todo_id).await {
+            Ok(_) => RecurrenceServiceLookupErr::NotRecurring(*todo_id),
+            Err(_) => RecurrenceServiceLookupErr::NoSuchTodo(*todo_id),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RecurrenceServiceLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Todo [{0:?}] doesn't recur")]
+    NotRecurring(TodoId),
+}
+
+#[derive(Error, Debug)]
+pub enum RecurrenceServiceSetErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Invalid recurrence: {reason}")]
+    InvalidData { reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::todo_service;
+    use futures::executor::block_on;
+    use futures::future::BoxFuture;
+    use std::collections::BTreeMap;
+    use std::sync::*;
+
+    #[test]
+    fn test_recurs_once_done() {
+        let todo_repo = MockTodoRepo::default();
+        let recurrence_repo = MockRecurrenceRepo::default();
+        let service = new(todo_service::new(todo_repo.clone()), recurrence_repo);
+        let todo = block_on(todo_repo.create(&TodoData {
+            task: "Water the plants".to_string(),
+            done: false,
+            list_id: None,
+        }))
+        .unwrap();
+        match block_on(service.set(&TodoId(2), &Recurrence::Daily)) {
+            Err(RecurrenceServiceSetErr::NoSuchTodo(id)) => assert_eq!(TodoId(2), id),
+            _ => panic!("Set a recurrence on a todo that doesn't exist"),
+        }
+        assert!(block_on(service.set(&todo.id, &Recurrence::Daily)).is_ok());
+
+        let today = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let updated = |done| {
+            TodoEvent::Updated(TodoUpdated {
+                todo: Todo {
+                    done,
+                    ..todo.clone()
+                },
+            })
+        };
+        block_on(service.handle(&updated(true), today));
+        // Undoing it cancels the occurrence, and redoing it schedules it again
+        block_on(service.handle(&updated(false), today));
+        assert!(block_on(service.materialize(today.succ_opt().unwrap())).is_empty());
+        block_on(service.handle(&updated(true), today));
+        assert!(block_on(service.materialize(today)).is_empty());
+        let materialized = block_on(service.materialize(today.succ_opt().unwrap()));
+        let next = match materialized.as_slice() {
+            [Ok(next)] => next.clone(),
+            _ => panic!("Expected a single new todo"),
+        };
+        assert_eq!("Water the plants", next.task);
+        assert!(!next.done);
+        assert_eq!(
+            Ok(Recurrence::Daily),
+            block_on(service.get(&next.id)).map_err(|_| ())
+        );
+        match block_on(service.get(&todo.id)) {
+            Err(RecurrenceServiceLookupErr::NotRecurring(id)) => assert_eq!(todo.id, id),
+            _ => panic!("The done todo still recurs"),
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct MockTodoRepo {
+        todos: Arc<Mutex<BTreeMap<TodoId, Todo>>>,
+    }
+
+    #[async_trait]
+    impl TodoRepo for MockTodoRepo {
+        async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
+            let mut todos = self.todos.lock().unwrap();
+            let todo = Todo {
+                id: TodoId(todos.len() as u128 + 1),
+                task: todo_data.task.clone(),
+                done: todo_data.done,
+                version: 1,
+                list_id: todo_data.list_id,
+                position: todos.len() as u64 + 1,
+            };
+            todos.insert(todo.id, todo.clone());
+            Ok(todo)
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+            let todos = self.todos.lock().unwrap();
+            todos
+                .get(todo_id)
+                .cloned()
+                .ok_or(TodoRepoErr::NotFound(*todo_id))
+        }
+
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+            Ok(self.todos.lock().unwrap().values().cloned().collect())
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn update(&self, _: &Todo, _: Option<u64>) -> Result<Todo, TodoRepoUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn complete(&self, _: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn reorder(&self, _: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            Ok(self
+                .list()
+                .await?
+                .into_iter()
+                .filter(|todo| same_task(&todo.task, task))
+                .collect())
+        }
+    }
+
+    #[async_trait]
+    impl TodoUnitOfWork for MockTodoRepo {
+        type Tx = MockTodoRepo;
+
+        async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+        where
+            T: Send,
+            E: Send,
+            F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+        {
+            work(self).await
+        }
+    }
+
+    #[derive(Default)]
+    struct MockRecurrenceRepo {
+        recurrences: Mutex<BTreeMap<TodoId, Recurrence>>,
+        occurrences: Mutex<Vec<Occurrence>>,
+    }
+
+    #[async_trait]
+    impl RecurrenceRepo for MockRecurrenceRepo {
+        async fn set(&self, todo_id: &TodoId, recurrence: &Recurrence) {
+            let mut recurrences = self.recurrences.lock().unwrap();
+            recurrences.insert(*todo_id, recurrence.clone());
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Option<Recurrence> {
+            self.recurrences.lock().unwrap().get(todo_id).cloned()
+        }
+
+        async fn remove(&self, todo_id: &TodoId) -> bool {
+            self.recurrences.lock().unwrap().remove(todo_id).is_some()
+        }
+
+        async fn schedule(&self, occurrence: &Occurrence) {
+            let mut occurrences = self.occurrences.lock().unwrap();
+            occurrences.retain(|o| o.after != occurrence.after);
+            occurrences.push(occurrence.clone());
+        }
+
+        async fn unschedule(&self, after: &TodoId) {
+            let mut occurrences = self.occurrences.lock().unwrap();
+            occurrences.retain(|o| o.after != *after);
+        }
+
+        async fn take_due(&self, today: NaiveDate) -> Vec<Occurrence> {
+            let mut occurrences = self.occurrences.lock().unwrap();
+            let (due, later) = occurrences.drain(..).partition(|o| o.due <= today);
+            *occurrences = later;
+            due
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use crate::events::*;
+use crate::reminder::*;
+use crate::todo::*;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, StreamExt};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[async_trait]
+pub trait ReminderService {
+    /// Replaces any reminder the todo already has. Times in the past are due straight away.
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        remind_at: DateTime<Utc>,
+    ) -> Result<Reminder, ReminderServiceLookupErr>;
+    async fn get(&self, todo_id: &TodoId) -> Result<Reminder, ReminderServiceLookupErr>;
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), ReminderServiceLookupErr>;
+    /// Reminders yet to be sent, soonest first
+    async fn pending(&self) -> Vec<Reminder>;
+    /// Sends the reminders due by `now` through the notifier, overdue ones first, returning how
+    /// each went. Reminders of todos that have since been done are dropped without being sent, and
+    /// those beyond the batch size are left pending for the next dispatch.
+    async fn dispatch(&self, now: DateTime<Utc>) -> Vec<(Reminder, Result<(), String>)>;
+}
+
+#[derive(Clone)]
+pub struct ReminderServiceImpl<A: TodoRepo + Sync, B: ReminderRepo + Sync> {
+    todo_repo: A,
+    reminder_repo: B,
+    notifier: Arc<dyn Notifier + Send + Sync>,
+    policy: DispatchPolicy,
+}
+
+/// Limits on dispatching, so that a large cohort of reminders falling due at once doesn't swamp
+/// the notifier's target
+#[derive(Debug, Clone)]
+pub struct DispatchPolicy {
+    /// Most reminders sent per dispatch
+    pub batch_size: usize,
+    /// Most reminders being sent at once
+    pub concurrency: usize,
+    /// How long after being due a reminder is overdue
+    pub overdue_after: Duration,
+}
+
+impl Default for DispatchPolicy {
+    // One at a time, all in one go
+    fn default() -> Self {
+        DispatchPolicy {
+            batch_size: usize::MAX,
+            concurrency: 1,
+            overdue_after: Duration::zero(),
+        }
+    }
+}
+
+pub fn new<A: TodoRepo + Sync, B: ReminderRepo + Sync>(
+    todo_repo: A,
+    reminder_repo: B,
+    notifier: Arc<dyn Notifier + Send + Sync>,
+) -> ReminderServiceImpl<A, B> {
+    ReminderServiceImpl {
+        todo_repo,
+        reminder_repo,
+        notifier,
+        policy: DispatchPolicy::default(),
+    }
+}
+
+#[async_trait]
+impl<A: TodoRepo + Sync, B: ReminderRepo + Sync> ReminderService for ReminderServiceImpl<A, B> {
+    async fn set(
+        &self,
+        todo_id: &TodoId,
+        remind_at: DateTime<Utc>,
+    ) -> Result<Reminder, ReminderServiceLookupErr> {
+        self.todo_repo
+            .get(todo_id)
+            .await
+            .map_err(|_| ReminderServiceLookupErr::NoSuchTodo(*todo_id))?;
+        let reminder = Reminder {
+            todo_id: *todo_id,
+            remind_at,
+        };
+        self.reminder_repo.set(&reminder).await;
+        Ok(reminder)
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Result<Reminder, ReminderServiceLookupErr> {
+        match self.reminder_repo.get(todo_id).await {
+            Some(reminder) => Ok(reminder),
+            None => Err(self.lookup_err(todo_id).await),
+        }
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), ReminderServiceLookupErr> {
+        if self.reminder_repo.remove(todo_id).await {
+            Ok(())
+        } else {
+            Err(self.lookup_err(todo_id).await)
+        }
+    }
+
+    async fn pending(&self) -> Vec<Reminder> {
+        self.reminder_repo.list().await
+    }
+
+    async fn dispatch(&self, now: DateTime<Utc>) -> Vec<(Reminder, Result<(), String>)> {
+        let mut due = self.reminder_repo.take_due(now).await;
+        due.sort_by_key(|r| (r.priority(now, self.policy.overdue_after), r.remind_at));
+        let held_back = due.split_off(self.policy.batch_size.min(due.len()));
+        for reminder in held_back {
+            // Unless it has been set again meanwhile
+            if self.reminder_repo.get(&reminder.todo_id).await.is_none() {
+                self.reminder_repo.set(&reminder).await;
+            }
+        }
+        let mut sendable = Vec::new();
+        for reminder in due {
+            match self.todo_repo.get(&reminder.todo_id).await {
+                Ok(todo) if !todo.done => sendable.push((reminder, todo)),
+                _ => {}
+            }
+        }
+        // Started in order, so that overdue ones go first
+        stream::iter(sendable)
+            .map(|(reminder, todo)| async move {
+                let outcome = self.notifier.notify(&reminder, &todo).await;
+                (reminder, outcome)
+            })
+            .buffered(self.policy.concurrency.max(1))
+            .collect()
+            .await
+    }
+}
+
+impl<A: TodoRepo + Sync, B: ReminderRepo + Sync> ReminderServiceImpl<A, B> {
+    pub fn with_policy(self, policy: DispatchPolicy) -> ReminderServiceImpl<A, B> {
+        ReminderServiceImpl { policy, ..self }
+    }
+
+    async fn lookup_err(&self, todo_id: &TodoId) -> ReminderServiceLookupErr {
+        match self.todo_repo.get(todo_id).await {
+            Ok(_) => ReminderServiceLookupErr::NoReminder(*todo_id),
+            Err(_) => ReminderServiceLookupErr::NoSuchTodo(*todo_id),
+        }
+    }
+}
+
+// Forgets the reminders of deleted todos, so that they don't linger as pending
+#[async_trait]
+impl<A: TodoRepo + Send + Sync, B: ReminderRepo + Send + Sync> Subscriber
+    for ReminderServiceImpl<A, B>
+{
+    async fn notify(&self, event: &TodoEvent) {
+        if let TodoEvent::Deleted(TodoDeleted { id }) = event {
+            self.reminder_repo.remove(id).await;
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReminderServiceLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Todo [{0:?}] has no reminder")]
+    NoReminder(TodoId),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use futures::executor::block_on;
+    use std::collections::BTreeMap;
+    use std::sync::*;
+
+    static OPEN_TODO_ID: TodoId = TodoId(1);
+    static DONE_TODO_ID: TodoId = TodoId(2);
+    static OTHER_OPEN_TODO_ID: TodoId = TodoId(4);
+
+    #[test]
+    fn test_dispatch() {
+        let notifier = Arc::new(MockNotifier::default());
+        let service = new(MockTodoRepo, MockReminderRepo::default(), notifier.clone());
+        let now = Utc::now();
+        match block_on(service.set(&TodoId(3), now)) {
+            Err(ReminderServiceLookupErr::NoSuchTodo(id)) => assert_eq!(TodoId(3), id),
+            _ => panic!("Set a reminder on a todo that doesn't exist"),
+        }
+        let later = now + Duration::hours(1);
+        assert!(block_on(service.set(&OPEN_TODO_ID, later)).is_ok());
+        assert!(block_on(service.set(&DONE_TODO_ID, now)).is_ok());
+        let pending: Vec<TodoId> = block_on(service.pending())
+            .into_iter()
+            .map(|r| r.todo_id)
+            .collect();
+        assert_eq!(vec![DONE_TODO_ID, OPEN_TODO_ID], pending);
+
+        // The done todo's reminder is dropped rather than sent
+        assert!(block_on(service.dispatch(now)).is_empty());
+        let dispatched = block_on(service.dispatch(later));
+        assert_eq!(1, dispatched.len());
+        assert_eq!(OPEN_TODO_ID, dispatched[0].0.todo_id);
+        assert_eq!(vec![OPEN_TODO_ID], *notifier.notified.lock().unwrap());
+        assert!(block_on(service.pending()).is_empty());
+        match block_on(service.get(&OPEN_TODO_ID)) {
+            Err(ReminderServiceLookupErr::NoReminder(id)) => assert_eq!(OPEN_TODO_ID, id),
+            _ => panic!("The sent reminder is still there"),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_batches() {
+        let notifier = Arc::new(MockNotifier::default());
+        let service = new(MockTodoRepo, MockReminderRepo::default(), notifier.clone()).with_policy(
+            DispatchPolicy {
+                batch_size: 1,
+                concurrency: 2,
+                overdue_after: Duration::minutes(1),
+            },
+        );
+        let now = Utc::now();
+        assert!(block_on(service.set(&OPEN_TODO_ID, now)).is_ok());
+        assert!(block_on(service.set(&OTHER_OPEN_TODO_ID, now - Duration::hours(1))).is_ok());
+        // The overdue one goes first, and the other waits for the next dispatch
+        let dispatched = block_on(service.dispatch(now));
+        assert_eq!(1, dispatched.len());
+        assert_eq!(OTHER_OPEN_TODO_ID, dispatched[0].0.todo_id);
+        assert_eq!(
+            vec![OPEN_TODO_ID],
+            block_on(service.pending())
+                .into_iter()
+                .map(|r| r.todo_id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, block_on(service.dispatch(now)).len());
+        assert_eq!(
+            vec![OTHER_OPEN_TODO_ID, OPEN_TODO_ID],
+            *notifier.notified.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_forgets_deleted_todos() {
+        let service = new(
+            MockTodoRepo,
+            MockReminderRepo::default(),
+            Arc::new(MockNotifier::default()),
+        );
+        assert!(block_on(service.set(&OPEN_TODO_ID, Utc::now())).is_ok());
+        block_on(Subscriber::notify(
+            &service,
+            &TodoEvent::Deleted(TodoDeleted { id: OPEN_TODO_ID }),
+        ));
+        assert!(block_on(service.pending()).is_empty());
+    }
+
+    struct MockTodoRepo;
+
+    #[async_trait]
+    impl TodoRepo for MockTodoRepo {
+        async fn create(&self, _: &TodoData) -> Result<Todo, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+            if [OPEN_TODO_ID, DONE_TODO_ID, OTHER_OPEN_TODO_ID].contains(todo_id) {
+                Ok(Todo {
+                    id: *todo_id,
+                    task: "Water the plants".to_string(),
+                    done: *todo_id == DONE_TODO_ID,
+                    version: 1,
+                    list_id: None,
+                    position: 0,
+                })
+            } else {
+                Err(TodoRepoErr::NotFound(*todo_id))
+            }
+        }
+
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn update(&self, _: &Todo, _: Option<u64>) -> Result<Todo, TodoRepoUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn complete(&self, _: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn reorder(&self, _: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn find_by_task(&self, _: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+    }
+
+    #[derive(Default)]
+    struct MockReminderRepo {
+        reminders: Mutex<BTreeMap<TodoId, Reminder>>,
+    }
+
+    #[async_trait]
+    impl ReminderRepo for MockReminderRepo {
+        async fn set(&self, reminder: &Reminder) {
+            let mut reminders = self.reminders.lock().unwrap();
+            reminders.insert(reminder.todo_id, reminder.clone());
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Option<Reminder> {
+            self.reminders.lock().unwrap().get(todo_id).cloned()
+        }
+
+        async fn remove(&self, todo_id: &TodoId) -> bool {
+            self.reminders.lock().unwrap().remove(todo_id).is_some()
+        }
+
+        async fn list(&self) -> Vec<Reminder> {
+            let mut reminders: Vec<Reminder> =
+                self.reminders.lock().unwrap().values().cloned().collect();
+            reminders.sort_by_key(|r| r.remind_at);
+            reminders
+        }
+
+        async fn take_due(&self, now: DateTime<Utc>) -> Vec<Reminder> {
+            let due: Vec<Reminder> = self
+                .list()
+                .await
+                .into_iter()
+                .filter(|r| r.remind_at <= now)
+                .collect();
+            let mut reminders = self.reminders.lock().unwrap();
+            for reminder in &due {
+                reminders.remove(&reminder.todo_id);
+            }
+            due
+        }
+    }
+
+    #[derive(Default)]
+    struct MockNotifier {
+        notified: Mutex<Vec<TodoId>>,
+    }
+
+    #[async_trait]
+    impl Notifier for MockNotifier {
+        async fn notify(&self, _: &Reminder, todo: &Todo) -> Result<(), String> {
+            self.notified.lock().unwrap().push(todo.id);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::rule::*;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[async_trait]
+pub trait RuleService {
+    async fn create(&self, rule_data: &RuleData) -> Result<Rule, RuleServiceDataErr>;
+    async fn list(&self) -> Vec<Rule>;
+    async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleServiceLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct RuleServiceImpl<A: RuleRepo + Sync> {
+    rule_repo: A,
+}
+
+pub fn new<A: RuleRepo + Sync>(repo: A) -> RuleServiceImpl<A> {
+    RuleServiceImpl { rule_repo: repo }
+}
+
+#[async_trait]
+impl<A: RuleRepo + Sync> RuleService for RuleServiceImpl<A> {
+    async fn create(&self, rule_data: &RuleData) -> Result<Rule, RuleServiceDataErr> {
+        rule_data
+            .validate()
+            .map_err(|reason| RuleServiceDataErr::InvalidData { reason })?;
+        Ok(self.rule_repo.create(rule_data).await)
+    }
+
+    async fn list(&self) -> Vec<Rule> {
+        self.rule_repo.list().await
+    }
+
+    async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleServiceLookupErr> {
+        Ok(self.rule_repo.delete(rule_id).await?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RuleServiceLookupErr {
+    #[error("No such rule: [{0:?}]")]
+    NotFound(RuleId),
+}
+
+#[derive(Error, Debug)]
+pub enum RuleServiceDataErr {
+    #[error("Invalid rule: {reason}")]
+    InvalidData { reason: String },
+}
+
+impl From<RuleRepoErr> for RuleServiceLookupErr {
+    fn from(repo_err: RuleRepoErr) -> Self {
+        match repo_err {
+            RuleRepoErr::NotFound(id) => RuleServiceLookupErr::NotFound(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::sync::{Arc, Mutex};
+
+    fn rule_data(name: &str, task: &str) -> RuleData {
+        RuleData {
+            name: name.to_string(),
+            when: Condition {
+                event: Trigger::Created,
+                task_contains: None,
+            },
+            then: Action::CreateTodo {
+                task: task.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_create_ok() {
+        let mock_repo = MockRuleRepo::new();
+        let service = new(mock_repo.clone());
+        match block_on(service.create(&rule_data("follow up", "Follow up on {{task}}"))) {
+            Ok(created) => {
+                assert_eq!("follow up".to_string(), created.name);
+                assert_eq!(1, *mock_repo.create_called.lock().unwrap());
+            }
+            Err(_) => panic!("Creation failed"),
+        }
+    }
+
+    #[test]
+    fn test_create_invalid() {
+        let mock_repo = MockRuleRepo::new();
+        let service = new(mock_repo.clone());
+        for invalid in [
+            rule_data("", "task"),
+            rule_data("follow up", ""),
+            rule_data("follow up", "Follow up on {{title}}"),
+        ] {
+            match block_on(service.create(&invalid)) {
+                Err(RuleServiceDataErr::InvalidData { .. }) => {}
+                Ok(_) => panic!("invalid data was saved"),
+            }
+        }
+        assert_eq!(0, *mock_repo.create_called.lock().unwrap());
+    }
+
+    #[test]
+    fn test_delete_not_found() {
+        let mock_repo = MockRuleRepo::new();
+        let service = new(mock_repo.clone());
+        match block_on(service.delete(&RuleId(1))) {
+            Err(RuleServiceLookupErr::NotFound(RuleId(1))) => {
+                assert_eq!(1, *mock_repo.delete_called.lock().unwrap());
+            }
+            _ => panic!("Unexpected."),
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockRuleRepo {
+        create_called: Arc<Mutex<usize>>,
+        delete_called: Arc<Mutex<usize>>,
+    }
+
+    impl MockRuleRepo {
+        fn new() -> MockRuleRepo {
+            MockRuleRepo {
+                create_called: Arc::new(Mutex::new(0)),
+                delete_called: Arc::new(Mutex::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl RuleRepo for MockRuleRepo {
+        async fn create(&self, rule_data: &RuleData) -> Rule {
+            let mut mutex = self.create_called.lock().unwrap();
+            *mutex += 1;
+            Rule {
+                id: RuleId(1),
+                name: rule_data.name.clone(),
+                when: rule_data.when.clone(),
+                then: rule_data.then.clone(),
+            }
+        }
+
+        async fn list(&self) -> Vec<Rule> {
+            Vec::new()
+        }
+
+        async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleRepoErr> {
+            let mut mutex = self.delete_called.lock().unwrap();
+            *mutex += 1;
+            Err(RuleRepoErr::NotFound(*rule_id))
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::share::*;
+use crate::todo::*;
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use thiserror::Error;
+
+#[async_trait]
+pub trait ShareService {
+    async fn create(
+        &self,
+        todo_id: &TodoId,
+        share_data: &ShareData,
+    ) -> Result<Share, ShareServiceCreateErr>;
+    /// Only the shares that haven't expired
+    async fn list(&self, todo_id: &TodoId) -> Result<Vec<Share>, ShareServiceLookupErr>;
+    async fn revoke(
+        &self,
+        todo_id: &TodoId,
+        token: &ShareToken,
+    ) -> Result<(), ShareServiceLookupErr>;
+    /// The todo behind an active share
+    async fn shared(&self, token: &ShareToken) -> Result<Todo, ShareServiceLookupErr>;
+}
+
+#[derive(Clone)]
+pub struct ShareServiceImpl<A: TodoRepo + Sync, B: ShareRepo + Sync> {
+    todo_repo: A,
+    share_repo: B,
+}
+
+pub fn new<A: TodoRepo + Sync, B: ShareRepo + Sync>(
+    todo_repo: A,
+    share_repo: B,
+) -> ShareServiceImpl<A, B> {
+    ShareServiceImpl {
+        todo_repo,
+        share_repo,
+    }
+}
+
+#[async_trait]
+impl<A: TodoRepo + Sync, B: ShareRepo + Sync> ShareService for ShareServiceImpl<A, B> {
+    async fn create(
+        &self,
+        todo_id: &TodoId,
+        share_data: &ShareData,
+    ) -> Result<Share, ShareServiceCreateErr> {
+        share_data
+            .validate()
+            .map_err(|reason| ShareServiceCreateErr::InvalidData { reason })?;
+        let expires_at = share_data
+            .expires_in_secs
+            .map(|secs| Utc::now() + Duration::seconds(secs as i64));
+        self.todo_repo
+            .get(todo_id)
+            .await
+            .map_err(|_| ShareServiceCreateErr::NoSuchTodo(*todo_id))?;
+        Ok(self.share_repo.create(todo_id, expires_at).await)
+    }
+
+    async fn list(&self, todo_id: &TodoId) -> Result<Vec<Share>, ShareServiceLookupErr> {
+        self.todo_repo
+            .get(todo_id)
+            .await
+            .map_err(|_| ShareServiceLookupErr::NoSuchTodo(*todo_id))?;
+        let now = Utc::now();
+        let shares = self.share_repo.list_for(todo_id).await;
+        Ok(shares.into_iter().filter(|s| s.is_active(now)).collect())
+    }
+
+    async fn revoke(
+        &self,
+        todo_id: &TodoId,
+        token: &ShareToken,
+    ) -> Result<(), ShareServiceLookupErr> {
+        match self.share_repo.get(token).await {
+            Ok(share) if share.todo_id == *todo_id => self
+                .share_repo
+                .delete(token)
+                .await
+                .map_err(|_| ShareServiceLookupErr::NoSuchShare),
+            _ => Err(ShareServiceLookupErr::NoSuchShare),
+        }
+    }
+
+    async fn shared(&self, token: &ShareToken) -> Result<Todo, ShareServiceLookupErr> {
+        let share = self
+            .share_repo
+            .get(token)
+            .await
+            .map_err(|_| ShareServiceLookupErr::NoSuchShare)?;
+        if !share.is_active(Utc::now()) {
+            let _ = self.share_repo.delete(token).await;
+            return Err(ShareServiceLookupErr::NoSuchShare);
+        }
+        // Shares outlive deleted todos, but there's nothing left to see
+        self.todo_repo
+            .get(&share.todo_id)
+            .await
+            .map_err(|_| ShareServiceLookupErr::NoSuchShare)
+    }
+}
+
+// Unknown, expired and revoked shares all look the same from the outside
+#[derive(Error, Debug)]
+pub enum ShareServiceLookupErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("No such share")]
+    NoSuchShare,
+}
+
+#[derive(Error, Debug)]
+pub enum ShareServiceCreateErr {
+    #[error("No such todo: [{0:?}]")]
+    NoSuchTodo(TodoId),
+    #[error("Invalid share: {reason}")]
+    InvalidData { reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use futures::executor::block_on;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    static SHARED_TODO_ID: TodoId = TodoId(1);
+
+    fn service() -> ShareServiceImpl<MockTodoRepo, MockShareRepo> {
+        new(MockTodoRepo, MockShareRepo::new())
+    }
+
+    #[test]
+    fn test_create_and_get() {
+        let service = service();
+        let share = block_on(service.create(
+            &SHARED_TODO_ID,
+            &ShareData {
+                expires_in_secs: Some(60),
+            },
+        ))
+        .ok()
+        .unwrap();
+        assert!(share.expires_at.unwrap() > Utc::now());
+        match block_on(service.shared(&share.token)) {
+            Ok(todo) => assert_eq!(SHARED_TODO_ID, todo.id),
+            Err(_) => panic!("share not found"),
+        }
+        assert_eq!(
+            1,
+            block_on(service.list(&SHARED_TODO_ID)).ok().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_create_invalid() {
+        let service = service();
+        for expires_in_secs in [Some(0), Some(u64::MAX)] {
+            match block_on(service.create(&SHARED_TODO_ID, &ShareData { expires_in_secs })) {
+                Err(ShareServiceCreateErr::InvalidData { .. }) => {}
+                _ => panic!("invalid share was created"),
+            }
+        }
+        match block_on(service.create(
+            &TodoId(2),
+            &ShareData {
+                expires_in_secs: None,
+            },
+        )) {
+            Err(ShareServiceCreateErr::NoSuchTodo(TodoId(2))) => {}
+            _ => panic!("share of a missing todo was created"),
+        }
+    }
+
+    #[test]
+    fn test_expired_and_revoked() {
+        let service = service();
+        let expired = block_on(
+            service
+                .share_repo
+                .create(&SHARED_TODO_ID, Some(Utc::now() - Duration::seconds(1))),
+        );
+        assert!(block_on(service.shared(&expired.token)).is_err());
+        assert!(block_on(service.list(&SHARED_TODO_ID))
+            .ok()
+            .unwrap()
+            .is_empty());
+
+        let share = block_on(service.create(
+            &SHARED_TODO_ID,
+            &ShareData {
+                expires_in_secs: None,
+            },
+        ))
+        .ok()
+        .unwrap();
+        // Only through the todo it shares
+        assert!(block_on(service.revoke(&TodoId(2), &share.token)).is_err());
+        assert!(block_on(service.revoke(&SHARED_TODO_ID, &share.token)).is_ok());
+        assert!(block_on(service.shared(&share.token)).is_err());
+    }
+
+    struct MockTodoRepo;
+
+    #[async_trait]
+    impl TodoRepo for MockTodoRepo {
+        async fn create(&self, _: &TodoData) -> Result<Todo, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+            if *todo_id == SHARED_TODO_ID {
+                Ok(Todo {
+                    id: *todo_id,
+                    task: "Make the bed".to_string(),
+                    done: false,
+                    version: 1,
+                    list_id: None,
+                    position: 0,
+                })
+            } else {
+                Err(TodoRepoErr::NotFound(*todo_id))
+            }
+        }
+
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn update(&self, _: &Todo, _: Option<u64>) -> Result<Todo, TodoRepoUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn complete(&self, _: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn reorder(&self, _: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn find_by_task(&self, _: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+    }
+
+    struct MockShareRepo {
+        shares: Arc<Mutex<BTreeMap<ShareToken, Share>>>,
+    }
+
+    impl MockShareRepo {
+        fn new() -> MockShareRepo {
+            MockShareRepo {
+                shares: Arc::new(Mutex::new(BTreeMap::new())),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ShareRepo for MockShareRepo {
+        async fn create(&self, todo_id: &TodoId, expires_at: Option<DateTime<Utc>>) -> Share {
+            let mut shares = self.shares.lock().unwrap();
+            let share = Share {
+                token: ShareToken(format!("token-{}", shares.len())),
+                todo_id: *todo_id,
+                expires_at,
+            };
+            shares.insert(share.token.clone(), share.clone());
+            share
+        }
+
+        async fn get(&self, token: &ShareToken) -> Result<Share, ShareRepoErr> {
+            let shares = self.shares.lock().unwrap();
+            shares
+                .get(token)
+                .cloned()
+                .ok_or_else(|| ShareRepoErr::NotFound(token.clone()))
+        }
+
+        async fn list_for(&self, todo_id: &TodoId) -> Vec<Share> {
+            let shares = self.shares.lock().unwrap();
+            shares
+                .values()
+                .filter(|s| s.todo_id == *todo_id)
+                .cloned()
+                .collect()
+        }
+
+        async fn delete(&self, token: &ShareToken) -> Result<(), ShareRepoErr> {
+            let mut shares = self.shares.lock().unwrap();
+            shares
+                .remove(token)
+                .map(|_| ())
+                .ok_or_else(|| ShareRepoErr::NotFound(token.clone()))
+        }
+    }
+}
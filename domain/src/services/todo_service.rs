@@ -1,78 +1,691 @@
+use crate::events::*;
+use crate::flood::*;
+use crate::history::*;
+use crate::list::*;
+use crate::rule::*;
+use crate::template::Sequences;
 use crate::todo::*;
+use crate::validation::ValidationPolicy;
 
 use async_trait::async_trait;
+use chrono::Utc;
+use futures::channel::mpsc::UnboundedReceiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
 
 #[async_trait]
 pub trait TodoService {
-    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoServiceDataErr>;
+    async fn create(&self, todo_data: &TodoData) -> Result<SavedTodo, TodoServiceDataErr>;
     async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoServiceLookupErr>;
-    async fn list(&self) -> Vec<Todo>;
+    async fn list(&self) -> Result<Vec<Todo>, TodoServiceLookupErr>;
+    /// Todos in id order, skipping the first `offset` and returning at most `limit`
+    async fn list_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Todo>, TodoServiceLookupErr>;
     async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoServiceLookupErr>;
-    async fn update(&self, todo: &Todo) -> Result<(), TodoServiceUpdateErr>;
+    /// Fails with a conflict when an expected version is given and the todo is at another one
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<SavedTodo, TodoServiceUpdateErr>;
+    /// Dry runs: these check and plan the change like the methods above, but leave the todos as
+    /// they are and emit nothing
+    async fn plan_create(&self, todo_data: &TodoData) -> Result<TodoPlan, TodoServiceDataErr>;
+    async fn plan_update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<TodoPlan, TodoServiceUpdateErr>;
+    async fn plan_delete(&self, todo_id: &TodoId) -> Result<TodoPlan, TodoServiceLookupErr>;
+    /// Marks the selected todos done all at once, emitting an update for each one that was open
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoServiceDataErr>;
+    /// Puts the todos with the given ids first, in that order, and returns every todo in the new
+    /// manual order. Only positions and versions change, so nothing is emitted or audited.
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoServiceLookupErr>;
+    /// The oldest open todo whose task is the same as the data's would be once saved, give or
+    /// take case and whitespace
+    async fn find_duplicate(
+        &self,
+        todo_data: &TodoData,
+    ) -> Result<Option<Todo>, TodoServiceLookupErr>;
+    /// Returns a stream of every event emitted by this service from now on
+    fn subscribe(&self) -> UnboundedReceiver<TodoEvent>;
 }
 
+// Clones share subscribers, so events emitted by one clone reach subscribers of all of them
+#[derive(Clone)]
 pub struct TodoServiceImpl<A: TodoRepo + Sync> {
     todo_repo: A,
+    broadcaster: Broadcaster,
+    subscribers: Arc<Vec<Arc<dyn Subscriber + Send + Sync>>>,
+    rule_repo: Option<Arc<dyn RuleRepo + Send + Sync>>,
+    list_repo: Option<Arc<dyn ListRepo + Send + Sync>>,
+    sequences: Sequences,
+    validators: Arc<Vec<Arc<dyn TodoValidator + Send + Sync>>>,
+    policy: Arc<ValidationPolicy>,
+    audit_sinks: Arc<Vec<Arc<dyn AuditSink + Send + Sync>>>,
+    flood_guard: Option<FloodGuard>,
+    // Where the current change comes from, e.g. the request being handled
+    origin: fn() -> ChangeOrigin,
 }
 
 pub fn new<A: TodoRepo + Sync>(repo: A) -> TodoServiceImpl<A> {
-    TodoServiceImpl { todo_repo: repo }
+    with_subscribers(repo, Vec::new())
 }
 
-impl<A: TodoRepo + Sync> TodoServiceImpl<A> {
-    fn validate_task(task: &str) -> Result<(), TodoServiceDataErr> {
-        if task.is_empty() {
-            Err(TodoServiceDataErr::InvalidData {
-                task: task.to_string(),
-            })
+pub fn with_subscribers<A: TodoRepo + Sync>(
+    repo: A,
+    subscribers: Vec<Arc<dyn Subscriber + Send + Sync>>,
+) -> TodoServiceImpl<A> {
+    TodoServiceImpl {
+        todo_repo: repo,
+        broadcaster: broadcaster(),
+        subscribers: Arc::new(subscribers),
+        rule_repo: None,
+        list_repo: None,
+        sequences: Sequences::default(),
+        validators: Arc::new(Vec::new()),
+        policy: Arc::new(ValidationPolicy::default()),
+        audit_sinks: Arc::new(Vec::new()),
+        flood_guard: None,
+        origin: ChangeOrigin::default,
+    }
+}
+
+impl<A: TodoRepo + TodoUnitOfWork + Sync> TodoServiceImpl<A> {
+    /// Evaluates the rules in the given repo against every event emitted from now on
+    pub fn with_rules(self, rule_repo: Arc<dyn RuleRepo + Send + Sync>) -> TodoServiceImpl<A> {
+        TodoServiceImpl {
+            rule_repo: Some(rule_repo),
+            ..self
+        }
+    }
+
+    /// Checks that the list todos are put in exists in the given repo
+    pub fn with_lists(self, list_repo: Arc<dyn ListRepo + Send + Sync>) -> TodoServiceImpl<A> {
+        TodoServiceImpl {
+            list_repo: Some(list_repo),
+            ..self
+        }
+    }
+
+    /// Runs the given validators, in order, after the built-in checks on every create and update
+    pub fn with_validators(
+        self,
+        validators: Vec<Arc<dyn TodoValidator + Send + Sync>>,
+    ) -> TodoServiceImpl<A> {
+        TodoServiceImpl {
+            validators: Arc::new(validators),
+            ..self
+        }
+    }
+
+    /// Normalizes and checks tasks by the given policy on every create and update, after the
+    /// built-in checks and before the validators
+    pub fn with_policy(self, policy: ValidationPolicy) -> TodoServiceImpl<A> {
+        TodoServiceImpl {
+            policy: Arc::new(policy),
+            ..self
+        }
+    }
+
+    /// Records every change to each of the given sinks from now on, attributed to wherever
+    /// `origin` says it comes from at the time
+    pub fn with_audit(
+        self,
+        audit_sinks: Vec<Arc<dyn AuditSink + Send + Sync>>,
+        origin: fn() -> ChangeOrigin,
+    ) -> TodoServiceImpl<A> {
+        TodoServiceImpl {
+            audit_sinks: Arc::new(audit_sinks),
+            origin,
+            ..self
+        }
+    }
+
+    /// Turns down updates to a todo beyond what the guard allows, recording the first of each
+    /// storm to the audit sinks
+    pub fn with_flood_guard(self, flood_guard: FloodGuard) -> TodoServiceImpl<A> {
+        TodoServiceImpl {
+            flood_guard: Some(flood_guard),
+            ..self
+        }
+    }
+
+    async fn audit(&self, todo_id: TodoId, old: Option<Todo>, new: Option<Todo>) {
+        self.record(todo_id, old, new, None).await
+    }
+
+    async fn record(
+        &self,
+        todo_id: TodoId,
+        old: Option<Todo>,
+        new: Option<Todo>,
+        rejected: Option<String>,
+    ) {
+        if self.audit_sinks.is_empty() {
+            return;
+        }
+        let origin = (self.origin)();
+        let change = TodoChange {
+            todo_id,
+            old,
+            new,
+            at: Utc::now(),
+            actor: origin.actor,
+            request_id: origin.request_id,
+            rejected,
+        };
+        for audit_sink in self.audit_sinks.iter() {
+            audit_sink.record(&change).await;
+        }
+    }
+
+    async fn check_flood(&self, todo: &Todo) -> Result<(), TodoServiceUpdateErr> {
+        let flood_guard = match &self.flood_guard {
+            Some(flood_guard) => flood_guard,
+            None => return Ok(()),
+        };
+        match flood_guard.check(&todo.id, Instant::now()) {
+            Ok(()) => Ok(()),
+            Err(flooded) => {
+                if flooded.first {
+                    let limit = flood_guard.limit();
+                    let reason = format!(
+                        "more than {} changes within {}ms",
+                        limit.max_changes,
+                        limit.per.as_millis()
+                    );
+                    let old = self.audited_old(&todo.id).await;
+                    self.record(todo.id, old, Some(todo.clone()), Some(reason))
+                        .await;
+                }
+                Err(TodoServiceUpdateErr::Flooded {
+                    id: todo.id,
+                    retry_after: flooded.retry_after,
+                })
+            }
+        }
+    }
+
+    // Only looked up when auditing, since nothing else needs it
+    async fn audited_old(&self, todo_id: &TodoId) -> Option<Todo> {
+        if self.audit_sinks.is_empty() {
+            None
         } else {
-            Ok(())
+            self.todo_repo.get(todo_id).await.ok()
+        }
+    }
+
+    // Returns warnings for follow-ups that failed validation, and so weren't created
+    async fn emit(&self, event: TodoEvent) -> Vec<String> {
+        self.publish(&event).await;
+        let (follow_ups, mut warnings) = self.checked_follow_ups(&event, &self.sequences).await;
+        // Follow-ups are published but not evaluated in turn, so rules can't keep triggering each other
+        for todo_data in follow_ups {
+            let todo = match self.create_in_list(&todo_data).await {
+                Ok(todo) => todo,
+                Err(e) => {
+                    warnings.push(format!(
+                        "Follow-up [{}] was not created: {}",
+                        todo_data.task,
+                        unsaved_reason(&e)
+                    ));
+                    continue;
+                }
+            };
+            self.audit(todo.id, None, Some(todo.clone())).await;
+            self.publish(&TodoEvent::Created(TodoCreated { todo }))
+                .await;
+        }
+        warnings
+    }
+
+    // What emit would do for the event, without doing it
+    async fn plan(&self, todo: Option<Todo>, event: TodoEvent, warnings: Vec<String>) -> TodoPlan {
+        let (follow_ups, follow_up_warnings) = self
+            .checked_follow_ups(&event, &self.sequences.detached())
+            .await;
+        let mut events = vec![event];
+        events.extend(follow_ups.iter().map(|todo_data| {
+            TodoEvent::Created(TodoCreated {
+                todo: unsaved(todo_data),
+            })
+        }));
+        TodoPlan {
+            todo,
+            events,
+            warnings: warnings.into_iter().chain(follow_up_warnings).collect(),
+        }
+    }
+
+    // The follow-ups that pass validation, and warnings about those that don't
+    async fn checked_follow_ups(
+        &self,
+        event: &TodoEvent,
+        sequences: &Sequences,
+    ) -> (Vec<TodoData>, Vec<String>) {
+        let mut valid = Vec::new();
+        let mut warnings = Vec::new();
+        for todo_data in self.follow_ups(event, sequences).await {
+            let todo_data = self.normalized(&todo_data);
+            if self.validate(&todo_data, None).await.is_ok() {
+                valid.push(todo_data);
+            } else {
+                warnings.push(format!(
+                    "Follow-up [{}] was not created, as it is invalid",
+                    todo_data.task
+                ));
+            }
+        }
+        (valid, warnings)
+    }
+
+    // Warns about, without preventing, open todos with the same task as the saved one
+    async fn duplicate_warnings(&self, saved: &Todo) -> Vec<String> {
+        if saved.done {
+            return Vec::new();
+        }
+        // Exact duplicates are among the repo's looser matches
+        match self.todo_repo.find_by_task(&saved.task).await {
+            Ok(matches) => matches
+                .iter()
+                .filter(|other| other.id != saved.id && !other.done && other.task == saved.task)
+                .map(|other| format!("Todo [{:?}] has the same task", other.id))
+                .collect(),
+            // Only a warning is lost, so the change it's about still goes ahead
+            Err(e) => vec![format!(
+                "Could not check for todos with the same task: {}",
+                unsaved_reason(&TodoServiceDataErr::Failed(e.into()))
+            )],
+        }
+    }
+
+    async fn publish(&self, event: &TodoEvent) {
+        self.broadcaster.notify(event).await;
+        for subscriber in self.subscribers.iter() {
+            subscriber.notify(event).await;
+        }
+    }
+
+    async fn follow_ups(&self, event: &TodoEvent, sequences: &Sequences) -> Vec<TodoData> {
+        match &self.rule_repo {
+            Some(rule_repo) => {
+                let today = chrono::Local::now().date_naive();
+                rule_repo
+                    .list()
+                    .await
+                    .iter()
+                    .filter_map(|rule| rule.follow_up(event, today, sequences))
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn normalized(&self, todo_data: &TodoData) -> TodoData {
+        TodoData {
+            task: self.policy.normalize(&todo_data.task),
+            ..todo_data.clone()
+        }
+    }
+
+    // Checks normalized data, for saving as the todo with the given id, if it already exists
+    async fn validate(
+        &self,
+        todo_data: &TodoData,
+        id: Option<TodoId>,
+    ) -> Result<(), TodoServiceDataErr> {
+        if !todo_data.is_valid() {
+            return Err(TodoServiceDataErr::InvalidData {
+                task: todo_data.task.clone(),
+            });
+        }
+        let disallowed = |reason| TodoServiceDataErr::Disallowed {
+            task: todo_data.task.clone(),
+            reason,
+        };
+        self.policy.check(&todo_data.task).map_err(disallowed)?;
+        if self.policy.unique && !todo_data.done {
+            let taken = self
+                .todo_repo
+                .find_by_task(&todo_data.task)
+                .await
+                .map_err(|e| TodoServiceDataErr::Failed(e.into()))?
+                .into_iter()
+                .find(|other| Some(other.id) != id && !other.done && other.task == todo_data.task);
+            if let Some(other) = taken {
+                return Err(disallowed(format!(
+                    "Todo [{:?}] has the same task",
+                    other.id
+                )));
+            }
+        }
+        if let (Some(list_id), Some(list_repo)) = (todo_data.list_id, &self.list_repo) {
+            if list_repo.get(&list_id).await.is_err() {
+                return Err(TodoServiceDataErr::NoSuchList(list_id));
+            }
+        }
+        for validator in self.validators.iter() {
+            validator
+                .validate(todo_data)
+                .await
+                .map_err(|reason| TodoServiceDataErr::Rejected { reason })?;
         }
+        Ok(())
+    }
+
+    // Todos put in a list are written under one unit of work with another check that the list
+    // exists, as the list service deletes lists under one, so that a todo can't end up in a list
+    // deleted since it was validated
+    async fn create_in_list(&self, todo_data: &TodoData) -> Result<Todo, TodoServiceDataErr> {
+        let (list_id, list_repo) = match (todo_data.list_id, &self.list_repo) {
+            (Some(list_id), Some(list_repo)) => (list_id, list_repo.clone()),
+            _ => {
+                return self
+                    .todo_repo
+                    .create(todo_data)
+                    .await
+                    .map_err(|e| TodoServiceDataErr::Failed(e.into()))
+            }
+        };
+        let todo_data = todo_data.clone();
+        self.todo_repo
+            .transact(move |tx| {
+                Box::pin(async move {
+                    if list_repo.get(&list_id).await.is_err() {
+                        return Err(TodoServiceDataErr::NoSuchList(list_id));
+                    }
+                    tx.create(&todo_data)
+                        .await
+                        .map_err(|e| TodoServiceDataErr::Failed(e.into()))
+                })
+            })
+            .await
+    }
+
+    async fn update_in_list(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Todo, TodoServiceUpdateErr> {
+        let (list_id, list_repo) = match (todo.list_id, &self.list_repo) {
+            (Some(list_id), Some(list_repo)) => (list_id, list_repo.clone()),
+            _ => return Ok(self.todo_repo.update(todo, expected_version).await?),
+        };
+        let todo = todo.clone();
+        self.todo_repo
+            .transact(move |tx| {
+                Box::pin(async move {
+                    if list_repo.get(&list_id).await.is_err() {
+                        return Err(TodoServiceDataErr::NoSuchList(list_id).into());
+                    }
+                    Ok(tx.update(&todo, expected_version).await?)
+                })
+            })
+            .await
     }
 }
 
 #[async_trait]
-impl<A: TodoRepo + Sync> TodoService for TodoServiceImpl<A> {
-    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoServiceDataErr> {
-        Self::validate_task(&todo_data.task)?;
-        Ok(self.todo_repo.create(todo_data).await)
+impl<A: TodoRepo + TodoUnitOfWork + Sync> TodoService for TodoServiceImpl<A> {
+    async fn create(&self, todo_data: &TodoData) -> Result<SavedTodo, TodoServiceDataErr> {
+        let todo_data = self.normalized(todo_data);
+        self.validate(&todo_data, None).await?;
+        let created = self.create_in_list(&todo_data).await?;
+        self.audit(created.id, None, Some(created.clone())).await;
+        let mut warnings = self.duplicate_warnings(&created).await;
+        warnings.extend(
+            self.emit(TodoEvent::Created(TodoCreated {
+                todo: created.clone(),
+            }))
+            .await,
+        );
+        Ok(SavedTodo {
+            todo: created,
+            warnings,
+        })
     }
 
     async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoServiceLookupErr> {
         Ok(self.todo_repo.get(todo_id).await?)
     }
 
-    async fn list(&self) -> Vec<Todo> {
-        self.todo_repo.list().await
+    async fn list(&self) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+        Ok(self.todo_repo.list().await?)
+    }
+
+    async fn list_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+        Ok(self.todo_repo.list_page(offset, limit).await?)
     }
 
     async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoServiceLookupErr> {
-        Ok(self.todo_repo.delete(todo_id).await?)
+        let old = self.audited_old(todo_id).await;
+        self.todo_repo.delete(todo_id).await?;
+        self.audit(*todo_id, old, None).await;
+        self.emit(TodoEvent::Deleted(TodoDeleted { id: *todo_id }))
+            .await;
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<SavedTodo, TodoServiceUpdateErr> {
+        let todo = &Todo {
+            task: self.policy.normalize(&todo.task),
+            ..todo.clone()
+        };
+        let todo_data = TodoData {
+            task: todo.task.clone(),
+            done: todo.done,
+            list_id: todo.list_id,
+        };
+        self.validate(&todo_data, Some(todo.id)).await?;
+        self.check_flood(todo).await?;
+        let old = self.audited_old(&todo.id).await;
+        let updated = self.update_in_list(todo, expected_version).await?;
+        self.audit(todo.id, old, Some(updated.clone())).await;
+        let mut warnings = self.duplicate_warnings(&updated).await;
+        warnings.extend(
+            self.emit(TodoEvent::Updated(TodoUpdated {
+                todo: updated.clone(),
+            }))
+            .await,
+        );
+        Ok(SavedTodo {
+            todo: updated,
+            warnings,
+        })
+    }
+
+    async fn plan_create(&self, todo_data: &TodoData) -> Result<TodoPlan, TodoServiceDataErr> {
+        let todo_data = self.normalized(todo_data);
+        self.validate(&todo_data, None).await?;
+        let todo = unsaved(&todo_data);
+        let warnings = self.duplicate_warnings(&todo).await;
+        let event = TodoEvent::Created(TodoCreated { todo: todo.clone() });
+        Ok(self.plan(Some(todo), event, warnings).await)
+    }
+
+    async fn plan_update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<TodoPlan, TodoServiceUpdateErr> {
+        let todo = &Todo {
+            task: self.policy.normalize(&todo.task),
+            ..todo.clone()
+        };
+        let todo_data = TodoData {
+            task: todo.task.clone(),
+            done: todo.done,
+            list_id: todo.list_id,
+        };
+        self.validate(&todo_data, Some(todo.id)).await?;
+        let current = self
+            .todo_repo
+            .get(&todo.id)
+            .await
+            .map_err(|e| TodoServiceUpdateErr::LookupErr(e.into()))?;
+        if expected_version.is_some_and(|v| v != current.version) {
+            return Err(TodoServiceUpdateErr::Conflict(todo.id));
+        }
+        let updated = Todo {
+            version: current.version + 1,
+            ..todo.clone()
+        };
+        let warnings = self.duplicate_warnings(&updated).await;
+        let event = TodoEvent::Updated(TodoUpdated {
+            todo: updated.clone(),
+        });
+        Ok(self.plan(Some(updated), event, warnings).await)
+    }
+
+    async fn plan_delete(&self, todo_id: &TodoId) -> Result<TodoPlan, TodoServiceLookupErr> {
+        self.todo_repo.get(todo_id).await?;
+        let event = TodoEvent::Deleted(TodoDeleted { id: *todo_id });
+        Ok(self.plan(None, event, Vec::new()).await)
+    }
+
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoServiceDataErr> {
+        if let (TodoSelection::List(list_id), Some(list_repo)) = (selection, &self.list_repo) {
+            if list_repo.get(list_id).await.is_err() {
+                return Err(TodoServiceDataErr::NoSuchList(*list_id));
+            }
+        }
+        let mut completed = self
+            .todo_repo
+            .complete(selection)
+            .await
+            .map_err(|e| TodoServiceDataErr::Failed(e.into()))?;
+        for todo in completed.completed.iter() {
+            // Completing only sets done and bumps the version, so this is what it was before
+            let old = Todo {
+                done: false,
+                version: todo.version - 1,
+                ..todo.clone()
+            };
+            self.audit(todo.id, Some(old), Some(todo.clone())).await;
+            let warnings = self
+                .emit(TodoEvent::Updated(TodoUpdated { todo: todo.clone() }))
+                .await;
+            completed.warnings.extend(warnings);
+        }
+        Ok(completed)
+    }
+
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoServiceLookupErr> {
+        Ok(self.todo_repo.reorder(ids).await?)
+    }
+
+    async fn find_duplicate(
+        &self,
+        todo_data: &TodoData,
+    ) -> Result<Option<Todo>, TodoServiceLookupErr> {
+        let todo_data = self.normalized(todo_data);
+        Ok(self
+            .todo_repo
+            .find_by_task(&todo_data.task)
+            .await?
+            .into_iter()
+            .find(|todo| !todo.done))
+    }
+
+    fn subscribe(&self) -> UnboundedReceiver<TodoEvent> {
+        self.broadcaster.subscribe()
+    }
+}
+
+// Why a write the change went ahead without failed, for warnings; storage failures' causes are
+// kept out of them
+fn unsaved_reason(e: &TodoServiceDataErr) -> String {
+    match e {
+        TodoServiceDataErr::Failed(TodoServiceLookupErr::Other(_)) => {
+            "the todos' storage failed".to_string()
+        }
+        e => e.to_string(),
     }
+}
 
-    async fn update(&self, todo: &Todo) -> Result<(), TodoServiceUpdateErr> {
-        Self::validate_task(&todo.task)?;
-        Ok(self.todo_repo.update(todo).await?)
+// How the todo would look once created, bar the id, which is only assigned when saving
+fn unsaved(todo_data: &TodoData) -> Todo {
+    Todo {
+        id: TodoId(0),
+        task: todo_data.task.clone(),
+        done: todo_data.done,
+        version: 1,
+        list_id: todo_data.list_id,
+        position: 0,
     }
 }
 
+#[derive(Error, Debug)]
 pub enum TodoServiceUpdateErr {
+    #[error(transparent)]
     LookupErr(TodoServiceLookupErr),
+    #[error(transparent)]
     DataErr(TodoServiceDataErr),
+    // Someone else updated the todo since the expected version
+    #[error("Todo [{0:?}] has been updated since the expected version")]
+    Conflict(TodoId),
+    // Changed too often lately; it can be changed again after `retry_after`
+    #[error(
+        "Todo [{id:?}] has been changed too often lately; retry in {}s",
+        .retry_after.as_secs()
+    )]
+    Flooded { id: TodoId, retry_after: Duration },
 }
 
+#[derive(Error, Debug)]
 pub enum TodoServiceLookupErr {
+    #[error("No such todo: [{0:?}]")]
     NotFound(TodoId),
+    // The repo's errors, passed on as they are
+    #[error("Could not connect to the todos' storage: {0}")]
+    ConnectionError(String),
+    #[error("Another write to todo [{0:?}] got in the way")]
+    Conflict(TodoId),
+    #[error("The todos' storage timed out")]
+    Timeout,
+    #[error(transparent)]
+    Other(anyhow::Error),
 }
 
+#[derive(Error, Debug)]
 pub enum TodoServiceDataErr {
+    #[error("Invalid task: [{task}]")]
     InvalidData { task: String },
+    // Turned down by one of the extra validators
+    #[error("Rejected: {reason}")]
+    Rejected { reason: String },
+    // Against the validation policy
+    #[error("Task [{task}] is against the policy: {reason}")]
+    Disallowed { task: String, reason: String },
+    #[error("No such list: [{0:?}]")]
+    NoSuchList(ListId),
+    // The repo failed, rather than the data being wrong
+    #[error(transparent)]
+    Failed(TodoServiceLookupErr),
 }
 
 impl From<TodoRepoErr> for TodoServiceLookupErr {
     fn from(repo_err: TodoRepoErr) -> Self {
         match repo_err {
             TodoRepoErr::NotFound(id) => TodoServiceLookupErr::NotFound(id),
+            TodoRepoErr::ConnectionError(reason) => TodoServiceLookupErr::ConnectionError(reason),
+            TodoRepoErr::Conflict(id) => TodoServiceLookupErr::Conflict(id),
+            TodoRepoErr::Timeout => TodoServiceLookupErr::Timeout,
+            TodoRepoErr::Other(e) => TodoServiceLookupErr::Other(e),
         }
     }
 }
@@ -83,9 +696,15 @@ impl From<TodoServiceDataErr> for TodoServiceUpdateErr {
     }
 }
 
-impl From<TodoRepoErr> for TodoServiceUpdateErr {
-    fn from(repo_err: TodoRepoErr) -> Self {
-        TodoServiceUpdateErr::LookupErr(repo_err.into())
+impl From<TodoRepoUpdateErr> for TodoServiceUpdateErr {
+    fn from(repo_err: TodoRepoUpdateErr) -> Self {
+        match repo_err {
+            TodoRepoUpdateErr::NotFound(id) => {
+                TodoServiceUpdateErr::LookupErr(TodoServiceLookupErr::NotFound(id))
+            }
+            TodoRepoUpdateErr::VersionConflict(id) => TodoServiceUpdateErr::Conflict(id),
+            TodoRepoUpdateErr::Failed(e) => TodoServiceUpdateErr::LookupErr(e.into()),
+        }
     }
 }
 
@@ -93,7 +712,9 @@ impl From<TodoRepoErr> for TodoServiceUpdateErr {
 mod tests {
     use super::*;
     use futures::executor::block_on;
-    use std::sync::*;
+    use futures::future::BoxFuture;
+    use futures::{FutureExt, StreamExt};
+    use std::sync::Mutex;
 
     #[test]
     fn test_create_ok() {
@@ -102,12 +723,15 @@ mod tests {
         let f_created = async {
             let todo_data = TodoData {
                 task: "Make the bed".to_string(),
+                done: false,
+                list_id: None,
             };
             service.create(&todo_data).await
         };
         match block_on(f_created) {
             Ok(saved) => {
-                assert_eq!("Make the bed".to_string(), saved.task);
+                assert_eq!("Make the bed".to_string(), saved.todo.task);
+                assert!(saved.warnings.is_empty());
                 assert_eq!(1, *mock_repo.create_called.lock().unwrap());
             }
             Err(_) => panic!("Creation failed"),
@@ -121,6 +745,8 @@ mod tests {
         let f_created = async {
             let todo_data = TodoData {
                 task: "".to_string(),
+                done: false,
+                list_id: None,
             };
             service.create(&todo_data).await
         };
@@ -128,7 +754,7 @@ mod tests {
             Err(TodoServiceDataErr::InvalidData { .. }) => {
                 assert_eq!(0, *mock_repo.create_called.lock().unwrap());
             }
-            Ok(_) => panic!("invalid data was saved"),
+            _ => panic!("invalid data was saved"),
         }
     }
 
@@ -152,10 +778,49 @@ mod tests {
             Err(TodoServiceLookupErr::NotFound { .. }) => {
                 assert_eq!(1, *mock_repo.get_called.lock().unwrap());
             }
-            Ok(_) => panic!("not found"),
+            _ => panic!("not found"),
         }
     }
 
+    #[test]
+    fn test_repo_errs_passed_on() {
+        match TodoServiceUpdateErr::from(TodoRepoUpdateErr::Failed(TodoRepoErr::Timeout)) {
+            TodoServiceUpdateErr::LookupErr(TodoServiceLookupErr::Timeout) => {}
+            _ => panic!("not passed on"),
+        }
+        let other = TodoRepoErr::Other(anyhow::anyhow!("disk full").context("Could not save"));
+        match TodoServiceLookupErr::from(other) {
+            TodoServiceLookupErr::Other(e) => {
+                assert_eq!("Could not save: disk full", format!("{:#}", e));
+                assert_eq!("disk full", e.root_cause().to_string());
+            }
+            _ => panic!("not passed on"),
+        }
+    }
+
+    #[test]
+    fn test_errs_displayed() {
+        use std::error::Error;
+        let flooded = TodoServiceUpdateErr::Flooded {
+            id: TodoId(1),
+            retry_after: Duration::from_secs(30),
+        };
+        assert_eq!(
+            "Todo [TodoId(1)] has been changed too often lately; retry in 30s",
+            flooded.to_string()
+        );
+        // Wrapped errors are displayed as they are, sources included
+        let failed: TodoServiceUpdateErr = TodoRepoUpdateErr::Failed(TodoRepoErr::Other(
+            anyhow::anyhow!("disk full").context("Could not save"),
+        ))
+        .into();
+        assert_eq!("Could not save", failed.to_string());
+        assert_eq!("disk full", failed.source().unwrap().to_string());
+        let boxed: Box<dyn Error + Send + Sync> =
+            Box::new(TodoServiceLookupErr::NotFound(TodoId(2)));
+        assert_eq!("No such todo: [TodoId(2)]", boxed.to_string());
+    }
+
     #[test]
     fn test_list() {
         let mock_repo = MockTodoRepo::new();
@@ -184,7 +849,7 @@ mod tests {
             Err(TodoServiceLookupErr::NotFound { .. }) => {
                 assert_eq!(1, *mock_repo.delete_called.lock().unwrap());
             }
-            Ok(_) => panic!("not found"),
+            _ => panic!("not found"),
         }
     }
 
@@ -195,15 +860,40 @@ mod tests {
         let update_data = Todo {
             id: TodoId(1),
             task: "hello".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
         };
-        match block_on(service.update(&update_data)) {
-            Ok(_) => {
+        match block_on(service.update(&update_data, None)) {
+            Ok(updated) => {
+                assert_eq!(2, updated.todo.version);
                 assert_eq!(1, *mock_repo.update_called.lock().unwrap());
             }
             Err(_) => panic!("eh wut"),
         }
     }
 
+    #[test]
+    fn test_update_conflict() {
+        let mock_repo = MockTodoRepo::new();
+        let service = new(mock_repo.clone());
+        let update_data = Todo {
+            id: TodoId(1),
+            task: "hello".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
+        };
+        match block_on(service.update(&update_data, Some(MOCK_STALE_VERSION))) {
+            Err(TodoServiceUpdateErr::Conflict(TodoId(1))) => {
+                assert_eq!(1, *mock_repo.update_called.lock().unwrap());
+            }
+            _ => panic!("Unexpected."),
+        }
+    }
+
     #[test]
     fn test_update_not_found() {
         let mock_repo = MockTodoRepo::new();
@@ -211,8 +901,12 @@ mod tests {
         let update_data = Todo {
             id: NOT_FOUND_TODO_ID,
             task: "hello".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 0,
         };
-        match block_on(service.update(&update_data)) {
+        match block_on(service.update(&update_data, None)) {
             Err(TodoServiceUpdateErr::LookupErr(_)) => {
                 assert_eq!(1, *mock_repo.update_called.lock().unwrap());
             }
@@ -227,8 +921,12 @@ mod tests {
         let update_data = Todo {
             id: TodoId(1),
             task: "".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
         };
-        match block_on(service.update(&update_data)) {
+        match block_on(service.update(&update_data, None)) {
             Err(TodoServiceUpdateErr::DataErr(_)) => {
                 assert_eq!(0, *mock_repo.update_called.lock().unwrap());
             }
@@ -236,6 +934,572 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_subscribe() {
+        let mock_repo = MockTodoRepo::new();
+        let service = new(mock_repo);
+        let mut changes = service.subscribe();
+        let f_changed = async {
+            let todo_data = TodoData {
+                task: "hello".to_string(),
+                done: false,
+                list_id: None,
+            };
+            let created = service.create(&todo_data).await.ok().unwrap();
+            let _ = service.delete(&NOT_FOUND_TODO_ID).await;
+            let _ = service.delete(&created.todo.id).await;
+            vec![changes.next().await, changes.next().await]
+        };
+        assert_eq!(
+            vec![
+                Some(TodoEvent::Created(TodoCreated {
+                    todo: Todo {
+                        id: TodoId(1),
+                        task: "hello".to_string(),
+                        done: false,
+                        version: 1,
+                        list_id: None,
+                        position: 1,
+                    }
+                })),
+                Some(TodoEvent::Deleted(TodoDeleted { id: TodoId(1) })),
+            ],
+            block_on(f_changed)
+        );
+    }
+
+    #[test]
+    fn test_subscribers_notified() {
+        let mock_repo = MockTodoRepo::new();
+        let subscriber = MockSubscriber::new();
+        let service = with_subscribers(mock_repo, vec![Arc::new(subscriber.clone())]);
+        let f_updated = async {
+            let update_data = Todo {
+                id: TodoId(1),
+                task: "hello".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
+            };
+            let _ = service.update(&update_data, None).await;
+            let _ = service
+                .update(
+                    &Todo {
+                        id: NOT_FOUND_TODO_ID,
+                        ..update_data
+                    },
+                    None,
+                )
+                .await;
+        };
+        block_on(f_updated);
+        assert_eq!(1, *subscriber.notify_called.lock().unwrap());
+    }
+
+    #[test]
+    fn test_rules_evaluated() {
+        let mock_repo = MockTodoRepo::new();
+        let rule = Rule {
+            id: RuleId(1),
+            name: "follow up".to_string(),
+            when: Condition {
+                event: Trigger::Created,
+                task_contains: Some("bug".to_string()),
+            },
+            then: Action::CreateTodo {
+                task: "Test {{task}}".to_string(),
+            },
+        };
+        let service = new(mock_repo.clone()).with_rules(Arc::new(MockRuleRepo(vec![rule])));
+        let changes = service.subscribe();
+        let f_created = async {
+            let todo_data = TodoData {
+                task: "bug".to_string(),
+                done: false,
+                list_id: None,
+            };
+            let _ = service.create(&todo_data).await;
+        };
+        block_on(f_created);
+        drop(service);
+        let created_tasks: Vec<_> = block_on(changes.collect::<Vec<_>>())
+            .into_iter()
+            .map(|event| match event {
+                TodoEvent::Created(e) => e.todo.task,
+                _ => panic!("Unexpected."),
+            })
+            .collect();
+        // The follow-up contains "bug" too, but isn't evaluated again
+        assert_eq!(
+            vec!["bug".to_string(), "Test bug".to_string()],
+            created_tasks
+        );
+        assert_eq!(2, *mock_repo.create_called.lock().unwrap());
+    }
+
+    #[test]
+    fn test_dry_runs() {
+        let mock_repo = MockTodoRepo::new();
+        let rule = Rule {
+            id: RuleId(1),
+            name: "follow up".to_string(),
+            when: Condition {
+                event: Trigger::Created,
+                task_contains: None,
+            },
+            then: Action::CreateTodo {
+                task: "Test {{task}} #{{seq}}".to_string(),
+            },
+        };
+        let service = new(mock_repo.clone()).with_rules(Arc::new(MockRuleRepo(vec![rule])));
+        let changes = service.subscribe();
+        let todo_data = TodoData {
+            task: "bug".to_string(),
+            done: false,
+            list_id: None,
+        };
+        let planned_tasks = |plan: TodoPlan| -> Vec<_> {
+            plan.events
+                .into_iter()
+                .map(|event| match event {
+                    TodoEvent::Created(e) => e.todo.task,
+                    _ => panic!("Unexpected."),
+                })
+                .collect()
+        };
+        // Planning twice gives the same seq, since nothing is used up
+        for _ in 0..2 {
+            match block_on(service.plan_create(&todo_data)) {
+                Ok(plan) => assert_eq!(
+                    vec!["bug".to_string(), "Test bug #1".to_string()],
+                    planned_tasks(plan)
+                ),
+                Err(_) => panic!("Planning failed"),
+            }
+        }
+        let todo = Todo {
+            id: TodoId(1),
+            task: "bug".to_string(),
+            done: true,
+            version: 1,
+            list_id: None,
+            position: 1,
+        };
+        match block_on(service.plan_update(&todo, Some(2))) {
+            Err(TodoServiceUpdateErr::Conflict(id)) => assert_eq!(TodoId(1), id),
+            _ => panic!("Planned an update of a stale version"),
+        }
+        match block_on(service.plan_update(&todo, Some(1))) {
+            Ok(plan) => assert_eq!(Some(2), plan.todo.map(|t| t.version)),
+            Err(_) => panic!("Planning failed"),
+        }
+        match block_on(service.plan_delete(&NOT_FOUND_TODO_ID)) {
+            Err(TodoServiceLookupErr::NotFound(id)) => assert_eq!(NOT_FOUND_TODO_ID, id),
+            _ => panic!("Planned deleting a todo that doesn't exist"),
+        }
+        drop(service);
+        assert!(block_on(changes.collect::<Vec<_>>()).is_empty());
+        assert_eq!(0, *mock_repo.create_called.lock().unwrap());
+        assert_eq!(0, *mock_repo.update_called.lock().unwrap());
+        assert_eq!(0, *mock_repo.delete_called.lock().unwrap());
+    }
+
+    #[test]
+    fn test_validators() {
+        let mock_repo = MockTodoRepo::new();
+        let service = new(mock_repo.clone()).with_validators(vec![Arc::new(MockValidator)]);
+        let f_written = async {
+            let created = service
+                .create(&TodoData {
+                    task: "fine".to_string(),
+                    done: false,
+                    list_id: None,
+                })
+                .await;
+            let rejected = service
+                .update(
+                    &Todo {
+                        id: TodoId(1),
+                        task: "nope".to_string(),
+                        done: false,
+                        version: 1,
+                        list_id: None,
+                        position: 1,
+                    },
+                    None,
+                )
+                .await;
+            (created, rejected)
+        };
+        match block_on(f_written) {
+            (
+                Ok(_),
+                Err(TodoServiceUpdateErr::DataErr(TodoServiceDataErr::Rejected { reason })),
+            ) => {
+                assert_eq!("no nopes", &reason);
+                assert_eq!(0, *mock_repo.update_called.lock().unwrap());
+            }
+            _ => panic!("Unexpected."),
+        }
+    }
+
+    #[test]
+    fn test_audited() {
+        let mock_repo = MockTodoRepo::new();
+        let audit_sink = MockAuditSink::default();
+        let service =
+            new(mock_repo).with_audit(vec![Arc::new(audit_sink.clone())], || ChangeOrigin {
+                actor: Some("me".to_string()),
+                request_id: None,
+            });
+        block_on(async {
+            let created = service
+                .create(&TodoData {
+                    task: "hello".to_string(),
+                    done: false,
+                    list_id: None,
+                })
+                .await
+                .ok()
+                .unwrap();
+            let _ = service
+                .update(
+                    &Todo {
+                        done: true,
+                        ..created.todo
+                    },
+                    None,
+                )
+                .await;
+            let _ = service.delete(&TodoId(1)).await;
+            let _ = service.delete(&NOT_FOUND_TODO_ID).await;
+        });
+        let changes = audit_sink.changes.lock().unwrap();
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                (
+                    c.old.as_ref().map(|t| t.done),
+                    c.new.as_ref().map(|t| t.done),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (None, Some(false)),
+                (Some(false), Some(true)),
+                (Some(false), None)
+            ],
+            summary
+        );
+        assert!(changes.iter().all(|c| c.actor.as_deref() == Some("me")));
+    }
+
+    #[test]
+    fn test_complete() {
+        let audit_sink = MockAuditSink::default();
+        let service = new(MockTodoRepo::new())
+            .with_audit(vec![Arc::new(audit_sink.clone())], ChangeOrigin::default);
+        let mut changes = service.subscribe();
+        let completed =
+            block_on(service.complete(&TodoSelection::Ids(vec![TodoId(1), NOT_FOUND_TODO_ID])))
+                .ok()
+                .unwrap();
+        assert_eq!(
+            vec![TodoId(1)],
+            completed.completed.iter().map(|t| t.id).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![NOT_FOUND_TODO_ID], completed.not_found);
+        match block_on(changes.next()) {
+            Some(TodoEvent::Updated(TodoUpdated { todo })) => assert!(todo.done),
+            other => panic!("Unexpected {:?}", other),
+        }
+        let changes = audit_sink.changes.lock().unwrap();
+        assert_eq!(1, changes.len());
+        assert_eq!(Some(1), changes[0].old.as_ref().map(|t| t.version));
+        assert_eq!(Some(false), changes[0].old.as_ref().map(|t| t.done));
+    }
+
+    #[test]
+    fn test_reorder() {
+        let audit_sink = MockAuditSink::default();
+        let service = new(MockTodoRepo::new())
+            .with_audit(vec![Arc::new(audit_sink.clone())], ChangeOrigin::default);
+        let mut changes = service.subscribe();
+        let reordered = block_on(service.reorder(&[TodoId(2), TodoId(1)]))
+            .ok()
+            .unwrap();
+        assert_eq!(
+            vec![(TodoId(2), 1), (TodoId(1), 2)],
+            reordered
+                .iter()
+                .map(|t| (t.id, t.position))
+                .collect::<Vec<_>>()
+        );
+        match block_on(service.reorder(&[TodoId(1), NOT_FOUND_TODO_ID])) {
+            Err(TodoServiceLookupErr::NotFound(id)) => assert_eq!(NOT_FOUND_TODO_ID, id),
+            _ => panic!("unexpectedly reordered..."),
+        }
+        assert!(changes.next().now_or_never().is_none());
+        assert!(audit_sink.changes.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        let service = new(MockTodoRepo::new());
+        let todo_data = |task: &str| TodoData {
+            task: task.to_string(),
+            done: false,
+            list_id: None,
+        };
+        let found = block_on(service.find_duplicate(&todo_data("  Say HELLO "))).unwrap();
+        assert_eq!(Some(TodoId(1)), found.map(|t| t.id));
+        assert_eq!(
+            None,
+            block_on(service.find_duplicate(&todo_data("say hi"))).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = ValidationPolicy {
+            trim: true,
+            max_len: Some(10),
+            unique: true,
+            ..ValidationPolicy::default()
+        };
+        let service = new(MockTodoRepo::new()).with_policy(policy);
+        let todo_data = |task: &str| TodoData {
+            task: task.to_string(),
+            done: false,
+            list_id: None,
+        };
+        let created = block_on(service.create(&todo_data("  say hi ")))
+            .ok()
+            .unwrap();
+        assert_eq!("say hi", created.todo.task);
+        match block_on(service.create(&todo_data("   "))) {
+            Err(TodoServiceDataErr::InvalidData { .. }) => {}
+            _ => panic!("created a blank todo"),
+        }
+        match block_on(service.create(&todo_data("say hello to everyone"))) {
+            Err(TodoServiceDataErr::Disallowed { task, .. }) => {
+                assert_eq!("say hello to everyone", task)
+            }
+            _ => panic!("created a todo that is too long"),
+        }
+        // The mock repo lists an open todo with this task
+        match block_on(service.create(&todo_data(RETRIEVED_TODO_TASK))) {
+            Err(TodoServiceDataErr::Disallowed { reason, .. }) => {
+                assert_eq!("Todo [TodoId(1)] has the same task", reason)
+            }
+            _ => panic!("created a duplicate todo"),
+        }
+        assert!(block_on(service.create(&TodoData {
+            done: true,
+            ..todo_data(RETRIEVED_TODO_TASK)
+        }))
+        .is_ok());
+        let todo = Todo {
+            id: TodoId(1),
+            task: format!("{} ", RETRIEVED_TODO_TASK),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
+        };
+        let updated = block_on(service.update(&todo, None)).ok().unwrap();
+        assert_eq!(RETRIEVED_TODO_TASK, updated.todo.task);
+    }
+
+    #[test]
+    fn test_flood_guarded() {
+        let mock_repo = MockTodoRepo::new();
+        let audit_sink = MockAuditSink::default();
+        let service = new(mock_repo.clone())
+            .with_audit(vec![Arc::new(audit_sink.clone())], ChangeOrigin::default)
+            .with_flood_guard(guard(FloodLimit {
+                max_changes: 2,
+                per: Duration::from_secs(60),
+            }));
+        let todo = Todo {
+            id: TodoId(1),
+            task: "hello".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
+        };
+        for _ in 0..2 {
+            assert!(block_on(service.update(&todo, None)).is_ok());
+        }
+        for _ in 0..2 {
+            match block_on(service.update(&todo, None)) {
+                Err(TodoServiceUpdateErr::Flooded { id, retry_after }) => {
+                    assert_eq!(TodoId(1), id);
+                    assert!(retry_after <= Duration::from_secs(60));
+                }
+                _ => panic!("Not turned down"),
+            }
+        }
+        assert_eq!(2, *mock_repo.update_called.lock().unwrap());
+        // Only the first of the storm is recorded
+        let changes = audit_sink.changes.lock().unwrap();
+        let rejected: Vec<_> = changes.iter().filter(|c| c.rejected.is_some()).collect();
+        assert_eq!(1, rejected.len());
+        assert_eq!(Some(todo), rejected[0].new);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mock_repo = MockTodoRepo::new();
+        let rule = Rule {
+            id: RuleId(1),
+            name: "follow up".to_string(),
+            when: Condition {
+                event: Trigger::Updated,
+                task_contains: None,
+            },
+            then: Action::CreateTodo {
+                task: "nope, not {{task}}".to_string(),
+            },
+        };
+        let service = new(mock_repo.clone())
+            .with_rules(Arc::new(MockRuleRepo(vec![rule])))
+            .with_validators(vec![Arc::new(MockValidator)]);
+        let update_data = Todo {
+            id: TodoId(2),
+            task: RETRIEVED_TODO_TASK.to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 2,
+        };
+        match block_on(service.update(&update_data, None)) {
+            Ok(updated) => assert_eq!(
+                vec![
+                    "Todo [TodoId(1)] has the same task".to_string(),
+                    "Follow-up [nope, not say hello] was not created, as it is invalid".to_string()
+                ],
+                updated.warnings
+            ),
+            Err(_) => panic!("Warnings failed the update"),
+        }
+        assert_eq!(0, *mock_repo.create_called.lock().unwrap());
+    }
+
+    #[test]
+    fn test_lists() {
+        let mock_repo = MockTodoRepo::new();
+        let service = new(mock_repo.clone()).with_lists(Arc::new(MockListRepo));
+        let todo_data = |list_id| TodoData {
+            task: "hello".to_string(),
+            done: false,
+            list_id,
+        };
+        match block_on(service.create(&todo_data(Some(ListId(2))))) {
+            Err(TodoServiceDataErr::NoSuchList(id)) => assert_eq!(ListId(2), id),
+            _ => panic!("Put in a list that doesn't exist"),
+        }
+        match block_on(service.create(&todo_data(Some(ListId(1))))) {
+            Ok(created) => assert_eq!(Some(ListId(1)), created.todo.list_id),
+            Err(_) => panic!("Not put in an existing list"),
+        }
+        assert_eq!(1, *mock_repo.create_called.lock().unwrap());
+    }
+
+    #[derive(Clone, Default)]
+    struct MockAuditSink {
+        changes: Arc<Mutex<Vec<TodoChange>>>,
+    }
+
+    #[async_trait]
+    impl AuditSink for MockAuditSink {
+        async fn record(&self, change: &TodoChange) {
+            self.changes.lock().unwrap().push(change.clone());
+        }
+    }
+
+    struct MockValidator;
+
+    #[async_trait]
+    impl TodoValidator for MockValidator {
+        async fn validate(&self, todo_data: &TodoData) -> Result<(), String> {
+            if todo_data.task.contains("nope") {
+                Err("no nopes".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    struct MockListRepo;
+
+    #[async_trait]
+    impl ListRepo for MockListRepo {
+        async fn create(&self, _: &ListData) -> List {
+            unimplemented!()
+        }
+
+        async fn get(&self, list_id: &ListId) -> Result<List, ListRepoErr> {
+            if *list_id == ListId(1) {
+                Ok(List {
+                    id: *list_id,
+                    name: "Chores".to_string(),
+                })
+            } else {
+                Err(ListRepoErr::NotFound(*list_id))
+            }
+        }
+
+        async fn list(&self) -> Vec<List> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, _: &ListId) -> Result<(), ListRepoErr> {
+            unimplemented!()
+        }
+    }
+
+    struct MockRuleRepo(Vec<Rule>);
+
+    #[async_trait]
+    impl RuleRepo for MockRuleRepo {
+        async fn create(&self, _: &RuleData) -> Rule {
+            unimplemented!()
+        }
+
+        async fn list(&self) -> Vec<Rule> {
+            self.0.clone()
+        }
+
+        async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleRepoErr> {
+            Err(RuleRepoErr::NotFound(*rule_id))
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockSubscriber {
+        notify_called: Arc<Mutex<usize>>,
+    }
+
+    impl MockSubscriber {
+        fn new() -> MockSubscriber {
+            MockSubscriber {
+                notify_called: Arc::new(Mutex::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Subscriber for MockSubscriber {
+        async fn notify(&self, _: &TodoEvent) {
+            let mut mutex = self.notify_called.lock().unwrap();
+            *mutex += 1;
+        }
+    }
+
     #[derive(Clone)]
     struct MockTodoRepo {
         create_called: Arc<Mutex<usize>>,
@@ -259,17 +1523,21 @@ mod tests {
 
     static NOT_FOUND_TODO_ID: TodoId = TodoId(999);
     static RETRIEVED_TODO_TASK: &str = "say hello";
+    static MOCK_STALE_VERSION: u64 = 0;
 
     #[async_trait]
     impl TodoRepo for MockTodoRepo {
-        async fn create(&self, todo_data: &TodoData) -> Todo {
+        async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
             let mut mutex = self.create_called.lock().unwrap();
             *mutex += 1;
-            let saved = Todo {
+            Ok(Todo {
                 id: TodoId(1),
                 task: todo_data.task.clone(),
-            };
-            saved
+                done: todo_data.done,
+                version: 1,
+                list_id: todo_data.list_id,
+                position: 1,
+            })
         }
 
         async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
@@ -281,17 +1549,25 @@ mod tests {
                 Ok(Todo {
                     id: *todo_id,
                     task: RETRIEVED_TODO_TASK.to_string(),
+                    done: false,
+                    version: 1,
+                    list_id: None,
+                    position: 0,
                 })
             }
         }
 
-        async fn list(&self) -> Vec<Todo> {
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
             let mut mutex = self.list_called.lock().unwrap();
             *mutex += 1;
-            vec![Todo {
+            Ok(vec![Todo {
                 id: TodoId(1),
                 task: RETRIEVED_TODO_TASK.to_string(),
-            }]
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
+            }])
         }
 
         async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoRepoErr> {
@@ -304,14 +1580,88 @@ mod tests {
             }
         }
 
-        async fn update(&self, todo: &Todo) -> Result<(), TodoRepoErr> {
+        async fn update(
+            &self,
+            todo: &Todo,
+            expected_version: Option<u64>,
+        ) -> Result<Todo, TodoRepoUpdateErr> {
             let mut mutex = self.update_called.lock().unwrap();
             *mutex += 1;
-            if &todo.id == &NOT_FOUND_TODO_ID {
-                Err(TodoRepoErr::NotFound(todo.id))
+            if todo.id == NOT_FOUND_TODO_ID {
+                Err(TodoRepoUpdateErr::NotFound(todo.id))
+            } else if expected_version == Some(MOCK_STALE_VERSION) {
+                Err(TodoRepoUpdateErr::VersionConflict(todo.id))
             } else {
-                Ok(())
+                Ok(Todo {
+                    version: todo.version + 1,
+                    ..todo.clone()
+                })
             }
         }
+
+        async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            let ids = match selection {
+                TodoSelection::Ids(ids) => ids.clone(),
+                TodoSelection::List(_) => vec![TodoId(1)],
+            };
+            let (not_found, found): (Vec<_>, Vec<_>) =
+                ids.into_iter().partition(|id| *id == NOT_FOUND_TODO_ID);
+            Ok(Completed {
+                completed: found
+                    .into_iter()
+                    .map(|id| Todo {
+                        id,
+                        task: RETRIEVED_TODO_TASK.to_string(),
+                        done: true,
+                        version: 2,
+                        list_id: None,
+                        position: 0,
+                    })
+                    .collect(),
+                not_found,
+                warnings: Vec::new(),
+            })
+        }
+
+        async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            match ids.iter().find(|id| **id == NOT_FOUND_TODO_ID) {
+                Some(id) => Err(TodoRepoErr::NotFound(*id)),
+                None => Ok(ids
+                    .iter()
+                    .enumerate()
+                    .map(|(i, id)| Todo {
+                        id: *id,
+                        task: RETRIEVED_TODO_TASK.to_string(),
+                        done: false,
+                        version: 1,
+                        list_id: None,
+                        position: i as u64 + 1,
+                    })
+                    .collect()),
+            }
+        }
+
+        async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            Ok(self
+                .list()
+                .await?
+                .into_iter()
+                .filter(|todo| same_task(&todo.task, task))
+                .collect())
+        }
+    }
+
+    #[async_trait]
+    impl TodoUnitOfWork for MockTodoRepo {
+        type Tx = MockTodoRepo;
+
+        async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+        where
+            T: Send,
+            E: Send,
+            F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+        {
+            work(self).await
+        }
     }
 }
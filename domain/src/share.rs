@@ -0,0 +1,87 @@
+use crate::todo::*;
+
+#[cfg(feature = "services")]
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+// Roughly 100 years; also keeps the expiry representable
+pub static MAX_EXPIRES_IN_SECS: u64 = 100 * 365 * 24 * 60 * 60;
+
+/// Unguessable, so that knowing it is what grants access
+#[derive(PartialEq, Eq, Ord, PartialOrd, Debug, Clone, Hash)]
+pub struct ShareToken(pub String);
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ShareData {
+    /// Never expires when not set
+    pub expires_in_secs: Option<u64>,
+}
+
+impl ShareData {
+    /// Returns why the share can't be created
+    pub fn validate(&self) -> Result<(), String> {
+        match self.expires_in_secs {
+            Some(0) => Err("expires_in_secs must be greater than 0".to_string()),
+            Some(secs) if secs > MAX_EXPIRES_IN_SECS => Err(format!(
+                "expires_in_secs must be at most {}",
+                MAX_EXPIRES_IN_SECS
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Read-only access to a single [[Todo]] for whoever has the token
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Share {
+    pub token: ShareToken,
+    pub todo_id: TodoId,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Share {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now < expires_at,
+            None => true,
+        }
+    }
+}
+
+// The algebra for a [[Share]] repository, dealing w/ persistence
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait ShareRepo {
+    /// Generates the token
+    async fn create(&self, todo_id: &TodoId, expires_at: Option<DateTime<Utc>>) -> Share;
+    async fn get(&self, token: &ShareToken) -> Result<Share, ShareRepoErr>;
+    /// Includes expired shares
+    async fn list_for(&self, todo_id: &TodoId) -> Vec<Share>;
+    async fn delete(&self, token: &ShareToken) -> Result<(), ShareRepoErr>;
+}
+
+#[derive(Error, Debug)]
+pub enum ShareRepoErr {
+    #[error("No such share")]
+    NotFound(ShareToken),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_is_active() {
+        let now = Utc::now();
+        let share = |expires_at| Share {
+            token: ShareToken("t".to_string()),
+            todo_id: TodoId(1),
+            expires_at,
+        };
+        assert!(share(None).is_active(now));
+        assert!(share(Some(now + Duration::seconds(1))).is_active(now));
+        assert!(!share(Some(now)).is_active(now));
+    }
+}
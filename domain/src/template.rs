@@ -0,0 +1,203 @@
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Expands `{{placeholder}}`s in task templates, e.g. "Standup notes {{date}}".
+///
+/// Built-in placeholders are `date` (2024-01-31), `week` (2024-W05), `year`, `seq` (a counter
+/// that goes up on every expansion) and `seq:<name>` (a separately counted, named counter).
+/// Anything else has to be given as a variable. Single braces are left as they are.
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Error, PartialEq, Eq, Debug)]
+pub enum TemplateErr {
+    #[error("unclosed placeholder at {at}")]
+    Unclosed { at: usize },
+    #[error("unknown placeholder [{placeholder}]")]
+    Unknown { placeholder: String },
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Date,
+    Week,
+    Year,
+    Seq(Option<String>),
+    Var(String),
+}
+
+/// What placeholders expand to
+pub struct TemplateContext<'a> {
+    pub today: NaiveDate,
+    pub vars: &'a [(&'a str, String)],
+    pub sequences: &'a Sequences,
+    // Counter used by a bare `{{seq}}`, so that e.g. each rule counts on its own
+    pub seq_scope: &'a str,
+}
+
+/// Counters for `seq` placeholders; clones share counts
+#[derive(Clone, Default)]
+pub struct Sequences {
+    counters: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl Sequences {
+    /// 1 the first time a name is seen
+    pub fn next(&self, name: &str) -> u64 {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let counter = counters.entry(name.to_string()).or_insert(0);
+        *counter += 1;
+        *counter
+    }
+
+    /// A copy that starts at the current counts but doesn't share them, e.g. for previews that
+    /// shouldn't use numbers up
+    pub fn detached(&self) -> Sequences {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        Sequences {
+            counters: Arc::new(Mutex::new(counters.clone())),
+        }
+    }
+}
+
+/// Parses the template, only accepting variables in `var_names`
+pub fn parse(template: &str, var_names: &[&str]) -> Result<Template, TemplateErr> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            parts.push(Part::Literal(rest[..start].to_string()));
+        }
+        let after_open = &rest[start + 2..];
+        let end = after_open.find("}}").ok_or(TemplateErr::Unclosed {
+            at: template.len() - rest.len() + start,
+        })?;
+        let placeholder = after_open[..end].trim();
+        parts.push(match placeholder {
+            "date" => Part::Date,
+            "week" => Part::Week,
+            "year" => Part::Year,
+            "seq" => Part::Seq(None),
+            _ => match placeholder.strip_prefix("seq:") {
+                Some(name) if !name.trim().is_empty() => Part::Seq(Some(name.trim().to_string())),
+                _ if var_names.contains(&placeholder) => Part::Var(placeholder.to_string()),
+                _ => {
+                    return Err(TemplateErr::Unknown {
+                        placeholder: placeholder.to_string(),
+                    })
+                }
+            },
+        });
+        rest = &after_open[end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+    Ok(Template { parts })
+}
+
+impl Template {
+    pub fn expand(&self, context: &TemplateContext) -> String {
+        let mut expanded = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => expanded.push_str(s),
+                Part::Date => expanded.push_str(&context.today.format("%Y-%m-%d").to_string()),
+                Part::Week => {
+                    let week = context.today.iso_week();
+                    expanded.push_str(&format!("{}-W{:02}", week.year(), week.week()))
+                }
+                Part::Year => expanded.push_str(&context.today.year().to_string()),
+                Part::Seq(name) => {
+                    let name = name.as_deref().unwrap_or(context.seq_scope);
+                    expanded.push_str(&context.sequences.next(name).to_string())
+                }
+                Part::Var(name) => {
+                    if let Some((_, value)) = context.vars.iter().find(|(n, _)| n == name) {
+                        expanded.push_str(value)
+                    }
+                }
+            }
+        }
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(template: &str, today: NaiveDate, sequences: &Sequences) -> String {
+        let vars = [("task", "Fix {it}".to_string())];
+        let context = TemplateContext {
+            today,
+            vars: &vars,
+            sequences,
+            seq_scope: "test",
+        };
+        parse(template, &["task", "id"]).unwrap().expand(&context)
+    }
+
+    #[test]
+    fn test_expand() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let sequences = Sequences::default();
+        assert_eq!(
+            "Notes 2024-01-31 (2024-W05): Fix {it}",
+            expand("Notes {{date}} ({{ week }}): {{task}}", today, &sequences)
+        );
+        assert_eq!(
+            "{single} braces",
+            expand("{single} braces", today, &sequences)
+        );
+        // Vars without a value expand to nothing
+        assert_eq!("#", expand("#{{id}}", today, &sequences));
+    }
+
+    #[test]
+    fn test_iso_week_edges() {
+        let sequences = Sequences::default();
+        // Belongs to the last week of the previous ISO year
+        let new_years_day = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        assert_eq!(
+            "2020-W53 2021",
+            expand("{{week}} {{year}}", new_years_day, &sequences)
+        );
+        // Belongs to the first week of the next one
+        let late_december = NaiveDate::from_ymd_opt(2024, 12, 30).unwrap();
+        assert_eq!("2025-W01", expand("{{week}}", late_december, &sequences));
+    }
+
+    #[test]
+    fn test_sequences() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let sequences = Sequences::default();
+        assert_eq!(
+            "1 1 2",
+            expand("{{seq}} {{seq:sprint}} {{seq}}", today, &sequences)
+        );
+        assert_eq!("3 2", expand("{{seq}} {{seq:sprint}}", today, &sequences));
+        // Clones share counts
+        assert_eq!("3", expand("{{seq:sprint}}", today, &sequences.clone()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Some(TemplateErr::Unclosed { at: 3 }),
+            parse("Hi {{date", &[]).err()
+        );
+        assert_eq!(
+            Some(TemplateErr::Unknown {
+                placeholder: "task".to_string()
+            }),
+            parse("{{task}}", &[]).err()
+        );
+        assert!(parse("{{seq:}}", &[]).is_err());
+        assert!(parse("{{}}", &[]).is_err());
+    }
+}
@@ -1,29 +1,295 @@
+use crate::events::TodoEvent;
+use crate::list::ListId;
+#[cfg(feature = "services")]
 use async_trait::async_trait;
+#[cfg(feature = "services")]
+use futures::future::BoxFuture;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
 
+// Sequential ids fit in 64 bits; ids generated as UUIDs take all 128
 #[derive(PartialEq, Eq, Ord, PartialOrd, Debug, Copy, Clone, Hash)]
-pub struct TodoId(pub u64);
+pub struct TodoId(pub u128);
+
+// Ids that fit in 64 bits as their number, and wider ones as hyphenated UUIDs
+impl fmt::Display for TodoId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let id = self.0;
+        if id <= u128::from(u64::MAX) {
+            return write!(f, "{}", id);
+        }
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            id >> 96,
+            (id >> 80) & 0xffff,
+            (id >> 64) & 0xffff,
+            (id >> 48) & 0xffff,
+            id & 0xffff_ffff_ffff
+        )
+    }
+}
+
+// Reads what Display writes
+impl FromStr for TodoId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let not_an_id = || format!("Not an id: {}", s);
+        if !s.contains('-') {
+            return s
+                .parse::<u64>()
+                .map(|id| TodoId(id.into()))
+                .map_err(|_| not_an_id());
+        }
+        let groups: Vec<&str> = s.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+        let hex = groups.concat();
+        if lengths != [8, 4, 4, 4, 12] || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(not_an_id());
+        }
+        match u128::from_str_radix(&hex, 16) {
+            // Narrower ids are only ever written as numbers
+            Ok(id) if id > u128::from(u64::MAX) => Ok(TodoId(id)),
+            _ => Err(not_an_id()),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub mod repo_tests;
+
+/// Picks the ids of new todos; implementations live in infra
+#[cfg(feature = "services")]
+pub trait IdGenerator {
+    fn next_id(&self) -> TodoId;
+}
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct TodoData {
     pub task: String,
+    pub done: bool,
+    pub list_id: Option<ListId>,
+}
+
+impl TodoData {
+    /// The built-in checks, run by the service before any validators
+    pub fn is_valid(&self) -> bool {
+        !self.task.is_empty()
+    }
+}
+
+/// Whether two tasks are the same as far as finding duplicates goes, i.e. but for case and
+/// whitespace
+pub fn same_task(a: &str, b: &str) -> bool {
+    let words = |task: &str| {
+        task.split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+    };
+    words(a) == words(b)
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Todo {
     pub id: TodoId,
     pub task: String,
+    pub done: bool,
+    // Starts at 1 and is bumped on every update, so that writers can tell whether they're stale
+    pub version: u64,
+    pub list_id: Option<ListId>,
+    // Where it goes when todos are in manual order; new todos go last
+    pub position: u64,
+}
+
+/// A todo as just saved, along with non-fatal issues worth telling whoever saved it
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SavedTodo {
+    pub todo: Todo,
+    pub warnings: Vec<String>,
+}
+
+/// What a change would do if it were made, for previewing it without saving or emitting anything
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TodoPlan {
+    /// As it would be saved, or None when it would be deleted. Todos that would be created have
+    /// an id of 0, since ids are only assigned when saving.
+    pub todo: Option<Todo>,
+    /// In the order they would be emitted, including the creation of rules' follow-ups
+    pub events: Vec<TodoEvent>,
+    pub warnings: Vec<String>,
+}
+
+/// Which todos a bulk change applies to
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TodoSelection {
+    Ids(Vec<TodoId>),
+    /// Every todo in the list
+    List(ListId),
+}
+
+/// What marking several todos done at once did
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Completed {
+    /// The todos that were open, as they are now; ones that were already done are left alone
+    pub completed: Vec<Todo>,
+    /// Selected ids that no todo has
+    pub not_found: Vec<TodoId>,
+    /// Non-fatal issues, e.g. rules' follow-ups that weren't created; repos leave it empty
+    pub warnings: Vec<String>,
+}
+
+/// How many todos a repo holds, for admins
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct TodoRepoStats {
+    pub count: usize,
+    /// The highest id, which with sequential ids is the latest todo's
+    pub last_id: Option<TodoId>,
 }
 
 // The algebra for a [[Todo]] repository, dealing w/ persistence
+#[cfg(feature = "services")]
 #[async_trait]
 pub trait TodoRepo {
-    async fn create(&self, todo_data: &TodoData) -> Todo;
+    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr>;
     async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr>;
-    async fn list(&self) -> Vec<Todo>;
+    async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr>;
+    // Todos in id order, skipping the first `offset` and returning at most `limit`; repos that can
+    // should read just those rather than listing every todo
+    async fn list_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, TodoRepoErr> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
     async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoRepoErr>;
-    async fn update(&self, todo: &Todo) -> Result<(), TodoRepoErr>;
+    // Replaces the task, done and list of the todo with the same id, bumping its version. When an
+    // expected version is given and the stored todo is at another one, nothing is written.
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Todo, TodoRepoUpdateErr>;
+    // Marks the selected todos that are still open done, bumping their versions, all under one
+    // write so that nothing else sees only some of them done
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoRepoErr>;
+    // Moves the todos with the given ids to the front in that order, followed by the rest in their
+    // current manual order, and returns every todo in the new order. The versions of the todos
+    // that moved are bumped. Fails without moving anything when an id has no todo.
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr>;
+    // Every todo whose task is the [[same_task]] as the given one, oldest first
+    async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr>;
+    // Maintenance for admins, going around the service so that nothing is emitted or audited.
+    // Repos that can should do each under one write rather than todo by todo.
+    //
+    // Deletes every done todo, returning how many there were
+    async fn purge_done(&self) -> Result<usize, TodoRepoErr> {
+        let mut purged = 0;
+        for todo in self.list().await?.into_iter().filter(|todo| todo.done) {
+            match self.delete(&todo.id).await {
+                Ok(()) => purged += 1,
+                // Deleted by someone else in the meantime
+                Err(TodoRepoErr::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(purged)
+    }
+    // Deletes every todo, returning how many there were
+    async fn delete_all(&self) -> Result<usize, TodoRepoErr> {
+        let mut deleted = 0;
+        for todo in self.list().await? {
+            match self.delete(&todo.id).await {
+                Ok(()) => deleted += 1,
+                Err(TodoRepoErr::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(deleted)
+    }
+    async fn todo_stats(&self) -> Result<TodoRepoStats, TodoRepoErr> {
+        let todos = self.list().await?;
+        Ok(TodoRepoStats {
+            count: todos.len(),
+            last_id: todos.last().map(|todo| todo.id),
+        })
+    }
+}
+
+// Runs several reads and writes of todos as one: when the work fails none of its writes are kept,
+// and nothing else sees them half done. How is up to the implementation, e.g. holding a lock in
+// memory or a transaction in SQL.
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait TodoUnitOfWork {
+    // What the work reads and writes through; going around it to the repo the work was started on
+    // may wait until the work is done, i.e. forever
+    type Tx: TodoRepo + Send + Sync;
+
+    async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+    where
+        T: Send,
+        E: Send,
+        F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send;
 }
 
+// Checks on [[TodoData]] on top of the built-in ones; implementations live in infra (plugins..)
+#[cfg(feature = "services")]
+#[async_trait]
+pub trait TodoValidator {
+    /// Returns why the data was rejected
+    async fn validate(&self, todo_data: &TodoData) -> Result<(), String>;
+}
+
+#[derive(Error, Debug)]
 pub enum TodoRepoErr {
+    #[error("No such todo: [{0:?}]")]
     NotFound(TodoId),
+    // The backend couldn't be reached, e.g. its connection pool is exhausted
+    #[error("Could not connect to the todos' storage: {0}")]
+    ConnectionError(String),
+    // Another write to the todo got in the way, e.g. a serialization failure; retrying may work
+    #[error("Another write to todo [{0:?}] got in the way")]
+    Conflict(TodoId),
+    // The backend took too long to answer
+    #[error("The todos' storage timed out")]
+    Timeout,
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum TodoRepoUpdateErr {
+    #[error("No such todo: [{0:?}]")]
+    NotFound(TodoId),
+    #[error("Todo [{0:?}] has been updated since the expected version")]
+    VersionConflict(TodoId),
+    // For anything else that went wrong
+    #[error(transparent)]
+    Failed(TodoRepoErr),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_todo_id_strings() {
+        let uuid = TodoId(0x6f9619ff_8b86_d011_b42d_00c04fc964ff);
+        for id in [TodoId(1), TodoId(u64::MAX.into()), uuid] {
+            assert_eq!(Ok(id), id.to_string().parse());
+        }
+        assert_eq!("42", TodoId(42).to_string());
+        assert_eq!("6f9619ff-8b86-d011-b42d-00c04fc964ff", uuid.to_string());
+        for not_an_id in ["", "-1", "0x2a", "6f9619ff8b86d011b42d00c04fc964ff"] {
+            assert!(not_an_id.parse::<TodoId>().is_err());
+        }
+        assert!("00000000-0000-0000-0000-00000000002a"
+            .parse::<TodoId>()
+            .is_err());
+    }
 }
@@ -0,0 +1,170 @@
+//! Checks that every [[TodoRepo]] has to pass, so that a new backend's tests can run them rather
+//! than writing their own, e.g. `block_on(repo_tests::run(my_repo::new))`. They panic on the
+//! first thing the repo gets wrong.
+
+use super::*;
+use futures::future::join_all;
+use std::collections::HashSet;
+
+/// Runs every check, each on a fresh, empty repo made by `new_repo`
+pub async fn run<R, F>(new_repo: F)
+where
+    R: TodoRepo + Send + Sync,
+    F: Fn() -> R,
+{
+    create_and_get(&new_repo()).await;
+    not_found(&new_repo()).await;
+    update(&new_repo()).await;
+    delete(&new_repo()).await;
+    reorder(&new_repo()).await;
+    maintenance(&new_repo()).await;
+    concurrent_creates(&new_repo()).await;
+}
+
+fn todo_data(task: &str) -> TodoData {
+    TodoData {
+        task: task.to_string(),
+        done: false,
+        list_id: None,
+    }
+}
+
+/// Created todos are at version 1 and can be got and listed as they were created
+pub async fn create_and_get<R: TodoRepo>(repo: &R) {
+    let created = repo.create(&todo_data("Make the bed")).await.unwrap();
+    assert_eq!("Make the bed", created.task);
+    assert!(!created.done);
+    assert_eq!(1, created.version);
+    match repo.get(&created.id).await {
+        Ok(got) => assert_eq!(created, got),
+        Err(e) => panic!("Could not get a created todo: {}", e),
+    }
+    assert_eq!(vec![created], repo.list().await.unwrap());
+}
+
+/// Ids that no todo has fail as not found, and nothing is written
+pub async fn not_found<R: TodoRepo>(repo: &R) {
+    let missing = TodoId(404);
+    match repo.get(&missing).await {
+        Err(TodoRepoErr::NotFound(id)) => assert_eq!(missing, id),
+        other => panic!("Expected not found, got {:?}", other),
+    }
+    match repo.delete(&missing).await {
+        Err(TodoRepoErr::NotFound(id)) => assert_eq!(missing, id),
+        other => panic!("Expected not found, got {:?}", other),
+    }
+    let todo = Todo {
+        id: missing,
+        task: "Make the bed".to_string(),
+        done: false,
+        version: 1,
+        list_id: None,
+        position: 1,
+    };
+    match repo.update(&todo, None).await {
+        Err(TodoRepoUpdateErr::NotFound(id)) => assert_eq!(missing, id),
+        other => panic!("Expected not found, got {:?}", other),
+    }
+    match repo.reorder(&[missing]).await {
+        Err(TodoRepoErr::NotFound(id)) => assert_eq!(missing, id),
+        other => panic!("Expected not found, got {:?}", other),
+    }
+    assert!(repo.list().await.unwrap().is_empty());
+}
+
+/// Updates bump the version, and aren't written when the expected version is stale
+pub async fn update<R: TodoRepo>(repo: &R) {
+    let created = repo.create(&todo_data("Make the bed")).await.unwrap();
+    let done = Todo {
+        done: true,
+        ..created.clone()
+    };
+    let updated = match repo.update(&done, Some(created.version)).await {
+        Ok(updated) => updated,
+        Err(e) => panic!("Could not update a todo: {}", e),
+    };
+    assert_eq!(
+        Todo {
+            version: created.version + 1,
+            ..done.clone()
+        },
+        updated
+    );
+    let renamed = Todo {
+        task: "Do the dishes".to_string(),
+        ..done
+    };
+    match repo.update(&renamed, Some(created.version)).await {
+        Err(TodoRepoUpdateErr::VersionConflict(id)) => assert_eq!(created.id, id),
+        other => panic!("Expected a version conflict, got {:?}", other),
+    }
+    assert_eq!(updated, repo.get(&created.id).await.unwrap());
+}
+
+/// Deleted todos are gone, and deleting them again fails as not found
+pub async fn delete<R: TodoRepo>(repo: &R) {
+    let kept = repo.create(&todo_data("Make the bed")).await.unwrap();
+    let deleted = repo.create(&todo_data("Do the dishes")).await.unwrap();
+    assert!(repo.delete(&deleted.id).await.is_ok());
+    assert!(matches!(
+        repo.get(&deleted.id).await,
+        Err(TodoRepoErr::NotFound(_))
+    ));
+    assert!(matches!(
+        repo.delete(&deleted.id).await,
+        Err(TodoRepoErr::NotFound(_))
+    ));
+    assert_eq!(vec![kept], repo.list().await.unwrap());
+}
+
+/// Reordering moves the given todos to the front, bumping the versions of just those that moved
+pub async fn reorder<R: TodoRepo>(repo: &R) {
+    let first = repo.create(&todo_data("Make the bed")).await.unwrap();
+    let second = repo.create(&todo_data("Do the dishes")).await.unwrap();
+    let third = repo.create(&todo_data("Water the plants")).await.unwrap();
+    let reordered = repo.reorder(&[second.id, first.id]).await.unwrap();
+    let order: Vec<_> = reordered.iter().map(|t| (t.id, t.version)).collect();
+    assert_eq!(vec![(second.id, 2), (first.id, 2), (third.id, 1)], order);
+    assert_eq!(reordered[1], repo.get(&first.id).await.unwrap());
+}
+
+/// Purging deletes just the done todos, deleting everything leaves the repo empty, and both
+/// say how many they deleted
+pub async fn maintenance<R: TodoRepo + Sync>(repo: &R) {
+    assert_eq!(TodoRepoStats::default(), repo.todo_stats().await.unwrap());
+    let open = repo.create(&todo_data("Make the bed")).await.unwrap();
+    let done = repo
+        .create(&TodoData {
+            done: true,
+            ..todo_data("Do the dishes")
+        })
+        .await
+        .unwrap();
+    let stats = repo.todo_stats().await.unwrap();
+    assert_eq!(2, stats.count);
+    assert_eq!(Some(open.id.max(done.id)), stats.last_id);
+    assert_eq!(1, repo.purge_done().await.unwrap());
+    assert!(matches!(
+        repo.get(&done.id).await,
+        Err(TodoRepoErr::NotFound(_))
+    ));
+    assert_eq!(vec![open.clone()], repo.list().await.unwrap());
+    assert_eq!(1, repo.delete_all().await.unwrap());
+    assert!(matches!(
+        repo.get(&open.id).await,
+        Err(TodoRepoErr::NotFound(_))
+    ));
+    assert_eq!(TodoRepoStats::default(), repo.todo_stats().await.unwrap());
+}
+
+/// Todos created at the same time all get ids of their own
+pub async fn concurrent_creates<R: TodoRepo>(repo: &R) {
+    let data: Vec<TodoData> = (0..50).map(|i| todo_data(&format!("Task {}", i))).collect();
+    let created = join_all(data.iter().map(|todo_data| repo.create(todo_data))).await;
+    let ids: HashSet<TodoId> = created
+        .into_iter()
+        .map(|todo| todo.expect("Could not create a todo").id)
+        .collect();
+    assert_eq!(data.len(), ids.len(), "Created todos share ids");
+    assert_eq!(data.len(), repo.list().await.unwrap().len());
+}
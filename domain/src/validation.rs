@@ -0,0 +1,83 @@
+use regex::Regex;
+
+/// Checks on tasks on top of the built-in one that they aren't empty, set up at startup. The
+/// default only has the built-in check.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationPolicy {
+    /// Whether whitespace is trimmed from both ends of tasks before they are checked and saved
+    pub trim: bool,
+    /// In characters
+    pub max_len: Option<usize>,
+    /// Tasks with a match are turned down
+    pub disallowed: Option<Regex>,
+    /// Whether open todos are turned down when another open todo has the same task, rather than
+    /// only warned about
+    pub unique: bool,
+}
+
+impl ValidationPolicy {
+    /// Fails with why when `pattern` isn't a valid regex
+    pub fn disallowing(self, pattern: &str) -> Result<ValidationPolicy, String> {
+        let disallowed = Regex::new(pattern).map_err(|e| e.to_string())?;
+        Ok(ValidationPolicy {
+            disallowed: Some(disallowed),
+            ..self
+        })
+    }
+
+    /// The task as it would be saved
+    pub fn normalize(&self, task: &str) -> String {
+        if self.trim {
+            task.trim().to_string()
+        } else {
+            task.to_string()
+        }
+    }
+
+    /// Returns why the normalized task is turned down. Uniqueness is left to the service, which
+    /// knows about the other todos.
+    pub fn check(&self, task: &str) -> Result<(), String> {
+        if let Some(max_len) = self.max_len {
+            let len = task.chars().count();
+            if len > max_len {
+                return Err(format!(
+                    "Tasks can be at most {} characters long, but this one is {}",
+                    max_len, len
+                ));
+            }
+        }
+        if let Some(found) = self.disallowed.as_ref().and_then(|d| d.find(task)) {
+            return Err(format!("Tasks may not contain [{}]", found.as_str()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let policy = ValidationPolicy {
+            trim: true,
+            max_len: Some(6),
+            ..ValidationPolicy::default()
+        }
+        .disallowing("(?i)urgent")
+        .unwrap();
+        assert_eq!("hello", policy.normalize("  hello\n"));
+        assert!(policy.check("hello").is_ok());
+        assert!(policy.check("héllo!").is_ok());
+        assert!(policy.check("hello!!").is_err());
+        assert_eq!(
+            Err("Tasks may not contain [URGENT]".to_string()),
+            policy.check("URGENT")
+        );
+        assert!(ValidationPolicy::default().disallowing("(").is_err());
+
+        let default = ValidationPolicy::default();
+        assert_eq!("  hello ", default.normalize("  hello "));
+        assert!(default.check(&"a".repeat(10_000)).is_ok());
+    }
+}
@@ -0,0 +1,64 @@
+// Reads of the in-memory todo repo from several threads at once, which shouldn't have to wait
+// for each other. Run with:
+//
+//     cargo bench -p infra --bench todo_repo
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use domain::todo::{TodoData, TodoId, TodoRepo};
+use futures::executor::block_on;
+use infra::in_mem::todo_repo;
+use std::thread;
+use std::time::{Duration, Instant};
+
+static TODOS: u64 = 1_000;
+// Split between the threads, so that more threads only help if they can read at once
+static READS: u64 = 512;
+
+fn bench_reads(c: &mut Criterion) {
+    let repo = todo_repo::new();
+    block_on(async {
+        for i in 0..TODOS {
+            let todo_data = TodoData {
+                task: format!("Task number {}", i),
+                done: false,
+                list_id: None,
+            };
+            repo.create(&todo_data).await.unwrap();
+        }
+    });
+    let mut group = c.benchmark_group("reads");
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::new("list_and_get", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    let mut elapsed = Duration::ZERO;
+                    for _ in 0..iters {
+                        let start = Instant::now();
+                        thread::scope(|s| {
+                            for t in 0..threads {
+                                let repo = &repo;
+                                s.spawn(move || {
+                                    for i in 0..READS / threads {
+                                        block_on(async {
+                                            repo.list().await.unwrap();
+                                            repo.get(&TodoId(u128::from((t * i) % TODOS + 1)))
+                                                .await
+                                                .unwrap();
+                                        })
+                                    }
+                                });
+                            }
+                        });
+                        elapsed += start.elapsed();
+                    }
+                    elapsed
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_reads);
+criterion_main!(benches);
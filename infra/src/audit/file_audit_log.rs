@@ -0,0 +1,188 @@
+use crate::ids::id_json::{self, todo_json};
+use chrono::{DateTime, Utc};
+use domain::history::*;
+use domain::list::ListId;
+use domain::todo::Todo;
+use futures_locks::Mutex;
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+/// Appends every change to a JSON Lines file, which is never truncated
+#[derive(Clone)]
+pub struct FileAuditLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+/// Creates the file if needed; changes are appended to whatever is already in it
+pub fn open(path: &Path) -> io::Result<FileAuditLog> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(FileAuditLog {
+        path: path.to_path_buf(),
+        file: Mutex::new(file),
+    })
+}
+
+#[async_trait]
+impl AuditSink for FileAuditLog {
+    async fn record(&self, change: &TodoChange) {
+        let line = format!("{}\n", to_json(change));
+        let mut file = self.file.lock().await;
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            log::error!(
+                "Could not append to audit log [{}]: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl AuditLog for FileAuditLog {
+    async fn since(&self, since: Option<DateTime<Utc>>) -> Result<Vec<TodoChange>, AuditLogErr> {
+        let mut changes = self.read().await?;
+        changes.retain(|change| since.is_none_or(|since| change.at >= since));
+        Ok(changes)
+    }
+
+    // Positions are line numbers, as nothing is ever dropped
+    async fn after(&self, seq: u64) -> Result<Vec<SequencedChange>, AuditLogErr> {
+        Ok(self
+            .read()
+            .await?
+            .into_iter()
+            .zip(1..)
+            .skip_while(|(_, position)| *position <= seq)
+            .map(|(change, position)| SequencedChange {
+                seq: position,
+                change,
+            })
+            .collect())
+    }
+}
+
+impl FileAuditLog {
+    async fn read(&self) -> Result<Vec<TodoChange>, AuditLogErr> {
+        // Held so that a half-written line isn't read
+        let _file = self.file.lock().await;
+        let unreadable = |e: &dyn std::fmt::Display| AuditLogErr::Unreadable {
+            reason: format!("[{}]: {}", self.path.display(), e),
+        };
+        let reader = BufReader::new(File::open(&self.path).map_err(|e| unreadable(&e))?);
+        let mut changes = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(|e| unreadable(&e))?;
+            let change = serde_json::from_str(&line)
+                .ok()
+                .and_then(|v| from_json(&v))
+                .ok_or_else(|| unreadable(&format!("malformed line [{}]", line)))?;
+            changes.push(change);
+        }
+        Ok(changes)
+    }
+}
+
+fn to_json(change: &TodoChange) -> Value {
+    json!({
+        "todo_id": id_json::to_json(change.todo_id),
+        "old": change.old.as_ref().map(todo_json),
+        "new": change.new.as_ref().map(todo_json),
+        "at": change.at.to_rfc3339(),
+        "actor": change.actor,
+        "request_id": change.request_id,
+        "rejected": change.rejected,
+    })
+}
+
+fn from_json(v: &Value) -> Option<TodoChange> {
+    let todo = |v: &Value| -> Option<Option<Todo>> {
+        if v.is_null() {
+            return Some(None);
+        }
+        Some(Some(Todo {
+            id: id_json::from_json(&v["id"])?,
+            task: v["task"].as_str()?.to_string(),
+            done: v["done"].as_bool()?,
+            version: v["version"].as_u64()?,
+            // Absent from lines written before todos could be put in lists
+            list_id: v["list_id"].as_u64().map(ListId),
+            position: v["position"].as_u64().unwrap_or_default(),
+        }))
+    };
+    let string = |v: &Value| v.as_str().map(|s| s.to_string());
+    Some(TodoChange {
+        todo_id: id_json::from_json(&v["todo_id"])?,
+        old: todo(&v["old"])?,
+        new: todo(&v["new"])?,
+        at: DateTime::parse_from_rfc3339(v["at"].as_str()?)
+            .ok()?
+            .with_timezone(&Utc),
+        actor: string(&v["actor"]),
+        request_id: string(&v["request_id"]),
+        // Absent from lines written before rejected changes were recorded
+        rejected: string(&v["rejected"]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use domain::todo::TodoId;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_record_since() {
+        let path = std::env::temp_dir().join("todddo_test_audit.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let start = Utc::now();
+        let change = TodoChange {
+            todo_id: TodoId(1),
+            old: None,
+            new: Some(Todo {
+                id: TodoId(1),
+                task: "Make the bed".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
+            }),
+            at: start,
+            actor: Some("curl/8.0".to_string()),
+            request_id: Some("abc".to_string()),
+            rejected: None,
+        };
+        block_on(async {
+            let file_log = open(&path).unwrap();
+            file_log.record(&change).await;
+            file_log
+                .record(&TodoChange {
+                    old: change.new.clone(),
+                    new: None,
+                    at: start + Duration::seconds(1),
+                    ..change.clone()
+                })
+                .await;
+        });
+        // Survives being reopened
+        let file_log = open(&path).unwrap();
+        let all = block_on(file_log.since(None)).ok().unwrap();
+        assert_eq!(2, all.len());
+        assert_eq!(change, all[0]);
+        let later = block_on(file_log.since(Some(start + Duration::milliseconds(500))))
+            .ok()
+            .unwrap();
+        assert_eq!(1, later.len());
+        assert_eq!(None, later[0].new);
+        let after = block_on(file_log.after(1)).ok().unwrap();
+        assert_eq!(1, after.len());
+        assert_eq!(2, after[0].seq);
+        assert_eq!(later[0], after[0].change);
+    }
+}
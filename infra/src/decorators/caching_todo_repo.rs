@@ -0,0 +1,332 @@
+use domain::metrics::Metrics;
+use domain::todo::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+/// Keeps recently got todos in front of a slower repo, e.g. one across the network. Writes go
+/// through to the wrapped repo, and update or drop what's cached, so that gets through this repo
+/// don't see stale todos; writes made to the wrapped repo by anything else are only seen once the
+/// cached todo expires.
+#[derive(Clone)]
+pub struct CachingTodoRepo<R: TodoRepo> {
+    inner: R,
+    capacity: usize,
+    ttl: Duration,
+    cache: Arc<Mutex<Cache>>,
+    metrics: Option<Arc<dyn Metrics + Send + Sync>>,
+}
+
+/// Keeps up to `capacity` todos, evicting the least recently used, each for up to `ttl`
+pub fn new<R: TodoRepo>(inner: R, capacity: usize, ttl: Duration) -> CachingTodoRepo<R> {
+    CachingTodoRepo {
+        inner,
+        capacity,
+        ttl,
+        cache: Arc::new(Mutex::new(Cache::default())),
+        metrics: None,
+    }
+}
+
+/// How gets have gone since the repo was created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub size: usize,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<TodoId, Entry>,
+    // Ids by when they were last used, oldest first
+    recency: BTreeMap<u64, TodoId>,
+    last_used: u64,
+    // Bumped by every write, so that a get that raced one doesn't cache what it read before it
+    writes: u64,
+    hits: u64,
+    misses: u64,
+}
+
+struct Entry {
+    todo: Todo,
+    cached_at: Instant,
+    used: u64,
+}
+
+impl<R: TodoRepo> CachingTodoRepo<R> {
+    /// Counts gets as `todo_cache_lookups`, by `result` (hit or miss)
+    pub fn with_metrics(self, metrics: Arc<dyn Metrics + Send + Sync>) -> CachingTodoRepo<R> {
+        CachingTodoRepo {
+            metrics: Some(metrics),
+            ..self
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.lock();
+        CacheStats {
+            hits: cache.hits,
+            misses: cache.misses,
+            size: cache.entries.len(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn count(&self, result: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.count("todo_cache_lookups", &[("result", result)], 1);
+        }
+    }
+
+    // The cached todo if it's still fresh, and otherwise the write count to check before caching
+    // what's read instead
+    fn lookup(&self, todo_id: &TodoId) -> Result<Todo, u64> {
+        let mut cache = self.lock();
+        let fresh = match cache.entries.get(todo_id) {
+            Some(entry) => entry.cached_at.elapsed() < self.ttl,
+            None => false,
+        };
+        if fresh {
+            cache.hits += 1;
+            let todo = cache.touch(todo_id);
+            self.count("hit");
+            Ok(todo)
+        } else {
+            cache.remove(todo_id);
+            cache.misses += 1;
+            self.count("miss");
+            Err(cache.writes)
+        }
+    }
+
+    fn put(&self, todo: &Todo) {
+        let mut cache = self.lock();
+        cache.writes += 1;
+        cache.insert(todo, self.capacity);
+    }
+
+    fn invalidate(&self, todo_id: &TodoId) {
+        let mut cache = self.lock();
+        cache.writes += 1;
+        cache.remove(todo_id);
+    }
+
+    fn invalidate_all(&self) {
+        let mut cache = self.lock();
+        cache.writes += 1;
+        cache.entries.clear();
+        cache.recency.clear();
+    }
+}
+
+impl Cache {
+    fn touch(&mut self, todo_id: &TodoId) -> Todo {
+        self.last_used += 1;
+        let used = self.last_used;
+        let entry = self
+            .entries
+            .get_mut(todo_id)
+            .expect("touched todos are cached");
+        self.recency.remove(&entry.used);
+        entry.used = used;
+        self.recency.insert(used, *todo_id);
+        entry.todo.clone()
+    }
+
+    fn insert(&mut self, todo: &Todo, capacity: usize) {
+        self.remove(&todo.id);
+        if capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= capacity {
+            match self.recency.pop_first() {
+                Some((_, oldest)) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        self.last_used += 1;
+        self.recency.insert(self.last_used, todo.id);
+        self.entries.insert(
+            todo.id,
+            Entry {
+                todo: todo.clone(),
+                cached_at: Instant::now(),
+                used: self.last_used,
+            },
+        );
+    }
+
+    fn remove(&mut self, todo_id: &TodoId) {
+        if let Some(entry) = self.entries.remove(todo_id) {
+            self.recency.remove(&entry.used);
+        }
+    }
+}
+
+#[async_trait]
+impl<R: TodoRepo + Send + Sync> TodoRepo for CachingTodoRepo<R> {
+    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
+        let created = self.inner.create(todo_data).await?;
+        self.put(&created);
+        Ok(created)
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+        let writes = match self.lookup(todo_id) {
+            Ok(todo) => return Ok(todo),
+            Err(writes) => writes,
+        };
+        let todo = self.inner.get(todo_id).await?;
+        let mut cache = self.lock();
+        if cache.writes == writes {
+            cache.insert(&todo, self.capacity);
+        }
+        Ok(todo)
+    }
+
+    async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.inner.list().await
+    }
+
+    async fn list_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.inner.list_page(offset, limit).await
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoRepoErr> {
+        let deleted = self.inner.delete(todo_id).await;
+        self.invalidate(todo_id);
+        deleted
+    }
+
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Todo, TodoRepoUpdateErr> {
+        let updated = self.inner.update(todo, expected_version).await;
+        match &updated {
+            Ok(updated) => self.put(updated),
+            // Whatever is cached is no longer to be trusted
+            Err(_) => self.invalidate(&todo.id),
+        }
+        updated
+    }
+
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+        match self.inner.complete(selection).await {
+            Ok(completed) => {
+                for todo in &completed.completed {
+                    self.put(todo);
+                }
+                Ok(completed)
+            }
+            // Some may have been completed anyway
+            Err(e) => {
+                self.invalidate_all();
+                Err(e)
+            }
+        }
+    }
+
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+        let reordered = self.inner.reorder(ids).await;
+        // Every position may have changed
+        self.invalidate_all();
+        reordered
+    }
+
+    async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.inner.find_by_task(task).await
+    }
+
+    async fn purge_done(&self) -> Result<usize, TodoRepoErr> {
+        let purged = self.inner.purge_done().await;
+        self.invalidate_all();
+        purged
+    }
+
+    async fn delete_all(&self) -> Result<usize, TodoRepoErr> {
+        let deleted = self.inner.delete_all().await;
+        self.invalidate_all();
+        deleted
+    }
+
+    async fn todo_stats(&self) -> Result<TodoRepoStats, TodoRepoErr> {
+        self.inner.todo_stats().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::in_mem::todo_repo;
+    use futures::executor::block_on;
+
+    fn todo_data(task: &str) -> TodoData {
+        TodoData {
+            task: task.to_string(),
+            done: false,
+            list_id: None,
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let repo = new(todo_repo::new(), 1, Duration::from_secs(60));
+        let first = block_on(repo.create(&todo_data("Make the bed"))).unwrap();
+        let second = block_on(repo.create(&todo_data("Do the dishes"))).unwrap();
+        // The first was evicted to make room for the second
+        assert_eq!(first, block_on(repo.get(&first.id)).unwrap());
+        assert_eq!(first, block_on(repo.get(&first.id)).unwrap());
+        assert_eq!(second, block_on(repo.get(&second.id)).unwrap());
+        assert_eq!(
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                size: 1
+            },
+            repo.stats()
+        );
+    }
+
+    #[test]
+    fn test_writes_through() {
+        let repo = new(todo_repo::new(), 10, Duration::from_secs(60));
+        let created = block_on(repo.create(&todo_data("Make the bed"))).unwrap();
+        let updated = block_on(repo.update(
+            &Todo {
+                done: true,
+                ..created.clone()
+            },
+            None,
+        ))
+        .unwrap();
+        assert_eq!(updated, block_on(repo.get(&created.id)).unwrap());
+        assert_eq!(1, repo.stats().hits);
+        block_on(repo.delete(&created.id)).unwrap();
+        assert!(block_on(repo.get(&created.id)).is_err());
+    }
+
+    #[test]
+    fn test_ttl() {
+        let repo = new(todo_repo::new(), 10, Duration::ZERO);
+        let created = block_on(repo.create(&todo_data("Make the bed"))).unwrap();
+        assert_eq!(created, block_on(repo.get(&created.id)).unwrap());
+        assert_eq!(0, repo.stats().hits);
+    }
+
+    #[test]
+    fn test_contract() {
+        block_on(repo_tests::run(|| {
+            new(todo_repo::new(), 10, Duration::from_secs(60))
+        }));
+    }
+}
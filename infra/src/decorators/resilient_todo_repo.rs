@@ -0,0 +1,438 @@
+use domain::todo::*;
+use futures::future::{self, BoxFuture, Either};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+/// Waits for the given time on whichever runtime the repo is used from
+pub type Sleep = Arc<dyn Fn(Duration) -> BoxFuture<'static, ()> + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct Resilience {
+    /// How long each attempt gets before failing with a timeout
+    pub timeout: Duration,
+    /// How many more attempts are made after transient failures
+    pub retries: u32,
+    /// The wait before the first retry, doubled before each one after
+    pub backoff: Duration,
+    /// After this many calls in a row fail transiently, calls fail straight away..
+    pub breaker_threshold: u32,
+    /// ..for this long, after which they go through again until the next failure
+    pub breaker_cooldown: Duration,
+}
+
+/// Guards a repo across a flaky network: attempts time out, transient failures (timeouts and
+/// connection errors) are retried with exponential backoff, and once the repo keeps failing, calls
+/// fail fast for a while rather than piling up on it. Reads are retried after any transient
+/// failure, but writes only after ones they can't have gone through on, i.e. not after timing out,
+/// so that e.g. a todo isn't created twice.
+#[derive(Clone)]
+pub struct ResilientTodoRepo<R: TodoRepo> {
+    inner: R,
+    resilience: Resilience,
+    sleep: Sleep,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+pub fn new<R: TodoRepo>(inner: R, resilience: Resilience, sleep: Sleep) -> ResilientTodoRepo<R> {
+    ResilientTodoRepo {
+        inner,
+        resilience,
+        sleep,
+        breaker: Arc::new(Mutex::new(Breaker::default())),
+    }
+}
+
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+// Which failures a call is retried after
+#[derive(Clone, Copy)]
+enum Retry {
+    // Reads can be made again whatever became of them
+    Transient,
+    // Writes only when they never reached the repo
+    Unreached,
+}
+
+// What the guarded calls' errors have to tell and be told
+trait Failure: Sized {
+    fn is_transient(&self) -> bool;
+    // Whether the call failed without the repo doing any of it
+    fn is_unreached(&self) -> bool;
+    fn timed_out() -> Self;
+    fn unavailable(reason: String) -> Self;
+}
+
+impl Failure for TodoRepoErr {
+    fn is_transient(&self) -> bool {
+        matches!(self, TodoRepoErr::ConnectionError(_) | TodoRepoErr::Timeout)
+    }
+
+    fn is_unreached(&self) -> bool {
+        matches!(self, TodoRepoErr::ConnectionError(_))
+    }
+
+    fn timed_out() -> Self {
+        TodoRepoErr::Timeout
+    }
+
+    fn unavailable(reason: String) -> Self {
+        TodoRepoErr::ConnectionError(reason)
+    }
+}
+
+impl Failure for TodoRepoUpdateErr {
+    fn is_transient(&self) -> bool {
+        match self {
+            TodoRepoUpdateErr::Failed(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+
+    fn is_unreached(&self) -> bool {
+        match self {
+            TodoRepoUpdateErr::Failed(e) => e.is_unreached(),
+            _ => false,
+        }
+    }
+
+    fn timed_out() -> Self {
+        TodoRepoUpdateErr::Failed(TodoRepoErr::timed_out())
+    }
+
+    fn unavailable(reason: String) -> Self {
+        TodoRepoUpdateErr::Failed(TodoRepoErr::unavailable(reason))
+    }
+}
+
+impl<R: TodoRepo> ResilientTodoRepo<R> {
+    async fn guarded<'a, T, E, F, Fut>(&'a self, retry: Retry, attempt: F) -> Result<T, E>
+    where
+        E: Failure,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>> + Send + 'a,
+    {
+        if let Some(until) = self.open_until() {
+            let left = until.saturating_duration_since(Instant::now());
+            return Err(E::unavailable(format!(
+                "Calls are being turned down for another [{}ms] after repeated failures",
+                left.as_millis()
+            )));
+        }
+        let mut backoff = self.resilience.backoff;
+        let mut retries = self.resilience.retries;
+        loop {
+            let timeout = (self.sleep)(self.resilience.timeout);
+            let outcome = match future::select(Box::pin(attempt()), timeout).await {
+                Either::Left((outcome, _)) => outcome,
+                Either::Right(_) => Err(E::timed_out()),
+            };
+            let retriable = |e: &E| match retry {
+                Retry::Transient => e.is_transient(),
+                Retry::Unreached => e.is_unreached(),
+            };
+            match outcome {
+                Err(e) if retriable(&e) && retries > 0 => {
+                    retries -= 1;
+                    (self.sleep)(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) if e.is_transient() => {
+                    self.failed();
+                    return Err(e);
+                }
+                other => {
+                    self.succeeded();
+                    return other;
+                }
+            }
+        }
+    }
+
+    fn open_until(&self) -> Option<Instant> {
+        let breaker = self.breaker.lock().unwrap_or_else(|e| e.into_inner());
+        breaker.open_until.filter(|until| Instant::now() < *until)
+    }
+
+    fn failed(&self) {
+        let mut breaker = self.breaker.lock().unwrap_or_else(|e| e.into_inner());
+        breaker.failures += 1;
+        if breaker.failures >= self.resilience.breaker_threshold {
+            breaker.open_until = Some(Instant::now() + self.resilience.breaker_cooldown);
+        }
+    }
+
+    fn succeeded(&self) {
+        let mut breaker = self.breaker.lock().unwrap_or_else(|e| e.into_inner());
+        breaker.failures = 0;
+        breaker.open_until = None;
+    }
+}
+
+#[async_trait]
+impl<R: TodoRepo + Send + Sync> TodoRepo for ResilientTodoRepo<R> {
+    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
+        self.guarded(Retry::Unreached, || self.inner.create(todo_data))
+            .await
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+        self.guarded(Retry::Transient, || self.inner.get(todo_id))
+            .await
+    }
+
+    async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.guarded(Retry::Transient, || self.inner.list()).await
+    }
+
+    async fn list_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.guarded(Retry::Transient, || self.inner.list_page(offset, limit))
+            .await
+    }
+
+    async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoRepoErr> {
+        self.guarded(Retry::Unreached, || self.inner.delete(todo_id))
+            .await
+    }
+
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Todo, TodoRepoUpdateErr> {
+        self.guarded(Retry::Unreached, || {
+            self.inner.update(todo, expected_version)
+        })
+        .await
+    }
+
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+        self.guarded(Retry::Unreached, || self.inner.complete(selection))
+            .await
+    }
+
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.guarded(Retry::Unreached, || self.inner.reorder(ids))
+            .await
+    }
+
+    async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.guarded(Retry::Transient, || self.inner.find_by_task(task))
+            .await
+    }
+
+    async fn purge_done(&self) -> Result<usize, TodoRepoErr> {
+        self.guarded(Retry::Unreached, || self.inner.purge_done())
+            .await
+    }
+
+    async fn delete_all(&self) -> Result<usize, TodoRepoErr> {
+        self.guarded(Retry::Unreached, || self.inner.delete_all())
+            .await
+    }
+
+    async fn todo_stats(&self) -> Result<TodoRepoStats, TodoRepoErr> {
+        self.guarded(Retry::Transient, || self.inner.todo_stats())
+            .await
+    }
+}
+
+// Passed straight through: work can only be run once, so it can't be retried or timed out
+#[async_trait]
+impl<R: TodoRepo + TodoUnitOfWork + Send + Sync> TodoUnitOfWork for ResilientTodoRepo<R> {
+    type Tx = R::Tx;
+
+    async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+    where
+        T: Send,
+        E: Send,
+        F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+    {
+        self.inner.transact(work).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::collections::VecDeque;
+
+    // Fails gets and deletes with the queued errors, then answers them, or never does when hanging
+    #[derive(Clone, Default)]
+    struct FlakyRepo {
+        errors: Arc<Mutex<VecDeque<TodoRepoErr>>>,
+        hanging: bool,
+        attempts: Arc<Mutex<u32>>,
+    }
+
+    // Waits of an hour never end; the rest are over straight away
+    fn sleep() -> Sleep {
+        Arc::new(|duration| {
+            if duration >= Duration::from_secs(3600) {
+                Box::pin(future::pending())
+            } else {
+                Box::pin(future::ready(()))
+            }
+        })
+    }
+
+    fn resilient(flaky: &FlakyRepo, timeout: Duration) -> ResilientTodoRepo<FlakyRepo> {
+        new(
+            flaky.clone(),
+            Resilience {
+                timeout,
+                retries: 2,
+                backoff: Duration::from_millis(1),
+                breaker_threshold: 2,
+                breaker_cooldown: Duration::from_secs(60),
+            },
+            sleep(),
+        )
+    }
+
+    fn flaky(errors: Vec<TodoRepoErr>) -> FlakyRepo {
+        let flaky = FlakyRepo::default();
+        flaky.errors.lock().unwrap().extend(errors);
+        flaky
+    }
+
+    fn attempts(flaky: &FlakyRepo) -> u32 {
+        *flaky.attempts.lock().unwrap()
+    }
+
+    #[test]
+    fn test_retries() {
+        let refused = || TodoRepoErr::ConnectionError("refused".to_string());
+        let transient = flaky(vec![TodoRepoErr::Timeout, refused()]);
+        let repo = resilient(&transient, Duration::from_secs(3600));
+        assert!(block_on(repo.get(&TodoId(1))).is_ok());
+        assert_eq!(3, attempts(&transient));
+        for e in [
+            TodoRepoErr::NotFound(TodoId(1)),
+            TodoRepoErr::Conflict(TodoId(1)),
+        ] {
+            let permanent = flaky(vec![e]);
+            let repo = resilient(&permanent, Duration::from_secs(3600));
+            assert!(block_on(repo.get(&TodoId(1))).is_err());
+            assert_eq!(1, attempts(&permanent));
+        }
+    }
+
+    #[test]
+    fn test_write_retries() {
+        let refused = flaky(vec![TodoRepoErr::ConnectionError("refused".to_string())]);
+        let repo = resilient(&refused, Duration::from_secs(3600));
+        assert!(block_on(repo.delete(&TodoId(1))).is_ok());
+        assert_eq!(2, attempts(&refused));
+        // It may have been deleted anyway
+        let timed_out = flaky(vec![TodoRepoErr::Timeout]);
+        let repo = resilient(&timed_out, Duration::from_secs(3600));
+        match block_on(repo.delete(&TodoId(1))) {
+            Err(TodoRepoErr::Timeout) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert_eq!(1, attempts(&timed_out));
+        let hanging = FlakyRepo {
+            hanging: true,
+            ..FlakyRepo::default()
+        };
+        let repo = resilient(&hanging, Duration::from_millis(10));
+        assert!(block_on(repo.delete(&TodoId(1))).is_err());
+        assert_eq!(1, attempts(&hanging));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let hanging = FlakyRepo {
+            hanging: true,
+            ..FlakyRepo::default()
+        };
+        let repo = resilient(&hanging, Duration::from_millis(10));
+        match block_on(repo.get(&TodoId(1))) {
+            Err(TodoRepoErr::Timeout) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert_eq!(3, attempts(&hanging));
+    }
+
+    #[test]
+    fn test_breaker() {
+        let refused = || TodoRepoErr::ConnectionError("refused".to_string());
+        let unreachable = flaky((0..6).map(|_| refused()).collect());
+        let repo = resilient(&unreachable, Duration::from_secs(3600));
+        for _ in 0..2 {
+            assert!(block_on(repo.get(&TodoId(1))).is_err());
+        }
+        assert_eq!(6, attempts(&unreachable));
+        // Turned down without reaching the repo
+        match block_on(repo.get(&TodoId(1))) {
+            Err(TodoRepoErr::ConnectionError(reason)) => assert!(reason.contains("turned down")),
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert_eq!(6, attempts(&unreachable));
+    }
+
+    #[async_trait]
+    impl TodoRepo for FlakyRepo {
+        async fn create(&self, _: &TodoData) -> Result<Todo, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
+            self.attempt().await?;
+            Ok(Todo {
+                id: *todo_id,
+                task: "Water the plants".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 1,
+            })
+        }
+
+        async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn delete(&self, _: &TodoId) -> Result<(), TodoRepoErr> {
+            self.attempt().await
+        }
+
+        async fn update(&self, _: &Todo, _: Option<u64>) -> Result<Todo, TodoRepoUpdateErr> {
+            unimplemented!()
+        }
+
+        async fn complete(&self, _: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn reorder(&self, _: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+
+        async fn find_by_task(&self, _: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+            unimplemented!()
+        }
+    }
+
+    impl FlakyRepo {
+        async fn attempt(&self) -> Result<(), TodoRepoErr> {
+            *self.attempts.lock().unwrap() += 1;
+            if self.hanging {
+                future::pending::<()>().await;
+            }
+            let error = self.errors.lock().unwrap().pop_front();
+            match error {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        }
+    }
+}
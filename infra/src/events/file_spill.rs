@@ -0,0 +1,347 @@
+use crate::ids::id_json::{self, todo_json};
+use domain::event_queue::{Spill, Spilled};
+use domain::events::*;
+use domain::list::ListId;
+use domain::todo::Todo;
+use log::*;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+// Most events read back at a time, and so held in memory on top of the queue's
+const READ_AHEAD: usize = 64;
+
+/// Spills events to a JSON Lines file, which is emptied whenever every event in it has been read
+/// back. Events still in it when the server stops are delivered after the next start.
+///
+/// The file is only written and read by a thread of its own, so that neither spilling nor reading
+/// back holds up the queue. It holds up to `max_events`, past which the oldest are dropped.
+pub struct FileSpill {
+    max_events: usize,
+    state: Arc<Mutex<State>>,
+    commands: Option<Sender<Command>>,
+    io: Option<JoinHandle<()>>,
+}
+
+struct State {
+    // Read back, oldest first
+    read: VecDeque<TodoEvent>,
+    // In the file or on their way to it, yet to be read back
+    unread: usize,
+    // How many of the oldest unread are to be dropped once read back, as they didn't fit
+    skip: usize,
+    reading: bool,
+    drainer: Option<Waker>,
+}
+
+impl State {
+    fn len(&self) -> usize {
+        self.read.len() + self.unread - self.skip
+    }
+}
+
+enum Command {
+    Append(String),
+    ReadBack,
+}
+
+/// Creates the file if needed, picking up any events left in it, up to `max_events` of the latest
+pub fn open(path: &Path, max_events: usize) -> io::Result<FileSpill> {
+    let file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    let unread = BufReader::new(&file).lines().count();
+    let state = Arc::new(Mutex::new(State {
+        read: VecDeque::new(),
+        unread,
+        skip: unread.saturating_sub(max_events),
+        reading: false,
+        drainer: None,
+    }));
+    let (commands, received) = mpsc::channel();
+    let spill_file = SpillFile {
+        path: path.to_path_buf(),
+        file,
+        read_at: 0,
+        on_disk: unread,
+        state: state.clone(),
+    };
+    let io = thread::Builder::new()
+        .name("event-spill".to_string())
+        .spawn(move || spill_file.run(received))?;
+    Ok(FileSpill {
+        max_events,
+        state,
+        commands: Some(commands),
+        io: Some(io),
+    })
+}
+
+impl FileSpill {
+    fn send(&self, command: Command) -> bool {
+        match &self.commands {
+            Some(commands) => commands.send(command).is_ok(),
+            None => false,
+        }
+    }
+}
+
+impl Spill for FileSpill {
+    fn push(&self, event: &TodoEvent) -> Spilled {
+        let mut state = lock(&self.state);
+        if !self.send(Command::Append(format!("{}\n", to_json(event)))) {
+            error!("Could not spill an event, as the spill's thread has stopped");
+            return Spilled::Failed;
+        }
+        state.unread += 1;
+        if state.len() <= self.max_events {
+            return Spilled::Spilled;
+        }
+        if state.read.pop_front().is_none() {
+            state.skip += 1;
+        }
+        Spilled::DroppedOldest
+    }
+
+    fn poll_pop(&self, cx: &mut Context) -> Poll<Option<TodoEvent>> {
+        let mut state = lock(&self.state);
+        if let Some(event) = state.read.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if state.len() == 0 {
+            return Poll::Ready(None);
+        }
+        state.drainer = Some(cx.waker().clone());
+        if !state.reading {
+            if !self.send(Command::ReadBack) {
+                error!("Could not read spilled events back, as the spill's thread has stopped");
+                return Poll::Ready(None);
+            }
+            state.reading = true;
+        }
+        Poll::Pending
+    }
+
+    fn len(&self) -> usize {
+        lock(&self.state).len()
+    }
+}
+
+// Lets the thread finish writing what was spilled, so that it's there after the next start
+impl Drop for FileSpill {
+    fn drop(&mut self) {
+        self.commands.take();
+        if let Some(io) = self.io.take() {
+            let _ = io.join();
+        }
+    }
+}
+
+// Owned by the spill's thread
+struct SpillFile {
+    path: PathBuf,
+    file: File,
+    // Where the oldest unread event starts
+    read_at: u64,
+    // Lines after it
+    on_disk: usize,
+    state: Arc<Mutex<State>>,
+}
+
+impl SpillFile {
+    fn run(mut self, commands: Receiver<Command>) {
+        for command in commands {
+            match command {
+                Command::Append(line) => self.append(&line),
+                Command::ReadBack => self.read_back(),
+            }
+        }
+    }
+
+    fn append(&mut self, line: &str) {
+        match self.file.write_all(line.as_bytes()) {
+            Ok(()) => self.on_disk += 1,
+            Err(e) => {
+                error!(
+                    "Could not spill an event to [{}]: {}",
+                    self.path.display(),
+                    e
+                );
+                let mut state = lock(&self.state);
+                state.unread -= 1;
+                state.skip = state.skip.min(state.unread);
+            }
+        }
+    }
+
+    fn read_back(&mut self) {
+        let mut lines = Vec::new();
+        let read = self.file.seek(SeekFrom::Start(self.read_at)).and_then(|_| {
+            let mut reader = BufReader::new(&self.file);
+            while lines.len() < READ_AHEAD.min(self.on_disk) {
+                let mut line = String::new();
+                self.read_at += reader.read_line(&mut line)? as u64;
+                lines.push(line);
+            }
+            Ok(())
+        });
+        // Given up on, so that later events can still be spilled and read back
+        let lost = match read {
+            Ok(()) => 0,
+            Err(e) => {
+                error!("Could not read [{}]: {}", self.path.display(), e);
+                self.on_disk - lines.len()
+            }
+        };
+        self.on_disk -= lines.len() + lost;
+        if self.on_disk == 0 {
+            self.read_at = 0;
+            if let Err(e) = self.file.set_len(0) {
+                error!("Could not empty [{}]: {}", self.path.display(), e);
+            }
+        }
+        let mut state = lock(&self.state);
+        state.unread -= lines.len() + lost;
+        let skipped = state.skip.min(lines.len());
+        state.skip = (state.skip - skipped).min(state.unread);
+        for line in lines.iter().skip(skipped) {
+            match serde_json::from_str(line).ok().and_then(|v| from_json(&v)) {
+                Some(event) => state.read.push_back(event),
+                None => error!(
+                    "Dropped malformed line [{}] from [{}]",
+                    line.trim_end(),
+                    self.path.display()
+                ),
+            }
+        }
+        state.reading = false;
+        if let Some(drainer) = state.drainer.take() {
+            drainer.wake();
+        }
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn to_json(event: &TodoEvent) -> Value {
+    match event {
+        TodoEvent::Created(e) => json!({ "event": "created", "todo": todo_json(&e.todo) }),
+        TodoEvent::Updated(e) => json!({ "event": "updated", "todo": todo_json(&e.todo) }),
+        TodoEvent::Deleted(e) => json!({ "event": "deleted", "id": id_json::to_json(e.id) }),
+    }
+}
+
+fn from_json(v: &Value) -> Option<TodoEvent> {
+    let todo = |v: &Value| -> Option<Todo> {
+        Some(Todo {
+            id: id_json::from_json(&v["id"])?,
+            task: v["task"].as_str()?.to_string(),
+            done: v["done"].as_bool()?,
+            version: v["version"].as_u64()?,
+            list_id: v["list_id"].as_u64().map(ListId),
+            position: v["position"].as_u64()?,
+        })
+    };
+    match v["event"].as_str()? {
+        "created" => Some(TodoEvent::Created(TodoCreated {
+            todo: todo(&v["todo"])?,
+        })),
+        "updated" => Some(TodoEvent::Updated(TodoUpdated {
+            todo: todo(&v["todo"])?,
+        })),
+        "deleted" => Some(TodoEvent::Deleted(TodoDeleted {
+            id: id_json::from_json(&v["id"])?,
+        })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::todo::TodoId;
+    use futures::executor::block_on;
+    use futures::future::poll_fn;
+
+    fn deleted(id: u128) -> TodoEvent {
+        TodoEvent::Deleted(TodoDeleted { id: TodoId(id) })
+    }
+
+    fn pop(spill: &FileSpill) -> Option<TodoEvent> {
+        block_on(poll_fn(|cx| spill.poll_pop(cx)))
+    }
+
+    #[test]
+    fn test_spill() {
+        let path = std::env::temp_dir().join("todddo_test_spill.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let created = TodoEvent::Created(TodoCreated {
+            todo: Todo {
+                id: TodoId(1),
+                task: "Make the bed".to_string(),
+                done: false,
+                version: 1,
+                list_id: Some(ListId(2)),
+                position: 1,
+            },
+        });
+        let deleted = deleted(1);
+        {
+            let spill = open(&path, 10).unwrap();
+            assert_eq!(Spilled::Spilled, spill.push(&created));
+            assert_eq!(Spilled::Spilled, spill.push(&deleted));
+        }
+        // Survives being reopened
+        let spill = open(&path, 10).unwrap();
+        assert_eq!(2, spill.len());
+        assert_eq!(Some(created.clone()), pop(&spill));
+        assert_eq!(Spilled::Spilled, spill.push(&created));
+        assert_eq!(Some(deleted), pop(&spill));
+        assert_eq!(Some(created), pop(&spill));
+        assert_eq!(None, pop(&spill));
+        assert_eq!(0, std::fs::metadata(&path).unwrap().len());
+    }
+
+    #[test]
+    fn test_spill_full() {
+        let path = std::env::temp_dir().join("todddo_test_spill_full.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let spill = open(&path, 2).unwrap();
+        assert_eq!(Spilled::Spilled, spill.push(&deleted(1)));
+        assert_eq!(Spilled::Spilled, spill.push(&deleted(2)));
+        assert_eq!(Spilled::DroppedOldest, spill.push(&deleted(3)));
+        assert_eq!(2, spill.len());
+        assert_eq!(Some(deleted(2)), pop(&spill));
+        assert_eq!(Spilled::Spilled, spill.push(&deleted(4)));
+        // 3 has been read back already, so it's dropped straight away
+        assert_eq!(Spilled::DroppedOldest, spill.push(&deleted(5)));
+        assert_eq!(Some(deleted(4)), pop(&spill));
+        assert_eq!(Some(deleted(5)), pop(&spill));
+        assert_eq!(None, pop(&spill));
+        drop(spill);
+        // Only the latest are picked up again
+        for id in 6..=8 {
+            std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .write_all(format!("{}\n", to_json(&deleted(id))).as_bytes())
+                .unwrap();
+        }
+        let spill = open(&path, 2).unwrap();
+        assert_eq!(2, spill.len());
+        assert_eq!(Some(deleted(7)), pop(&spill));
+        assert_eq!(Some(deleted(8)), pop(&spill));
+    }
+}
@@ -0,0 +1,23 @@
+use domain::events::*;
+use log::*;
+
+use async_trait::async_trait;
+
+/// Logs every todo event; handy for seeing what the service is doing when debugging
+#[derive(Clone)]
+pub struct LoggingSubscriber;
+
+pub fn new() -> LoggingSubscriber {
+    LoggingSubscriber
+}
+
+#[async_trait]
+impl Subscriber for LoggingSubscriber {
+    async fn notify(&self, event: &TodoEvent) {
+        match event {
+            TodoEvent::Created(e) => debug!("Todo created [{:?}]", e.todo),
+            TodoEvent::Updated(e) => debug!("Todo updated [{:?}]", e.todo),
+            TodoEvent::Deleted(e) => debug!("Todo deleted [{:?}]", e.id),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Todo ids in stored and published JSON: numbers while they fit in 64 bits, as they always have
+//! been, and UUID strings beyond that
+
+use domain::todo::{Todo, TodoId};
+use serde_json::{json, Value};
+use std::convert::TryFrom;
+
+pub fn to_json(id: TodoId) -> Value {
+    match u64::try_from(id.0) {
+        Ok(id) => Value::from(id),
+        Err(_) => Value::String(id.to_string()),
+    }
+}
+
+pub fn from_json(v: &Value) -> Option<TodoId> {
+    match v {
+        Value::String(s) => s.parse().ok(),
+        _ => v.as_u64().map(|id| TodoId(id.into())),
+    }
+}
+
+/// A whole todo, as the API serves it
+pub fn todo_json(todo: &Todo) -> Value {
+    json!({
+        "id": to_json(todo.id),
+        "task": todo.task,
+        "done": todo.done,
+        "version": todo.version,
+        "list_id": todo.list_id.map(|id| id.0),
+        "position": todo.position,
+    })
+}
@@ -0,0 +1,19 @@
+use domain::todo::{IdGenerator, TodoId};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counts up from 1; only safe while a single process creates todos
+pub struct SequentialIds {
+    last: AtomicU64,
+}
+
+pub fn new() -> SequentialIds {
+    SequentialIds {
+        last: AtomicU64::new(0),
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn next_id(&self) -> TodoId {
+        TodoId((self.last.fetch_add(1, Ordering::Relaxed) + 1).into())
+    }
+}
@@ -0,0 +1,30 @@
+use domain::todo::{IdGenerator, TodoId};
+use uuid::Uuid;
+
+/// Random (v4) UUIDs, which any number of processes can generate without coordinating
+pub struct UuidIds;
+
+pub fn new() -> UuidIds {
+    UuidIds
+}
+
+impl IdGenerator for UuidIds {
+    fn next_id(&self) -> TodoId {
+        TodoId(Uuid::new_v4().as_u128())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_id() {
+        let ids = new();
+        let (first, second) = (ids.next_id(), ids.next_id());
+        assert_ne!(first, second);
+        // Never mistaken for sequential ids
+        assert!(first.0 > u128::from(u64::MAX));
+        assert_eq!(Ok(first), first.to_string().parse());
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use domain::history::*;
+use futures_locks::Mutex;
+use std::collections::VecDeque;
+
+use async_trait::async_trait;
+
+/// Keeps the latest changes, dropping the oldest once full
+#[derive(Clone)]
+pub struct InMemAuditLog {
+    size: usize,
+    storage: Mutex<Log>,
+}
+
+struct Log {
+    changes: VecDeque<TodoChange>,
+    // So that positions stay the same as changes are dropped
+    dropped: u64,
+}
+
+pub fn new(size: usize) -> InMemAuditLog {
+    InMemAuditLog {
+        size,
+        storage: Mutex::new(Log {
+            changes: VecDeque::with_capacity(size),
+            dropped: 0,
+        }),
+    }
+}
+
+#[async_trait]
+impl AuditSink for InMemAuditLog {
+    async fn record(&self, change: &TodoChange) {
+        let mut storage = self.storage.lock().await;
+        if storage.changes.len() >= self.size {
+            storage.changes.pop_front();
+            storage.dropped += 1;
+        }
+        storage.changes.push_back(change.clone());
+    }
+}
+
+#[async_trait]
+impl AuditLog for InMemAuditLog {
+    async fn since(&self, since: Option<DateTime<Utc>>) -> Result<Vec<TodoChange>, AuditLogErr> {
+        let storage = self.storage.lock().await;
+        Ok(storage
+            .changes
+            .iter()
+            .filter(|change| since.is_none_or(|since| change.at >= since))
+            .cloned()
+            .collect())
+    }
+
+    async fn after(&self, seq: u64) -> Result<Vec<SequencedChange>, AuditLogErr> {
+        let storage = self.storage.lock().await;
+        if seq > 0 && seq < storage.dropped {
+            return Err(AuditLogErr::Truncated {
+                oldest: storage.dropped + 1,
+            });
+        }
+        Ok(storage
+            .changes
+            .iter()
+            .zip(storage.dropped + 1..)
+            .filter(|(_, position)| *position > seq)
+            .map(|(change, position)| SequencedChange {
+                seq: position,
+                change: change.clone(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use domain::todo::TodoId;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_record_since() {
+        let inmem_log = new(2);
+        let start = Utc::now();
+        let change = |id, secs| TodoChange {
+            todo_id: TodoId(id),
+            old: None,
+            new: None,
+            at: start + Duration::seconds(secs),
+            actor: None,
+            request_id: None,
+            rejected: None,
+        };
+        block_on(async {
+            inmem_log.record(&change(1, 0)).await;
+            inmem_log.record(&change(2, 1)).await;
+            inmem_log.record(&change(3, 2)).await;
+        });
+        let ids = |since| -> Vec<u128> {
+            block_on(inmem_log.since(since))
+                .ok()
+                .unwrap()
+                .iter()
+                .map(|c| c.todo_id.0)
+                .collect()
+        };
+        assert_eq!(vec![2, 3], ids(None));
+        assert_eq!(vec![3], ids(Some(start + Duration::seconds(2))));
+
+        let seqs = |after| -> Vec<(u64, u128)> {
+            block_on(inmem_log.after(after))
+                .ok()
+                .unwrap()
+                .iter()
+                .map(|c| (c.seq, c.change.todo_id.0))
+                .collect()
+        };
+        assert_eq!(vec![(2, 2), (3, 3)], seqs(0));
+        assert_eq!(vec![(2, 2), (3, 3)], seqs(1));
+        assert_eq!(vec![(3, 3)], seqs(2));
+        assert!(seqs(3).is_empty());
+        block_on(inmem_log.record(&change(4, 3)));
+        match block_on(inmem_log.after(1)) {
+            Ok(_) => panic!("Change 2 was still kept"),
+            Err(e) => assert_eq!("Changes before [3] are no longer kept", e.to_string()),
+        }
+    }
+}
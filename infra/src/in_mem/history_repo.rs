@@ -0,0 +1,68 @@
+use domain::history::*;
+use domain::todo::TodoId;
+use futures_locks::Mutex;
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct InMemHistoryRepo {
+    storage: Mutex<HashMap<TodoId, Vec<TodoChange>>>,
+}
+
+pub fn new() -> InMemHistoryRepo {
+    InMemHistoryRepo {
+        storage: Mutex::new(HashMap::new()),
+    }
+}
+
+#[async_trait]
+impl AuditSink for InMemHistoryRepo {
+    async fn record(&self, change: &TodoChange) {
+        let mut storage = self.storage.lock().await;
+        storage
+            .entry(change.todo_id)
+            .or_default()
+            .push(change.clone());
+    }
+}
+
+#[async_trait]
+impl TodoHistoryRepo for InMemHistoryRepo {
+    async fn history(&self, todo_id: &TodoId) -> Vec<TodoChange> {
+        let storage = self.storage.lock().await;
+        storage.get(todo_id).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_record_history() {
+        let inmem_repo = new();
+        let change = |todo_id, actor: &str| TodoChange {
+            todo_id,
+            old: None,
+            new: None,
+            at: Utc::now(),
+            actor: Some(actor.to_string()),
+            request_id: None,
+            rejected: None,
+        };
+        block_on(async {
+            inmem_repo.record(&change(TodoId(1), "first")).await;
+            inmem_repo.record(&change(TodoId(2), "other")).await;
+            inmem_repo.record(&change(TodoId(1), "second")).await;
+        });
+        let actors: Vec<_> = block_on(inmem_repo.history(&TodoId(1)))
+            .into_iter()
+            .filter_map(|c| c.actor)
+            .collect();
+        assert_eq!(vec!["first".to_string(), "second".to_string()], actors);
+        assert!(block_on(inmem_repo.history(&TodoId(3))).is_empty());
+    }
+}
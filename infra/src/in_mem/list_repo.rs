@@ -0,0 +1,88 @@
+use domain::list::*;
+use futures_locks::Mutex;
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct InMemListRepo {
+    data: Mutex<Data>,
+}
+
+pub fn new() -> InMemListRepo {
+    InMemListRepo {
+        data: Mutex::new(Data {
+            last_id: 0,
+            storage: BTreeMap::new(),
+        }),
+    }
+}
+
+#[async_trait]
+impl ListRepo for InMemListRepo {
+    async fn create(&self, list_data: &ListData) -> List {
+        let mut data = self.data.lock().await;
+        data.last_id += 1;
+        let list = List {
+            id: ListId(data.last_id),
+            name: list_data.name.clone(),
+        };
+        data.storage.insert(list.id, list.clone());
+        list
+    }
+
+    async fn get(&self, list_id: &ListId) -> Result<List, ListRepoErr> {
+        let data = self.data.lock().await;
+        match data.storage.get(list_id) {
+            Some(list) => Ok(list.clone()),
+            None => Err(ListRepoErr::NotFound(*list_id)),
+        }
+    }
+
+    // Ordered by id
+    async fn list(&self) -> Vec<List> {
+        let data = self.data.lock().await;
+        data.storage.values().cloned().collect()
+    }
+
+    async fn delete(&self, list_id: &ListId) -> Result<(), ListRepoErr> {
+        let mut data = self.data.lock().await;
+        match data.storage.remove(list_id) {
+            Some(_) => Ok(()),
+            None => Err(ListRepoErr::NotFound(*list_id)),
+        }
+    }
+}
+
+struct Data {
+    last_id: u64,
+    storage: BTreeMap<ListId, List>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_create_get_delete() {
+        let inmem_repo = new();
+        let list_data = ListData {
+            name: "Chores".to_string(),
+        };
+        let (first, second) = block_on(async {
+            (
+                inmem_repo.create(&list_data).await,
+                inmem_repo.create(&list_data).await,
+            )
+        });
+        assert_ne!(first.id, second.id);
+        assert_eq!(
+            Ok(first.clone()),
+            block_on(inmem_repo.get(&first.id)).map_err(|_| ())
+        );
+        assert!(block_on(inmem_repo.delete(&first.id)).is_ok());
+        assert!(block_on(inmem_repo.get(&first.id)).is_err());
+        assert_eq!(vec![second], block_on(inmem_repo.list()));
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::NaiveDate;
+use domain::recurrence::*;
+use domain::todo::TodoId;
+use futures_locks::Mutex;
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct InMemRecurrenceRepo {
+    data: Mutex<Data>,
+}
+
+pub fn new() -> InMemRecurrenceRepo {
+    InMemRecurrenceRepo {
+        data: Mutex::new(Data {
+            recurrences: HashMap::new(),
+            occurrences: HashMap::new(),
+        }),
+    }
+}
+
+#[async_trait]
+impl RecurrenceRepo for InMemRecurrenceRepo {
+    async fn set(&self, todo_id: &TodoId, recurrence: &Recurrence) {
+        let mut data = self.data.lock().await;
+        data.recurrences.insert(*todo_id, recurrence.clone());
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Option<Recurrence> {
+        let data = self.data.lock().await;
+        data.recurrences.get(todo_id).cloned()
+    }
+
+    async fn remove(&self, todo_id: &TodoId) -> bool {
+        let mut data = self.data.lock().await;
+        data.recurrences.remove(todo_id).is_some()
+    }
+
+    async fn schedule(&self, occurrence: &Occurrence) {
+        let mut data = self.data.lock().await;
+        data.occurrences
+            .insert(occurrence.after, occurrence.clone());
+    }
+
+    async fn unschedule(&self, after: &TodoId) {
+        let mut data = self.data.lock().await;
+        data.occurrences.remove(after);
+    }
+
+    async fn take_due(&self, today: NaiveDate) -> Vec<Occurrence> {
+        let mut data = self.data.lock().await;
+        let mut due: Vec<_> = data
+            .occurrences
+            .values()
+            .filter(|o| o.due <= today)
+            .cloned()
+            .collect();
+        for occurrence in &due {
+            data.occurrences.remove(&occurrence.after);
+        }
+        // Oldest first, so that todos come back in the order they were due
+        due.sort_by_key(|o| (o.due, o.after));
+        due
+    }
+}
+
+struct Data {
+    recurrences: HashMap<TodoId, Recurrence>,
+    // Keyed by the todo that was done
+    occurrences: HashMap<TodoId, Occurrence>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::todo::TodoData;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_schedule_take_due() {
+        let repo = new();
+        let today = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let occurrence = |after, due| Occurrence {
+            after: TodoId(after),
+            due,
+            todo_data: TodoData {
+                task: "Water the plants".to_string(),
+                done: false,
+                list_id: None,
+            },
+            recurrence: Recurrence::Daily,
+        };
+        block_on(repo.schedule(&occurrence(1, today.succ_opt().unwrap())));
+        // Replaces the first one
+        block_on(repo.schedule(&occurrence(1, today)));
+        block_on(repo.schedule(&occurrence(2, today.succ_opt().unwrap())));
+        block_on(repo.schedule(&occurrence(3, today)));
+        block_on(repo.unschedule(&TodoId(3)));
+        assert_eq!(vec![occurrence(1, today)], block_on(repo.take_due(today)));
+        assert!(block_on(repo.take_due(today)).is_empty());
+        block_on(repo.set(&TodoId(1), &Recurrence::Weekly));
+        assert_eq!(Some(Recurrence::Weekly), block_on(repo.get(&TodoId(1))));
+        assert!(block_on(repo.remove(&TodoId(1))));
+        assert!(!block_on(repo.remove(&TodoId(1))));
+    }
+}
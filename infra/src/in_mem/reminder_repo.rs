@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use domain::reminder::*;
+use domain::todo::TodoId;
+use futures_locks::Mutex;
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct InMemReminderRepo {
+    reminders: Mutex<HashMap<TodoId, Reminder>>,
+}
+
+pub fn new() -> InMemReminderRepo {
+    InMemReminderRepo {
+        reminders: Mutex::new(HashMap::new()),
+    }
+}
+
+#[async_trait]
+impl ReminderRepo for InMemReminderRepo {
+    async fn set(&self, reminder: &Reminder) {
+        let mut reminders = self.reminders.lock().await;
+        reminders.insert(reminder.todo_id, reminder.clone());
+    }
+
+    async fn get(&self, todo_id: &TodoId) -> Option<Reminder> {
+        let reminders = self.reminders.lock().await;
+        reminders.get(todo_id).cloned()
+    }
+
+    async fn remove(&self, todo_id: &TodoId) -> bool {
+        let mut reminders = self.reminders.lock().await;
+        reminders.remove(todo_id).is_some()
+    }
+
+    async fn list(&self) -> Vec<Reminder> {
+        let reminders = self.reminders.lock().await;
+        soonest_first(reminders.values().cloned().collect())
+    }
+
+    async fn take_due(&self, now: DateTime<Utc>) -> Vec<Reminder> {
+        let mut reminders = self.reminders.lock().await;
+        let due: Vec<_> = reminders
+            .values()
+            .filter(|r| r.remind_at <= now)
+            .cloned()
+            .collect();
+        for reminder in &due {
+            reminders.remove(&reminder.todo_id);
+        }
+        soonest_first(due)
+    }
+}
+
+fn soonest_first(mut reminders: Vec<Reminder>) -> Vec<Reminder> {
+    reminders.sort_by_key(|r| (r.remind_at, r.todo_id));
+    reminders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_take_due() {
+        let repo = new();
+        let now = Utc::now();
+        let reminder = |id, remind_at| Reminder {
+            todo_id: TodoId(id),
+            remind_at,
+        };
+        block_on(repo.set(&reminder(1, now + Duration::hours(1))));
+        block_on(repo.set(&reminder(2, now)));
+        block_on(repo.set(&reminder(3, now - Duration::hours(1))));
+        assert_eq!(
+            vec![reminder(3, now - Duration::hours(1)), reminder(2, now)],
+            block_on(repo.take_due(now))
+        );
+        assert_eq!(
+            vec![reminder(1, now + Duration::hours(1))],
+            block_on(repo.list())
+        );
+        assert!(block_on(repo.take_due(now)).is_empty());
+    }
+}
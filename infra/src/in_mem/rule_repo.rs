@@ -0,0 +1,88 @@
+use domain::rule::*;
+use futures_locks::Mutex;
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct InMemRuleRepo {
+    data: Mutex<Data>,
+}
+
+pub fn new() -> InMemRuleRepo {
+    InMemRuleRepo {
+        data: Mutex::new(Data {
+            last_id: 0,
+            storage: BTreeMap::new(),
+        }),
+    }
+}
+
+#[async_trait]
+impl RuleRepo for InMemRuleRepo {
+    async fn create(&self, rule_data: &RuleData) -> Rule {
+        let mut data = self.data.lock().await;
+        data.last_id += 1;
+        let rule = Rule {
+            id: RuleId(data.last_id),
+            name: rule_data.name.clone(),
+            when: rule_data.when.clone(),
+            then: rule_data.then.clone(),
+        };
+        data.storage.insert(rule.id, rule.clone());
+        rule
+    }
+
+    // Ordered by id, which is also the order rules are evaluated in
+    async fn list(&self) -> Vec<Rule> {
+        let data = self.data.lock().await;
+        data.storage.values().cloned().collect()
+    }
+
+    async fn delete(&self, rule_id: &RuleId) -> Result<(), RuleRepoErr> {
+        let mut data = self.data.lock().await;
+        match data.storage.remove(rule_id) {
+            Some(_) => Ok(()),
+            None => Err(RuleRepoErr::NotFound(*rule_id)),
+        }
+    }
+}
+
+struct Data {
+    last_id: u64,
+    storage: BTreeMap<RuleId, Rule>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_create_list_delete() {
+        let inmem_repo = new();
+        let rule_data = RuleData {
+            name: "follow up".to_string(),
+            when: Condition {
+                event: Trigger::Created,
+                task_contains: None,
+            },
+            then: Action::CreateTodo {
+                task: "Follow up on {{task}}".to_string(),
+            },
+        };
+        let (first, second) = block_on(async {
+            (
+                inmem_repo.create(&rule_data).await,
+                inmem_repo.create(&rule_data).await,
+            )
+        });
+        assert_eq!(
+            vec![first.clone(), second.clone()],
+            block_on(inmem_repo.list())
+        );
+        assert!(block_on(inmem_repo.delete(&first.id)).is_ok());
+        assert!(block_on(inmem_repo.delete(&first.id)).is_err());
+        assert_eq!(vec![second], block_on(inmem_repo.list()));
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use domain::share::*;
+use domain::todo::TodoId;
+use futures_locks::Mutex;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct InMemShareRepo {
+    storage: Mutex<HashMap<ShareToken, Share>>,
+}
+
+pub fn new() -> InMemShareRepo {
+    InMemShareRepo {
+        storage: Mutex::new(HashMap::new()),
+    }
+}
+
+#[async_trait]
+impl ShareRepo for InMemShareRepo {
+    async fn create(&self, todo_id: &TodoId, expires_at: Option<DateTime<Utc>>) -> Share {
+        let mut storage = self.storage.lock().await;
+        let share = Share {
+            // 122 random bits
+            token: ShareToken(Uuid::new_v4().simple().to_string()),
+            todo_id: *todo_id,
+            expires_at,
+        };
+        storage.insert(share.token.clone(), share.clone());
+        share
+    }
+
+    async fn get(&self, token: &ShareToken) -> Result<Share, ShareRepoErr> {
+        let storage = self.storage.lock().await;
+        match storage.get(token) {
+            Some(share) => Ok(share.clone()),
+            None => Err(ShareRepoErr::NotFound(token.clone())),
+        }
+    }
+
+    async fn list_for(&self, todo_id: &TodoId) -> Vec<Share> {
+        let storage = self.storage.lock().await;
+        storage
+            .values()
+            .filter(|share| share.todo_id == *todo_id)
+            .cloned()
+            .collect()
+    }
+
+    async fn delete(&self, token: &ShareToken) -> Result<(), ShareRepoErr> {
+        let mut storage = self.storage.lock().await;
+        match storage.remove(token) {
+            Some(_) => Ok(()),
+            None => Err(ShareRepoErr::NotFound(token.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_create_get_delete() {
+        let inmem_repo = new();
+        let (first, second) = block_on(async {
+            (
+                inmem_repo.create(&TodoId(1), None).await,
+                inmem_repo.create(&TodoId(1), None).await,
+            )
+        });
+        assert_ne!(first.token, second.token);
+        assert_eq!(32, first.token.0.len());
+        assert!(block_on(inmem_repo.get(&first.token)).is_ok());
+        assert_eq!(2, block_on(inmem_repo.list_for(&TodoId(1))).len());
+        assert!(block_on(inmem_repo.list_for(&TodoId(2))).is_empty());
+        assert!(block_on(inmem_repo.delete(&first.token)).is_ok());
+        assert!(block_on(inmem_repo.delete(&first.token)).is_err());
+        assert!(block_on(inmem_repo.get(&first.token)).is_err());
+    }
+}
@@ -1,56 +1,115 @@
+use crate::ids::sequential_ids;
+use domain::list::ListId;
 use domain::todo::*;
-use futures_locks::{Mutex, MutexGuard};
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use futures::future::BoxFuture;
+use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use async_trait::async_trait;
-use futures::compat::Future01CompatExt;
 
+/// Reads share the lock, so that lists and gets don't wait for each other, only for writes
 #[derive(Clone)]
 pub struct InMemTodoRepo {
-    data: Mutex<Data>,
+    data: RwLock<Data>,
+    ids: Arc<dyn IdGenerator + Send + Sync>,
+    lock_counters: Arc<LockCounters>,
 }
 
+/// Numbers todos sequentially
 pub fn new() -> InMemTodoRepo {
     InMemTodoRepo {
-        data: Mutex::new(Data {
-            last_id: LastId(0),
-            storage: HashMap::new(),
-        }),
+        data: RwLock::new(Data::default()),
+        ids: Arc::new(sequential_ids::new()),
+        lock_counters: Arc::new(LockCounters::default()),
     }
 }
 
+/// How often the repo's lock was taken, for reading or writing, and how often that meant waiting
+/// for another holder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockStats {
+    pub acquisitions: u64,
+    pub contended: u64,
+}
+
 impl InMemTodoRepo {
-    async fn unlock(&self) -> MutexGuard<Data> {
-        let guard = self.data.lock().compat().await;
-        guard.expect("Removing compat layer Result")
+    async fn read(&self) -> RwLockReadGuard<Data> {
+        self.lock_counters
+            .acquisitions
+            .fetch_add(1, Ordering::Relaxed);
+        match self.data.try_read() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.lock_counters.contended.fetch_add(1, Ordering::Relaxed);
+                self.data.read().await
+            }
+        }
+    }
+
+    async fn write(&self) -> RwLockWriteGuard<Data> {
+        self.lock_counters
+            .acquisitions
+            .fetch_add(1, Ordering::Relaxed);
+        match self.data.try_write() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.lock_counters.contended.fetch_add(1, Ordering::Relaxed);
+                self.data.write().await
+            }
+        }
+    }
+
+    pub fn with_id_generator(self, ids: Arc<dyn IdGenerator + Send + Sync>) -> InMemTodoRepo {
+        InMemTodoRepo { ids, ..self }
+    }
+
+    pub fn lock_stats(&self) -> LockStats {
+        LockStats {
+            acquisitions: self.lock_counters.acquisitions.load(Ordering::Relaxed),
+            contended: self.lock_counters.contended.load(Ordering::Relaxed),
+        }
     }
 }
 
 #[async_trait]
 impl TodoRepo for InMemTodoRepo {
-    async fn create(&self, todo_data: &TodoData) -> Todo {
-        let mut data = self.unlock().await;
-        let next_id = data.last_id.0 + 1;
-        let id = TodoId(next_id);
-        data.last_id = LastId(next_id);
+    async fn create(&self, todo_data: &TodoData) -> Result<Todo, TodoRepoErr> {
+        let mut data = self.write().await;
+        let id = self.ids.next_id();
+        data.last_position += 1;
+        let position = data.last_position;
         let persistable_todo = PersistedTodo {
             task: todo_data.task.clone(),
+            done: todo_data.done,
+            version: 1,
+            list_id: todo_data.list_id,
+            position,
         };
         data.storage.insert(id, persistable_todo);
-        Todo {
-            id: id,
+        Ok(Todo {
+            id,
             task: todo_data.task.clone(),
-        }
+            done: todo_data.done,
+            version: 1,
+            list_id: todo_data.list_id,
+            position,
+        })
     }
 
     async fn get(&self, todo_id: &TodoId) -> Result<Todo, TodoRepoErr> {
-        let data = self.unlock().await;
+        let data = self.read().await;
         match data.storage.get(todo_id) {
             Some(persisted) => {
                 let todo = Todo {
-                    id: todo_id.clone(),
+                    id: *todo_id,
                     task: persisted.task.clone(),
+                    done: persisted.done,
+                    version: persisted.version,
+                    list_id: persisted.list_id,
+                    position: persisted.position,
                 };
                 Ok(todo)
             }
@@ -58,56 +117,236 @@ impl TodoRepo for InMemTodoRepo {
         }
     }
 
-    async fn list(&self) -> Vec<Todo> {
-        let data = self.unlock().await;
-        let mut vec: Vec<_> = data
+    async fn list(&self) -> Result<Vec<Todo>, TodoRepoErr> {
+        self.list_page(0, usize::MAX).await
+    }
+
+    async fn list_page(&self, offset: usize, limit: usize) -> Result<Vec<Todo>, TodoRepoErr> {
+        let data = self.read().await;
+        Ok(data
             .storage
             .iter()
+            .skip(offset)
+            .take(limit)
             .map(|(id, persisted)| Todo {
                 id: *id,
                 task: persisted.task.clone(),
+                done: persisted.done,
+                version: persisted.version,
+                list_id: persisted.list_id,
+                position: persisted.position,
             })
-            .collect();
-        vec.sort_by(|a, b| a.id.cmp(&b.id));
-        vec
+            .collect())
     }
 
     async fn delete(&self, todo_id: &TodoId) -> Result<(), TodoRepoErr> {
-        let mut data = self.unlock().await;
+        let mut data = self.write().await;
         match data.storage.remove_entry(todo_id) {
             Some(_) => Ok(()),
             None => Err(TodoRepoErr::NotFound(*todo_id)),
         }
     }
 
-    async fn update(&self, todo: &Todo) -> Result<(), TodoRepoErr> {
-        let mut data = self.unlock().await;
+    async fn update(
+        &self,
+        todo: &Todo,
+        expected_version: Option<u64>,
+    ) -> Result<Todo, TodoRepoUpdateErr> {
+        let mut data = self.write().await;
         match data.storage.entry(todo.id) {
             Entry::Occupied(mut existing) => {
+                let current_version = existing.get().version;
+                if expected_version.is_some_and(|v| v != current_version) {
+                    return Err(TodoRepoUpdateErr::VersionConflict(todo.id));
+                }
+                // Only reordering moves todos
+                let position = existing.get().position;
                 existing.insert(PersistedTodo {
                     task: todo.task.clone(),
+                    done: todo.done,
+                    version: current_version + 1,
+                    list_id: todo.list_id,
+                    position,
+                });
+                Ok(Todo {
+                    version: current_version + 1,
+                    position,
+                    ..todo.clone()
+                })
+            }
+            Entry::Vacant(_) => Err(TodoRepoUpdateErr::NotFound(todo.id)),
+        }
+    }
+
+    async fn complete(&self, selection: &TodoSelection) -> Result<Completed, TodoRepoErr> {
+        let mut data = self.write().await;
+        let ids = match selection {
+            TodoSelection::Ids(ids) => ids.clone(),
+            TodoSelection::List(list_id) => data
+                .storage
+                .iter()
+                .filter(|(_, persisted)| persisted.list_id == Some(*list_id))
+                .map(|(id, _)| *id)
+                .collect(),
+        };
+        let mut completed = Completed::default();
+        for id in ids {
+            match data.storage.get_mut(&id) {
+                Some(persisted) if persisted.done => {}
+                Some(persisted) => {
+                    persisted.done = true;
+                    persisted.version += 1;
+                    completed.completed.push(Todo {
+                        id,
+                        task: persisted.task.clone(),
+                        done: true,
+                        version: persisted.version,
+                        list_id: persisted.list_id,
+                        position: persisted.position,
+                    });
+                }
+                None => completed.not_found.push(id),
+            }
+        }
+        Ok(completed)
+    }
+
+    async fn reorder(&self, ids: &[TodoId]) -> Result<Vec<Todo>, TodoRepoErr> {
+        let mut data = self.write().await;
+        if let Some(missing) = ids.iter().find(|id| !data.storage.contains_key(id)) {
+            return Err(TodoRepoErr::NotFound(*missing));
+        }
+        let mut rest: Vec<_> = data
+            .storage
+            .iter()
+            .filter(|(id, _)| !ids.contains(id))
+            .map(|(id, persisted)| (persisted.position, *id))
+            .collect();
+        rest.sort();
+        let mut order = Vec::with_capacity(data.storage.len());
+        for id in ids
+            .iter()
+            .copied()
+            .chain(rest.into_iter().map(|(_, id)| id))
+        {
+            // Only the first of repeated ids counts
+            if !order.contains(&id) {
+                order.push(id);
+            }
+        }
+        let mut reordered = Vec::with_capacity(order.len());
+        for (i, id) in order.into_iter().enumerate() {
+            if let Some(persisted) = data.storage.get_mut(&id) {
+                let position = i as u64 + 1;
+                // Moving a todo changes it as it's served, so its version too
+                if persisted.position != position {
+                    persisted.position = position;
+                    persisted.version += 1;
+                }
+                reordered.push(Todo {
+                    id,
+                    task: persisted.task.clone(),
+                    done: persisted.done,
+                    version: persisted.version,
+                    list_id: persisted.list_id,
+                    position: persisted.position,
                 });
-                Ok(())
             }
-            Entry::Vacant(_) => Err(TodoRepoErr::NotFound(todo.id)),
         }
+        Ok(reordered)
+    }
+
+    async fn find_by_task(&self, task: &str) -> Result<Vec<Todo>, TodoRepoErr> {
+        let data = self.read().await;
+        Ok(data
+            .storage
+            .iter()
+            .filter(|(_, persisted)| same_task(&persisted.task, task))
+            .map(|(id, persisted)| Todo {
+                id: *id,
+                task: persisted.task.clone(),
+                done: persisted.done,
+                version: persisted.version,
+                list_id: persisted.list_id,
+                position: persisted.position,
+            })
+            .collect())
+    }
+
+    async fn purge_done(&self) -> Result<usize, TodoRepoErr> {
+        let mut data = self.write().await;
+        let before = data.storage.len();
+        data.storage.retain(|_, persisted| !persisted.done);
+        Ok(before - data.storage.len())
+    }
+
+    async fn delete_all(&self) -> Result<usize, TodoRepoErr> {
+        let mut data = self.write().await;
+        Ok(std::mem::take(&mut data.storage).len())
+    }
+
+    async fn todo_stats(&self) -> Result<TodoRepoStats, TodoRepoErr> {
+        let data = self.read().await;
+        Ok(TodoRepoStats {
+            count: data.storage.len(),
+            last_id: data.storage.keys().next_back().copied(),
+        })
     }
 }
 
-struct LastId(u64);
+/// Work runs against a copy of every todo while holding the write lock, so that other reads and
+/// writes wait for it, and the copy replaces the todos only when the work succeeds
+#[async_trait]
+impl TodoUnitOfWork for InMemTodoRepo {
+    type Tx = InMemTodoRepo;
 
+    async fn transact<T, E, F>(&self, work: F) -> Result<T, E>
+    where
+        T: Send,
+        E: Send,
+        F: for<'tx> FnOnce(&'tx Self::Tx) -> BoxFuture<'tx, Result<T, E>> + Send,
+    {
+        let mut data = self.write().await;
+        let tx = InMemTodoRepo {
+            data: RwLock::new(data.clone()),
+            ids: self.ids.clone(),
+            lock_counters: self.lock_counters.clone(),
+        };
+        let outcome = work(&tx).await;
+        if outcome.is_ok() {
+            *data = std::mem::take(&mut *tx.data.write().await);
+        }
+        outcome
+    }
+}
+
+#[derive(Default)]
+struct LockCounters {
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+}
+
+#[derive(Clone)]
 struct PersistedTodo {
     task: String,
+    done: bool,
+    version: u64,
+    list_id: Option<ListId>,
+    position: u64,
 }
 
+#[derive(Clone, Default)]
 struct Data {
-    last_id: LastId,
-    storage: HashMap<TodoId, PersistedTodo>,
+    // New todos go after every other
+    last_position: u64,
+    // Ordered by id, so that pages can be read off without sorting
+    storage: BTreeMap<TodoId, PersistedTodo>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ids::uuid_ids;
     use futures::executor::block_on;
 
     #[test]
@@ -116,8 +355,10 @@ mod tests {
         let f_create_retrieve = async {
             let to_create = TodoData {
                 task: "hello".to_string(),
+                done: false,
+                list_id: None,
             };
-            let created = inmem_repo.create(&to_create).await;
+            let created = inmem_repo.create(&to_create).await.unwrap();
             let retrieved = inmem_repo.get(&created.id).await;
             retrieved
         };
@@ -133,8 +374,10 @@ mod tests {
         let created = block_on(async {
             let to_create = TodoData {
                 task: "hammertime".to_string(),
+                done: false,
+                list_id: None,
             };
-            inmem_repo.create(&to_create).await
+            inmem_repo.create(&to_create).await.unwrap()
         });
         let retrieved = block_on(inmem_repo.get(&created.id));
         match retrieved {
@@ -161,15 +404,22 @@ mod tests {
             for i in 0..9 {
                 let to_create = TodoData {
                     task: format!("to something {}", i),
+                    done: false,
+                    list_id: None,
                 };
-                createds.push(inmem_repo.create(&to_create).await);
+                createds.push(inmem_repo.create(&to_create).await.unwrap());
             }
             createds
         });
         // We could do all of this inside the same `async` block, but this tests
         // that we are doing the right thing across async boundaries
-        let listed = block_on(inmem_repo.list());
+        let listed = block_on(inmem_repo.list()).unwrap();
         assert_eq!(createds, listed);
+        assert_eq!(
+            createds[3..5],
+            block_on(inmem_repo.list_page(3, 2)).unwrap()[..]
+        );
+        assert!(block_on(inmem_repo.list_page(9, 2)).unwrap().is_empty());
     }
 
     #[test]
@@ -178,8 +428,10 @@ mod tests {
         let created = block_on(async {
             let to_create = TodoData {
                 task: "hammertime".to_string(),
+                done: false,
+                list_id: None,
             };
-            inmem_repo.create(&to_create).await
+            inmem_repo.create(&to_create).await.unwrap()
         });
         let deleted = block_on(inmem_repo.delete(&created.id));
         match deleted {
@@ -209,14 +461,16 @@ mod tests {
         let mut created = block_on(async {
             let to_create = TodoData {
                 task: "hammertime".to_string(),
+                done: false,
+                list_id: None,
             };
-            inmem_repo.create(&to_create).await
+            inmem_repo.create(&to_create).await.unwrap()
         });
         let updated_task = "stop!".to_string();
         created.task = updated_task.clone();
-        let updated = block_on(inmem_repo.update(&created));
+        let updated = block_on(inmem_repo.update(&created, None));
         match updated {
-            Ok(_) => {}
+            Ok(updated) => assert_eq!(2, updated.version),
             _ => panic!("unsuccessful"),
         }
         let retrieve_after_update = block_on(inmem_repo.get(&created.id));
@@ -232,11 +486,215 @@ mod tests {
         let unpersisted_update = Todo {
             id: TodoId(123213),
             task: "hammertime".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 123213,
         };
-        let update = block_on(inmem_repo.update(&unpersisted_update));
+        let update = block_on(inmem_repo.update(&unpersisted_update, None));
         match update {
             Err(_) => {}
             _ => panic!("unexpectedly found..."),
         }
     }
+
+    #[test]
+    fn test_update_version_conflict() {
+        let inmem_repo = new();
+        let created = block_on(inmem_repo.create(&TodoData {
+            task: "hammertime".to_string(),
+            done: false,
+            list_id: None,
+        }))
+        .unwrap();
+        assert!(block_on(inmem_repo.update(&created, Some(created.version))).is_ok());
+        match block_on(inmem_repo.update(&created, Some(created.version))) {
+            Err(TodoRepoUpdateErr::VersionConflict(id)) => assert_eq!(created.id, id),
+            _ => panic!("stale update went through"),
+        }
+        match block_on(inmem_repo.get(&created.id)) {
+            Ok(retrieved) => assert_eq!(2, retrieved.version),
+            _ => panic!("unexpectedly not found..."),
+        }
+    }
+
+    #[test]
+    fn test_complete() {
+        let inmem_repo = new();
+        let (open, done, listed) = block_on(async {
+            let todo_data = |done, list_id| TodoData {
+                task: "hammertime".to_string(),
+                done,
+                list_id,
+            };
+            (
+                inmem_repo.create(&todo_data(false, None)).await.unwrap(),
+                inmem_repo.create(&todo_data(true, None)).await.unwrap(),
+                inmem_repo
+                    .create(&todo_data(false, Some(ListId(1))))
+                    .await
+                    .unwrap(),
+            )
+        });
+        let missing = TodoId(123213);
+        let completed =
+            block_on(inmem_repo.complete(&TodoSelection::Ids(vec![open.id, done.id, missing])))
+                .unwrap();
+        assert_eq!(
+            vec![Todo {
+                done: true,
+                version: 2,
+                ..open.clone()
+            }],
+            completed.completed
+        );
+        assert_eq!(vec![missing], completed.not_found);
+        match block_on(inmem_repo.get(&done.id)) {
+            Ok(retrieved) => assert_eq!(1, retrieved.version),
+            _ => panic!("unexpectedly not found..."),
+        }
+        let completed = block_on(inmem_repo.complete(&TodoSelection::List(ListId(1)))).unwrap();
+        assert_eq!(
+            vec![listed.id],
+            completed.completed.iter().map(|t| t.id).collect::<Vec<_>>()
+        );
+        assert!(completed.not_found.is_empty());
+    }
+
+    #[test]
+    fn test_reorder() {
+        let inmem_repo = new();
+        let ids: Vec<_> = block_on(async {
+            let mut ids = Vec::new();
+            for _ in 0..4 {
+                let todo_data = TodoData {
+                    task: "hammertime".to_string(),
+                    done: false,
+                    list_id: None,
+                };
+                ids.push(inmem_repo.create(&todo_data).await.unwrap().id);
+            }
+            ids
+        });
+        let order = |todos: Vec<Todo>| todos.iter().map(|t| (t.id, t.position)).collect::<Vec<_>>();
+        let reordered = block_on(inmem_repo.reorder(&[ids[2], ids[0]]))
+            .ok()
+            .unwrap();
+        assert_eq!(
+            vec![(ids[2], 1), (ids[0], 2), (ids[1], 3), (ids[3], 4)],
+            order(reordered)
+        );
+        let reordered = block_on(inmem_repo.reorder(&[ids[3]])).ok().unwrap();
+        assert_eq!(
+            vec![(ids[3], 1), (ids[2], 2), (ids[0], 3), (ids[1], 4)],
+            order(reordered)
+        );
+        // Only the todos that moved have their versions bumped, and updates keep positions
+        let mut retrieved = block_on(inmem_repo.get(&ids[0])).ok().unwrap();
+        assert_eq!((3, 3), (retrieved.version, retrieved.position));
+        retrieved.position = 0;
+        let updated = block_on(inmem_repo.update(&retrieved, None)).ok().unwrap();
+        assert_eq!(3, updated.position);
+
+        match block_on(inmem_repo.reorder(&[ids[1], TodoId(123131)])) {
+            Err(TodoRepoErr::NotFound(id)) => assert_eq!(TodoId(123131), id),
+            _ => panic!("unexpectedly reordered..."),
+        }
+        let listed = block_on(inmem_repo.list()).unwrap();
+        assert_eq!(4, listed.iter().find(|t| t.id == ids[1]).unwrap().position);
+    }
+
+    #[test]
+    fn test_find_by_task() {
+        let inmem_repo = new();
+        let found = block_on(async {
+            for task in ["Buy  Milk", "buy milk!", " buy milk\n", "eggs"] {
+                let todo_data = TodoData {
+                    task: task.to_string(),
+                    done: false,
+                    list_id: None,
+                };
+                inmem_repo.create(&todo_data).await.unwrap();
+            }
+            inmem_repo.find_by_task("BUY MILK").await.unwrap()
+        });
+        let ids: Vec<_> = found.iter().map(|t| t.id).collect();
+        assert_eq!(vec![TodoId(1), TodoId(3)], ids);
+        assert!(block_on(inmem_repo.find_by_task("bread"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lock_stats() {
+        let inmem_repo = new();
+        block_on(async {
+            let guard = inmem_repo.write().await;
+            // The list is polled first, so it has to wait for the guard to be dropped
+            let (listed, _) = futures::join!(inmem_repo.list(), async move { drop(guard) });
+            listed.unwrap();
+        });
+        assert_eq!(
+            LockStats {
+                acquisitions: 2,
+                contended: 1,
+            },
+            inmem_repo.lock_stats()
+        );
+
+        // Reads don't wait for each other
+        block_on(async {
+            let guard = inmem_repo.read().await;
+            let (listed, got) = futures::join!(inmem_repo.list(), inmem_repo.get(&TodoId(1)));
+            drop(guard);
+            assert!(listed.unwrap().is_empty() && got.is_err());
+        });
+        assert_eq!(
+            LockStats {
+                acquisitions: 5,
+                contended: 1,
+            },
+            inmem_repo.lock_stats()
+        );
+    }
+
+    #[test]
+    fn test_transact() {
+        let inmem_repo = new();
+        let todo_data = |task: &str| TodoData {
+            task: task.to_string(),
+            done: false,
+            list_id: None,
+        };
+        let kept = block_on(inmem_repo.create(&todo_data("Make the bed"))).unwrap();
+        let kept_id = kept.id;
+        // Rolled back, as the second step failed
+        let failed: Result<(), TodoRepoErr> = block_on(inmem_repo.transact(|tx| {
+            Box::pin(async move {
+                tx.create(&todo_data("Do the dishes")).await.unwrap();
+                tx.delete(&kept_id).await?;
+                tx.delete(&kept_id).await
+            })
+        }));
+        assert!(failed.is_err());
+        assert_eq!(vec![kept.clone()], block_on(inmem_repo.list()).unwrap());
+        let created = block_on(inmem_repo.transact(|tx| {
+            Box::pin(async move {
+                let created = tx.create(&todo_data("Water the plants")).await.unwrap();
+                tx.delete(&kept_id).await.map(|_| created)
+            })
+        }))
+        .unwrap();
+        // The rolled back create's id isn't reused
+        assert_eq!(TodoId(3), created.id);
+        assert_eq!(vec![created], block_on(inmem_repo.list()).unwrap());
+    }
+
+    #[test]
+    fn test_contract() {
+        block_on(repo_tests::run(new));
+        block_on(repo_tests::run(|| {
+            new().with_id_generator(Arc::new(uuid_ids::new()))
+        }));
+    }
 }
@@ -1,5 +1,60 @@
-#![feature(async_await)]
+pub mod audit {
+    pub mod file_audit_log;
+}
+
+pub mod decorators {
+    pub mod caching_todo_repo;
+    pub mod resilient_todo_repo;
+}
+
+pub mod ids {
+    pub mod id_json;
+    pub mod sequential_ids;
+    pub mod uuid_ids;
+}
+
+pub mod events {
+    pub mod file_spill;
+    pub mod logging_subscriber;
+}
+
+#[cfg(any(feature = "kafka", feature = "nats", feature = "plugins"))]
+pub mod messaging {
+    pub mod event_payload;
+    #[cfg(feature = "kafka")]
+    pub mod kafka_publisher;
+    #[cfg(feature = "nats")]
+    pub mod nats_publisher;
+}
+
+pub mod metrics {
+    #[cfg(feature = "otlp")]
+    pub mod otlp_metrics;
+    pub mod prometheus_metrics;
+    pub mod registry;
+    pub mod statsd_metrics;
+}
+
+pub mod notifiers {
+    pub mod logging_notifier;
+    #[cfg(feature = "smtp")]
+    pub mod smtp_notifier;
+    #[cfg(feature = "webhook")]
+    pub mod webhook_notifier;
+}
+
+#[cfg(feature = "plugins")]
+pub mod plugins {
+    pub mod wasm_plugin;
+}
 
 pub mod in_mem {
+    pub mod audit_log;
+    pub mod history_repo;
+    pub mod list_repo;
+    pub mod recurrence_repo;
+    pub mod reminder_repo;
+    pub mod rule_repo;
+    pub mod share_repo;
     pub mod todo_repo;
 }
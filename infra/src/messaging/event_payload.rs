@@ -0,0 +1,54 @@
+use crate::ids::id_json::{self, todo_json};
+use domain::events::*;
+use serde_json::json;
+
+/// The id of the todo an event is about; used as the message key so that a todo's events stay ordered
+pub fn key(event: &TodoEvent) -> String {
+    let id = match event {
+        TodoEvent::Created(e) => e.todo.id,
+        TodoEvent::Updated(e) => e.todo.id,
+        TodoEvent::Deleted(e) => e.id,
+    };
+    id.to_string()
+}
+
+// Same shape as the events pushed over the /tasks/ws WebSocket
+pub fn payload(event: &TodoEvent) -> Vec<u8> {
+    let value = match event {
+        TodoEvent::Created(e) => json!({
+            "event": "created",
+            "todo": todo_json(&e.todo),
+        }),
+        TodoEvent::Updated(e) => json!({
+            "event": "updated",
+            "todo": todo_json(&e.todo),
+        }),
+        TodoEvent::Deleted(e) => json!({ "event": "deleted", "id": id_json::to_json(e.id) }),
+    };
+    value.to_string().into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::todo::{Todo, TodoId};
+
+    #[test]
+    fn test_payload() {
+        let event = TodoEvent::Created(TodoCreated {
+            todo: Todo {
+                id: TodoId(3),
+                task: "hello".to_string(),
+                done: false,
+                version: 1,
+                list_id: None,
+                position: 3,
+            },
+        });
+        assert_eq!("3", key(&event));
+        assert_eq!(
+            json!({"event": "created", "todo": {"id": 3, "task": "hello", "done": false, "version": 1, "list_id": null, "position": 3}}),
+            serde_json::from_slice::<serde_json::Value>(&payload(&event)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::messaging::event_payload;
+use domain::events::*;
+use log::*;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::KafkaError;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+/// Publishes todo events to a Kafka topic
+#[derive(Clone)]
+pub struct KafkaPublisher {
+    producer: FutureProducer,
+    topic: String,
+}
+
+// `brokers` is a comma-separated list of host:port pairs
+pub fn new(brokers: &str, topic: &str) -> Result<KafkaPublisher, KafkaError> {
+    let producer = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .create()?;
+    Ok(KafkaPublisher {
+        producer,
+        topic: topic.to_string(),
+    })
+}
+
+#[async_trait]
+impl Subscriber for KafkaPublisher {
+    async fn notify(&self, event: &TodoEvent) {
+        let key = event_payload::key(event);
+        let payload = event_payload::payload(event);
+        let record = FutureRecord::to(&self.topic).key(&key).payload(&payload);
+        if let Err((e, _)) = self.producer.send(record, Duration::from_secs(0)).await {
+            warn!(
+                "Could not publish todo event to Kafka [{}]: {}",
+                self.topic, e
+            );
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::messaging::event_payload;
+use async_nats::{Client, ConnectError};
+use domain::events::*;
+use log::*;
+
+use async_trait::async_trait;
+
+/// Publishes todo events to a NATS subject
+#[derive(Clone)]
+pub struct NatsPublisher {
+    client: Client,
+    subject: String,
+}
+
+pub async fn new(url: &str, subject: &str) -> Result<NatsPublisher, ConnectError> {
+    let client = async_nats::connect(url).await?;
+    Ok(NatsPublisher {
+        client,
+        subject: subject.to_string(),
+    })
+}
+
+#[async_trait]
+impl Subscriber for NatsPublisher {
+    async fn notify(&self, event: &TodoEvent) {
+        let payload = event_payload::payload(event);
+        if let Err(e) = self
+            .client
+            .publish(self.subject.clone(), payload.into())
+            .await
+        {
+            warn!(
+                "Could not publish todo event to NATS [{}]: {}",
+                self.subject, e
+            );
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::metrics::registry;
+use crate::metrics::registry::{Registry, BUCKETS};
+use domain::metrics::*;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Keeps totals and pushes them to an OpenTelemetry collector over OTLP/HTTP, as JSON
+pub struct OtlpMetrics {
+    registry: Registry,
+    client: Client,
+    url: String,
+    service_name: String,
+}
+
+/// `url` is the collector's base URL, e.g. http://localhost:4318
+pub fn new(url: &str, service_name: &str) -> OtlpMetrics {
+    OtlpMetrics {
+        registry: registry::new(),
+        client: Client::new(),
+        url: format!("{}/v1/metrics", url.trim_end_matches('/')),
+        service_name: service_name.to_string(),
+    }
+}
+
+impl Metrics for OtlpMetrics {
+    fn count(&self, name: &str, labels: &Labels, value: u64) {
+        self.registry.count(name, labels, value)
+    }
+
+    fn time(&self, name: &str, labels: &Labels, duration: Duration) {
+        self.registry.time(name, labels, duration)
+    }
+}
+
+impl OtlpMetrics {
+    /// Sends every total so far; they are cumulative, so a failed push loses nothing
+    pub async fn push(&self) -> Result<(), String> {
+        self.client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(self.payload(SystemTime::now()).to_string())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| format!("Could not push metrics to [{}]: {}", self.url, e))
+    }
+
+    /// An `ExportMetricsServiceRequest` of the totals as of `now`
+    pub fn payload(&self, now: SystemTime) -> Value {
+        let start = nanos(self.registry.started);
+        let now = nanos(now);
+        let mut metrics = Vec::new();
+        let counters = self.registry.counters();
+        for (name, points) in group(&counters) {
+            let data_points: Vec<Value> = points
+                .iter()
+                .map(|(labels, value)| {
+                    json!({
+                        "attributes": attributes(labels),
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": now,
+                        "asInt": value.to_string(),
+                    })
+                })
+                .collect();
+            metrics.push(json!({
+                "name": name,
+                "sum": {
+                    "aggregationTemporality": CUMULATIVE,
+                    "isMonotonic": true,
+                    "dataPoints": data_points,
+                },
+            }));
+        }
+        let histograms = self.registry.histograms();
+        for (name, points) in group(&histograms) {
+            let data_points: Vec<Value> = points
+                .iter()
+                .map(|(labels, histogram)| {
+                    json!({
+                        "attributes": attributes(labels),
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": now,
+                        "count": histogram.count.to_string(),
+                        "sum": histogram.sum,
+                        "bucketCounts": histogram
+                            .counts
+                            .iter()
+                            .map(|c| c.to_string())
+                            .collect::<Vec<_>>(),
+                        "explicitBounds": BUCKETS,
+                    })
+                })
+                .collect();
+            metrics.push(json!({
+                "name": name,
+                "unit": "s",
+                "histogram": {
+                    "aggregationTemporality": CUMULATIVE,
+                    "dataPoints": data_points,
+                },
+            }));
+        }
+        json!({
+            "resourceMetrics": [{
+                "resource": {
+                    "attributes": [{
+                        "key": "service.name",
+                        "value": { "stringValue": self.service_name },
+                    }],
+                },
+                "scopeMetrics": [{
+                    "scope": { "name": self.service_name },
+                    "metrics": metrics,
+                }],
+            }],
+        })
+    }
+}
+
+static CUMULATIVE: u8 = 2;
+
+// 64-bit integers are strings in OTLP's JSON
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn attributes(labels: &[(String, String)]) -> Vec<Value> {
+    labels
+        .iter()
+        .map(|(k, v)| json!({ "key": k, "value": { "stringValue": v } }))
+        .collect()
+}
+
+// A metric's values, by label set
+type Points<'a, T> = Vec<(&'a [(String, String)], &'a T)>;
+
+// The registry's entries are sorted by name, so each name's are next to each other
+fn group<T>(entries: &[(registry::Key, T)]) -> Vec<(&str, Points<'_, T>)> {
+    let mut grouped: Vec<(&str, Points<'_, T>)> = Vec::new();
+    for ((name, labels), value) in entries {
+        match grouped.last_mut() {
+            Some((last, points)) if last == name => points.push((labels, value)),
+            _ => grouped.push((name, vec![(labels, value)])),
+        }
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let metrics = new("http://localhost:4318/", "todddo");
+        assert_eq!("http://localhost:4318/v1/metrics", metrics.url);
+        metrics.count("http_requests", &[("method", "GET")], 2);
+        metrics.count("http_requests", &[("method", "PUT")], 1);
+        metrics.time("http_request_duration", &[], Duration::from_millis(20));
+        let payload = metrics.payload(UNIX_EPOCH + Duration::from_secs(1));
+        let scope = &payload["resourceMetrics"][0]["scopeMetrics"][0];
+        assert_eq!("todddo", scope["scope"]["name"]);
+
+        let requests = &scope["metrics"][0];
+        assert_eq!("http_requests", requests["name"]);
+        assert_eq!(true, requests["sum"]["isMonotonic"]);
+        let points = requests["sum"]["dataPoints"].as_array().unwrap();
+        assert_eq!(2, points.len());
+        assert_eq!("2", points[0]["asInt"]);
+        assert_eq!("1000000000", points[0]["timeUnixNano"]);
+        assert_eq!(
+            json!([{ "key": "method", "value": { "stringValue": "PUT" } }]),
+            points[1]["attributes"]
+        );
+
+        let durations = &scope["metrics"][1];
+        assert_eq!("s", durations["unit"]);
+        let point = &durations["histogram"]["dataPoints"][0];
+        assert_eq!("1", point["count"]);
+        assert_eq!(
+            BUCKETS.len(),
+            point["explicitBounds"].as_array().unwrap().len()
+        );
+        assert_eq!("1", point["bucketCounts"][2]);
+    }
+}
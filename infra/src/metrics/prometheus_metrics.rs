@@ -0,0 +1,138 @@
+use crate::metrics::registry;
+use crate::metrics::registry::{Registry, BUCKETS};
+use domain::metrics::*;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Keeps totals for Prometheus to scrape, in its text format
+pub struct PrometheusMetrics {
+    registry: Registry,
+}
+
+pub fn new() -> PrometheusMetrics {
+    PrometheusMetrics {
+        registry: registry::new(),
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn count(&self, name: &str, labels: &Labels, value: u64) {
+        self.registry.count(name, labels, value)
+    }
+
+    fn time(&self, name: &str, labels: &Labels, duration: Duration) {
+        self.registry.time(name, labels, duration)
+    }
+}
+
+impl PrometheusMetrics {
+    /// Every total so far, as Prometheus' text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut last_name = None;
+        for ((name, labels), value) in self.registry.counters() {
+            let name = format!("{}_total", name);
+            if last_name.as_ref() != Some(&name) {
+                let _ = writeln!(out, "# TYPE {} counter", name);
+            }
+            let _ = writeln!(out, "{}{} {}", name, label_set(&labels, None), value);
+            last_name = Some(name);
+        }
+        for ((name, labels), histogram) in self.registry.histograms() {
+            let name = format!("{}_seconds", name);
+            if last_name.as_ref() != Some(&name) {
+                let _ = writeln!(out, "# TYPE {} histogram", name);
+            }
+            let mut cumulative = 0;
+            let bounds = BUCKETS.iter().map(|b| b.to_string());
+            for (bound, count) in bounds
+                .chain(std::iter::once("+Inf".to_string()))
+                .zip(&histogram.counts)
+            {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    name,
+                    label_set(&labels, Some(&bound)),
+                    cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_sum{} {}",
+                name,
+                label_set(&labels, None),
+                histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "{}_count{} {}",
+                name,
+                label_set(&labels, None),
+                histogram.count
+            );
+            last_name = Some(name);
+        }
+        out
+    }
+}
+
+fn label_set(labels: &[(String, String)], le: Option<&str>) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .chain(le.map(|le| ("le", le)))
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+        .collect();
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = new();
+        let labels = [("method", "GET"), ("route", "/tasks/{id}")];
+        metrics.count("http_requests", &labels, 2);
+        metrics.count("http_requests", &[("method", "PUT"), ("route", "a\"b")], 1);
+        metrics.time("http_request_duration", &labels, Duration::from_millis(20));
+        let rendered = metrics.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            &lines[..3],
+            &[
+                "# TYPE http_requests_total counter",
+                "http_requests_total{method=\"GET\",route=\"/tasks/{id}\"} 2",
+                "http_requests_total{method=\"PUT\",route=\"a\\\"b\"} 1",
+            ]
+        );
+        assert_eq!("# TYPE http_request_duration_seconds histogram", lines[3]);
+        assert!(lines.contains(
+            &"http_request_duration_seconds_bucket{method=\"GET\",route=\"/tasks/{id}\",le=\"0.01\"} 0"
+        ));
+        assert!(lines.contains(
+            &"http_request_duration_seconds_bucket{method=\"GET\",route=\"/tasks/{id}\",le=\"0.025\"} 1"
+        ));
+        assert!(lines.contains(
+            &"http_request_duration_seconds_bucket{method=\"GET\",route=\"/tasks/{id}\",le=\"+Inf\"} 1"
+        ));
+        assert_eq!(
+            "http_request_duration_seconds_count{method=\"GET\",route=\"/tasks/{id}\"} 1",
+            lines[lines.len() - 1]
+        );
+    }
+}
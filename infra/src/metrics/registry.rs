@@ -0,0 +1,113 @@
+use domain::metrics::Labels;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Upper bounds of the timing buckets, in seconds
+pub static BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A name and its labels, sorted by label name
+pub type Key = (String, Vec<(String, String)>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// How many timings fell in each bucket of [[BUCKETS]], then how many were beyond them all;
+    /// not cumulative
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+#[derive(Default)]
+struct Data {
+    counters: BTreeMap<Key, u64>,
+    histograms: BTreeMap<Key, Histogram>,
+}
+
+/// Keeps running totals in process, for backends that are scraped or that push periodically
+pub struct Registry {
+    data: Mutex<Data>,
+    /// When the totals started, which OTLP wants to know
+    pub started: SystemTime,
+}
+
+pub fn new() -> Registry {
+    Registry {
+        data: Mutex::new(Data::default()),
+        started: SystemTime::now(),
+    }
+}
+
+impl Registry {
+    pub fn count(&self, name: &str, labels: &Labels, value: u64) {
+        let mut data = self.data.lock().unwrap();
+        *data.counters.entry(key(name, labels)).or_insert(0) += value;
+    }
+
+    pub fn time(&self, name: &str, labels: &Labels, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let mut data = self.data.lock().unwrap();
+        let histogram = data
+            .histograms
+            .entry(key(name, labels))
+            .or_insert_with(|| Histogram {
+                counts: vec![0; BUCKETS.len() + 1],
+                count: 0,
+                sum: 0.0,
+            });
+        let bucket = BUCKETS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(BUCKETS.len());
+        histogram.counts[bucket] += 1;
+        histogram.count += 1;
+        histogram.sum += secs;
+    }
+
+    pub fn counters(&self) -> Vec<(Key, u64)> {
+        let data = self.data.lock().unwrap();
+        data.counters.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    pub fn histograms(&self) -> Vec<(Key, Histogram)> {
+        let data = self.data.lock().unwrap();
+        data.histograms
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+}
+
+fn key(name: &str, labels: &Labels) -> Key {
+    let mut labels: Vec<(String, String)> = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    labels.sort();
+    (name.to_string(), labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = new();
+        registry.count("requests", &[("status", "200"), ("method", "GET")], 1);
+        registry.count("requests", &[("method", "GET"), ("status", "200")], 2);
+        registry.time("latency", &[], Duration::from_millis(3));
+        registry.time("latency", &[], Duration::from_millis(30));
+        registry.time("latency", &[], Duration::from_secs(60));
+        let counters = registry.counters();
+        assert_eq!(1, counters.len());
+        assert_eq!(3, counters[0].1);
+        let histogram = &registry.histograms()[0].1;
+        assert_eq!(3, histogram.count);
+        assert_eq!(1, histogram.counts[0]);
+        assert_eq!(1, histogram.counts[3]);
+        assert_eq!(1, histogram.counts[BUCKETS.len()]);
+    }
+}
@@ -0,0 +1,91 @@
+use domain::metrics::*;
+use log::*;
+use std::io;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+/// Sends every measurement to a StatsD agent over UDP as it's made, with DogStatsD-style tags
+pub struct StatsdMetrics {
+    socket: UdpSocket,
+}
+
+/// `addr` is the agent's, e.g. localhost:8125
+pub fn new(addr: &str) -> io::Result<StatsdMetrics> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(addr)?;
+    // Measurements are dropped rather than hold up a request
+    socket.set_nonblocking(true)?;
+    Ok(StatsdMetrics { socket })
+}
+
+/// e.g. `http_requests:1|c|#method:GET,status:200`
+pub fn line(name: &str, labels: &Labels, value: &str, kind: &str) -> String {
+    let tags: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}:{}", sanitise(k), sanitise(v)))
+        .collect();
+    if tags.is_empty() {
+        format!("{}:{}|{}", sanitise(name), value, kind)
+    } else {
+        format!("{}:{}|{}|#{}", sanitise(name), value, kind, tags.join(","))
+    }
+}
+
+// Keeps the separators of the line format out of names and tags
+fn sanitise(s: &str) -> String {
+    s.replace(['|', ',', '#', '\n'], "_")
+}
+
+impl StatsdMetrics {
+    fn send(&self, line: String) {
+        if let Err(e) = self.socket.send(line.as_bytes()) {
+            debug!("Could not send [{}] to StatsD: {}", line, e);
+        }
+    }
+}
+
+impl Metrics for StatsdMetrics {
+    fn count(&self, name: &str, labels: &Labels, value: u64) {
+        self.send(line(name, labels, &value.to_string(), "c"))
+    }
+
+    fn time(&self, name: &str, labels: &Labels, duration: Duration) {
+        let millis = duration.as_secs_f64() * 1000.0;
+        self.send(line(name, labels, &format!("{:.3}", millis), "ms"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let metrics = new(&agent.local_addr().unwrap().to_string()).unwrap();
+        let mut buf = [0; 512];
+
+        metrics.count(
+            "http_requests",
+            &[("method", "GET"), ("route", "/tasks/{id}")],
+            1,
+        );
+        let len = agent.recv(&mut buf).unwrap();
+        assert_eq!(
+            "http_requests:1|c|#method:GET,route:/tasks/{id}",
+            std::str::from_utf8(&buf[..len]).unwrap()
+        );
+
+        metrics.time("http_request_duration", &[], Duration::from_micros(1500));
+        let len = agent.recv(&mut buf).unwrap();
+        assert_eq!(
+            "http_request_duration:1.500|ms",
+            std::str::from_utf8(&buf[..len]).unwrap()
+        );
+
+        assert_eq!("a_b:1|c|#k:x_y", line("a|b", &[("k", "x,y")], "1", "c"));
+    }
+}
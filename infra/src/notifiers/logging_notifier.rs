@@ -0,0 +1,26 @@
+use domain::reminder::*;
+use domain::todo::Todo;
+use log::*;
+
+use async_trait::async_trait;
+
+/// Logs reminders, for when nothing else is set up to deliver them
+#[derive(Clone)]
+pub struct LoggingNotifier;
+
+pub fn new() -> LoggingNotifier {
+    LoggingNotifier
+}
+
+#[async_trait]
+impl Notifier for LoggingNotifier {
+    async fn notify(&self, reminder: &Reminder, todo: &Todo) -> Result<(), String> {
+        info!(
+            "Reminder for todo [{:?}], due at [{}]: {}",
+            todo.id,
+            reminder.remind_at.to_rfc3339(),
+            todo.task
+        );
+        Ok(())
+    }
+}
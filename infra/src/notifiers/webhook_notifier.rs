@@ -0,0 +1,86 @@
+use crate::ids::id_json::todo_json;
+use domain::reminder::*;
+use domain::todo::Todo;
+use reqwest::Client;
+use serde_json::json;
+
+use async_trait::async_trait;
+
+/// POSTs reminders as JSON to a URL
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+pub fn new(url: &str) -> WebhookNotifier {
+    WebhookNotifier {
+        client: Client::new(),
+        url: url.to_string(),
+    }
+}
+
+/// What gets POSTed, e.g.
+/// `{"todo": {"id": 1, "task": "Water the plants", "done": false, "version": 1, "list_id": null, "position": 1}, "remind_at": "2024-01-31T12:00:00+00:00"}`
+pub fn payload(reminder: &Reminder, todo: &Todo) -> Vec<u8> {
+    json!({
+        "todo": todo_json(todo),
+        "remind_at": reminder.remind_at.to_rfc3339(),
+    })
+    .to_string()
+    .into_bytes()
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, reminder: &Reminder, todo: &Todo) -> Result<(), String> {
+        self.client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(payload(reminder, todo))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| format!("Could not POST the reminder to [{}]: {}", self.url, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use domain::todo::TodoId;
+
+    #[test]
+    fn test_payload() {
+        let todo = Todo {
+            id: TodoId(1),
+            task: "Water the plants".to_string(),
+            done: false,
+            version: 1,
+            list_id: None,
+            position: 1,
+        };
+        let reminder = Reminder {
+            todo_id: todo.id,
+            remind_at: Utc.with_ymd_and_hms(2024, 1, 31, 12, 0, 0).unwrap(),
+        };
+        let payload: serde_json::Value =
+            serde_json::from_slice(&payload(&reminder, &todo)).unwrap();
+        assert_eq!(
+            json!({
+                "todo": {
+                    "id": 1,
+                    "task": "Water the plants",
+                    "done": false,
+                    "version": 1,
+                    "list_id": null,
+                    "position": 1,
+                },
+                "remind_at": "2024-01-31T12:00:00+00:00",
+            }),
+            payload
+        );
+    }
+}
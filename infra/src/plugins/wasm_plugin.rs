@@ -0,0 +1,214 @@
+use crate::messaging::event_payload;
+use async_trait::async_trait;
+use domain::events::{Subscriber, TodoEvent};
+use domain::todo::{TodoData, TodoValidator};
+use log::*;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use wasmtime::{Config, Engine, Error, Instance, Module, Store};
+
+// Plenty for munging a bit of JSON, while bounding how long a misbehaving hook can hold up a request
+static FUEL_PER_CALL: u64 = 10_000_000;
+
+/// A sandboxed WebAssembly plugin. It can't import anything, so all it can do is compute on what
+/// it's given, and every call runs in a fresh instance with a bounded amount of fuel.
+///
+/// Plugins export `memory` and `alloc(len: i32) -> i32`, plus any of these hooks, which are given
+/// JSON:
+/// - `validate(ptr: i32, len: i32) -> i64` gets todo data being written. 0 accepts it; anything
+///   else rejects it and is the `(ptr << 32) | len` of a UTF-8 reason.
+/// - `on_event(ptr: i32, len: i32)` gets every todo event, in the same shape as live updates.
+pub struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+}
+
+/// Loads every `.wasm` (or `.wat`) file in the directory, ordered by file name
+pub fn load_dir(dir: &Path) -> Result<Vec<WasmPlugin>, Error> {
+    let engine = engine()?;
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "wasm" || ext == "wat")
+        })
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            new(&engine, &name, &fs::read(path)?)
+                .map_err(|e| e.context(format!("loading [{}]", path.display())))
+        })
+        .collect()
+}
+
+/// An engine that meters fuel, which plugins have to be compiled with
+pub fn engine() -> Result<Engine, Error> {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config)
+}
+
+pub fn new(engine: &Engine, name: &str, bytes: &[u8]) -> Result<WasmPlugin, Error> {
+    let module = Module::new(engine, bytes)?;
+    if module.imports().len() > 0 {
+        return Err(Error::msg("plugins must not import anything"));
+    }
+    for export in &["memory", "alloc"] {
+        if module.get_export(export).is_none() {
+            return Err(Error::msg(format!("plugins must export [{}]", export)));
+        }
+    }
+    Ok(WasmPlugin {
+        name: name.to_string(),
+        engine: engine.clone(),
+        module,
+    })
+}
+
+impl WasmPlugin {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Which of the hooks this plugin implements
+    pub fn hooks(&self) -> Vec<&'static str> {
+        ["validate", "on_event"]
+            .iter()
+            .filter(|hook| self.module.get_export(hook).is_some())
+            .copied()
+            .collect()
+    }
+
+    // Instantiates the module and copies the input into its memory
+    fn instantiate(&self, input: &[u8]) -> Result<(Store<()>, Instance, i32, i32), Error> {
+        let mut store = Store::new(&self.engine, ());
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = Instance::new(&mut store, &self.module, &[])?;
+        let len = input.len() as i32;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory(&instance, &mut store)?.write(&mut store, ptr as u32 as usize, input)?;
+        Ok((store, instance, ptr, len))
+    }
+
+    fn call_validate(&self, input: &[u8]) -> Result<Option<String>, Error> {
+        let (mut store, instance, ptr, len) = self.instantiate(input)?;
+        let validate = instance.get_typed_func::<(i32, i32), i64>(&mut store, "validate")?;
+        let packed = validate.call(&mut store, (ptr, len))?;
+        if packed == 0 {
+            return Ok(None);
+        }
+        let (reason_ptr, reason_len) = ((packed >> 32) as u32, packed as u32);
+        let mut reason = vec![0; reason_len as usize];
+        memory(&instance, &mut store)?.read(&store, reason_ptr as usize, &mut reason)?;
+        Ok(Some(String::from_utf8_lossy(&reason).into_owned()))
+    }
+
+    fn call_on_event(&self, input: &[u8]) -> Result<(), Error> {
+        let (mut store, instance, ptr, len) = self.instantiate(input)?;
+        let on_event = instance.get_typed_func::<(i32, i32), ()>(&mut store, "on_event")?;
+        on_event.call(&mut store, (ptr, len))
+    }
+}
+
+fn memory(instance: &Instance, store: &mut Store<()>) -> Result<wasmtime::Memory, Error> {
+    instance
+        .get_memory(store, "memory")
+        .ok_or_else(|| Error::msg("no [memory] export"))
+}
+
+#[async_trait]
+impl TodoValidator for WasmPlugin {
+    // A plugin that fails (e.g. runs out of fuel) rejects the data rather than letting it through
+    async fn validate(&self, todo_data: &TodoData) -> Result<(), String> {
+        if self.module.get_export("validate").is_none() {
+            return Ok(());
+        }
+        let input = json!({ "task": todo_data.task, "done": todo_data.done }).to_string();
+        match self.call_validate(input.as_bytes()) {
+            Ok(None) => Ok(()),
+            Ok(Some(reason)) => Err(reason),
+            Err(e) => Err(format!("plugin [{}] failed: {}", self.name, e)),
+        }
+    }
+}
+
+#[async_trait]
+impl Subscriber for WasmPlugin {
+    async fn notify(&self, event: &TodoEvent) {
+        if self.module.get_export("on_event").is_none() {
+            return;
+        }
+        if let Err(e) = self.call_on_event(&event_payload::payload(event)) {
+            error!("Plugin [{}] failed handling a todo event: {}", self.name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    // Rejects anything longer than 40 bytes of JSON, and spins forever on events
+    static PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "too long")
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (global.get $next)
+            (global.set $next (i32.add (global.get $next) (local.get $len))))
+          (func (export "validate") (param $ptr i32) (param $len i32) (result i64)
+            (if (result i64) (i32.gt_u (local.get $len) (i32.const 40))
+              (then (i64.const 8))
+              (else (i64.const 0))))
+          (func (export "on_event") (param i32 i32)
+            (loop $forever (br $forever))))
+    "#;
+
+    fn compile(source: &str) -> Result<WasmPlugin, Error> {
+        new(&engine()?, "test", source.as_bytes())
+    }
+
+    fn todo_data(task: &str) -> TodoData {
+        TodoData {
+            task: task.to_string(),
+            done: false,
+            list_id: None,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let plugin = compile(PLUGIN).unwrap();
+        assert_eq!(vec!["validate", "on_event"], plugin.hooks());
+        assert!(block_on(plugin.validate(&todo_data("short"))).is_ok());
+        assert_eq!(
+            Err("too long".to_string()),
+            block_on(plugin.validate(&todo_data("a task that goes on and on")))
+        );
+    }
+
+    #[test]
+    fn test_sandboxed() {
+        let plugin = compile(PLUGIN).unwrap();
+        let event = TodoEvent::Deleted(domain::events::TodoDeleted {
+            id: domain::todo::TodoId(1),
+        });
+        // Runs out of fuel instead of hanging
+        assert!(plugin
+            .call_on_event(&event_payload::payload(&event))
+            .is_err());
+        let importing = r#"(module (import "env" "f" (func)) (memory (export "memory") 1))"#;
+        assert!(compile(importing).is_err());
+    }
+}
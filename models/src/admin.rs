@@ -0,0 +1,167 @@
+use crate::todo::TodoId;
+#[cfg(feature = "openapi")]
+use paperclip::actix::Apiv2Schema;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct RouteConsumer {
+    pub client: String,
+    pub calls: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct DeprecatedRouteReport {
+    pub method: String,
+    pub path: String,
+    pub sunset: String,
+    pub link: Option<String>,
+    pub consumers: Vec<RouteConsumer>,
+}
+
+/// A snapshot of server internals, for debugging slowdowns
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct Diagnostics {
+    pub runtime: RuntimeDiagnostics,
+    pub repo_lock: LockDiagnostics,
+    /// Only present when built with the `jemalloc` feature
+    pub allocations: Option<AllocationDiagnostics>,
+    pub event_queues: Vec<EventQueueDiagnostics>,
+}
+
+/// Stats for the async runtime of the worker that served the request
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct RuntimeDiagnostics {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub queue_depth: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct LockDiagnostics {
+    pub acquisitions: u64,
+    /// Acquisitions that had to wait for another holder
+    pub contended: u64,
+}
+
+/// The queue in front of a todo event subscriber
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct EventQueueDiagnostics {
+    pub subscriber: String,
+    /// Events waiting in memory
+    pub depth: usize,
+    /// Events waiting on disk
+    pub spilled: usize,
+    /// Events dropped since startup
+    pub dropped: u64,
+    /// Changes that had to wait for room since startup
+    pub blocked: u64,
+}
+
+/// Bytes, as reported by jemalloc
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct AllocationDiagnostics {
+    pub allocated: usize,
+    pub resident: usize,
+}
+
+/// Recent readiness check outcomes, oldest first
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct HealthHistory {
+    /// How many periods are kept before the oldest are dropped
+    pub size: usize,
+    pub periods: Vec<HealthPeriod>,
+}
+
+/// Consecutive readiness checks with the same outcome
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct HealthPeriod {
+    pub ready: bool,
+    pub detail: String,
+    /// RFC 3339
+    pub since: String,
+    /// RFC 3339
+    pub last_checked: String,
+    pub checks: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileFormat {
+    /// An SVG flamegraph
+    Flamegraph,
+    /// An uncompressed pprof protobuf, e.g. for `go tool pprof`
+    Pprof,
+}
+
+impl ProfileFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ProfileFormat::Flamegraph => "image/svg+xml",
+            ProfileFormat::Pprof => "application/octet-stream",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ProfileParams {
+    /// How long to sample for; defaults to 10 and is capped at 60
+    pub seconds: Option<u64>,
+    /// Defaults to a flamegraph
+    pub format: Option<ProfileFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct AuditParams {
+    /// RFC 3339; only changes made from then on are returned
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ExportParams {
+    /// The `seq` of the last event loaded; every event still kept is streamed when not given
+    pub since: Option<u64>,
+}
+
+/// How many todos there are
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct TodoStats {
+    pub count: usize,
+    /// The highest id, which with sequential ids is the latest todo's
+    pub last_id: Option<TodoId>,
+}
+
+/// How many todos a purge deleted
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct Purged {
+    pub deleted: usize,
+}
+
+/// Whether changes are turned down with a 503 while reads still work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ReadOnlyMode {
+    pub read_only: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct VersionInfo {
+    pub version: String,
+    /// SHA-256 of the embedded static assets, for telling builds apart
+    pub asset_bundle_hash: String,
+}
@@ -0,0 +1,99 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[cfg(feature = "openapi")]
+use paperclip::actix::Apiv2Schema;
+
+/// A human-readable outcome, used for both successes and errors
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct Message {
+    #[cfg_attr(
+        feature = "openapi",
+        openapi(example = "Successfully deleted: [TodoId(1)]")
+    )]
+    pub message: String,
+    /// On errors, what went wrong; unlike the message, it won't change between releases
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    /// On successes, non-fatal issues worth knowing about, e.g. that another open todo has the
+    /// same task
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// On errors, the id of the request, as in its `X-Request-Id` response header and server logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// A [[Message]] for JSON bodies that couldn't be parsed, saying where they went wrong
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MalformedBody {
+    #[serde(flatten)]
+    pub message: Message,
+    /// Where in the body, e.g. `done` or `ids[2]`; absent when the body isn't JSON at all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// What the field should have been, e.g. `a boolean`; absent for missing fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+}
+
+/// Stable, machine-readable reasons for error responses, for clients to branch on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request's body, path or query couldn't be parsed
+    MalformedRequest,
+    /// No route has the request's path
+    RouteNotFound,
+    /// The path is served, but not under the request's method; the Allow header lists which
+    MethodNotAllowed,
+    TodoNotFound,
+    TaskEmpty,
+    /// With dedupe, an open todo already has the same task; the Location header has its path
+    TaskDuplicate,
+    /// Against the server's task policy, e.g. too long
+    TaskDisallowed,
+    /// A plugin turned the todo down
+    TodoRejected,
+    /// The todo has been updated since the version in If-Match
+    VersionConflict,
+    /// The todo has been changed too often lately; retry after the Retry-After header's seconds
+    TooManyChanges,
+    ImportUnreadable,
+    /// Bulk changes need either ids or a list_id
+    SelectionInvalid,
+    /// Reordering was given the same id more than once
+    OrderInvalid,
+    ListNotFound,
+    ListInvalid,
+    /// Todos are still in the list
+    ListNotEmpty,
+    /// The todo exists, but doesn't recur
+    RecurrenceNotFound,
+    RecurrenceInvalid,
+    /// The todo exists, but has no reminder
+    ReminderNotFound,
+    ReminderInvalid,
+    RuleNotFound,
+    RuleInvalid,
+    /// Also covers expired and revoked links
+    ShareLinkNotFound,
+    ShareLinkInvalid,
+    InvalidSince,
+    AuditLogUnreadable,
+    /// The in-memory audit log has dropped events after `since`; the message says which is the
+    /// oldest it still has
+    ExportTruncated,
+    AdminTokenNotConfigured,
+    Unauthorized,
+    NotReady,
+    /// The server is in read-only mode, e.g. during a migration; reads still work
+    ReadOnly,
+    /// The todos' storage couldn't be reached in time; retrying later may work
+    StorageUnavailable,
+    /// Another write to the todo got in the way; retrying may work
+    WriteConflict,
+    /// Anything unexpected on the server's side
+    Internal,
+}
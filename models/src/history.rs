@@ -0,0 +1,83 @@
+use crate::todo::{Todo, TodoId};
+use domain::history as domain_models;
+#[cfg(feature = "openapi")]
+use paperclip::actix::Apiv2Schema;
+use serde_derive::{Deserialize, Serialize};
+
+/// One change to a todo
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct TodoChange {
+    pub todo_id: TodoId,
+    /// Not set when the change created the todo
+    pub old: Option<Todo>,
+    /// Not set when the change deleted the todo
+    pub new: Option<Todo>,
+    /// RFC 3339
+    #[cfg_attr(feature = "openapi", openapi(example = "2024-01-31T12:00:00+00:00"))]
+    pub at: String,
+    /// The client that made the change: its X-Client-Id header, user agent, or address
+    pub actor: Option<String>,
+    /// The X-Request-Id of the request that made the change
+    pub request_id: Option<String>,
+    /// Only set for changes that were turned down, with why; `new` is what was asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<String>,
+}
+
+impl From<domain_models::TodoChange> for TodoChange {
+    fn from(v: domain_models::TodoChange) -> Self {
+        TodoChange {
+            todo_id: v.todo_id.into(),
+            old: v.old.map(|t| t.into()),
+            new: v.new.map(|t| t.into()),
+            at: v.at.to_rfc3339(),
+            actor: v.actor,
+            request_id: v.request_id,
+            rejected: v.rejected,
+        }
+    }
+}
+
+/// Bumped whenever [[ExportedEvent]] changes in a way that loaders have to know about
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExportedEventKind {
+    Created,
+    Updated,
+    Deleted,
+    /// Turned down; nothing changed
+    Rejected,
+}
+
+/// A line of the export stream: a change, numbered by its position in the audit log
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ExportedEvent {
+    pub schema_version: u32,
+    /// Pass the last one loaded as `since` to carry on from there
+    pub seq: u64,
+    pub event: ExportedEventKind,
+    pub change: TodoChange,
+}
+
+impl From<domain_models::SequencedChange> for ExportedEvent {
+    fn from(v: domain_models::SequencedChange) -> Self {
+        let change = v.change;
+        let event = match (&change.old, &change.new) {
+            _ if change.rejected.is_some() => ExportedEventKind::Rejected,
+            (None, _) => ExportedEventKind::Created,
+            (_, None) => ExportedEventKind::Deleted,
+            _ => ExportedEventKind::Updated,
+        };
+        ExportedEvent {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            seq: v.seq,
+            event,
+            change: change.into(),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Opaque ids: with a codec installed, ids are written and read as short strings derived from a
+//! secret instead of their sequential numbers, so that they can't be guessed
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::OnceLock;
+
+static CODEC: OnceLock<IdCodec> = OnceLock::new();
+
+static ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+const ROUNDS: usize = 4;
+
+/// Reversibly scrambles ids with a secret, then writes them in base62
+pub struct IdCodec {
+    keys: [u64; ROUNDS],
+}
+
+pub fn codec(secret: &str) -> IdCodec {
+    // FNV-1a, then a splitmix64 sequence seeded with it for the round keys
+    let mut seed = secret.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    let mut keys = [0; ROUNDS];
+    for key in keys.iter_mut() {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        *key = mix(seed);
+    }
+    IdCodec { keys }
+}
+
+/// Makes every id (de)serialized or displayed from now on in this process go through `codec`.
+/// Fails when one is already installed.
+pub fn install(codec: IdCodec) -> Result<(), IdCodec> {
+    CODEC.set(codec)
+}
+
+impl IdCodec {
+    pub fn encode(&self, id: u64) -> String {
+        // A Feistel network over the two halves, so it is a permutation whatever the keys
+        let (mut l, mut r) = ((id >> 32) as u32, id as u32);
+        for key in self.keys.iter() {
+            let next = l ^ round(r, *key);
+            l = r;
+            r = next;
+        }
+        base62(u64::from(l) << 32 | u64::from(r))
+    }
+
+    /// None when `s` isn't something `encode` returns
+    pub fn decode(&self, s: &str) -> Option<u64> {
+        let scrambled = unbase62(s)?;
+        let (mut l, mut r) = ((scrambled >> 32) as u32, scrambled as u32);
+        for key in self.keys.iter().rev() {
+            let prev = r ^ round(l, *key);
+            r = l;
+            l = prev;
+        }
+        Some(u64::from(l) << 32 | u64::from(r))
+    }
+}
+
+fn round(half: u32, key: u64) -> u32 {
+    (mix(u64::from(half) ^ key) >> 32) as u32
+}
+
+// splitmix64's finalizer
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn base62(mut n: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(ALPHABET[(n % 62) as usize]);
+        n /= 62;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().map(|&d| d as char).collect()
+}
+
+// Leading zeros are rejected so that every id has exactly one encoding
+fn unbase62(s: &str) -> Option<u64> {
+    if s.is_empty() || (s.len() > 1 && s.starts_with('0')) {
+        return None;
+    }
+    s.bytes().try_fold(0_u64, |n, b| {
+        let digit = ALPHABET.iter().position(|&a| a == b)? as u64;
+        n.checked_mul(62)?.checked_add(digit)
+    })
+}
+
+pub(crate) fn fmt(id: u64, f: &mut fmt::Formatter) -> fmt::Result {
+    match CODEC.get() {
+        Some(codec) => f.write_str(&codec.encode(id)),
+        None => write!(f, "{}", id),
+    }
+}
+
+pub(crate) fn parse(s: &str) -> Result<u64, String> {
+    match CODEC.get() {
+        Some(codec) => codec.decode(s).ok_or_else(|| format!("No such id: {}", s)),
+        None => s.parse().map_err(|_| format!("Not an id: {}", s)),
+    }
+}
+
+pub(crate) fn serialize<S: Serializer>(id: u64, serializer: S) -> Result<S::Ok, S::Error> {
+    match CODEC.get() {
+        Some(codec) => serializer.serialize_str(&codec.encode(id)),
+        None => serializer.serialize_u64(id),
+    }
+}
+
+// Todo ids wider than 64 bits are UUIDs, written as such whether or not a codec is installed, and
+// told apart from the rest by their hyphens
+
+pub(crate) fn fmt_wide(id: u128, f: &mut fmt::Formatter) -> fmt::Result {
+    match u64::try_from(id) {
+        Ok(id) => fmt(id, f),
+        Err(_) => write!(f, "{}", domain::todo::TodoId(id)),
+    }
+}
+
+pub(crate) fn parse_wide(s: &str) -> Result<u128, String> {
+    if s.contains('-') {
+        s.parse::<domain::todo::TodoId>().map(|id| id.0)
+    } else {
+        parse(s).map(u128::from)
+    }
+}
+
+pub(crate) fn serialize_wide<S: Serializer>(id: u128, serializer: S) -> Result<S::Ok, S::Error> {
+    match u64::try_from(id) {
+        Ok(id) => serialize(id, serializer),
+        Err(_) => serializer.collect_str(&domain::todo::TodoId(id)),
+    }
+}
+
+pub(crate) fn deserialize_wide<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u128, D::Error> {
+    deserializer.deserialize_any(WideIdVisitor)
+}
+
+// For fields that are strings whether or not a codec is installed, as in the v2 models
+pub(crate) fn serialize_as_string<T: fmt::Display, S: Serializer>(
+    id: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(id)
+}
+
+pub(crate) fn serialize_opt_as_string<T: fmt::Display, S: Serializer>(
+    id: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match id {
+        Some(id) => serializer.collect_str(id),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    // Path segments and CSV fields that look like numbers come through as numbers
+    deserializer.deserialize_any(IdVisitor)
+}
+
+struct IdVisitor;
+
+impl<'de> Visitor<'de> for IdVisitor {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match CODEC.get() {
+            Some(_) => f.write_str("an id string"),
+            None => f.write_str("a numeric id"),
+        }
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<u64, E> {
+        match CODEC.get() {
+            // The number's digits are the encoded id
+            Some(_) => self.visit_str(&v.to_string()),
+            None => Ok(v),
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<u64, E> {
+        match CODEC.get() {
+            Some(_) => self.visit_str(&v.to_string()),
+            None => u64::try_from(v).map_err(|_| E::custom(format!("Not an id: {}", v))),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<u64, E> {
+        parse(v).map_err(E::custom)
+    }
+}
+
+struct WideIdVisitor;
+
+impl<'de> Visitor<'de> for WideIdVisitor {
+    type Value = u128;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        IdVisitor.expecting(f)?;
+        f.write_str(" or a UUID")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<u128, E> {
+        IdVisitor.visit_u64(v).map(u128::from)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<u128, E> {
+        IdVisitor.visit_i64(v).map(u128::from)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<u128, E> {
+        parse_wide(v).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let codec = codec("secret");
+        for id in [0, 1, 2, 62, 1 << 32, u64::MAX] {
+            let encoded = codec.encode(id);
+            assert!(encoded.bytes().all(|b| b.is_ascii_alphanumeric()));
+            assert_eq!(Some(id), codec.decode(&encoded));
+        }
+        assert_eq!(None, codec.decode(""));
+        assert_eq!(None, codec.decode("a-b"));
+        assert_eq!(None, codec.decode("zzzzzzzzzzzz"));
+        assert_eq!(None, codec.decode(&format!("0{}", codec.encode(1))));
+    }
+
+    #[test]
+    fn test_unguessable() {
+        let codec = codec("secret");
+        let (one, two) = (codec.encode(1), codec.encode(2));
+        assert_ne!(one, two);
+        assert_ne!(unbase62(&one).map(|n| n + 1), unbase62(&two));
+        assert_ne!(one, super::codec("other").encode(1));
+    }
+
+    #[test]
+    fn test_wide() {
+        let uuid = "6f9619ff-8b86-d011-b42d-00c04fc964ff";
+        assert_eq!(Ok(0x6f9619ff_8b86_d011_b42d_00c04fc964ff), parse_wide(uuid));
+        assert_eq!(Ok(42), parse_wide("42"));
+        // Narrow ids have just the one way of being written
+        assert!(parse_wide("00000000-0000-0000-0000-00000000002a").is_err());
+    }
+}
@@ -0,0 +1,13 @@
+//! The JSON bodies of the HTTP API, shared by the server and its clients
+
+pub mod admin;
+pub mod common;
+pub mod history;
+pub mod ids;
+pub mod list;
+pub mod recurrence;
+pub mod reminder;
+pub mod rule;
+pub mod share;
+pub mod todo;
+pub mod v2;
@@ -0,0 +1,98 @@
+use crate::ids;
+use domain::list as domain_models;
+#[cfg(feature = "openapi")]
+use paperclip::actix::{Apiv2Schema, OperationModifier};
+#[cfg(feature = "openapi")]
+use paperclip::v2::schema::Apiv2Schema as Apiv2SchemaTrait;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct ListId(pub u64);
+
+// Opaque strings rather than numbers once an id codec is installed
+impl serde::Serialize for ListId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ids::serialize(self.0, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ListId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ids::deserialize(deserializer).map(ListId)
+    }
+}
+
+// As it appears in URLs
+impl fmt::Display for ListId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        ids::fmt(self.0, f)
+    }
+}
+
+// Empty schema; the id shows up as a path parameter
+#[cfg(feature = "openapi")]
+impl Apiv2SchemaTrait for ListId {}
+#[cfg(feature = "openapi")]
+impl OperationModifier for ListId {}
+
+/// Data for creating a list
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ListData {
+    /// Must not be blank
+    #[cfg_attr(feature = "openapi", openapi(example = "Chores"))]
+    pub name: String,
+}
+
+/// A named collection of todos
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct List {
+    pub id: ListId,
+    #[cfg_attr(feature = "openapi", openapi(example = "Chores"))]
+    pub name: String,
+}
+
+impl ListData {
+    /// The server's checks, failing with the message it would respond with
+    pub fn validate(&self) -> Result<(), String> {
+        domain_models::ListData::from(self)
+            .validate()
+            .map_err(|reason| invalid_list_message(&reason))
+    }
+}
+
+/// The message of the 400 for list data that fails the checks
+pub fn invalid_list_message(reason: &str) -> String {
+    format!("Invalid list: {}", reason)
+}
+
+impl From<&ListId> for domain_models::ListId {
+    fn from(v: &ListId) -> Self {
+        domain_models::ListId(v.0)
+    }
+}
+
+impl From<&ListData> for domain_models::ListData {
+    fn from(v: &ListData) -> Self {
+        domain_models::ListData {
+            name: v.name.clone(),
+        }
+    }
+}
+
+impl From<domain_models::ListId> for ListId {
+    fn from(v: domain_models::ListId) -> Self {
+        ListId(v.0)
+    }
+}
+
+impl From<domain_models::List> for List {
+    fn from(v: domain_models::List) -> Self {
+        List {
+            id: v.id.into(),
+            name: v.name,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use domain::recurrence as domain_models;
+#[cfg(feature = "openapi")]
+use paperclip::actix::Apiv2Schema;
+use serde_derive::{Deserialize, Serialize};
+
+/// How a todo comes back once done: its next occurrence is created as a new todo on the day it's
+/// due, and recurs in turn
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct Recurrence {
+    pub repeat: Repeat,
+    /// Only for `weekdays`, which needs at least one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on: Vec<Day>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum Repeat {
+    /// The day after it's done
+    Daily,
+    /// A week after it's done
+    Weekly,
+    /// On the next of the days in `on`
+    Weekdays,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Recurrence {
+    /// The server's checks, failing with the message it would respond with
+    pub fn validate(&self) -> Result<(), String> {
+        domain_models::Recurrence::from(self)
+            .validate()
+            .map_err(|reason| invalid_recurrence_message(&reason))
+    }
+}
+
+/// The message of the 400 for a recurrence that fails the checks
+pub fn invalid_recurrence_message(reason: &str) -> String {
+    format!("Invalid recurrence: {}", reason)
+}
+
+impl From<&Recurrence> for domain_models::Recurrence {
+    fn from(v: &Recurrence) -> Self {
+        match v.repeat {
+            Repeat::Daily => domain_models::Recurrence::Daily,
+            Repeat::Weekly => domain_models::Recurrence::Weekly,
+            Repeat::Weekdays => {
+                domain_models::Recurrence::Weekdays(v.on.iter().map(|&d| d.into()).collect())
+            }
+        }
+    }
+}
+
+impl From<domain_models::Recurrence> for Recurrence {
+    fn from(v: domain_models::Recurrence) -> Self {
+        match v {
+            domain_models::Recurrence::Daily => Recurrence {
+                repeat: Repeat::Daily,
+                on: Vec::new(),
+            },
+            domain_models::Recurrence::Weekly => Recurrence {
+                repeat: Repeat::Weekly,
+                on: Vec::new(),
+            },
+            domain_models::Recurrence::Weekdays(days) => Recurrence {
+                repeat: Repeat::Weekdays,
+                on: days.into_iter().map(|d| d.into()).collect(),
+            },
+        }
+    }
+}
+
+impl From<Day> for domain_models::Weekday {
+    fn from(v: Day) -> Self {
+        match v {
+            Day::Mon => domain_models::Weekday::Mon,
+            Day::Tue => domain_models::Weekday::Tue,
+            Day::Wed => domain_models::Weekday::Wed,
+            Day::Thu => domain_models::Weekday::Thu,
+            Day::Fri => domain_models::Weekday::Fri,
+            Day::Sat => domain_models::Weekday::Sat,
+            Day::Sun => domain_models::Weekday::Sun,
+        }
+    }
+}
+
+impl From<domain_models::Weekday> for Day {
+    fn from(v: domain_models::Weekday) -> Self {
+        match v {
+            domain_models::Weekday::Mon => Day::Mon,
+            domain_models::Weekday::Tue => Day::Tue,
+            domain_models::Weekday::Wed => Day::Wed,
+            domain_models::Weekday::Thu => Day::Thu,
+            domain_models::Weekday::Fri => Day::Fri,
+            domain_models::Weekday::Sat => Day::Sat,
+            domain_models::Weekday::Sun => Day::Sun,
+        }
+    }
+}
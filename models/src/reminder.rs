@@ -0,0 +1,47 @@
+use crate::todo::TodoId;
+use domain::reminder as domain_models;
+#[cfg(feature = "openapi")]
+use paperclip::actix::Apiv2Schema;
+use serde_derive::{Deserialize, Serialize};
+
+/// Data for setting a todo's reminder
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ReminderData {
+    /// RFC 3339; times in the past are due straight away
+    #[cfg_attr(feature = "openapi", openapi(example = "2024-01-31T12:00:00+00:00"))]
+    pub remind_at: String,
+}
+
+/// Sent once, when it's due, unless the todo has been done by then
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct Reminder {
+    pub todo_id: TodoId,
+    /// RFC 3339, in UTC
+    #[cfg_attr(feature = "openapi", openapi(example = "2024-01-31T12:00:00+00:00"))]
+    pub remind_at: String,
+}
+
+impl ReminderData {
+    /// The server's checks, failing with the message it would respond with
+    pub fn validate(&self) -> Result<(), String> {
+        domain_models::parse_remind_at(&self.remind_at)
+            .map(|_| ())
+            .map_err(|reason| invalid_reminder_message(&reason))
+    }
+}
+
+/// The message of the 400 for reminder data that fails the checks
+pub fn invalid_reminder_message(reason: &str) -> String {
+    format!("Invalid reminder: {}", reason)
+}
+
+impl From<domain_models::Reminder> for Reminder {
+    fn from(v: domain_models::Reminder) -> Self {
+        Reminder {
+            todo_id: v.todo_id.into(),
+            remind_at: v.remind_at.to_rfc3339(),
+        }
+    }
+}
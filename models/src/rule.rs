@@ -0,0 +1,192 @@
+use crate::ids;
+use domain::rule as domain_models;
+#[cfg(feature = "openapi")]
+use paperclip::actix::{Apiv2Schema, OperationModifier};
+#[cfg(feature = "openapi")]
+use paperclip::v2::schema::Apiv2Schema as Apiv2SchemaTrait;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct RuleId(pub u64);
+
+// Opaque strings rather than numbers once an id codec is installed
+impl serde::Serialize for RuleId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ids::serialize(self.0, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for RuleId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ids::deserialize(deserializer).map(RuleId)
+    }
+}
+
+// As it appears in URLs
+impl fmt::Display for RuleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        ids::fmt(self.0, f)
+    }
+}
+
+// Empty schema; the id shows up as a path parameter
+#[cfg(feature = "openapi")]
+impl Apiv2SchemaTrait for RuleId {}
+#[cfg(feature = "openapi")]
+impl OperationModifier for RuleId {}
+
+/// The kind of todo event a rule reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum RuleTrigger {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// When a rule fires
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct RuleCondition {
+    pub event: RuleTrigger,
+    /// Only fire for todos whose task contains this, ignoring case. Never matches deletions.
+    #[cfg_attr(feature = "openapi", openapi(example = "bug"))]
+    pub task_contains: Option<String>,
+}
+
+/// What a rule does once it fires
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct RuleAction {
+    /// Task of a todo to create; `{{id}}` and `{{task}}` are those of the triggering todo, and `{{date}}`,
+    /// `{{week}}`, `{{year}}`, `{{seq}}` and `{{seq:<name>}}` are also expanded
+    #[cfg_attr(
+        feature = "openapi",
+        openapi(example = "Write a regression test for: {{task}} ({{date}})")
+    )]
+    pub create_todo: String,
+}
+
+/// Data for creating a rule
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct RuleData {
+    #[cfg_attr(feature = "openapi", openapi(example = "Follow up on bugs"))]
+    pub name: String,
+    pub when: RuleCondition,
+    pub then: RuleAction,
+}
+
+/// A persisted rule
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct Rule {
+    pub id: RuleId,
+    #[cfg_attr(feature = "openapi", openapi(example = "Follow up on bugs"))]
+    pub name: String,
+    pub when: RuleCondition,
+    pub then: RuleAction,
+}
+
+impl From<&RuleId> for domain_models::RuleId {
+    fn from(v: &RuleId) -> Self {
+        domain_models::RuleId(v.0)
+    }
+}
+
+impl From<RuleTrigger> for domain_models::Trigger {
+    fn from(v: RuleTrigger) -> Self {
+        match v {
+            RuleTrigger::Created => domain_models::Trigger::Created,
+            RuleTrigger::Updated => domain_models::Trigger::Updated,
+            RuleTrigger::Deleted => domain_models::Trigger::Deleted,
+        }
+    }
+}
+
+impl From<&RuleCondition> for domain_models::Condition {
+    fn from(v: &RuleCondition) -> Self {
+        domain_models::Condition {
+            event: v.event.into(),
+            task_contains: v.task_contains.clone(),
+        }
+    }
+}
+
+impl From<&RuleAction> for domain_models::Action {
+    fn from(v: &RuleAction) -> Self {
+        domain_models::Action::CreateTodo {
+            task: v.create_todo.clone(),
+        }
+    }
+}
+
+impl RuleData {
+    /// The server's checks, failing with the message it would respond with
+    pub fn validate(&self) -> Result<(), String> {
+        domain_models::RuleData::from(self)
+            .validate()
+            .map_err(|reason| invalid_rule_message(&reason))
+    }
+}
+
+/// The message of the 400 for rule data that fails the checks
+pub fn invalid_rule_message(reason: &str) -> String {
+    format!("Invalid rule: {}", reason)
+}
+
+impl From<&RuleData> for domain_models::RuleData {
+    fn from(v: &RuleData) -> Self {
+        domain_models::RuleData {
+            name: v.name.clone(),
+            when: (&v.when).into(),
+            then: (&v.then).into(),
+        }
+    }
+}
+
+impl From<domain_models::RuleId> for RuleId {
+    fn from(v: domain_models::RuleId) -> Self {
+        RuleId(v.0)
+    }
+}
+
+impl From<domain_models::Trigger> for RuleTrigger {
+    fn from(v: domain_models::Trigger) -> Self {
+        match v {
+            domain_models::Trigger::Created => RuleTrigger::Created,
+            domain_models::Trigger::Updated => RuleTrigger::Updated,
+            domain_models::Trigger::Deleted => RuleTrigger::Deleted,
+        }
+    }
+}
+
+impl From<domain_models::Condition> for RuleCondition {
+    fn from(v: domain_models::Condition) -> Self {
+        RuleCondition {
+            event: v.event.into(),
+            task_contains: v.task_contains,
+        }
+    }
+}
+
+impl From<domain_models::Action> for RuleAction {
+    fn from(v: domain_models::Action) -> Self {
+        match v {
+            domain_models::Action::CreateTodo { task } => RuleAction { create_todo: task },
+        }
+    }
+}
+
+impl From<domain_models::Rule> for Rule {
+    fn from(v: domain_models::Rule) -> Self {
+        Rule {
+            id: v.id.into(),
+            name: v.name,
+            when: v.when.into(),
+            then: v.then.into(),
+        }
+    }
+}
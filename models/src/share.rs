@@ -0,0 +1,96 @@
+use crate::todo::TodoId;
+use domain::share as domain_models;
+#[cfg(feature = "openapi")]
+use paperclip::actix::{Apiv2Schema, OperationModifier};
+#[cfg(feature = "openapi")]
+use paperclip::v2::schema::Apiv2Schema as Apiv2SchemaTrait;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct ShareToken(pub String);
+
+// Empty schema; the token shows up as a path parameter
+#[cfg(feature = "openapi")]
+impl Apiv2SchemaTrait for ShareToken {}
+#[cfg(feature = "openapi")]
+impl OperationModifier for ShareToken {}
+
+/// Identifies a share link of a todo
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ShareLinkPath {
+    /// The shared todo's id
+    pub id: TodoId,
+    pub token: String,
+}
+
+/// Data for creating a share link
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ShareLinkData {
+    /// Seconds until the link stops working; it works until revoked when not set
+    #[cfg_attr(feature = "openapi", openapi(example = "86400"))]
+    pub expires_in_secs: Option<u64>,
+}
+
+/// Gives anyone with the token read-only access to a todo
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ShareLink {
+    pub token: ShareToken,
+    pub todo_id: TodoId,
+    /// RFC 3339
+    #[cfg_attr(feature = "openapi", openapi(example = "2024-01-31T12:00:00+00:00"))]
+    pub expires_at: Option<String>,
+    /// Where the todo can be read, relative to the server
+    #[cfg_attr(
+        feature = "openapi",
+        openapi(example = "/shared/4f1c9e0e8b2d4c5fa7d3e6b1c2a9f8e7")
+    )]
+    pub path: String,
+}
+
+impl ShareLinkData {
+    /// The server's checks, failing with the message it would respond with
+    pub fn validate(&self) -> Result<(), String> {
+        domain_models::ShareData::from(self)
+            .validate()
+            .map_err(|reason| invalid_share_link_message(&reason))
+    }
+}
+
+/// The message of the 400 for share link data that fails the checks
+pub fn invalid_share_link_message(reason: &str) -> String {
+    format!("Invalid share link: {}", reason)
+}
+
+impl From<&ShareToken> for domain_models::ShareToken {
+    fn from(v: &ShareToken) -> Self {
+        domain_models::ShareToken(v.0.clone())
+    }
+}
+
+impl From<&ShareLinkData> for domain_models::ShareData {
+    fn from(v: &ShareLinkData) -> Self {
+        domain_models::ShareData {
+            expires_in_secs: v.expires_in_secs,
+        }
+    }
+}
+
+impl From<domain_models::ShareToken> for ShareToken {
+    fn from(v: domain_models::ShareToken) -> Self {
+        ShareToken(v.0)
+    }
+}
+
+impl From<domain_models::Share> for ShareLink {
+    fn from(v: domain_models::Share) -> Self {
+        ShareLink {
+            path: format!("/shared/{}", v.token.0),
+            token: v.token.into(),
+            todo_id: v.todo_id.into(),
+            expires_at: v.expires_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use crate::ids;
+use crate::list::ListId;
+use domain::events as domain_events;
+use domain::todo as domain_models;
+#[cfg(feature = "openapi")]
+use paperclip::actix::{Apiv2Schema, OperationModifier};
+#[cfg(feature = "openapi")]
+use paperclip::v2::schema::Apiv2Schema as Apiv2SchemaTrait;
+use serde_derive::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct TodoId(pub u128);
+
+// Opaque strings rather than numbers once an id codec is installed, and UUID strings when the
+// server generates ids as UUIDs
+impl serde::Serialize for TodoId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ids::serialize_wide(self.0, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TodoId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ids::deserialize_wide(deserializer).map(TodoId)
+    }
+}
+
+// As it appears in URLs
+impl fmt::Display for TodoId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        ids::fmt_wide(self.0, f)
+    }
+}
+
+impl FromStr for TodoId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ids::parse_wide(s).map(TodoId)
+    }
+}
+
+// Untyped, as the id is a number or a string depending on how the server is configured; it shows
+// up as a path parameter
+#[cfg(feature = "openapi")]
+impl Apiv2SchemaTrait for TodoId {
+    fn description() -> &'static str {
+        "A number, or a string for opaque ids and ids generated as UUIDs"
+    }
+}
+#[cfg(feature = "openapi")]
+impl OperationModifier for TodoId {}
+
+/// Data for creating or updating a todo
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct TodoData {
+    /// What needs doing; must not be empty
+    #[cfg_attr(feature = "openapi", openapi(example = "Make the bed"))]
+    pub task: String,
+    /// Whether it has been completed; defaults to false
+    #[serde(default)]
+    pub done: bool,
+    /// The list to put it in, if any; must exist
+    #[serde(default)]
+    pub list_id: Option<ListId>,
+}
+
+/// A persisted todo
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct Todo {
+    pub id: TodoId,
+    /// What needs doing
+    #[cfg_attr(feature = "openapi", openapi(example = "Make the bed"))]
+    pub task: String,
+    pub done: bool,
+    /// Starts at 1 and is bumped on every update; also the todo's ETag
+    pub version: u64,
+    pub list_id: Option<ListId>,
+    /// Where the todo goes when listed in manual order; only reordering changes it
+    #[serde(default)]
+    pub position: u64,
+}
+
+/// A todo as just created, along with non-fatal issues found while saving it
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct SavedTodo {
+    pub id: TodoId,
+    #[cfg_attr(feature = "openapi", openapi(example = "Make the bed"))]
+    pub task: String,
+    pub done: bool,
+    pub version: u64,
+    pub list_id: Option<ListId>,
+    #[serde(default)]
+    pub position: u64,
+    /// E.g. that another open todo has the same task
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum TransferFormat {
+    Csv,
+    Json,
+}
+
+impl TransferFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            TransferFormat::Csv => "text/csv",
+            TransferFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            TransferFormat::Csv => "csv",
+            TransferFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct TransferParams {
+    /// Export defaults to JSON; import defaults to the request's content type
+    pub format: Option<TransferFormat>,
+}
+
+/// A row of an import that could not be turned into a todo
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ImportRowError {
+    /// 1-based, not counting a CSV header
+    pub row: usize,
+    pub message: String,
+}
+
+/// The outcome of an import; valid rows are created even when others fail
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ImportReport {
+    /// For dry runs, the todos that would be created, with an id of 0
+    pub created: Vec<Todo>,
+    pub errors: Vec<ImportRowError>,
+    /// Only for dry runs: every event the import would emit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub planned_events: Vec<PlannedEvent>,
+}
+
+/// Which todos to mark done: either ids, or a list to mark every todo in done
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct CompleteTodos {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ids: Option<Vec<TodoId>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_id: Option<ListId>,
+}
+
+/// The outcome of marking several todos done at once
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct CompletedTodos {
+    /// The todos that were open, as they are now; ones that were already done are left alone
+    pub completed: Vec<Todo>,
+    /// Given ids that no todo has
+    pub not_found: Vec<TodoId>,
+    /// E.g. rules' follow-ups that weren't created
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// How listed todos are ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum TodoOrder {
+    Id,
+    /// By position, as set by reordering
+    Manual,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ListParams {
+    /// Defaults to id
+    pub order: Option<TodoOrder>,
+}
+
+/// The ids of todos in the order they should go in; todos left out follow them, in the order
+/// they were already in
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct ReorderTodos {
+    pub ids: Vec<TodoId>,
+}
+
+impl ReorderTodos {
+    /// Returns why the order was rejected
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, id) in self.ids.iter().enumerate() {
+            if self.ids[..i].contains(id) {
+                return Err(format!("Invalid order: {} is given more than once", id));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct DryRunParams {
+    /// When true, the change is checked and planned, but not made
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Which todos of a list to return; all of them by default
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct PageParams {
+    /// How many todos to skip
+    pub offset: Option<usize>,
+    /// Most todos to return
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct DedupeParams {
+    /// When true, nothing is created if an open todo already has the same task, give or take case
+    /// and whitespace
+    #[serde(default)]
+    pub dedupe: bool,
+}
+
+/// What a change would do, as found by a dry run; nothing has been saved or emitted
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct TodoPlan {
+    /// As it would be saved; absent for deletes. Todos that would be created have an id of 0, as
+    /// ids are only assigned when saving.
+    pub todo: Option<Todo>,
+    /// In the order they would be emitted, including the creation of rules' follow-ups
+    pub events: Vec<PlannedEvent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// An event a dry run found would be emitted, shaped like the live update ones
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct PlannedEvent {
+    pub event: EventKind,
+    /// For created and updated events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub todo: Option<Todo>,
+    /// For deleted events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<TodoId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A change pushed to clients subscribed to live updates
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TodoEvent {
+    Created { todo: Todo },
+    Updated { todo: Todo },
+    Deleted { id: TodoId },
+}
+
+impl TodoData {
+    /// The server's built-in checks, failing with the message it would respond with. The server's
+    /// task policy and plugins may still reject data that passes.
+    pub fn validate(&self) -> Result<(), String> {
+        if domain_models::TodoData::from(self).is_valid() {
+            Ok(())
+        } else {
+            Err(invalid_task_message(&self.task))
+        }
+    }
+}
+
+impl CompleteTodos {
+    /// The server's checks, failing with the message it would respond with
+    pub fn validate(&self) -> Result<(), String> {
+        domain_models::TodoSelection::try_from(self).map(|_| ())
+    }
+}
+
+/// The message of the 400 for todo data that fails the built-in checks
+pub fn invalid_task_message(task: &str) -> String {
+    format!("Invalid task: [{}]", task)
+}
+
+impl From<&TodoId> for domain_models::TodoId {
+    fn from(v: &TodoId) -> Self {
+        domain_models::TodoId(v.0)
+    }
+}
+
+impl From<&TodoData> for domain_models::TodoData {
+    fn from(v: &TodoData) -> Self {
+        domain_models::TodoData {
+            task: v.task.clone(),
+            done: v.done,
+            list_id: v.list_id.as_ref().map(|id| id.into()),
+        }
+    }
+}
+
+impl From<&Todo> for domain_models::Todo {
+    fn from(v: &Todo) -> Self {
+        domain_models::Todo {
+            id: (&v.id).into(),
+            task: v.task.clone(),
+            done: v.done,
+            version: v.version,
+            list_id: v.list_id.as_ref().map(|id| id.into()),
+            position: v.position,
+        }
+    }
+}
+
+impl TryFrom<&CompleteTodos> for domain_models::TodoSelection {
+    type Error = String;
+
+    fn try_from(v: &CompleteTodos) -> Result<Self, Self::Error> {
+        match (&v.ids, &v.list_id) {
+            (Some(ids), None) => Ok(domain_models::TodoSelection::Ids(
+                ids.iter().map(|id| id.into()).collect(),
+            )),
+            (None, Some(list_id)) => Ok(domain_models::TodoSelection::List(list_id.into())),
+            _ => Err("Invalid selection: give either ids or a list_id".to_string()),
+        }
+    }
+}
+
+impl From<domain_models::TodoId> for TodoId {
+    fn from(v: domain_models::TodoId) -> Self {
+        TodoId(v.0)
+    }
+}
+
+impl From<domain_models::TodoData> for TodoData {
+    fn from(v: domain_models::TodoData) -> Self {
+        TodoData {
+            task: v.task,
+            done: v.done,
+            list_id: v.list_id.map(|id| id.into()),
+        }
+    }
+}
+
+impl From<domain_models::Todo> for Todo {
+    fn from(v: domain_models::Todo) -> Self {
+        Todo {
+            id: v.id.into(),
+            task: v.task,
+            done: v.done,
+            version: v.version,
+            list_id: v.list_id.map(|id| id.into()),
+            position: v.position,
+        }
+    }
+}
+
+impl From<domain_models::SavedTodo> for SavedTodo {
+    fn from(v: domain_models::SavedTodo) -> Self {
+        SavedTodo {
+            id: v.todo.id.into(),
+            task: v.todo.task,
+            done: v.todo.done,
+            version: v.todo.version,
+            list_id: v.todo.list_id.map(|id| id.into()),
+            position: v.todo.position,
+            warnings: v.warnings,
+        }
+    }
+}
+
+impl From<SavedTodo> for Todo {
+    fn from(v: SavedTodo) -> Self {
+        Todo {
+            id: v.id,
+            task: v.task,
+            done: v.done,
+            version: v.version,
+            list_id: v.list_id,
+            position: v.position,
+        }
+    }
+}
+
+impl From<domain_models::Completed> for CompletedTodos {
+    fn from(v: domain_models::Completed) -> Self {
+        CompletedTodos {
+            completed: v.completed.into_iter().map(|t| t.into()).collect(),
+            not_found: v.not_found.into_iter().map(|id| id.into()).collect(),
+            warnings: v.warnings,
+        }
+    }
+}
+
+impl From<domain_models::TodoPlan> for TodoPlan {
+    fn from(v: domain_models::TodoPlan) -> Self {
+        TodoPlan {
+            todo: v.todo.map(|todo| todo.into()),
+            events: v.events.into_iter().map(|e| e.into()).collect(),
+            warnings: v.warnings,
+        }
+    }
+}
+
+impl From<domain_events::TodoEvent> for PlannedEvent {
+    fn from(v: domain_events::TodoEvent) -> Self {
+        let (event, todo, id) = match v {
+            domain_events::TodoEvent::Created(e) => (EventKind::Created, Some(e.todo.into()), None),
+            domain_events::TodoEvent::Updated(e) => (EventKind::Updated, Some(e.todo.into()), None),
+            domain_events::TodoEvent::Deleted(e) => (EventKind::Deleted, None, Some(e.id.into())),
+        };
+        PlannedEvent { event, todo, id }
+    }
+}
+
+impl From<domain_events::TodoEvent> for TodoEvent {
+    fn from(v: domain_events::TodoEvent) -> Self {
+        match v {
+            domain_events::TodoEvent::Created(e) => TodoEvent::Created {
+                todo: e.todo.into(),
+            },
+            domain_events::TodoEvent::Updated(e) => TodoEvent::Updated {
+                todo: e.todo.into(),
+            },
+            domain_events::TodoEvent::Deleted(e) => TodoEvent::Deleted { id: e.id.into() },
+        }
+    }
+}
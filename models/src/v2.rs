@@ -0,0 +1,176 @@
+//! The v2 shapes of todos, served under /v2/tasks while /tasks keeps the v1 ones: ids are always
+//! strings, done is a status, and todos say when they were created and last updated
+
+use crate::history::TodoChange;
+use crate::ids;
+use crate::list::ListId;
+use crate::todo::{self as v1, TodoId};
+#[cfg(feature = "openapi")]
+use paperclip::actix::Apiv2Schema;
+use serde_derive::{Deserialize, Serialize};
+
+/// Whether a todo still needs doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    #[default]
+    Open,
+    Done,
+}
+
+/// Data for creating or updating a todo
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct TodoDataV2 {
+    /// What needs doing; must not be empty
+    #[cfg_attr(feature = "openapi", openapi(example = "Make the bed"))]
+    pub task: String,
+    /// Defaults to open
+    #[serde(default)]
+    pub status: TodoStatus,
+    /// The list to put it in, if any; must exist
+    #[serde(default, serialize_with = "ids::serialize_opt_as_string")]
+    pub list_id: Option<ListId>,
+}
+
+/// A persisted todo
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct TodoV2 {
+    #[serde(serialize_with = "ids::serialize_as_string")]
+    pub id: TodoId,
+    #[cfg_attr(feature = "openapi", openapi(example = "Make the bed"))]
+    pub task: String,
+    pub status: TodoStatus,
+    /// Starts at 1 and is bumped on every update; also the todo's ETag
+    pub version: u64,
+    #[serde(serialize_with = "ids::serialize_opt_as_string")]
+    pub list_id: Option<ListId>,
+    /// Where the todo goes when listed in manual order
+    #[serde(default)]
+    pub position: u64,
+    /// RFC 3339; absent when the todo's history has been lost, e.g. by a restart
+    #[cfg_attr(feature = "openapi", openapi(example = "2024-01-31T12:00:00+00:00"))]
+    pub created_at: Option<String>,
+    /// RFC 3339, like created_at
+    pub updated_at: Option<String>,
+}
+
+/// A todo as just created, along with non-fatal issues found while saving it
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "openapi", derive(Apiv2Schema))]
+pub struct SavedTodoV2 {
+    pub todo: TodoV2,
+    /// E.g. that another open todo has the same task
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl TodoV2 {
+    /// Dated by the changes in its history that were made
+    pub fn dated(self, history: &[TodoChange]) -> Self {
+        let made: Vec<_> = history.iter().filter(|c| c.rejected.is_none()).collect();
+        TodoV2 {
+            created_at: made.iter().find(|c| c.old.is_none()).map(|c| c.at.clone()),
+            updated_at: made.last().map(|c| c.at.clone()),
+            ..self
+        }
+    }
+}
+
+impl From<TodoStatus> for bool {
+    fn from(v: TodoStatus) -> Self {
+        v == TodoStatus::Done
+    }
+}
+
+fn status(done: bool) -> TodoStatus {
+    if done {
+        TodoStatus::Done
+    } else {
+        TodoStatus::Open
+    }
+}
+
+impl From<TodoDataV2> for v1::TodoData {
+    fn from(v: TodoDataV2) -> Self {
+        v1::TodoData {
+            task: v.task,
+            done: v.status.into(),
+            list_id: v.list_id,
+        }
+    }
+}
+
+// Undated; see TodoV2::dated
+impl From<v1::Todo> for TodoV2 {
+    fn from(v: v1::Todo) -> Self {
+        TodoV2 {
+            id: v.id,
+            task: v.task,
+            status: status(v.done),
+            version: v.version,
+            list_id: v.list_id,
+            position: v.position,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+impl From<v1::SavedTodo> for SavedTodoV2 {
+    fn from(v: v1::SavedTodo) -> Self {
+        let warnings = v.warnings.clone();
+        SavedTodoV2 {
+            todo: v1::Todo::from(v).into(),
+            warnings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dated() {
+        let todo = v1::Todo {
+            id: TodoId(1),
+            task: "Make the bed".to_string(),
+            done: false,
+            version: 2,
+            list_id: None,
+            position: 1,
+        };
+        let change = |old: Option<v1::Todo>, at: &str, rejected: Option<&str>| TodoChange {
+            todo_id: TodoId(1),
+            old,
+            new: Some(todo.clone()),
+            at: at.to_string(),
+            actor: None,
+            request_id: None,
+            rejected: rejected.map(|r| r.to_string()),
+        };
+        let history = vec![
+            change(None, "2024-01-31T12:00:00+00:00", None),
+            change(Some(todo.clone()), "2024-01-31T13:00:00+00:00", None),
+            change(
+                Some(todo.clone()),
+                "2024-01-31T14:00:00+00:00",
+                Some("flooded"),
+            ),
+        ];
+        let dated = TodoV2::from(todo.clone()).dated(&history);
+        assert_eq!(TodoStatus::Open, dated.status);
+        assert_eq!(
+            Some("2024-01-31T12:00:00+00:00"),
+            dated.created_at.as_deref()
+        );
+        assert_eq!(
+            Some("2024-01-31T13:00:00+00:00"),
+            dated.updated_at.as_deref()
+        );
+        assert_eq!(None, TodoV2::from(todo).dated(&[]).created_at);
+    }
+}
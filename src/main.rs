@@ -1,14 +1,68 @@
-use api;
+use api::config::LogFormat;
+use std::io::Write;
 
-static LOG_ENV_KEY: &str = "RUST_LOG";
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
-fn main() -> Result<(), std::io::Error> {
-    setup_logging();
-    api::run_server()
+#[actix_web::main]
+async fn main() -> Result<(), std::io::Error> {
+    if let Some(command) = api::config::command(std::env::args_os()) {
+        std::process::exit(api::config::run_command(&command))
+    }
+    let config = match api::config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1)
+        }
+    };
+    setup_logging(config.log_format, &config.log_level);
+    for deprecation in &config.deprecations {
+        log::warn!("{}", deprecation);
+    }
+    let report = api::self_check::run(&config).await;
+    report.print(config.log_format);
+    if config.check_only || !report.passed() {
+        std::process::exit(if report.passed() { 0 } else { 1 })
+    }
+    api::run_server(config).await
 }
 
-fn setup_logging() {
-    let _ = std::env::var(LOG_ENV_KEY)
-        .map_err(|_| std::env::set_var(LOG_ENV_KEY, "info,actix_web=info,api=info"));
-    env_logger::init();
+fn setup_logging(log_format: LogFormat, log_level: &str) {
+    let mut builder = env_logger::Builder::new();
+    builder.parse_filters(log_level);
+    // Lines logged while handling a request, from any layer, carry its id
+    builder.format(move |buf, record| {
+        let request_id = api::request_id::current();
+        match log_format {
+            LogFormat::Json => {
+                let mut line = serde_json::json!({
+                    "timestamp": buf.timestamp().to_string(),
+                    "level": record.level().to_string(),
+                    "target": record.target(),
+                    "message": record.args().to_string(),
+                });
+                if let Some(request_id) = request_id {
+                    line["request_id"] = serde_json::Value::String(request_id);
+                }
+                writeln!(buf, "{}", line)
+            }
+            LogFormat::Text => {
+                let request_id = request_id
+                    .map(|id| format!(" request_id={}", id))
+                    .unwrap_or_default();
+                writeln!(
+                    buf,
+                    "[{} {:<5} {}{}] {}",
+                    buf.timestamp(),
+                    record.level(),
+                    record.target(),
+                    request_id,
+                    record.args()
+                )
+            }
+        }
+    });
+    builder.init();
 }